@@ -0,0 +1,89 @@
+//! Compares answering drone questions like day 19's with an [Oracle] against starting a new
+//! process with its own channels for each question, which is how day 19 used to do it. Run it with
+//! `cargo bench --bench oracle`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use intcode::ipc::{Channel, RecvResult};
+use intcode::oracle::Oracle;
+use intcode::process::Process;
+
+// A stand-in for the drone, since puzzle inputs aren't checked in. It reads x and y and outputs 1
+// if the point is in the beam between y = x and y = 2x, and 0 otherwise.
+const PROGRAM: &str =
+    "3,27,3,28,1002,27,2,29,7,29,28,30,7,28,27,31,1,30,31,32,1008,32,0,32,4,32,99,\
+    0,0,0,0,0,0";
+
+// The size of the corner of the beam that's checked.
+const SIZE: isize = 20;
+
+fn points() -> Vec<(isize, isize)> {
+    (0..SIZE)
+        .flat_map(|y| (0..SIZE).map(move |x| (x, y)))
+        .collect()
+}
+
+// Parse the program and run it in a new process for a single point.
+async fn check_point(x: isize, y: isize) -> bool {
+    let (_, mut input, receiver) = Channel::new(true);
+    let (_, sender, mut output) = Channel::new(true);
+    input.send(x).await.unwrap();
+    input.send(y).await.unwrap();
+    let mut process = Process::new(PROGRAM, receiver, sender);
+    process.run().await.unwrap();
+    matches!(output.recv().await, RecvResult::Value(1))
+}
+
+fn bench_grid(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let points = points();
+    let questions = points.iter().map(|&(x, y)| vec![x, y]).collect::<Vec<_>>();
+
+    // Each of these counts the points in the beam, parsing the program as often as it would in a
+    // fresh run.
+    let mut group = c.benchmark_group("day 19 grid");
+    group.bench_function("process per query", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let mut count = 0;
+                for &(x, y) in &points {
+                    count += check_point(x, y).await as usize;
+                }
+                count
+            })
+        })
+    });
+    group.bench_function("oracle ask", |b| {
+        b.iter(|| {
+            let oracle = Oracle::new(PROGRAM);
+            points
+                .iter()
+                .filter(|&&(x, y)| oracle.ask(&[x, y]).unwrap() == 1)
+                .count()
+        })
+    });
+    group.bench_function("oracle query_all", |b| {
+        b.iter(|| {
+            let oracle = Oracle::new(PROGRAM);
+            let answers = oracle.query_all(&questions).unwrap();
+            answers.iter().filter(|answer| answer[..] == [1]).count()
+        })
+    });
+
+    // Asking again is only a lookup.
+    let oracle = Oracle::new(PROGRAM);
+    oracle.query_all(&questions).unwrap();
+    group.bench_function("oracle cached", |b| {
+        b.iter(|| {
+            points
+                .iter()
+                .filter(|&&(x, y)| oracle.ask(&[x, y]).unwrap() == 1)
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_grid);
+criterion_main!(benches);
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use intcode::{
+    plugin::{Computer, IntcodeOutput, IntcodePlugin, IntcodeSet},
+    theme::ColorScheme,
+};
+
+const INPUT: &str = include_str!("../src/bin/inputs/day13");
+const TILE: f32 = 16.0;
+
+/// What the game has drawn so far, along with where the ball and paddle are so the paddle can
+/// follow the ball when no keys are pressed.
+#[derive(Resource, Default)]
+struct Screen {
+    tiles: HashMap<(isize, isize), Entity>,
+    pending: Vec<isize>,
+    ball: isize,
+    paddle: isize,
+}
+
+#[derive(Component)]
+struct Score;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, IntcodePlugin))
+        .insert_resource(ClearColor(ColorScheme::Background.into()))
+        .init_resource::<Screen>()
+        .add_systems(Startup, setup)
+        .add_systems(Update, joystick.before(IntcodeSet))
+        .add_systems(Update, draw.after(IntcodeSet))
+        .run();
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
+
+    // Put in some quarters so we can play for free.
+    let mut computer = Computer::new(INPUT, 100_000);
+    computer.set_memory(0, 2);
+    commands.spawn(computer);
+
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "Score: 0",
+                TextStyle {
+                    font_size: 24.0,
+                    color: ColorScheme::White.into(),
+                    ..default()
+                },
+            ),
+            transform: Transform::from_xyz(0.0, 14.0 * TILE, 1.0),
+            ..default()
+        },
+        Score,
+    ));
+}
+
+fn joystick(
+    keys: Res<ButtonInput<KeyCode>>,
+    screen: Res<Screen>,
+    mut computers: Query<&mut Computer>,
+) {
+    for mut computer in &mut computers {
+        if !computer.waiting() {
+            continue;
+        }
+        let value = if keys.pressed(KeyCode::ArrowLeft) {
+            -1
+        } else if keys.pressed(KeyCode::ArrowRight) {
+            1
+        } else {
+            (screen.ball - screen.paddle).signum()
+        };
+        computer.push_input(value);
+    }
+}
+
+fn draw(
+    mut commands: Commands,
+    mut outputs: EventReader<IntcodeOutput>,
+    mut screen: ResMut<Screen>,
+    mut score: Query<&mut Text, With<Score>>,
+) {
+    for output in outputs.read() {
+        screen.pending.push(output.value);
+        if screen.pending.len() < 3 {
+            continue;
+        }
+        let (x, y, tile) = (screen.pending[0], screen.pending[1], screen.pending[2]);
+        screen.pending.clear();
+
+        if (x, y) == (-1, 0) {
+            for mut text in &mut score {
+                text.sections[0].value = format!("Score: {}", tile);
+            }
+            continue;
+        }
+
+        if let Some(entity) = screen.tiles.remove(&(x, y)) {
+            commands.entity(entity).despawn();
+        }
+        let color = match tile {
+            1 => ColorScheme::Grey,
+            2 => ColorScheme::Blue,
+            3 => {
+                screen.paddle = x;
+                ColorScheme::Green
+            }
+            4 => {
+                screen.ball = x;
+                ColorScheme::Orange
+            }
+            _ => continue,
+        };
+        let entity = commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: color.into(),
+                    custom_size: Some(Vec2::splat(TILE - 1.0)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(
+                    (x as f32 - 20.0) * TILE,
+                    (12.0 - y as f32) * TILE,
+                    0.0,
+                ),
+                ..default()
+            })
+            .id();
+        screen.tiles.insert((x, y), entity);
+    }
+}
@@ -0,0 +1,47 @@
+/*
+ * The C API for the Intcode computer. Build the library with
+ * `cargo build --release --features ffi` and link against
+ * `target/release/libintcode.so` (or `.dylib` / `.dll`).
+ *
+ * Cells are 64 bits on 64 bit hosts. Every function other than `intcode_new`
+ * takes a computer returned by `intcode_new` that hasn't been freed.
+ */
+#ifndef INTCODE_H
+#define INTCODE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Vm intcode_vm;
+
+/* Create a computer running the comma separated program, or NULL if it isn't valid. */
+intcode_vm *intcode_new(const char *program);
+
+/* Free a computer. */
+void intcode_free(intcode_vm *vm);
+
+/* Run one instruction. Returns 1 if it ran, 0 if the computer has halted or is
+ * waiting for input, and -1 if the instruction failed. */
+int intcode_step(intcode_vm *vm);
+
+/* Queue a value for the computer to read. */
+void intcode_push_input(intcode_vm *vm, int64_t value);
+
+/* Take the oldest output into `value`. Returns 0 if there isn't one. */
+int intcode_pop_output(intcode_vm *vm, int64_t *value);
+
+/* Read memory. Addresses past the end of the program read as zero. */
+int64_t intcode_read_mem(const intcode_vm *vm, size_t address);
+
+/* Whether the computer has halted, as 1 or 0. */
+int intcode_halted(const intcode_vm *vm);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,347 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use crate::{instruction::Instruction, parameter::Parameter};
+
+/// A straight run of instructions that is only entered at the top and only left at the bottom.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// The address of the first instruction in the block.
+    pub start: usize,
+    /// The address just past the last instruction in the block.
+    pub end: usize,
+    /// The instructions in the block along with their addresses.
+    pub instructions: Vec<(usize, Instruction)>,
+    /// The start of the blocks that can run after this one.
+    pub successors: Vec<usize>,
+    /// Whether the block ends in a jump whose target isn't known until the program runs.
+    pub dynamic_jump: bool,
+}
+
+/// The control-flow graph of a program. Only jumps with immediate targets can be followed, so any
+/// code that is only reached through a dynamic jump won't be in the graph.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cfg {
+    /// The basic blocks keyed by their start address.
+    pub blocks: BTreeMap<usize, BasicBlock>,
+    /// The statically known jumps from the address of the jump instruction to its target.
+    pub jumps: BTreeMap<usize, usize>,
+}
+
+impl Cfg {
+    /// Get the block that contains the given address.
+    pub fn block(&self, address: usize) -> Option<&BasicBlock> {
+        self.blocks
+            .range(..=address)
+            .next_back()
+            .map(|(_, block)| block)
+            .filter(|block| address < block.end)
+    }
+
+    /// Get the instruction at the given address if it's the start of a reachable instruction.
+    pub fn instruction(&self, address: usize) -> Option<Instruction> {
+        self.block(address)?
+            .instructions
+            .iter()
+            .find(|(a, _)| *a == address)
+            .map(|(_, instruction)| *instruction)
+    }
+
+    /// Iterate over all of the reachable instructions in address order.
+    pub fn instructions(&self) -> impl Iterator<Item = (usize, Instruction)> + '_ {
+        self.blocks
+            .values()
+            .flat_map(|block| block.instructions.iter().copied())
+    }
+
+    /// The addresses that are the target of a statically known jump.
+    pub fn jump_targets(&self) -> BTreeSet<usize> {
+        self.jumps.values().copied().collect()
+    }
+}
+
+/// Build the control-flow graph of the given program by following every path we can from address
+/// 0.
+pub fn cfg(program: &[isize]) -> Cfg {
+    // Find all of the instructions we can reach and where the blocks must start.
+    let mut reachable = BTreeMap::new();
+    let mut jumps = BTreeMap::new();
+    let mut leaders = BTreeSet::from([0]);
+    let mut frontier = vec![0];
+    while let Some(address) = frontier.pop() {
+        if reachable.contains_key(&address) {
+            continue;
+        }
+        let Ok((instruction, size)) = Instruction::decode_unchecked(program, address) else {
+            continue;
+        };
+        reachable.insert(address, (instruction, size));
+        let (target, falls_through) = exits(&instruction, address + size);
+        if let Some(target) = target {
+            jumps.insert(address, target);
+            leaders.insert(target);
+            frontier.push(target);
+        }
+        if let Some(next) = falls_through {
+            if is_jump(&instruction) {
+                leaders.insert(next);
+            }
+            frontier.push(next);
+        }
+    }
+
+    // Now split the instructions into blocks.
+    let mut blocks = BTreeMap::new();
+    let mut current: Option<BasicBlock> = None;
+    for (&address, &(instruction, size)) in &reachable {
+        // A block ends when something jumps into the next instruction or it isn't right after
+        // the last one.
+        if let Some(block) = current.take() {
+            if leaders.contains(&address) || block.end != address {
+                blocks.insert(block.start, finish(block, &reachable));
+            } else {
+                current = Some(block);
+            }
+        }
+        let block = current.get_or_insert_with(|| BasicBlock {
+            start: address,
+            end: address,
+            instructions: Vec::new(),
+            successors: Vec::new(),
+            dynamic_jump: false,
+        });
+        block.instructions.push((address, instruction));
+        block.end = address + size;
+
+        if is_jump(&instruction) || instruction == Instruction::Halt {
+            let block = current.take().unwrap();
+            blocks.insert(block.start, finish(block, &reachable));
+        }
+    }
+    if let Some(block) = current {
+        blocks.insert(block.start, finish(block, &reachable));
+    }
+
+    Cfg { blocks, jumps }
+}
+
+// Fill in where the block can go once we know what's in it.
+fn finish(mut block: BasicBlock, reachable: &BTreeMap<usize, (Instruction, usize)>) -> BasicBlock {
+    let Some(&(_, last)) = block.instructions.last() else {
+        return block;
+    };
+    let (target, falls_through) = exits(&last, block.end);
+    block.successors = target
+        .into_iter()
+        .chain(falls_through)
+        .filter(|address| reachable.contains_key(address))
+        .collect();
+    block.successors.dedup();
+    block.dynamic_jump = is_jump(&last) && target.is_none() && !never_jumps(&last);
+    block
+}
+
+fn is_jump(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::JumpIfTrue(_, _) | Instruction::JumpIfFalse(_, _)
+    )
+}
+
+// Check whether the instruction is a jump with an immediate condition that can never be taken.
+fn never_jumps(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::JumpIfTrue(Parameter::Immediate(0), _)
+            | Instruction::JumpIfFalse(Parameter::Immediate(1..), _)
+            | Instruction::JumpIfFalse(Parameter::Immediate(..=-1), _)
+    )
+}
+
+// Get the statically known jump target of an instruction and the next address if it can continue
+// on to it.
+fn exits(instruction: &Instruction, next: usize) -> (Option<usize>, Option<usize>) {
+    let (condition, target, jumps_when) = match *instruction {
+        Instruction::Halt => return (None, None),
+        Instruction::JumpIfTrue(condition, target) => (condition, target, true),
+        Instruction::JumpIfFalse(condition, target) => (condition, target, false),
+        _ => return (None, Some(next)),
+    };
+
+    let target = match (target, never_jumps(instruction)) {
+        (Parameter::Immediate(target), false) if target >= 0 => Some(target as usize),
+        _ => None,
+    };
+
+    // A jump with an immediate condition that always jumps never falls through.
+    let always_jumps =
+        matches!(condition, Parameter::Immediate(value) if (value != 0) == jumps_when);
+    (target, (!always_jumps).then_some(next))
+}
+
+/// A problem found in a program by [lint].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The address of the instruction (or the start of the code) with the problem.
+    pub address: usize,
+    /// The problem that was found.
+    pub kind: DiagnosticKind,
+}
+
+/// The kinds of problems [lint] looks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// The instruction writes to an immediate mode parameter.
+    ImmediateWrite(Instruction),
+    /// The instruction jumps to an address outside of the program.
+    JumpOutOfRange(Instruction),
+    /// The code from the address up to (but not including) the given address is never run.
+    Unreachable(usize),
+    /// The instruction reads a cell past the end of the program that nothing writes to.
+    UninitializedRead(Instruction, usize),
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:08}: ", self.address)?;
+        match &self.kind {
+            DiagnosticKind::ImmediateWrite(instruction) => {
+                write!(f, "write to an immediate parameter in '{}'", instruction)
+            }
+            DiagnosticKind::JumpOutOfRange(instruction) => {
+                write!(f, "jump outside of the program in '{}'", instruction)
+            }
+            DiagnosticKind::Unreachable(end) => {
+                write!(f, "unreachable code up to {:08}", end)
+            }
+            DiagnosticKind::UninitializedRead(instruction, address) => write!(
+                f,
+                "read of {} which is never written in '{}'",
+                address, instruction
+            ),
+        }
+    }
+}
+
+/// Look for common problems in a program. This is all static, so it only knows about immediate
+/// jump targets and position mode parameters. Unreachable code is only reported when there are no
+/// dynamic jumps since we can't know where those go.
+pub fn lint(program: &[isize]) -> Vec<Diagnostic> {
+    let cfg = cfg(program);
+    let mut diagnostics = Vec::new();
+
+    // We need to know everything that gets written to before we can look at the reads.
+    let writes = cfg
+        .instructions()
+        .filter_map(|(_, instruction)| match instruction.write_parameter() {
+            Some(Parameter::Position(address)) => Some(address),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+
+    for (address, instruction) in cfg.instructions() {
+        if let Some(Parameter::Immediate(_)) = instruction.write_parameter() {
+            diagnostics.push(Diagnostic {
+                address,
+                kind: DiagnosticKind::ImmediateWrite(instruction),
+            });
+        }
+
+        let out_of_range = match instruction {
+            Instruction::JumpIfTrue(_, Parameter::Immediate(target))
+            | Instruction::JumpIfFalse(_, Parameter::Immediate(target)) => {
+                target < 0 || target as usize >= program.len()
+            }
+            _ => false,
+        };
+        if out_of_range && !never_jumps(&instruction) {
+            diagnostics.push(Diagnostic {
+                address,
+                kind: DiagnosticKind::JumpOutOfRange(instruction),
+            });
+        }
+
+        for read in instruction.read_parameters() {
+            if let Parameter::Position(read) = read {
+                if read >= program.len() && !writes.contains(&read) {
+                    diagnostics.push(Diagnostic {
+                        address,
+                        kind: DiagnosticKind::UninitializedRead(instruction, read),
+                    });
+                }
+            }
+        }
+    }
+
+    // Look for runs of cells that aren't reached but look like code. Data rarely decodes.
+    if !cfg.blocks.values().any(|block| block.dynamic_jump) {
+        let mut address = 0;
+        let blocks = cfg.blocks.values().map(|block| (block.start, block.end));
+        for (start, end) in blocks.chain([(program.len(), program.len())]) {
+            if address < start && Instruction::decode_unchecked(program, address).is_ok() {
+                diagnostics.push(Diagnostic {
+                    address,
+                    kind: DiagnosticKind::Unreachable(start),
+                });
+            }
+            address = address.max(end);
+        }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.address);
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cfg() {
+        // 0: read a value, 2: jump to 9 if it's zero, 5: output it, 7: halt, 8: data, 9: halt.
+        let cfg = cfg(&[3, 8, 1006, 8, 9, 4, 8, 99, 0, 99]);
+        assert_eq!(
+            cfg.blocks.keys().copied().collect::<Vec<_>>(),
+            vec![0, 5, 9]
+        );
+        assert_eq!(cfg.blocks[&0].successors, vec![9, 5]);
+        assert!(cfg.blocks[&5].successors.is_empty());
+        assert_eq!(cfg.jumps, BTreeMap::from([(2, 9)]));
+        assert_eq!(cfg.block(6).unwrap().start, 5);
+        assert!(cfg.block(8).is_none());
+        assert_eq!(cfg.instruction(9), Some(Instruction::Halt));
+    }
+
+    #[test]
+    fn test_lint() {
+        // 0: write to an immediate, 4: read 100 which is never written, 6: jump out of the
+        // program, 9: unreachable halt.
+        let diagnostics = lint(&[11101, 1, 2, 3, 4, 100, 1105, 1, 50, 99]);
+        let kinds = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.address, diagnostic.kind.clone()))
+            .collect::<Vec<_>>();
+        let p = Parameter::Immediate;
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    0,
+                    DiagnosticKind::ImmediateWrite(Instruction::Add(p(1), p(2), p(3)))
+                ),
+                (
+                    4,
+                    DiagnosticKind::UninitializedRead(
+                        Instruction::Output(Parameter::Position(100)),
+                        100
+                    )
+                ),
+                (
+                    6,
+                    DiagnosticKind::JumpOutOfRange(Instruction::JumpIfTrue(p(1), p(50)))
+                ),
+                (9, DiagnosticKind::Unreachable(10)),
+            ]
+        );
+    }
+}
@@ -1,61 +1,845 @@
-use std::sync::{Arc, Mutex};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, VecDeque};
+use std::future::Future;
+use std::path::Path;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use std::time::Duration;
 
-use crate::{breakpoint::Breakpoints, ipc::Channel, process::State};
+#[cfg(feature = "serde")]
+use crate::session::{Session, SessionBreakpoint};
+use crate::{
+    breakpoint::{format_with_action, parse_with_action, Action, Breakpoint, Breakpoints, Scope},
+    condition::Condition,
+    disasm::disassemble,
+    history::History,
+    instruction::Instruction,
+    ipc::{Channel, ChannelReceiver, ChannelSender},
+    process::{Process, State},
+    script::Target,
+    symbols::SymbolTable,
+    timing::ProcessStatus,
+};
 
-use anyhow::Result;
-use tokio::sync::mpsc::Sender;
+use anyhow::{anyhow, Result};
+use tokio::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        oneshot,
+    },
+    task::JoinHandle,
+};
 
 /// The type of notification to send to the process.
 pub enum Notification {
     Step,
     StepUntil(Breakpoints),
+    /// Take up to the given number of steps, stopping early at a breakpoint. Whether a breakpoint
+    /// was hit is sent back once the steps are done.
+    StepCount(usize, Breakpoints, oneshot::Sender<bool>),
+    /// Run until the instruction pointer reaches the given address again.
+    RunToAddress(usize),
+    /// Run the given number of instructions, ignoring breakpoints.
+    RunN(usize),
+    /// Set the memory at the given address to the given value.
+    SetMemory(usize, isize),
+    /// Move the instruction pointer.
+    SetIp(usize),
+    /// Set the instruction pointer and the relative base.
+    SetRegisters(usize, isize),
+    /// Go back to the state the process started in.
+    Reset,
+    /// Stop whatever the process is running. It has no effect if the process isn't running.
+    Interrupt,
+}
+
+impl Notification {
+    /// The name of the notification, for logging.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Notification::Step => "step",
+            Notification::StepUntil(_) => "step until",
+            Notification::StepCount(..) => "step count",
+            Notification::RunToAddress(_) => "run to address",
+            Notification::RunN(_) => "run n",
+            Notification::SetMemory(..) => "set memory",
+            Notification::SetIp(_) => "set ip",
+            Notification::SetRegisters(..) => "set registers",
+            Notification::Reset => "reset",
+            Notification::Interrupt => "interrupt",
+        }
+    }
+}
+
+// Check for an interrupt while the process is running. Anything else that was sent in the
+// meantime is kept to be handled once the process stops.
+fn interrupted(
+    notifier: &mut Receiver<Notification>,
+    pending: &mut VecDeque<Notification>,
+) -> bool {
+    while let Ok(notification) = notifier.try_recv() {
+        match notification {
+            Notification::Interrupt => return true,
+            notification => pending.push_back(notification),
+        }
+    }
+    false
+}
+
+/// Run the process in the background, doing what the notifications ask and keeping the shared
+/// state and history up to date for the tui. The task stops once every notifier is dropped.
+pub async fn run_process(
+    mut notifier: Receiver<Notification>,
+    mut process: Process,
+    state: Arc<Mutex<State>>,
+    history: History,
+) -> JoinHandle<()> {
+    // Keep the state we started with (including any memory patches) so we can reset to it. We keep
+    // listening after the process halts for the same reason.
+    let initial = process.state();
+    tokio::spawn(async move {
+        let timing = history.timing().clone();
+        let mut pending = VecDeque::new();
+        loop {
+            let notification = match pending.pop_front() {
+                Some(notification) => notification,
+                None => match notifier.recv().await {
+                    Some(notification) => notification,
+                    None => break,
+                },
+            };
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                process = process.id(),
+                notification = notification.name(),
+                "notified"
+            );
+
+            // Everything that runs more than one instruction records what it runs and can be
+            // interrupted. The first instruction is where we stopped last time, so it never stops
+            // us again.
+            let count = AtomicUsize::new(0);
+            // Whoever asked for a step count hears back once the shared state is up to date.
+            let mut done = None;
+            let mut run = |state: &State, instruction: &Instruction, stop: bool| {
+                count.fetch_add(1, Ordering::SeqCst);
+                let stop = (stop && count.load(Ordering::SeqCst) > 1)
+                    || interrupted(&mut notifier, &mut pending);
+                if !stop {
+                    timing.before(instruction);
+                    history.record(state, instruction);
+                }
+                stop
+            };
+
+            // Errors are kept in the history for the tui to show, since there is no one here to
+            // hand them to. They last until the process is told to do something else.
+            timing.start();
+            let result = match notification {
+                Notification::Step => {
+                    let current = process.state();
+                    if let Some((instruction, _)) = current.next_instruction() {
+                        history.record(&current, &instruction);
+                        timing.before(&instruction);
+                    }
+                    process.step().await.map(|_| ())
+                }
+                Notification::StepUntil(breakpoints) => {
+                    process
+                        .run_until(|state, instruction| {
+                            run(state, instruction, breakpoints.evaluate(state, instruction))
+                        })
+                        .await
+                }
+                Notification::StepCount(steps, breakpoints, hit) => {
+                    let mut stopped = false;
+                    let result = process
+                        .run_until(|state, instruction| {
+                            if count.load(Ordering::SeqCst) >= steps {
+                                return true;
+                            }
+                            let breakpoint = breakpoints.evaluate(state, instruction);
+                            stopped = run(state, instruction, breakpoint);
+                            stopped
+                        })
+                        .await;
+                    done = Some((hit, stopped));
+                    result
+                }
+                Notification::RunToAddress(address) => {
+                    process
+                        .run_until(|state, instruction| {
+                            run(state, instruction, state.instruction_pointer == address)
+                        })
+                        .await
+                }
+                Notification::RunN(steps) => {
+                    process
+                        .run_until(|state, instruction| {
+                            count.load(Ordering::SeqCst) >= steps || run(state, instruction, false)
+                        })
+                        .await
+                }
+                Notification::SetMemory(address, value) => {
+                    process.set_memory(address, value);
+                    Ok(())
+                }
+                Notification::SetIp(instruction_pointer) => {
+                    process.set_instruction_pointer(instruction_pointer);
+                    Ok(())
+                }
+                Notification::SetRegisters(instruction_pointer, relative_base) => {
+                    process.set_instruction_pointer(instruction_pointer);
+                    process.set_relative_base(relative_base);
+                    Ok(())
+                }
+                Notification::Reset => {
+                    process.reset(initial.clone());
+                    history.clear();
+                    Ok(())
+                }
+                Notification::Interrupt => Ok(()),
+            };
+            #[cfg(feature = "tracing")]
+            if let Err(e) = &result {
+                tracing::warn!(process = process.id(), error = %e, "notification failed");
+            }
+            history.set_error(result.err().map(|e| e.to_string()));
+            timing.stop(process.state().halted);
+            *state.lock().unwrap() = process.state();
+            if let Some((hit, stopped)) = done {
+                let _ = hit.send(stopped);
+            }
+        }
+    })
 }
 
 /// The application state for the tui.
 pub struct App {
+    names: Vec<String>,
     channels: Vec<Channel>,
+    endpoints: Vec<(Option<usize>, Option<usize>)>,
+    inputs: Vec<Option<ChannelSender>>,
+    outputs: Vec<Option<ChannelReceiver>>,
     states: Vec<Arc<Mutex<State>>>,
-    notifiers: Vec<Sender<Notification>>,
+    // A process's notifier is dropped when the app shuts down, which stops its task.
+    notifiers: Vec<Option<Sender<Notification>>>,
+    symbols: Vec<SymbolTable>,
+    histories: Vec<History>,
+    tasks: Vec<Option<JoinHandle<()>>>,
+    breakpoints: Vec<(Scope, Breakpoint, Action)>,
+    watches: Vec<Condition>,
+    trace: Arc<Mutex<Vec<String>>>,
 }
 
 impl App {
+    /// Create an app for the processes with the given names and states. The notifiers tell each
+    /// process what to do and the channels are shown in the channels pane.
     pub fn new(
+        names: Vec<String>,
         channels: Vec<Channel>,
         states: Vec<Arc<Mutex<State>>>,
         notifiers: Vec<Sender<Notification>>,
     ) -> Self {
+        let symbols = vec![SymbolTable::new(); states.len()];
+        let histories = (0..states.len()).map(|_| History::default()).collect();
+        let tasks = (0..states.len()).map(|_| None).collect();
         Self {
+            names,
+            endpoints: vec![(None, None); channels.len()],
             channels,
+            inputs: (0..states.len()).map(|_| None).collect(),
+            outputs: (0..states.len()).map(|_| None).collect(),
             states,
-            notifiers,
+            notifiers: notifiers.into_iter().map(Some).collect(),
+            symbols,
+            histories,
+            tasks,
+            breakpoints: Vec::new(),
+            watches: Vec::new(),
+            trace: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
+    // Get the notifier for the process at the given index, unless the app has shut down.
+    fn notifier(&self, index: usize) -> Result<&Sender<Notification>> {
+        self.notifiers[index]
+            .as_ref()
+            .ok_or_else(|| anyhow!("{} has been shut down", self.names[index]))
+    }
+
     /// Send a notification to the process at the given index to take a step.
     pub async fn step(&self, index: usize) -> Result<()> {
         if self.states[index].lock().unwrap().halted {
             return Ok(());
         }
-        self.notifiers[index].send(Notification::Step).await?;
+        self.notifier(index)?.send(Notification::Step).await?;
         Ok(())
     }
 
-    pub async fn step_until(&self, index: usize, breakpoints: Breakpoints) -> Result<()> {
+    /// Send a notification to the process at the given index to run until one of its breakpoints
+    /// is hit.
+    pub async fn step_until(&self, index: usize) -> Result<()> {
         if self.states[index].lock().unwrap().halted {
             return Ok(());
         }
-        self.notifiers[index]
-            .send(Notification::StepUntil(breakpoints))
+        self.notifier(index)?
+            .send(Notification::StepUntil(self.process_breakpoints(index)))
             .await?;
         Ok(())
     }
 
+    /// Set the sender for the input of the process at the given index so values can be typed in.
+    pub fn set_input(&mut self, index: usize, sender: ChannelSender) {
+        self.inputs[index] = Some(sender);
+    }
+
+    /// Set the receiver for the output of the process at the given index so what it outputs can
+    /// be taken with [App::take_output].
+    pub fn set_output(&mut self, index: usize, receiver: ChannelReceiver) {
+        self.outputs[index] = Some(receiver);
+    }
+
+    /// Take everything the process at the given index has output since the last time. Processes
+    /// without an output receiver never have anything to take.
+    pub fn take_output(&mut self, index: usize) -> Vec<isize> {
+        self.outputs[index]
+            .as_mut()
+            .map(|receiver| receiver.drain())
+            .unwrap_or_default()
+    }
+
+    /// Send a line of input to the process at the given index. A line of numbers (separated by
+    /// spaces or commas) is sent as those numbers. Anything else is sent as ASCII followed by a
+    /// newline, which is what the text based programs expect.
+    pub async fn send_input(&mut self, index: usize, line: &str) -> Result<()> {
+        let numbers = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .map(|token| token.parse::<isize>())
+            .collect::<Result<Vec<_>, _>>();
+        let values = match numbers {
+            Ok(numbers) if !numbers.is_empty() => numbers,
+            _ => line.chars().chain(['\n']).map(|c| c as isize).collect(),
+        };
+        self.send_values(index, &values).await
+    }
+
+    /// Send the values to the process at the given index all at once.
+    pub async fn send_values(&mut self, index: usize, values: &[isize]) -> Result<()> {
+        let sender = self.inputs[index]
+            .as_mut()
+            .ok_or_else(|| anyhow!("process {} has no input", index))?;
+        sender.send_slice(values).await
+    }
+
+    /// Send a notification to the process at the given index to take up to `steps` steps, stopping
+    /// early if one of its breakpoints is hit. The returned receiver gets whether a breakpoint was
+    /// hit once the process is done.
+    pub async fn step_count(&self, index: usize, steps: usize) -> Result<oneshot::Receiver<bool>> {
+        self.step_count_with(index, steps, self.process_breakpoints(index))
+            .await
+    }
+
+    /// Like [App::step_count], but stopping at the given breakpoints instead of the app's.
+    pub async fn step_count_with(
+        &self,
+        index: usize,
+        steps: usize,
+        breakpoints: Breakpoints,
+    ) -> Result<oneshot::Receiver<bool>> {
+        let (sender, receiver) = oneshot::channel();
+        self.notifier(index)?
+            .send(Notification::StepCount(steps, breakpoints, sender))
+            .await?;
+        Ok(receiver)
+    }
+
+    /// Send a notification to the process at the given index to run until its instruction pointer
+    /// gets to the given address.
+    pub async fn run_to_address(&self, index: usize, address: usize) -> Result<()> {
+        self.notifier(index)?
+            .send(Notification::RunToAddress(address))
+            .await?;
+        Ok(())
+    }
+
+    /// Send a notification to the process at the given index to run the given number of
+    /// instructions.
+    pub async fn run_n(&self, index: usize, steps: usize) -> Result<()> {
+        self.notifier(index)?
+            .send(Notification::RunN(steps))
+            .await?;
+        Ok(())
+    }
+
+    /// Send a notification to the process at the given index to change its memory.
+    pub async fn set_memory(&self, index: usize, address: usize, value: isize) -> Result<()> {
+        self.notifier(index)?
+            .send(Notification::SetMemory(address, value))
+            .await?;
+        Ok(())
+    }
+
+    /// Send a notification to the process at the given index to move its instruction pointer.
+    pub async fn set_ip(&self, index: usize, instruction_pointer: usize) -> Result<()> {
+        self.notifier(index)?
+            .send(Notification::SetIp(instruction_pointer))
+            .await?;
+        Ok(())
+    }
+
+    /// Send a notification to the process at the given index to stop what it's running.
+    pub async fn interrupt(&self, index: usize) -> Result<()> {
+        self.notifier(index)?.send(Notification::Interrupt).await?;
+        Ok(())
+    }
+
+    /// Send a notification to the process at the given index to move its instruction pointer and
+    /// change its relative base.
+    pub async fn set_registers(
+        &self,
+        index: usize,
+        instruction_pointer: usize,
+        relative_base: isize,
+    ) -> Result<()> {
+        self.notifier(index)?
+            .send(Notification::SetRegisters(
+                instruction_pointer,
+                relative_base,
+            ))
+            .await?;
+        Ok(())
+    }
+
+    /// Send a notification to the process at the given index to start over from the state it
+    /// started in. Any input waiting for it is thrown away.
+    pub async fn reset(&self, index: usize) -> Result<()> {
+        self.notifier(index)?.send(Notification::Reset).await?;
+        Ok(())
+    }
+
+    /// Add a breakpoint that applies to the given scope and stops the process.
+    pub fn add_breakpoint(&mut self, scope: Scope, breakpoint: Breakpoint) {
+        self.breakpoints.push((scope, breakpoint, Action::Stop));
+    }
+
+    /// Remove the breakpoint at the given index, if there is one.
+    pub fn remove_breakpoint(&mut self, index: usize) -> Option<(Scope, Breakpoint, Action)> {
+        (index < self.breakpoints.len()).then(|| self.breakpoints.remove(index))
+    }
+
+    /// Change the scope of the breakpoint at the given index.
+    pub fn set_breakpoint_scope(&mut self, index: usize, scope: Scope) {
+        if let Some((s, _, _)) = self.breakpoints.get_mut(index) {
+            *s = scope;
+        }
+    }
+
+    /// Change what the breakpoint at the given index does when it's hit.
+    pub fn set_breakpoint_action(&mut self, index: usize, action: Action) {
+        if let Some((_, _, a)) = self.breakpoints.get_mut(index) {
+            *a = action;
+        }
+    }
+
+    /// Get all of the breakpoints along with their scopes and actions.
+    pub fn breakpoints(&self) -> &[(Scope, Breakpoint, Action)] {
+        &self.breakpoints
+    }
+
+    /// Watch the value of the expression in every process.
+    pub fn add_watch(&mut self, watch: Condition) {
+        self.watches.push(watch);
+    }
+
+    /// Stop watching the expression at the given index, if there is one.
+    pub fn remove_watch(&mut self, index: usize) -> Option<Condition> {
+        (index < self.watches.len()).then(|| self.watches.remove(index))
+    }
+
+    /// Get the watched expressions in the order they were added.
+    pub fn watches(&self) -> &[Condition] {
+        &self.watches
+    }
+
+    /// Get the breakpoints that apply to the process at the given index.
+    pub fn process_breakpoints(&self, index: usize) -> Breakpoints {
+        let mut breakpoints = Breakpoints::with_trace(self.trace.clone());
+        self.breakpoints
+            .iter()
+            .filter(|(scope, _, _)| scope.contains(index))
+            .for_each(|(_, breakpoint, action)| {
+                breakpoints.add_with_action(breakpoint.clone(), action.clone())
+            });
+        breakpoints
+    }
+
+    /// Get the addresses the breakpoints that apply to the process at the given index are tied to.
+    pub fn breakpoint_addresses(&self, index: usize) -> BTreeSet<usize> {
+        self.breakpoints
+            .iter()
+            .filter(|(scope, _, _)| scope.contains(index))
+            .filter_map(|(_, breakpoint, _)| breakpoint.address())
+            .collect()
+    }
+
+    /// Get the messages logged by tracepoints in every process.
+    pub fn trace(&self) -> Vec<String> {
+        self.trace.lock().unwrap().clone()
+    }
+
+    /// Load breakpoints from a breakpoint list, adding them to the ones we already have. Each line
+    /// is a scope (`*` for every process or a process index) followed by a breakpoint in the
+    /// format [Breakpoints] uses (e.g. `0 ip 124`). Lines starting with `#` are comments.
+    pub fn load_breakpoints(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let list = std::fs::read_to_string(path)?;
+        for (number, line) in list.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (scope, breakpoint) = line
+                .split_once(' ')
+                .ok_or_else(|| anyhow!("line {}: expected a scope and a breakpoint", number + 1))?;
+            let scope = scope
+                .parse()
+                .map_err(|e| anyhow!("line {}: {}", number + 1, e))?;
+            let (breakpoint, action) =
+                parse_with_action(breakpoint).map_err(|e| anyhow!("line {}: {}", number + 1, e))?;
+            self.breakpoints.push((scope, breakpoint, action));
+        }
+        Ok(())
+    }
+
+    /// Save the breakpoints to a breakpoint list that [App::load_breakpoints] can read.
+    /// Breakpoints with a predicate can't be written down, so they are left out and returned.
+    pub fn save_breakpoints(&self, path: impl AsRef<Path>) -> Result<Vec<Breakpoint>> {
+        let (saved, skipped) = self.saved_breakpoints();
+        let list = saved
+            .iter()
+            .filter_map(|(scope, breakpoint, action)| {
+                let line = format_with_action(breakpoint, action)?;
+                Some(format!("{} {}\n", scope, line))
+            })
+            .collect::<String>();
+        std::fs::write(path, list)?;
+        Ok(skipped)
+    }
+
+    // Split the breakpoints into the ones that can be written down and the ones with a predicate.
+    fn saved_breakpoints(&self) -> (Vec<(Scope, Breakpoint, Action)>, Vec<Breakpoint>) {
+        let (skipped, saved): (Vec<_>, Vec<_>) = self
+            .breakpoints
+            .iter()
+            .cloned()
+            .partition(|(_, breakpoint, _)| breakpoint.has_predicate());
+        let skipped = skipped
+            .into_iter()
+            .map(|(_, breakpoint, _)| breakpoint)
+            .collect();
+        (saved, skipped)
+    }
+
+    /// Load the breakpoints and watch expressions from a session file (see [Session]), adding them
+    /// to the ones we already have.
+    #[cfg(feature = "serde")]
+    pub fn load_session(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let session = Session::from_json(&std::fs::read_to_string(path)?)?;
+        for b in session.breakpoints {
+            self.breakpoints.push((b.scope, b.breakpoint, b.action));
+        }
+        self.watches.extend(session.watches);
+        Ok(())
+    }
+
+    /// Save the breakpoints and watch expressions to a session file that [App::load_session] can
+    /// read. Breakpoints with a predicate can't be written down, so they are left out and
+    /// returned.
+    #[cfg(feature = "serde")]
+    pub fn save_session(&self, path: impl AsRef<Path>) -> Result<Vec<Breakpoint>> {
+        let (saved, skipped) = self.saved_breakpoints();
+        let breakpoints = saved
+            .into_iter()
+            .map(|(scope, breakpoint, action)| SessionBreakpoint {
+                scope,
+                breakpoint,
+                action,
+            })
+            .collect();
+        let session = Session::new(breakpoints, self.watches.clone());
+        std::fs::write(path, session.to_json()?)?;
+        Ok(skipped)
+    }
+
+    /// Write everything about the process at the given index that would help someone else debug it
+    /// to files in the given directory, creating it if needed:
+    ///
+    /// - `memory.txt` is the memory in the usual program format.
+    /// - `state.txt` has the registers, the last input and output, and any additional memory.
+    /// - `disassembly.txt` is the disassembly of the memory.
+    /// - `breakpoints.txt` is the breakpoints in the format [App::load_breakpoints] reads.
+    /// - `session.json` is the breakpoints and watch expressions in the format
+    ///   [App::load_session] reads, when the serde feature is on.
+    /// - `output.txt` is the values the process output that are still in its history, oldest
+    ///   first.
+    /// - `trace.txt` is the messages logged by tracepoints.
+    /// - `channels.txt` is the values waiting in each channel.
+    pub fn export(&self, index: usize, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let state = self.states[index].lock().unwrap().clone();
+
+        let memory = state
+            .memory
+            .iter()
+            .map(|cell| cell.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        std::fs::write(dir.join("memory.txt"), memory + "\n")?;
+
+        let mut registers = format!(
+            "ip {}\nrb {}\nhalted {}\nlast input {:?}\nlast output {:?}\n",
+            state.instruction_pointer,
+            state.relative_base,
+            state.halted,
+            state.last_input,
+            state.last_output
+        );
+        for (address, value) in &state.additional_memory {
+            registers += &format!("mem[{}] {}\n", address, value);
+        }
+        std::fs::write(dir.join("state.txt"), registers)?;
+
+        let mut listing = disassemble(&state.memory);
+        listing.symbols = self.symbols[index].clone();
+        listing.breakpoints = self.breakpoint_addresses(index);
+        std::fs::write(dir.join("disassembly.txt"), listing.to_string())?;
+
+        self.save_breakpoints(dir.join("breakpoints.txt"))?;
+        #[cfg(feature = "serde")]
+        self.save_session(dir.join("session.json"))?;
+
+        let output = self.histories[index]
+            .entries()
+            .into_iter()
+            .filter(|entry| matches!(entry.instruction, Instruction::Output(_)))
+            .map(|entry| format!("{}\n", entry.values[0]))
+            .collect::<String>();
+        std::fs::write(dir.join("output.txt"), output)?;
+
+        let trace = self
+            .trace()
+            .into_iter()
+            .map(|message| message + "\n")
+            .collect::<String>();
+        std::fs::write(dir.join("trace.txt"), trace)?;
+
+        let channels = self
+            .channels
+            .iter()
+            .map(|channel| {
+                let values = channel
+                    .buffer()
+                    .iter()
+                    .map(|value| value.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                format!("{}: {}\n", channel.id(), values)
+            })
+            .collect::<String>();
+        std::fs::write(dir.join("channels.txt"), channels)?;
+        Ok(())
+    }
+
+    /// Get the channels so they can be inspected.
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    /// Note which processes write to and read from the channel at the given index. `None` means
+    /// the tui (or nobody) is on that end.
+    pub fn connect(&mut self, channel: usize, writer: Option<usize>, reader: Option<usize>) {
+        self.endpoints[channel] = (writer, reader);
+    }
+
+    /// Get the processes that write to and read from the channel at the given index.
+    pub fn endpoints(&self, channel: usize) -> (Option<usize>, Option<usize>) {
+        self.endpoints[channel]
+    }
+
+    /// Describe the channel at the given index by its endpoints (e.g. `AMP A -> AMP B`).
+    pub fn channel_label(&self, channel: usize) -> String {
+        let (writer, reader) = self.endpoints[channel];
+        format!(
+            "{} -> {}",
+            self.endpoint_name(writer),
+            self.endpoint_name(reader)
+        )
+    }
+
+    /// Describe how the processes are connected by following the channels from the one the tui
+    /// writes to (or the first one if the processes only feed each other) until we get back to
+    /// somewhere we've been, like `AMP E -> AMP A -> ... -> AMP E` for a feedback loop.
+    pub fn topology(&self) -> String {
+        let mut current = self
+            .endpoints
+            .iter()
+            .position(|(writer, _)| writer.is_none())
+            .or((!self.endpoints.is_empty()).then_some(0));
+        let mut seen = BTreeSet::new();
+        let mut parts = Vec::new();
+        while let Some(channel) = current.filter(|channel| seen.insert(*channel)) {
+            let (writer, reader) = self.endpoints[channel];
+            if parts.is_empty() {
+                parts.push(self.endpoint_name(writer));
+            }
+            parts.push(self.endpoint_name(reader));
+            current = reader.and_then(|reader| {
+                self.endpoints
+                    .iter()
+                    .position(|(writer, _)| *writer == Some(reader))
+            });
+        }
+        parts.join(" -> ")
+    }
+
+    // Get the name of one end of a channel.
+    fn endpoint_name(&self, process: Option<usize>) -> String {
+        match process {
+            Some(index) => self.names[index].clone(),
+            None => "tui".to_string(),
+        }
+    }
+
     /// Get the buffers for the channels.
     pub fn buffers(&self) -> Vec<Vec<isize>> {
         self.channels.iter().map(|c| c.buffer()).collect()
     }
 
-    /// Get the states of the processes.
+    /// Set the symbols used to display the program of the process at the given index.
+    pub fn set_symbols(&mut self, index: usize, symbols: SymbolTable) {
+        self.symbols[index] = symbols;
+    }
+
+    /// Load the program into a new process with the given name and its own input and output
+    /// channels and start it in the background. The index of the new process is returned.
+    pub async fn open(&mut self, name: &str, program: &str) -> Result<usize> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(name, "open");
+        if let Some(cell) = program
+            .trim()
+            .split(',')
+            .find(|cell| cell.parse::<isize>().is_err())
+        {
+            return Err(anyhow!("'{}' isn't a number", cell));
+        }
+
+        let (input, input_sender, input_receiver) = Channel::new(true);
+        let (output, output_sender, output_receiver) = Channel::new(true);
+        let process = Process::new(program, input_receiver, output_sender);
+        let state = Arc::new(Mutex::new(process.state()));
+        let (notifier, notifier_receiver) = mpsc::channel::<Notification>(32);
+        let history = History::default();
+        let task = run_process(notifier_receiver, process, state.clone(), history.clone()).await;
+
+        let index = self.states.len();
+        self.names.push(name.to_string());
+        self.channels.extend([input, output]);
+        self.endpoints
+            .extend([(None, Some(index)), (Some(index), None)]);
+        self.inputs.push(Some(input_sender));
+        self.outputs.push(Some(output_receiver));
+        self.states.push(state);
+        self.notifiers.push(Some(notifier));
+        self.symbols.push(SymbolTable::new());
+        self.histories.push(history);
+        self.tasks.push(Some(task));
+        Ok(index)
+    }
+
+    /// Set the task running the process at the given index so it can be waited on at shutdown.
+    pub fn set_task(&mut self, index: usize, task: JoinHandle<()>) {
+        self.tasks[index] = Some(task);
+    }
+
+    /// Stop every process and wait for the tasks running them to finish so their final states
+    /// are kept. Sending a process anything after this is an error. Tasks that don't all stop in
+    /// time (like one waiting on input from another process) are aborted.
+    pub async fn shutdown(&mut self) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(processes = self.names.len(), "shutdown");
+        for notifier in self.notifiers.iter_mut().filter_map(Option::take) {
+            let _ = notifier.try_send(Notification::Interrupt);
+        }
+        for sender in self.inputs.iter().flatten() {
+            sender.close();
+        }
+        let tasks = self
+            .tasks
+            .iter_mut()
+            .filter_map(Option::take)
+            .collect::<Vec<_>>();
+        let aborts = tasks
+            .iter()
+            .map(JoinHandle::abort_handle)
+            .collect::<Vec<_>>();
+        if tokio::time::timeout(Duration::from_secs(1), futures::future::join_all(tasks))
+            .await
+            .is_err()
+        {
+            for abort in aborts {
+                abort.abort();
+            }
+        }
+    }
+
+    /// Set the history the process at the given index records the instructions it runs into.
+    pub fn set_history(&mut self, index: usize, history: History) {
+        self.histories[index] = history;
+    }
+
+    /// Get the name of the process at the given index.
+    pub fn name(&self, index: usize) -> &str {
+        &self.names[index]
+    }
+
+    /// Get the names of all the processes.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Get the last error the process at the given index ran into, if it hasn't been reset since.
+    pub fn last_error(&self, index: usize) -> Option<String> {
+        self.histories[index].error()
+    }
+
+    /// Get the history of the instructions the process at the given index has run.
+    pub fn history(&self, index: usize) -> &History {
+        &self.histories[index]
+    }
+
+    /// Get the symbols for the process at the given index.
+    pub fn symbols(&self, index: usize) -> &SymbolTable {
+        &self.symbols[index]
+    }
+
+    /// Get a copy of the state of the process at the given index. Unlike [App::states], only the
+    /// one process is copied.
+    pub fn state(&self, index: usize) -> State {
+        self.states[index].lock().unwrap().clone()
+    }
+
+    /// Look at the state of the process at the given index without copying it. The process can't
+    /// run an instruction until the function returns, so keep it quick.
+    pub fn with_state<R>(&self, index: usize, f: impl FnOnce(&State) -> R) -> R {
+        f(&self.states[index].lock().unwrap())
+    }
+
+    /// Get copies of the states of all the processes.
     pub fn states(&self) -> Vec<State> {
         self.states
             .iter()
@@ -63,3 +847,357 @@ impl App {
             .collect()
     }
 }
+
+/// A process in the app that scripts can drive (see [crate::script]). The script runs on one of
+/// tokio's worker threads and waits on the process there, so this needs the multi-threaded
+/// runtime.
+pub struct AppTarget {
+    app: App,
+    index: usize,
+    // A run that stopped to wait for input. It's been interrupted, so it stops once it reads the
+    // input, before the next instruction.
+    pending: Option<oneshot::Receiver<bool>>,
+}
+
+impl AppTarget {
+    pub fn new(app: App, index: usize) -> Self {
+        Self {
+            app,
+            index,
+            pending: None,
+        }
+    }
+
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    /// Give the app back once the script is done with it.
+    pub fn into_app(self) -> App {
+        self.app
+    }
+
+    // Let a run that was waiting for input finish reading it. This returns why the process is
+    // stopped if it's still waiting (or halted) and nothing if it moved past the input.
+    fn settle(&mut self) -> Result<Option<&'static str>> {
+        let Some(done) = self.pending.take() else {
+            return Ok(None);
+        };
+        match self.wait(done)? {
+            reason @ ("waiting for input" | "halted") => Ok(Some(reason)),
+            _ => Ok(None),
+        }
+    }
+
+    // Whether the process is stuck on an input instruction with nothing to read.
+    fn blocked(&self) -> bool {
+        self.app.history(self.index).timing().status() == ProcessStatus::Blocked
+            && self
+                .app
+                .channels
+                .iter()
+                .zip(&self.app.endpoints)
+                .filter(|(_, (_, reader))| *reader == Some(self.index))
+                .all(|(channel, _)| channel.buffer().is_empty())
+    }
+
+    // Wait for the process to finish what it was told to do and say why it stopped. If it waits
+    // for input along the way, we stop waiting on it and keep the rest for the next run. It has to
+    // look stuck twice in a row, since the status lags behind a value that was just read.
+    fn wait(&mut self, mut done: oneshot::Receiver<bool>) -> Result<&'static str> {
+        let mut stuck = 0;
+        let hit = block(async {
+            loop {
+                tokio::select! {
+                    hit = &mut done => return Some(hit),
+                    _ = tokio::time::sleep(Duration::from_millis(1)) => {
+                        stuck = match self.blocked() {
+                            true => stuck + 1,
+                            false => 0,
+                        };
+                        if stuck > 1 {
+                            return None;
+                        }
+                    }
+                }
+            }
+        });
+        let Some(hit) = hit else {
+            block(self.app.interrupt(self.index))?;
+            self.pending = Some(done);
+            return Ok("waiting for input");
+        };
+        if let Some(error) = self.app.last_error(self.index) {
+            return Err(anyhow!(error));
+        }
+        Ok(match hit? {
+            true => "breakpoint",
+            false if self.app.with_state(self.index, |state| state.halted) => "halted",
+            false => "stopped",
+        })
+    }
+}
+
+// Wait on the future from a script running on one of the runtime's worker threads.
+fn block<F: Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
+impl Target for AppTarget {
+    fn input(&mut self, values: &[isize]) -> Result<()> {
+        block(self.app.send_values(self.index, values))
+    }
+
+    fn advance(&mut self, mut count: usize) -> Result<()> {
+        // Reading the input counts as a step.
+        if self.pending.is_some() {
+            match self.settle()? {
+                Some(_) => return Ok(()),
+                None => count = count.saturating_sub(1),
+            }
+        }
+        let done = block(
+            self.app
+                .step_count_with(self.index, count, Breakpoints::default()),
+        )?;
+        self.wait(done)?;
+        Ok(())
+    }
+
+    fn resume(&mut self, breakpoints: &Breakpoints) -> Result<&'static str> {
+        // A new run doesn't check the instruction it starts on, so we check the one after the
+        // input ourselves.
+        if self.pending.is_some() {
+            if let Some(reason) = self.settle()? {
+                return Ok(reason);
+            }
+            let stop = self.app.with_state(self.index, |state| {
+                state
+                    .next_instruction()
+                    .is_some_and(|(instruction, _)| breakpoints.evaluate(state, &instruction))
+            });
+            if stop {
+                return Ok("breakpoint");
+            }
+        }
+        let done = block(
+            self.app
+                .step_count_with(self.index, usize::MAX, breakpoints.clone()),
+        )?;
+        self.wait(done)
+    }
+
+    fn poke(&mut self, address: usize, value: isize) -> Result<()> {
+        // The process only takes notifications between instructions.
+        if self.settle()?.is_some_and(|reason| reason != "halted") {
+            return Err(anyhow!(
+                "can't change memory while the process waits for input"
+            ));
+        }
+        block(self.app.set_memory(self.index, address, value))?;
+        // Taking no steps is done once the memory is set, since notifications are handled in
+        // order.
+        let done = block(
+            self.app
+                .step_count_with(self.index, 0, Breakpoints::default()),
+        )?;
+        self.wait(done)?;
+        Ok(())
+    }
+
+    fn outputs(&mut self) -> Vec<isize> {
+        self.app.take_output(self.index)
+    }
+
+    fn state(&self) -> Cow<'_, State> {
+        Cow::Owned(self.app.state(self.index))
+    }
+
+    fn peek(&self, address: usize) -> isize {
+        self.app.with_state(self.index, |state| state[address])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::script::Script;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_app_target() {
+        let mut app = App::new(Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        let index = app
+            .open("adder", "3,9,1001,9,21,10,4,10,99,0,0")
+            .await
+            .unwrap();
+        let mut target = AppTarget::new(app, index);
+        let script = "
+            run
+            input 21
+            break output 42
+            run
+            assert mem[10] == 42
+            poke 100 7
+            run
+            output
+            dump 99..101
+        "
+        .parse::<Script>()
+        .unwrap();
+        assert_eq!(
+            script.run(&mut target).unwrap(),
+            vec![
+                "stopped: waiting for input",
+                "stopped: breakpoint",
+                "stopped: halted",
+                "output: 42",
+                "00000099: 0,7",
+            ]
+        );
+        assert!(target.into_app().state(index).halted);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_shutdown() {
+        // The amplifier loop from day 7, where the first channel is both the app's input and the
+        // last amplifier's output, so shutting down closes a channel a running process writes to.
+        let program = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,\
+                       28,6,99,0,0,5";
+        let (channel, mut sender, mut receiver) = Channel::new(false);
+        let first = sender.clone();
+        let mut channels = vec![channel];
+        let mut states = Vec::new();
+        let mut notifiers = Vec::new();
+        let mut tasks = Vec::new();
+        for phase in [9, 8, 7, 6, 5] {
+            sender.send(phase).await.unwrap();
+            let (channel, next_sender, next_receiver) = Channel::new(false);
+            let next_sender = match phase {
+                5 => first.clone(),
+                _ => next_sender,
+            };
+            channels.push(channel);
+
+            let process = Process::new(program, receiver, next_sender.clone());
+            let state = Arc::new(Mutex::new(process.state()));
+            let (notifier, notifier_receiver) = mpsc::channel(32);
+            tasks.push(
+                run_process(
+                    notifier_receiver,
+                    process,
+                    state.clone(),
+                    History::default(),
+                )
+                .await,
+            );
+            states.push(state);
+            notifiers.push(notifier);
+            (sender, receiver) = (next_sender, next_receiver);
+        }
+
+        let names = (0..5).map(|i| format!("AMP {}", i)).collect();
+        let mut app = App::new(names, channels, states, notifiers);
+        app.set_input(0, first.clone());
+        for (index, task) in tasks.into_iter().enumerate() {
+            app.set_task(index, task);
+            app.step_until(index).await.unwrap();
+        }
+        first.clone().send(0).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(2), app.shutdown())
+            .await
+            .unwrap();
+        for index in 0..5 {
+            assert_eq!(
+                app.interrupt(index).await.unwrap_err().to_string(),
+                format!("AMP {} has been shut down", index)
+            );
+            assert!(app.set_memory(index, 0, 1).await.is_err());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_export() {
+        let dir = std::env::temp_dir().join(format!("export-{}", std::process::id()));
+        let mut app = App::new(Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        let index = app.open("counter", "104,7,104,8,99").await.unwrap();
+        app.add_breakpoint(Scope::Global, Breakpoint::InstructionPointer(4));
+        app.step_count_with(index, 10, Breakpoints::default())
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+
+        app.export(index, &dir).unwrap();
+        let read = |name| std::fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("breakpoints.txt"), "* ip 4\n");
+        assert_eq!(read("output.txt"), "7\n8\n");
+        assert_eq!(read("memory.txt"), "104,7,104,8,99\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_breakpoint_list() {
+        use crate::breakpoint::Predicate;
+
+        let path = std::env::temp_dir().join(format!("breakpoints-{}.txt", std::process::id()));
+        let deep = Breakpoint::RelativeBaseChanged(Some(Predicate::new(|base| base > 5)));
+        let mut app = App::new(Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        app.add_breakpoint(Scope::Global, Breakpoint::InstructionPointer(4));
+        app.add_breakpoint(Scope::Process(1), deep.clone());
+        app.set_breakpoint_action(0, "log # {mem[9]}".parse().unwrap());
+        assert_eq!(app.save_breakpoints(&path).unwrap(), vec![deep]);
+
+        // Only a whole line can be a comment, so the `#` in the message is kept.
+        let list = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, format!("# saved by the test\n{}", list)).unwrap();
+        let mut loaded = App::new(Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        loaded.load_breakpoints(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.breakpoints(), &app.breakpoints()[..1]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_session() {
+        use crate::breakpoint::Predicate;
+
+        let path = std::env::temp_dir().join(format!("session-{}.json", std::process::id()));
+        let deep = Breakpoint::RelativeBaseChanged(Some(Predicate::new(|base| base > 5)));
+        let mut app = App::new(Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        app.add_breakpoint(Scope::Global, Breakpoint::InstructionPointer(4));
+        app.add_breakpoint(Scope::Process(1), deep.clone());
+        app.set_breakpoint_action(0, "log # {mem[9]}".parse().unwrap());
+        app.add_watch("mem[9] + rb".parse().unwrap());
+        assert_eq!(app.save_session(&path).unwrap(), vec![deep]);
+
+        let mut loaded = App::new(Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        loaded.load_session(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.breakpoints(), &app.breakpoints()[..1]);
+        assert_eq!(loaded.watches(), app.watches());
+    }
+
+    #[cfg(feature = "rhai")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_app_target_rhai() {
+        use std::{cell::RefCell, rc::Rc};
+
+        let mut app = App::new(Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        let index = app
+            .open("adder", "3,9,1001,9,21,10,4,10,99,0,0")
+            .await
+            .unwrap();
+        let target = Rc::new(RefCell::new(AppTarget::new(app, index)));
+        let script = r#"
+            input(21);
+            step(2);
+            print(`${ip()} ${read(10)}`);
+            print(run());
+            print(output());
+        "#
+        .parse::<crate::script::RhaiScript>()
+        .unwrap();
+        assert_eq!(script.run(&target).unwrap(), vec!["6 42", "halted", "[42]"]);
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+
+use crate::machine::Machine;
+
+/// The tile ids the day 13 arcade game draws with.
+pub const PADDLE: isize = 3;
+pub const BALL: isize = 4;
+
+/// Plays the day 13 arcade game by keeping the paddle under the ball. Give it everything the game
+/// draws and it says which way to push the joystick.
+#[derive(Debug, Clone, Default)]
+pub struct AutoPlayer {
+    partial: Vec<isize>,
+    ball: Option<isize>,
+    paddle: Option<isize>,
+    /// The last score the game showed.
+    pub score: isize,
+}
+
+impl AutoPlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle a single value the game output. Every three values are an x, y, and tile id (or
+    /// -1, 0, and the score).
+    pub fn output(&mut self, value: isize) {
+        self.partial.push(value);
+        if let [x, y, tile] = self.partial[..] {
+            self.partial.clear();
+            self.update(x, y, tile);
+        }
+    }
+
+    /// Handle the game drawing the tile at x and y, or showing the score if x is -1.
+    pub fn update(&mut self, x: isize, y: isize, tile: isize) {
+        match (x, y, tile) {
+            (-1, 0, score) => self.score = score,
+            (x, _, PADDLE) => self.paddle = Some(x),
+            (x, _, BALL) => self.ball = Some(x),
+            _ => (),
+        }
+    }
+
+    /// The joystick position that moves the paddle toward the ball: -1 for left, 0 for neutral,
+    /// and 1 for right.
+    pub fn joystick(&self) -> isize {
+        match (self.ball, self.paddle) {
+            (Some(ball), Some(paddle)) => (ball - paddle).signum(),
+            _ => 0,
+        }
+    }
+
+    /// Put in quarters and play the game in the program until it's over, returning the final
+    /// score.
+    pub fn play(program: &str) -> Result<isize> {
+        let mut machine = Machine::new(program);
+        machine.set_memory(0, 2);
+        let mut player = Self::new();
+        while !machine.halted() {
+            machine.run(usize::MAX)?;
+            machine
+                .poll_output()
+                .into_iter()
+                .for_each(|value| player.output(value));
+            if machine.waiting() {
+                machine.push_input(player.joystick());
+            }
+        }
+        Ok(player.score)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_auto_player() {
+        // Draw the ball at 5 and the paddle at 3, then show the joystick position as the score.
+        let program = "1,0,0,30,104,5,104,0,104,4,104,3,104,0,104,3,3,31,104,-1,104,0,4,31,99";
+        assert_eq!(AutoPlayer::play(program).unwrap(), 1);
+
+        let mut player = AutoPlayer::new();
+        assert_eq!(player.joystick(), 0);
+        for value in [6, 2, BALL, 9, 20, PADDLE] {
+            player.output(value);
+        }
+        assert_eq!(player.joystick(), -1);
+    }
+}
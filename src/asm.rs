@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    instruction::{Instruction, Operation},
+    parameter::Parameter,
+    symbols::SymbolTable,
+};
+
+/// Assemble the given source into an Intcode program. Each line holds a single instruction written
+/// the same way instructions are displayed (e.g. `ADD P[1] + I[2] -> R[3]`), a macro, or a
+/// directive. Parameters are written as `P[n]` for position, `I[n]` for immediate, and `R[n]` for
+/// relative mode, where `n` is a number, a label, or a constant. Mnemonics are case insensitive and
+/// anything after a `;` is a comment.
+///
+/// A line can start with any number of labels (e.g. `loop:`), which take the address of whatever
+/// comes next. The directives are:
+///
+/// - `.word a, b, ...` (or `DAT`) emits the given values.
+/// - `.data "text", a, ...` emits the characters of strings and the given values.
+/// - `.const NAME value` defines a constant.
+///
+/// The macros use the relative base as a stack pointer that points at the next free cell, so the
+/// program should point it at some free memory (e.g. `ARO I[stack]`) before using them:
+///
+/// - `PUSH x` pushes the value of `x`.
+/// - `POP x` pops the top of the stack into `x`.
+/// - `CALL x` pushes the return address and jumps to `x`.
+/// - `RET` pops the return address and jumps to it.
+///
+/// Inside a function, `R[-1]` is the return address and `R[-2]` is the last thing pushed before the
+/// call.
+pub fn assemble(source: &str) -> Result<Vec<isize>> {
+    Ok(assemble_with_symbols(source)?.0)
+}
+
+/// Assemble the given source like [assemble] and also return a symbol table with the address of
+/// every label.
+pub fn assemble_with_symbols(source: &str) -> Result<(Vec<isize>, SymbolTable)> {
+    let mut assembler = Assembler::default();
+    for (number, line) in source.lines().enumerate() {
+        assembler.line = number + 1;
+        assembler
+            .assemble_line(line)
+            .map_err(|e| anyhow!("line {}: {}", number + 1, e))?;
+    }
+    let labels = std::mem::take(&mut assembler.labels);
+    Ok((assembler.resolve()?, labels))
+}
+
+// A value in the source. Symbols are resolved once we know where all the labels are.
+#[derive(Debug, Clone)]
+enum Value {
+    Literal(isize),
+    Symbol(String),
+}
+
+// A parameter whose value may not be known yet. The parameter carries the mode and the value is
+// filled in when the program is resolved.
+#[derive(Debug, Clone)]
+struct Operand {
+    parameter: Parameter,
+    value: Value,
+}
+
+impl Operand {
+    fn immediate(value: isize) -> Self {
+        Self {
+            parameter: Parameter::Immediate(0),
+            value: Value::Literal(value),
+        }
+    }
+
+    fn relative(offset: isize) -> Self {
+        Self {
+            parameter: Parameter::Relative(0),
+            value: Value::Literal(offset),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Assembler {
+    // The cells of the program along with the line they came from, for error messages.
+    cells: Vec<(Value, usize)>,
+    symbols: HashMap<String, isize>,
+    labels: SymbolTable,
+    line: usize,
+}
+
+impl Assembler {
+    fn assemble_line(&mut self, line: &str) -> Result<()> {
+        let mut line = strip_comment(line).trim();
+
+        // Peel off any labels at the start of the line.
+        while let Some((label, rest)) = line.split_once(':') {
+            if !is_identifier(label.trim()) {
+                break;
+            }
+            self.define(label.trim(), self.cells.len() as isize)?;
+            self.labels.insert(self.cells.len(), label.trim());
+            line = rest.trim();
+        }
+
+        let (mnemonic, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let mnemonic = mnemonic.to_uppercase();
+        let rest = rest.trim();
+        if mnemonic.is_empty() {
+            return Ok(());
+        }
+        if mnemonic == ".DATA" {
+            return self.data(rest);
+        }
+
+        // The operators are only there to make it read like the display format, so we can ignore
+        // them.
+        let tokens = rest
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .filter(|token| !matches!(*token, "+" | "*" | "<" | "==" | "->"))
+            .collect::<Vec<_>>();
+
+        match mnemonic.as_str() {
+            ".WORD" | "DAT" => {
+                for token in tokens {
+                    let value = parse_value(token)?;
+                    self.cells.push((value, self.line));
+                }
+                Ok(())
+            }
+            ".CONST" => match tokens[..] {
+                [name, value] if is_identifier(name) => {
+                    let value = value
+                        .parse()
+                        .map_err(|_| anyhow!("invalid constant value '{}'", value))?;
+                    self.define(name, value)
+                }
+                _ => Err(anyhow!(".const expects a name and a value")),
+            },
+            "PUSH" | "POP" | "CALL" | "RET" => {
+                let operands = parse_operands(&tokens)?;
+                self.expand(&mnemonic, &operands)
+            }
+            _ => {
+                let operands = parse_operands(&tokens)?;
+                self.instruction(&mnemonic, &operands)
+            }
+        }
+    }
+
+    // Emit a single instruction.
+    fn instruction(&mut self, mnemonic: &str, operands: &[Operand]) -> Result<()> {
+        let operation = Operation::from_mnemonic(mnemonic)
+            .ok_or_else(|| anyhow!("unknown instruction '{}'", mnemonic))?;
+        let parameters = operands
+            .iter()
+            .map(|operand| operand.parameter)
+            .collect::<Vec<_>>();
+        let instruction = Instruction::new(operation, &parameters)?;
+
+        // We only need the opcode from the encoding. The values come from the operands.
+        let opcode = instruction.encode()[0];
+        self.cells.push((Value::Literal(opcode), self.line));
+        for operand in operands {
+            self.cells.push((operand.value.clone(), self.line));
+        }
+        Ok(())
+    }
+
+    // Expand one of the stack macros.
+    fn expand(&mut self, mnemonic: &str, operands: &[Operand]) -> Result<()> {
+        let top = Operand::relative(0);
+        match (mnemonic, operands) {
+            ("PUSH", [value]) => {
+                self.instruction("ADD", &[value.clone(), Operand::immediate(0), top])?;
+                self.instruction("ARO", &[Operand::immediate(1)])
+            }
+            ("POP", [dest]) => {
+                self.instruction("ARO", &[Operand::immediate(-1)])?;
+                self.instruction("ADD", &[top, Operand::immediate(0), dest.clone()])
+            }
+            ("CALL", [target]) => {
+                // The return address is just past the 9 cells this expands to.
+                let ret = Operand::immediate(self.cells.len() as isize + 9);
+                self.instruction("ADD", &[ret, Operand::immediate(0), top])?;
+                self.instruction("ARO", &[Operand::immediate(1)])?;
+                self.instruction("JIT", &[Operand::immediate(1), target.clone()])
+            }
+            ("RET", []) => {
+                self.instruction("ARO", &[Operand::immediate(-1)])?;
+                self.instruction("JIT", &[Operand::immediate(1), top])
+            }
+            ("RET", _) => Err(anyhow!("RET expects no parameters")),
+            _ => Err(anyhow!("{} expects 1 parameter", mnemonic)),
+        }
+    }
+
+    // Emit the strings and values of a `.data` directive.
+    fn data(&mut self, rest: &str) -> Result<()> {
+        let mut chars = rest.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() || c == ',' {
+                chars.next();
+            } else if c == '"' {
+                chars.next();
+                loop {
+                    let c = match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => '\n',
+                            Some(c @ ('"' | '\\')) => c,
+                            _ => return Err(anyhow!("invalid escape in string")),
+                        },
+                        Some(c) => c,
+                        None => return Err(anyhow!("unterminated string")),
+                    };
+                    self.cells.push((Value::Literal(c as isize), self.line));
+                }
+            } else {
+                let mut token = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == ',' {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+                let value = parse_value(&token)?;
+                self.cells.push((value, self.line));
+            }
+        }
+        Ok(())
+    }
+
+    fn define(&mut self, name: &str, value: isize) -> Result<()> {
+        if self.symbols.insert(name.to_string(), value).is_some() {
+            return Err(anyhow!("'{}' is already defined", name));
+        }
+        Ok(())
+    }
+
+    // Replace all the symbols with their values.
+    fn resolve(self) -> Result<Vec<isize>> {
+        self.cells
+            .into_iter()
+            .map(|(value, line)| match value {
+                Value::Literal(value) => Ok(value),
+                Value::Symbol(name) => self
+                    .symbols
+                    .get(&name)
+                    .copied()
+                    .ok_or_else(|| anyhow!("line {}: undefined symbol '{}'", line, name)),
+            })
+            .collect()
+    }
+}
+
+// Remove the comment from a line, ignoring any `;` inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ';' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn is_identifier(token: &str) -> bool {
+    let mut chars = token.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_value(token: &str) -> Result<Value> {
+    if let Ok(value) = token.parse() {
+        Ok(Value::Literal(value))
+    } else if is_identifier(token) {
+        Ok(Value::Symbol(token.to_string()))
+    } else {
+        Err(anyhow!("invalid value '{}'", token))
+    }
+}
+
+fn parse_operands(tokens: &[&str]) -> Result<Vec<Operand>> {
+    tokens.iter().map(|token| parse_operand(token)).collect()
+}
+
+// Parse a parameter in the `P[n]`, `I[n]`, or `R[n]` format.
+fn parse_operand(token: &str) -> Result<Operand> {
+    let invalid = || anyhow!("invalid parameter '{}'", token);
+    let (mode, value) = token.split_once('[').ok_or_else(invalid)?;
+    let value = parse_value(value.strip_suffix(']').ok_or_else(invalid)?)?;
+    let parameter = match mode.to_uppercase().as_str() {
+        "P" => Parameter::Position(0),
+        "I" => Parameter::Immediate(0),
+        "R" => Parameter::Relative(0),
+        _ => return Err(invalid()),
+    };
+    Ok(Operand { parameter, value })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            ; Add two numbers and print the result.
+            ADD I[1] + I[2] -> P[9]
+            out -> P[9]
+            ARO R[-3]
+            HLT
+            DAT 0
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            vec![1101, 1, 2, 9, 4, 9, 209, -3, 99, 0]
+        );
+        assert!(assemble("ADD I[1] -> P[3]").is_err());
+        assert!(assemble("NOP").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_assemble_labels_and_macros() {
+        use crate::{ipc::Channel, process::Process};
+        use futures::StreamExt;
+
+        let source = r#"
+            .const ANSWER 21
+                    ARO I[stack]
+                    PUSH I[ANSWER]
+                    CALL I[double]
+                    POP P[result]
+                    OUT P[result]
+                    OUT P[text]
+                    HLT
+            double: ADD R[-2] + R[-2] -> R[-2]
+                    RET
+            result: .word 0
+            text:   .data "*;"
+            stack:
+        "#;
+        let program = assemble(source).unwrap();
+
+        let (_, _input_sender, input_receiver) = Channel::new(true);
+        let (_, output_sender, output_receiver) = Channel::new(true);
+        let program = program
+            .iter()
+            .map(|cell| cell.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut computer = Process::new(&program, input_receiver, output_sender);
+        tokio::spawn(async move { computer.run().await });
+        let outputs = output_receiver.collect::<Vec<_>>().await;
+        assert_eq!(outputs, vec![42, '*' as isize]);
+
+        assert!(assemble("JIT I[1] -> I[nowhere]").is_err());
+    }
+}
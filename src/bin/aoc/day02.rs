@@ -0,0 +1,47 @@
+use anyhow::{anyhow, Result};
+use futures::future::LocalBoxFuture;
+use intcode::{
+    ipc::Channel,
+    process::Process,
+    search::{parallel_find, Params},
+    solutions::Solution,
+};
+
+pub struct Day02;
+
+impl Solution for Day02 {
+    fn day(&self) -> usize {
+        2
+    }
+
+    fn part1(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        // For part 1, we can simply run the program with the two given inputs.
+        Box::pin(async move { Ok(run(input, 12, 2).await?.to_string()) })
+    }
+
+    fn part2(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(part2(input))
+    }
+}
+
+// Run the program with the given noun and verb and get the value it leaves at address 0.
+async fn run(input: &str, noun: isize, verb: isize) -> Result<isize> {
+    let (_, sender, receiver) = Channel::new(true);
+    let mut process = Process::new(input, receiver, sender.clone());
+    process.set_memory(1, noun);
+    process.set_memory(2, verb);
+    process.run().await?;
+    Ok(process.state()[0])
+}
+
+async fn part2(input: &str) -> Result<String> {
+    // For part 2, we are looking for a specific output. The numbers are small enough to brute
+    // force, so we just look for the correct output, trying many at once.
+    let params = (0..=99)
+        .flat_map(|noun| (0..=99).map(move |verb| Params::patch(vec![(1, noun), (2, verb)])));
+    let found = parallel_find(input, params, |_, outcome| outcome.state[0] == 19_690_720)
+        .await?
+        .ok_or_else(|| anyhow!("no noun and verb give 19690720"))?;
+    let (noun, verb) = (found.patch[0].1, found.patch[1].1);
+    Ok((100 * noun + verb).to_string())
+}
@@ -0,0 +1,51 @@
+use anyhow::{anyhow, Result};
+use futures::{future::LocalBoxFuture, StreamExt};
+use intcode::{ipc::Channel, process::Process, solutions::Solution};
+
+pub struct Day05;
+
+impl Solution for Day05 {
+    fn day(&self) -> usize {
+        5
+    }
+
+    fn part1(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(part1(input))
+    }
+
+    fn part2(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(part2(input))
+    }
+}
+
+async fn part1(input: &'static str) -> Result<String> {
+    // Part 1 - Once we've added the given features to our Intcode computer, we can simply run it.
+    // We'll want to make sure we get all zeros except for the last value. Then the last value is
+    // the answer.
+    let (_, mut input_sender, input_receiver) = Channel::new(true);
+    let (_, output_sender, output_receiver) = Channel::new(true);
+    let mut computer = Process::new(input, input_receiver, output_sender);
+    tokio::spawn(async move { computer.run().await });
+    input_sender.send(1).await?;
+
+    let outputs = output_receiver.collect::<Vec<_>>().await;
+    let (last, tests) = outputs.split_last().ok_or(anyhow!("no value received"))?;
+    if tests.iter().any(|&x| x != 0) {
+        return Err(anyhow!("diagnostic tests failed: {:?}", tests));
+    }
+    Ok(last.to_string())
+}
+
+async fn part2(input: &'static str) -> Result<String> {
+    // Part 2 - We can do the same thing as part 1 but with a different input.
+    let (_, mut input_sender, input_receiver) = Channel::new(true);
+    let (_, output_sender, mut output_receiver) = Channel::new(true);
+    let mut computer = Process::new(input, input_receiver, output_sender);
+    tokio::spawn(async move { computer.run().await });
+    input_sender.send(5).await?;
+    let p2 = output_receiver
+        .next()
+        .await
+        .ok_or(anyhow!("no value received"))?;
+    Ok(p2.to_string())
+}
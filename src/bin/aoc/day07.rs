@@ -0,0 +1,90 @@
+use intcode::ipc::RecvResult;
+use intcode::pipeline;
+use intcode::solutions::Solution;
+
+use anyhow::{anyhow, Result};
+use futures::future::LocalBoxFuture;
+use tokio::sync::mpsc::{self, Sender};
+
+pub struct Day07;
+
+impl Solution for Day07 {
+    fn day(&self) -> usize {
+        7
+    }
+
+    fn part1(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(part1(input))
+    }
+
+    fn part2(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(part2(input))
+    }
+}
+
+async fn part1_run_permutation(
+    input: &'static str,
+    permutation: &[usize],
+    output: Sender<isize>,
+) -> Result<()> {
+    let phases = permutation.iter().map(|&p| p as isize).collect::<Vec<_>>();
+    let (mut first, mut last) = pipeline::chain(input, &phases).await?;
+
+    // The last value on the final channel is the solution for this permutation.
+    first.send(0).await?;
+    match last.recv().await {
+        RecvResult::Value(value) => Ok(output.send(value).await?),
+        _ => Err(anyhow!("no value received")),
+    }
+}
+
+async fn part1(input: &'static str) -> Result<String> {
+    let (max_send, mut max_recv) = mpsc::channel(10);
+
+    // Create a new task for each permutation.
+    for permutation in icub3d_combinatorics::Permutation::new(5) {
+        let sender = max_send.clone();
+        tokio::spawn(async move { part1_run_permutation(input, &permutation, sender).await });
+    }
+
+    // We don't need the original sender anymore and recv() will block forever if we don't drop it.
+    drop(max_send);
+
+    // Find the maximum value from all of the permutations.
+    let mut p1 = 0;
+    while let Some(max) = max_recv.recv().await {
+        p1 = p1.max(max);
+    }
+    Ok(p1.to_string())
+}
+
+async fn part2_run_permutation(
+    input: &'static str,
+    permutation: &[usize],
+    output: Sender<isize>,
+) -> Result<()> {
+    // This is similar to part 1 but the amplifiers are in a feedback loop.
+    let phases = permutation
+        .iter()
+        .map(|&p| p as isize + 5)
+        .collect::<Vec<_>>();
+    let (mut first, signal) = pipeline::feedback_loop(input, &phases).await?;
+    first.send(0).await?;
+    let signal = signal.await??.ok_or_else(|| anyhow!("no signal"))?;
+    Ok(output.send(signal).await?)
+}
+
+async fn part2(input: &'static str) -> Result<String> {
+    // Similar to part 1, we need to create a new task for each permutation and then find the maximum.
+    let (max_send, mut max_recv) = mpsc::channel(10);
+    for permutation in icub3d_combinatorics::Permutation::new(5) {
+        let sender = max_send.clone();
+        tokio::spawn(async move { part2_run_permutation(input, &permutation, sender).await });
+    }
+    drop(max_send);
+    let mut max = 0;
+    while let Some(value) = max_recv.recv().await {
+        max = max.max(value);
+    }
+    Ok(max.to_string())
+}
@@ -0,0 +1,38 @@
+use anyhow::{anyhow, Result};
+use futures::{future::LocalBoxFuture, StreamExt};
+use intcode::{ipc::Channel, process::Process, solutions::Solution};
+
+pub struct Day09;
+
+impl Solution for Day09 {
+    fn day(&self) -> usize {
+        9
+    }
+
+    fn part1(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(run(input, 1))
+    }
+
+    fn part2(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(run(input, 2))
+    }
+}
+
+// Once we have the updates to the Intcode computer, we can use the new code to run the input
+// program. For part 1, we send a 1. For part 2, we send a 2.
+async fn run(input: &'static str, part: isize) -> Result<String> {
+    let (_, mut tx, rx) = Channel::new(true);
+    let (_, tx2, output) = Channel::new(true);
+    tx.send(part).await?;
+    tokio::spawn(async move {
+        let mut process = Process::new(input, rx, tx2);
+        process.run().await
+    });
+
+    // The program only outputs more than one value if something is wrong with the computer.
+    let outputs = output.collect::<Vec<_>>().await;
+    match outputs.as_slice() {
+        [value] => Ok(value.to_string()),
+        _ => Err(anyhow!("unexpected outputs: {:?}", outputs)),
+    }
+}
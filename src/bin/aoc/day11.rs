@@ -0,0 +1,47 @@
+use intcode::ocr;
+use intcode::robot::{TurtleRobot, BLACK, WHITE};
+use intcode::solutions::Solution;
+
+use anyhow::{anyhow, Result};
+use futures::future::LocalBoxFuture;
+
+pub struct Day11;
+
+impl Solution for Day11 {
+    fn day(&self) -> usize {
+        11
+    }
+
+    fn part1(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(part1(input))
+    }
+
+    fn part2(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(part2(input))
+    }
+}
+
+async fn part1(input: &'static str) -> Result<String> {
+    // For part 1, start on a black panel and then run the robot.
+    let robot = TurtleRobot::run(input, BLACK).await?;
+    Ok(robot.grid().len().to_string())
+}
+
+async fn part2(input: &'static str) -> Result<String> {
+    // For part 2, start on a white panel and then run the robot.
+    let robot = TurtleRobot::run(input, WHITE).await?;
+
+    // The answer is the letters it paints.
+    let invalid = robot
+        .grid()
+        .iter()
+        .map(|(_, &color)| color)
+        .find(|color| !(BLACK..=WHITE).contains(color));
+    if let Some(color) = invalid {
+        return Err(anyhow!("invalid color {}", color));
+    }
+
+    // The letters are read for us if we can. Otherwise, they're shown so they can be read by eye.
+    let text = robot.ascii();
+    Ok(ocr::read_text(&text).unwrap_or_else(|_| format!("\n{}", text)))
+}
@@ -0,0 +1,319 @@
+use std::io::stdout;
+use std::time::Duration;
+
+use intcode::event::{Event, EventHandler};
+use intcode::grid::{Grid, Point};
+use intcode::oracle::Oracle;
+use intcode::renderer::ColorScheme;
+use intcode::solutions::Solution;
+
+use anyhow::{anyhow, Result};
+use crossterm::{
+    event::{KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use futures::future::LocalBoxFuture;
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Direction, Layout},
+    style::Style,
+    widgets::{block::Title, Block, BorderType, Borders, Paragraph},
+    Frame, Terminal,
+};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+// The width and height of Santa's ship.
+const SHIP: isize = 100;
+
+pub struct Day19;
+
+impl Solution for Day19 {
+    fn day(&self) -> usize {
+        19
+    }
+
+    fn part1(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(async move { Ok(part1(&Beam::new(input)).await?.to_string()) })
+    }
+
+    fn part2(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(async move { part2(&Beam::new(input)).await })
+    }
+
+    fn visualize(&self, input: &'static str) -> LocalBoxFuture<'static, Result<()>> {
+        Box::pin(visualize(input))
+    }
+}
+
+// The drone program, which tells the observer (if there is one) about every point it checks.
+struct Beam {
+    oracle: Oracle,
+    observer: Option<UnboundedSender<(Point, bool)>>,
+}
+
+impl Beam {
+    fn new(input: &'static str) -> Self {
+        Self {
+            oracle: Oracle::new(input),
+            observer: None,
+        }
+    }
+
+    async fn check(&self, x: isize, y: isize) -> Result<bool> {
+        Ok(self.check_all(y, &[x]).await?[0])
+    }
+
+    // Check the points of row y at once. The ones we haven't checked before are run in parallel.
+    async fn check_all(&self, y: isize, xs: &[isize]) -> Result<Vec<bool>> {
+        let questions = xs.iter().map(|&x| vec![x, y]).collect::<Vec<_>>();
+        let answers = self.oracle.query_all(&questions)?;
+        xs.iter()
+            .zip(answers)
+            .map(|(&x, answer)| {
+                let hit = match answer[..] {
+                    [hit] => hit == 1,
+                    _ => return Err(anyhow!("expected one output for {},{}", x, y)),
+                };
+                if let Some(observer) = &self.observer {
+                    let _ = observer.send((Point::new(x, y), hit));
+                }
+                Ok(hit)
+            })
+            .collect()
+    }
+}
+
+// The first and last x of the beam in a row.
+type Edges = (isize, isize);
+
+// Follow the edges of the beam down the first `size` rows. The beam only moves right as it goes
+// down, so each row is checked at once from the left edge of the row above to `size`. Rows near
+// the emitter can miss the beam entirely within `size` columns, and those are None. Further down,
+// the beam can be wider than that, so we keep stepping right until it ends.
+async fn follow(beam: &Beam, size: isize) -> Result<Vec<Option<Edges>>> {
+    let mut rows = Vec::new();
+    let mut left = 0;
+    for y in 0..size {
+        let xs = (left..size).collect::<Vec<_>>();
+        let hits = beam.check_all(y, &xs).await?;
+        let Some(start) = hits.iter().position(|&hit| hit) else {
+            rows.push(None);
+            continue;
+        };
+        let width = hits[start..].iter().take_while(|&&hit| hit).count() as isize;
+        left = xs[start];
+        let mut right = left + width - 1;
+        if right == size - 1 {
+            while beam.check(right + 1, y).await? {
+                right += 1;
+            }
+        }
+        rows.push(Some((left, right)));
+    }
+    Ok(rows)
+}
+
+async fn part1(beam: &Beam) -> Result<isize> {
+    Ok(follow(beam, 50)
+        .await?
+        .into_iter()
+        .flatten()
+        .map(|(left, right)| right.min(49) - left + 1)
+        .sum())
+}
+
+// How many points of a row are checked at once when searching for the left edge.
+const PROBES: isize = 16;
+
+// Find where the beam starts in row y. The beam is a cone from the emitter, so scaling the middle
+// of a row we know the edges of gives a point inside it. The edge is then searched for between the
+// emitter and there, checking evenly spaced points at once and narrowing to the gap between the
+// last miss and the first hit.
+async fn left(beam: &Beam, reference: (isize, Edges), y: isize) -> Result<isize> {
+    let (row, (left, right)) = reference;
+    let middle = (left + right) * y / (2 * row);
+    if !beam.check(middle, y).await? {
+        return Err(anyhow!("lost the beam at {},{}", middle, y));
+    }
+    let (mut low, mut high) = (0, middle);
+    while low < high {
+        let step = ((high - low) / PROBES).max(1);
+        let xs = (low..high).step_by(step as usize).collect::<Vec<_>>();
+        let hits = beam.check_all(y, &xs).await?;
+        match hits.iter().position(|&hit| hit) {
+            Some(0) => high = low,
+            Some(i) => (low, high) = (xs[i - 1] + 1, xs[i]),
+            None => low = xs[xs.len() - 1] + 1,
+        }
+    }
+    Ok(high)
+}
+
+// Where the ship's left edge would be if its bottom left corner were in row y, if it fits there.
+async fn fits(beam: &Beam, reference: (isize, Edges), y: isize) -> Result<Option<isize>> {
+    if y < SHIP - 1 {
+        return Ok(None);
+    }
+    let x = left(beam, reference, y).await?;
+    Ok(beam.check(x + SHIP - 1, y - SHIP + 1).await?.then_some(x))
+}
+
+async fn part2(beam: &Beam) -> Result<String> {
+    let reference = follow(beam, 50)
+        .await?
+        .into_iter()
+        .enumerate()
+        .rev()
+        .find_map(|(y, edges)| edges.map(|edges| (y as isize, edges)))
+        .ok_or_else(|| anyhow!("the beam isn't in the first 50 rows"))?;
+
+    // The beam gets wider as it goes, so double the row until the ship fits and then binary
+    // search for the first row it fits in.
+    let (mut low, mut high) = (reference.0, reference.0 * 2);
+    while fits(beam, reference, high).await?.is_none() {
+        (low, high) = (high, high * 2);
+    }
+    while high - low > 1 {
+        let y = (low + high) / 2;
+        match fits(beam, reference, y).await? {
+            Some(_) => high = y,
+            None => low = y,
+        }
+    }
+
+    // The edges of the beam are rounded, so the ship can occasionally fit in a row or two above
+    // one it doesn't fit in. We check a few rows above to be sure we have the first.
+    for y in (high - 10).max(SHIP - 1)..=high {
+        if let Some(x) = fits(beam, reference, y).await? {
+            return Ok((x * 10000 + y - SHIP + 1).to_string());
+        }
+    }
+    Err(anyhow!("the ship stopped fitting at row {}", high))
+}
+
+// Show every point the drone checks while solving part 2 until the user quits.
+async fn visualize(input: &'static str) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut search = tokio::spawn(async move {
+        let beam = Beam {
+            observer: Some(tx),
+            ..Beam::new(input)
+        };
+        part2(&beam).await
+    });
+
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+    execute!(stdout(), EnterAlternateScreen)?;
+    enable_raw_mode()?;
+    terminal.hide_cursor()?;
+    terminal.clear()?;
+
+    // Draw until the user quits. An error stops us too, but we still clean up the terminal.
+    let mut events = EventHandler::new(Duration::from_millis(16));
+    let mut probes = Grid::new();
+    let mut answer = None;
+    let result = async {
+        loop {
+            while let Ok((point, hit)) = rx.try_recv() {
+                probes.insert(point, hit);
+            }
+            terminal.draw(|f| ui(&probes, answer.as_deref(), f))?;
+
+            tokio::select! {
+                result = &mut search, if answer.is_none() => {
+                    answer = Some(match result? {
+                        Ok(answer) => answer,
+                        Err(e) => format!("error: {}", e),
+                    });
+                }
+                event = events.next() => match event? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => {
+                        if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                            return Ok(());
+                        }
+                    }
+                    Event::Error(e) => return Err(anyhow!(e)),
+                    _ => (),
+                },
+            }
+        }
+    }
+    .await;
+
+    search.abort();
+    disable_raw_mode()?;
+    execute!(stdout(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+fn ui(probes: &Grid<bool>, answer: Option<&str>, f: &mut Frame) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .split(f.size());
+
+    // Draw the title block.
+    let title_block = Block::default().style(
+        Style::default()
+            .fg(ColorScheme::Background.into())
+            .bg(ColorScheme::Violet.into()),
+    );
+    let title = Paragraph::new("TRACTOR BEAM")
+        .block(title_block)
+        .alignment(Alignment::Center);
+    f.render_widget(title, chunks[0]);
+
+    // Draw the status block.
+    let status_block = Block::default().style(
+        Style::default()
+            .fg(ColorScheme::Background.into())
+            .bg(ColorScheme::Green.into()),
+    );
+    let status = Paragraph::new(format!(
+        "Checked: {} | {} | (q) quit",
+        probes.len(),
+        answer.unwrap_or("searching"),
+    ))
+    .block(status_block)
+    .alignment(Alignment::Left);
+    f.render_widget(status, chunks[2]);
+
+    // The search goes far down the beam, so each cell of the grid is a square of points that's
+    // big enough for everything to fit. A cell shows a hit if any point in it was in the beam.
+    let area = chunks[1];
+    let width = area.width.saturating_sub(2).max(1) as isize;
+    let height = area.height.saturating_sub(2).max(1) as isize;
+    let (_, max) = probes.bounds().unwrap_or_default();
+    let scale = (max.x / width).max(max.y / height) + 1;
+    let mut cells = Grid::new();
+    for (point, &hit) in probes.iter() {
+        let cell = Point::new(point.x / scale, point.y / scale);
+        if hit || !cells.contains(cell) {
+            cells.insert(cell, hit);
+        }
+    }
+
+    let block = Block::default()
+        .title(Title::from(format!("BEAM (1:{})", scale)).alignment(Alignment::Center))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(ColorScheme::Orange.into()))
+        .border_type(BorderType::Rounded)
+        .style(
+            Style::default()
+                .fg(ColorScheme::White.into())
+                .bg(ColorScheme::Background.into()),
+        );
+    let grid = cells
+        .widget(|_, &hit| match hit {
+            true => Some(('#', ColorScheme::Yellow)),
+            false => Some(('.', ColorScheme::DarkGrey)),
+        })
+        .block(block);
+    f.render_widget(grid, area);
+}
@@ -0,0 +1,71 @@
+use futures::{future::LocalBoxFuture, StreamExt};
+use intcode::ipc::Channel;
+use intcode::process::Process;
+use intcode::solutions::Solution;
+
+use anyhow::{anyhow, Result};
+
+pub struct Day21;
+
+impl Solution for Day21 {
+    fn day(&self) -> usize {
+        21
+    }
+
+    fn part1(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(run(
+            input,
+            "NOT B J
+NOT C T
+OR T J
+AND D J
+NOT A T
+OR T J
+WALK
+",
+        ))
+    }
+
+    fn part2(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(run(
+            input,
+            "NOT B J
+NOT C T
+OR T J
+AND D J
+AND H J
+NOT A T
+OR T J
+RUN
+",
+        ))
+    }
+}
+
+// Give the springdroid the program and get the hull damage it reports. If it falls in a hole we
+// get a drawing of its last moments instead, which we use as the error.
+async fn run(input: &'static str, program: &'static str) -> Result<String> {
+    let (_, mut input_tx, input_rx) = Channel::new(true);
+    let (_, output_tx, mut output_rx) = Channel::new(true);
+    tokio::spawn(async move {
+        let mut process = Process::new(input, input_rx, output_tx);
+        process.run().await
+    });
+
+    tokio::spawn(async move {
+        let program = program.chars().map(|c| c as isize).collect::<Vec<_>>();
+        input_tx.send_slice(&program).await
+    });
+    let mut damage = None;
+    let mut text = String::new();
+    while let Some(output) = output_rx.next().await {
+        if output > 255 {
+            damage = Some(output);
+        } else {
+            text.push(output as u8 as char);
+        }
+    }
+    damage
+        .map(|damage| damage.to_string())
+        .ok_or_else(|| anyhow!("the springdroid fell:\n{}", text))
+}
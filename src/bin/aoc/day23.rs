@@ -0,0 +1,37 @@
+use intcode::network::{FirstPacket, Network, Wakeup};
+use intcode::solutions::Solution;
+
+use anyhow::{anyhow, Result};
+use futures::future::LocalBoxFuture;
+
+pub struct Day23;
+
+impl Solution for Day23 {
+    fn day(&self) -> usize {
+        23
+    }
+
+    fn part1(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(part1(input))
+    }
+
+    fn part2(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+        Box::pin(part2(input))
+    }
+}
+
+async fn part1(input: &'static str) -> Result<String> {
+    let mut nat = FirstPacket::default();
+    Network::new(input, 50).run(&mut nat).await?;
+    nat.packet
+        .map(|packet| packet.y.to_string())
+        .ok_or(anyhow!("no packet was sent to the NAT"))
+}
+
+async fn part2(input: &'static str) -> Result<String> {
+    let mut nat = Wakeup::default();
+    Network::new(input, 50).run(&mut nat).await?;
+    nat.y
+        .map(|y| y.to_string())
+        .ok_or(anyhow!("the NAT never sent the same y twice"))
+}
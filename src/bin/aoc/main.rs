@@ -0,0 +1,135 @@
+mod day02;
+mod day05;
+mod day07;
+mod day09;
+mod day11;
+mod day19;
+mod day21;
+mod day23;
+
+use std::time::Duration;
+
+use intcode::{
+    inputs::input,
+    solutions::{timed, Solution},
+};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+
+/// Run the Advent of Code solutions.
+#[derive(Parser)]
+#[command(author, about, version)]
+struct Cli {
+    /// The day to run.
+    #[arg(short, long, required_unless_present = "all")]
+    day: Option<usize>,
+
+    /// The part to run. Both parts are run if this isn't given.
+    #[arg(short, long)]
+    part: Option<usize>,
+
+    /// Show the solution running instead of solving it.
+    #[arg(short, long, requires = "day")]
+    visualize: bool,
+
+    /// Run and time every day.
+    #[arg(short, long, conflicts_with = "day")]
+    all: bool,
+
+    /// Record a timeline of the run to this file for chrome://tracing or Perfetto.
+    #[cfg(feature = "chrome-trace")]
+    #[arg(short, long)]
+    trace: Option<String>,
+
+    /// Serve metrics for Prometheus at this address (e.g. 127.0.0.1:9100) while running.
+    #[cfg(feature = "metrics")]
+    #[arg(short, long)]
+    metrics: Option<String>,
+}
+
+fn solutions() -> Vec<Box<dyn Solution>> {
+    vec![
+        Box::new(day02::Day02),
+        Box::new(day05::Day05),
+        Box::new(day07::Day07),
+        Box::new(day09::Day09),
+        Box::new(day11::Day11),
+        Box::new(day19::Day19),
+        Box::new(day21::Day21),
+        Box::new(day23::Day23),
+    ]
+}
+
+// Solutions hand their input to processes in other tasks, so it has to live for the whole run.
+fn leak(input: String) -> &'static str {
+    Box::leak(input.into_boxed_str())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let parts = match cli.part {
+        Some(part) => vec![part],
+        None => vec![1, 2],
+    };
+    let solutions = solutions()
+        .into_iter()
+        .filter(|solution| cli.all || cli.day == Some(solution.day()))
+        .collect::<Vec<_>>();
+    if solutions.is_empty() {
+        return Err(anyhow!("there is no solution for day {:?}", cli.day));
+    }
+
+    if cli.visualize {
+        let solution = &solutions[0];
+        return solution.visualize(leak(input(solution.day())?)).await;
+    }
+
+    #[cfg(feature = "metrics")]
+    if let Some(address) = cli.metrics.clone() {
+        tokio::spawn(async move {
+            if let Err(e) = intcode::metrics::serve(address).await {
+                eprintln!("serving metrics: {}", e);
+            }
+        });
+    }
+
+    #[cfg(feature = "chrome-trace")]
+    if cli.trace.is_some() {
+        intcode::chrome_trace::start();
+    }
+
+    let mut total = Duration::ZERO;
+    for solution in &solutions {
+        let input = leak(input(solution.day())?);
+        for &part in &parts {
+            let (answer, elapsed) = timed(solution.as_ref(), input, part).await;
+            total += elapsed;
+            match answer {
+                Ok(answer) => println!(
+                    "day {:02} p{}: {} ({:?})",
+                    solution.day(),
+                    part,
+                    answer,
+                    elapsed
+                ),
+                Err(e) => println!(
+                    "day {:02} p{}: error: {} ({:?})",
+                    solution.day(),
+                    part,
+                    e,
+                    elapsed
+                ),
+            }
+        }
+    }
+    if cli.all {
+        println!("total: {:?}", total);
+    }
+    #[cfg(feature = "chrome-trace")]
+    if let Some(path) = &cli.trace {
+        intcode::chrome_trace::write(path)?;
+    }
+    Ok(())
+}
@@ -4,12 +4,14 @@ use std::{
 };
 
 use intcode::{
+    arcade::AutoPlayer,
+    grid::GridWidget,
     ipc::{Channel, ChannelReceiver, ChannelSender},
     process::Process,
     renderer::ColorScheme,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Parser;
 use crossterm::{
     execute,
@@ -19,8 +21,7 @@ use ratatui::{
     backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout},
     style::Style,
-    text::Text,
-    widgets::{block::Title, Block, BorderType, Borders, Cell, Paragraph, Row, Table},
+    widgets::{block::Title, Block, BorderType, Borders, Paragraph},
     Frame, Terminal,
 };
 
@@ -35,6 +36,14 @@ struct Cli {
 
     #[arg(short, long)]
     replay: Option<String>,
+
+    /// Let the autopilot play part 2 instead of the keyboard.
+    #[arg(short, long)]
+    auto: bool,
+
+    /// Have the autopilot play part 2 without the TUI and just print the score.
+    #[arg(long, requires = "auto")]
+    headless: bool,
 }
 
 #[tokio::main]
@@ -42,7 +51,14 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
     match cli.part {
         1 => part1().await,
-        _ => part2(cli.replay).await?,
+        _ if cli.headless => {
+            // The autopilot runs the game without a runtime, so it gets its own thread.
+            let score = std::thread::spawn(|| AutoPlayer::play(INPUT))
+                .join()
+                .map_err(|_| anyhow!("the autopilot panicked"))??;
+            println!("p2: {}", score);
+        }
+        _ => part2(cli.replay, cli.auto).await?,
     };
     Ok(())
 }
@@ -76,7 +92,7 @@ async fn part1() {
     println!("p1: {}", block_count);
 }
 
-async fn part2(replay: Option<String>) -> Result<()> {
+async fn part2(replay: Option<String>, auto: bool) -> Result<()> {
     // Initialize our TUI.
     let mut tui = Tui::new()?;
     tui.init()?;
@@ -95,8 +111,9 @@ async fn part2(replay: Option<String>) -> Result<()> {
     let (output_handle, mut output_events) = output_event_emitter::start(output);
     let (input_handle, mut input_events) = input_event_emitter::start(replay)?;
 
-    // Create our app state.
+    // Create our app state. The autopilot watches the game even when it isn't playing.
     let mut app = AppState::new();
+    let mut player = AutoPlayer::new();
 
     // Main loop to handle input and output. We draw first and then handle each event. We use
     // "biased;" to favor output events because they'll be most important for drawing and there are
@@ -111,6 +128,12 @@ async fn part2(replay: Option<String>) -> Result<()> {
                 match evt {
                     Some(OutputEvent::GridUpdate(x, y, tile)) => {
                         app.grid[y][x] = tile;
+                        player.update(x as isize, y as isize, tile as isize);
+
+                        // The game reads the joystick once for every time it moves the ball.
+                        if auto && tile == Tile::Ball {
+                            input.send(player.joystick()).await?;
+                        }
                     }
                     Some(OutputEvent::Score(score)) => {
                         app.score = score;
@@ -119,8 +142,8 @@ async fn part2(replay: Option<String>) -> Result<()> {
                 }
             }
 
-            // Handle input events.
-            evt = input_events.recv() => {
+            // Handle input events unless the autopilot is playing.
+            evt = input_events.recv(), if !auto => {
                 if let Some(evt) = evt {
                     input.send(evt.into()).await?;
                 }
@@ -143,8 +166,7 @@ async fn part2(replay: Option<String>) -> Result<()> {
 
 /// A helper function to create the process for day13 and its input and output channels.
 fn create_process() -> (Process, ChannelSender, ChannelReceiver) {
-    let (_, input_tx, input_rx) = Channel::new(true);
-    let (_, output_tx, output_rx) = Channel::new(true);
+    let ((input_tx, output_rx), (output_tx, input_rx)) = Channel::pair(true);
     let process = Process::new(INPUT, input_rx, output_tx);
     (process, input_tx, output_rx)
 }
@@ -188,16 +210,17 @@ impl From<isize> for Tile {
     }
 }
 
-impl From<Tile> for Text<'_> {
-    fn from(tile: Tile) -> Self {
-        match tile {
-            Tile::Empty => " ",
-            Tile::Wall => "█",
-            Tile::Block => "▒",
-            Tile::Paddle => "▀",
-            Tile::Ball => "o",
-        }
-        .into()
+impl Tile {
+    /// How the tile is drawn. Empty tiles aren't drawn at all.
+    fn cell(&self) -> Option<(char, ColorScheme)> {
+        let c = match self {
+            Tile::Empty => return None,
+            Tile::Wall => '█',
+            Tile::Block => '▒',
+            Tile::Paddle => '▀',
+            Tile::Ball => 'o',
+        };
+        Some((c, ColorScheme::White))
     }
 }
 
@@ -307,9 +330,11 @@ mod output_event_emitter {
         // receive and then send them to the main loop.
         let handle = tokio::spawn(async move {
             let mut emitter = OutputEventEmitter::new();
-            while let Some(value) = receiver.recv().await {
-                if let Some(event) = emitter.handle_output(value) {
-                    tx.send(event).await?;
+            while let Some(chunk) = receiver.recv_chunk().await {
+                for value in chunk {
+                    if let Some(event) = emitter.handle_output(value) {
+                        tx.send(event).await?;
+                    }
                 }
             }
             Ok(())
@@ -480,12 +505,10 @@ fn ui(app: &AppState, f: &mut Frame) {
                 .fg(ColorScheme::White.into())
                 .bg(ColorScheme::Background.into()),
         );
-    let mut rows = vec![];
-    for row in app.grid.iter() {
-        let row = row.iter().map(|&tile| Cell::from(tile)).collect::<Vec<_>>();
-        rows.push(Row::new(row));
-    }
-    let widths = [Constraint::Length(1); 44];
-    let table = Table::new(rows, widths).column_spacing(0).block(block);
-    f.render_widget(table, chunks[1]);
+    let (width, height) = (app.grid[0].len() as isize, app.grid.len() as isize);
+    let grid = GridWidget::new(0..=width - 1, 0..=height - 1, |x, y| {
+        app.grid[y as usize][x as usize].cell()
+    })
+    .block(block);
+    f.render_widget(grid, chunks[1]);
 }
@@ -1,39 +1,56 @@
 use std::{
     collections::{HashSet, VecDeque},
-    ops::Add,
+    io::{stdout, Stdout},
+    time::Duration,
 };
 
-use enum_iterator::{all, Sequence};
+use enum_iterator::all;
 use intcode::{
+    grid::{Direction, Grid, Point},
     instruction::Instruction,
-    ipc::Channel,
+    ipc::{Channel, RecvResult},
+    machine::Machine,
     process::{Process, State},
+    renderer::ColorScheme,
 };
 
 use anyhow::{anyhow, Result};
+use clap::Parser;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
 use pathfinding::directed::dijkstra::dijkstra_all;
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{self, Alignment, Constraint, Layout},
+    style::Style,
+    widgets::{block::Title, Block, BorderType, Borders, Paragraph},
+    Frame, Terminal,
+};
 
 // The input for the Intcode program.
 const INPUT: &str = include_str!("inputs/day15");
 
-// The possible movements the robot can make.
-#[derive(Debug, Clone, Copy, Sequence)]
-enum Movement {
-    North,
-    South,
-    East,
-    West,
-}
-
-// Convert a movement command into an integer for the Intcode program.
-impl From<Movement> for isize {
-    fn from(m: Movement) -> isize {
-        match m {
-            Movement::North => 1,
-            Movement::South => 2,
-            Movement::West => 3,
-            Movement::East => 4,
-        }
+#[derive(Debug, Parser)]
+struct Cli {
+    /// Show the map as the droid discovers it instead of just solving.
+    #[arg(short, long)]
+    gui: bool,
+
+    /// Drive the droid yourself with WASD instead of letting it search.
+    #[arg(short, long, requires = "gui")]
+    manual: bool,
+}
+
+// The movement command for the Intcode program to move the droid in a direction. North is up.
+fn command(direction: Direction) -> isize {
+    match direction {
+        Direction::Up => 1,
+        Direction::Down => 2,
+        Direction::Left => 3,
+        Direction::Right => 4,
     }
 }
 
@@ -55,45 +72,6 @@ impl From<isize> for Reply {
     }
 }
 
-// A point in 2D space.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Point {
-    x: isize,
-    y: isize,
-}
-
-impl Point {
-    fn new(x: isize, y: isize) -> Self {
-        Point { x, y }
-    }
-}
-
-// Add a movement to a point to get a new point.
-impl Add<Movement> for Point {
-    type Output = Self;
-
-    fn add(self, movement: Movement) -> Self {
-        match movement {
-            Movement::North => Point {
-                x: self.x,
-                y: self.y + 1,
-            },
-            Movement::South => Point {
-                x: self.x,
-                y: self.y - 1,
-            },
-            Movement::East => Point {
-                x: self.x + 1,
-                y: self.y,
-            },
-            Movement::West => Point {
-                x: self.x - 1,
-                y: self.y,
-            },
-        }
-    }
-}
-
 // A node in the search space.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Node {
@@ -105,7 +83,18 @@ struct Node {
     oxygen: bool,
 }
 
-fn main() {
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    match cli.gui {
+        true => gui(cli.manual),
+        false => {
+            solve();
+            Ok(())
+        }
+    }
+}
+
+fn solve() {
     // For part 2, we'll be starting at the oxygen position and doing a BFS, so we'll need to keep
     // track of the grid and the oxygen position.
     let mut grid = HashSet::new();
@@ -153,7 +142,7 @@ fn main() {
     let mut frontier = VecDeque::new();
     frontier.push_back((0, oxygen.unwrap()));
     let mut seen = HashSet::new();
-    let mut last = std::isize::MIN;
+    let mut last = isize::MIN;
     while let Some((dist, point)) = frontier.pop_front() {
         if !seen.insert(point) {
             continue;
@@ -173,7 +162,7 @@ fn neighbors(node: &Node) -> Result<Vec<Node>> {
 
     // For each direction, perform the I/O loop and add the neighbor to the list of neighbors if it
     // is a valid next state.
-    for dir in all::<Movement>() {
+    for dir in all::<Direction>() {
         // Clone the state and perform the I/O loop. Note the use of `block_on` to run the async
         // function in a synchronous context.
         let state = node.state.clone();
@@ -199,11 +188,11 @@ fn neighbors(node: &Node) -> Result<Vec<Node>> {
 }
 
 // Perform a single I/O loop with the given state.
-async fn io_loop(state: State, movement: Movement) -> Result<(State, Reply)> {
+async fn io_loop(state: State, direction: Direction) -> Result<(State, Reply)> {
     // Create a new channel for the input and output and send the movement command.
     let (_, mut input_tx, input_rx) = Channel::new(false);
     let (_, output_tx, mut output_rx) = Channel::new(false);
-    input_tx.send(movement.into()).await?;
+    input_tx.send(command(direction)).await?;
 
     // Create a new process with the given state and run it until the output is received.
     let mut process = Process::with_state(state, input_rx, output_tx);
@@ -214,14 +203,286 @@ async fn io_loop(state: State, movement: Movement) -> Result<(State, Reply)> {
     process.step().await?;
 
     // Get the output and return it with the state.
-    let output = output_rx.recv().await.ok_or(anyhow!("no output = bad"))?;
+    let output = match output_rx.recv().await {
+        RecvResult::Value(value) => value,
+        _ => return Err(anyhow!("no output = bad")),
+    };
     Ok((process.state(), output.into()))
 }
 
 // Get the neighbors of a point in the grid for part 2.
 fn neighbors_p2(point: &Point, grid: &HashSet<Point>) -> Vec<Point> {
-    all::<Movement>()
-        .map(|dir| *point + dir)
-        .filter(|p| grid.contains(p))
-        .collect()
+    point.neighbors().filter(|p| grid.contains(p)).collect()
+}
+
+// What the droid has found at a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Wall,
+    Open,
+    Oxygen,
+}
+
+// A single droid driven by a single process, for watching it explore. Unlike the solver, which
+// clones the program for every position, this moves the droid around like the puzzle intends.
+struct Explorer {
+    machine: Machine,
+    droid: Point,
+    map: Grid<Cell>,
+    // The moves that got us here from the start, so we can backtrack.
+    path: Vec<Direction>,
+    moves: usize,
+    done: bool,
+}
+
+impl Explorer {
+    fn new() -> Self {
+        Self {
+            machine: Machine::new(INPUT),
+            droid: Point::new(0, 0),
+            map: [(Point::new(0, 0), Cell::Open)].into_iter().collect(),
+            path: vec![],
+            moves: 0,
+            done: false,
+        }
+    }
+
+    // Try to move the droid, recording what it finds.
+    fn go(&mut self, direction: Direction) -> Result<Reply> {
+        self.machine.push_input(command(direction));
+        self.machine.run(usize::MAX)?;
+        let reply = Reply::from(
+            self.machine
+                .poll_output()
+                .pop()
+                .ok_or_else(|| anyhow!("the droid didn't reply"))?,
+        );
+        self.moves += 1;
+
+        let position = self.droid + direction;
+        let cell = match reply {
+            Reply::Wall => Cell::Wall,
+            Reply::Moved => Cell::Open,
+            Reply::Found => Cell::Oxygen,
+        };
+        self.map.insert(position, cell);
+        if cell != Cell::Wall {
+            self.droid = position;
+        }
+        Ok(reply)
+    }
+
+    // Make one move of a depth first search: look at the first neighbor we don't know yet or go
+    // back the way we came once there aren't any.
+    fn explore(&mut self) -> Result<()> {
+        if self.done {
+            return Ok(());
+        }
+        if let Some(direction) = all::<Direction>().find(|&d| !self.map.contains(self.droid + d)) {
+            if !matches!(self.go(direction)?, Reply::Wall) {
+                self.path.push(direction);
+            }
+            return Ok(());
+        }
+        match self.path.pop() {
+            Some(direction) => {
+                self.go(direction.reverse())?;
+            }
+            None => self.done = true,
+        }
+        Ok(())
+    }
+
+    // How far it is to the oxygen system from the start, if we know where it is.
+    fn distance(&self) -> Option<usize> {
+        let oxygen = self
+            .map
+            .iter()
+            .find(|(_, &cell)| cell == Cell::Oxygen)
+            .map(|(&position, _)| position)?;
+        let mut frontier = VecDeque::from([(0, Point::new(0, 0))]);
+        let mut seen = HashSet::new();
+        while let Some((distance, position)) = frontier.pop_front() {
+            if position == oxygen {
+                return Some(distance);
+            }
+            if !seen.insert(position) {
+                continue;
+            }
+            for neighbor in position.neighbors() {
+                if matches!(self.map.get(neighbor), Some(Cell::Open | Cell::Oxygen)) {
+                    frontier.push_back((distance + 1, neighbor));
+                }
+            }
+        }
+        None
+    }
+}
+
+// Watch the droid explore or drive it around. The map is drawn as it's discovered.
+fn gui(manual: bool) -> Result<()> {
+    let mut tui = Tui::new()?;
+    tui.init()?;
+
+    let mut explorer = Explorer::new();
+    let mut delay = Duration::from_millis(20);
+    loop {
+        tui.draw(&explorer, manual)?;
+
+        // Wait for a key for as long as a move should take. If none comes, the search moves on.
+        if event::poll(delay)? {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let direction = match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => break,
+                KeyCode::Char('+') => {
+                    delay /= 2;
+                    None
+                }
+                KeyCode::Char('-') => {
+                    delay = (delay * 2).max(Duration::from_millis(1));
+                    None
+                }
+                KeyCode::Char('w') => Some(Direction::Up),
+                KeyCode::Char('s') => Some(Direction::Down),
+                KeyCode::Char('a') => Some(Direction::Left),
+                KeyCode::Char('d') => Some(Direction::Right),
+                _ => None,
+            };
+            if let Some(direction) = direction.filter(|_| manual) {
+                explorer.go(direction)?;
+            }
+            continue;
+        }
+        if !manual {
+            explorer.explore()?;
+        }
+    }
+
+    tui.exit()?;
+    if let Some(distance) = explorer.distance() {
+        println!("p1: {}", distance);
+    }
+    Ok(())
+}
+
+struct Tui {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+}
+
+impl Tui {
+    /// Create a new TUI using crossterm as the backend.
+    fn new() -> Result<Self> {
+        let backend = CrosstermBackend::new(stdout());
+        let terminal = Terminal::new(backend)?;
+        Ok(Self { terminal })
+    }
+
+    /// Draw the TUI using the given explorer.
+    fn draw(&mut self, explorer: &Explorer, manual: bool) -> Result<()> {
+        self.terminal.draw(|f| ui(explorer, manual, f))?;
+        Ok(())
+    }
+
+    /// Initialize the terminal for the TUI.
+    fn init(&mut self) -> Result<()> {
+        execute!(stdout(), EnterAlternateScreen)?;
+        enable_raw_mode()?;
+        self.terminal.hide_cursor()?;
+        self.terminal.clear()?;
+
+        // This will help us reset the terminal if we panic.
+        let panic_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |panic_info| {
+            Self::reset().unwrap();
+            panic_hook(panic_info);
+        }));
+
+        Ok(())
+    }
+
+    fn reset() -> Result<()> {
+        disable_raw_mode()?;
+        execute!(stdout(), LeaveAlternateScreen)?;
+        Ok(())
+    }
+
+    /// Clean up the terminal when we are done.
+    fn exit(&mut self) -> Result<()> {
+        Self::reset()?;
+        self.terminal.show_cursor()?;
+        Ok(())
+    }
+}
+
+fn ui(explorer: &Explorer, manual: bool, f: &mut Frame) {
+    let chunks = Layout::default()
+        .direction(layout::Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .split(f.size());
+
+    // Draw the title block.
+    let title_block = Block::default().style(
+        Style::default()
+            .fg(ColorScheme::Background.into())
+            .bg(ColorScheme::Violet.into()),
+    );
+    let title = Paragraph::new("REPAIR DROID")
+        .block(title_block)
+        .alignment(Alignment::Center);
+    f.render_widget(title, chunks[0]);
+
+    // Draw the status block.
+    let status_block = Block::default().style(
+        Style::default()
+            .fg(ColorScheme::Background.into())
+            .bg(ColorScheme::Green.into()),
+    );
+    let oxygen = match explorer.distance() {
+        Some(distance) => format!("oxygen at {}", distance),
+        None => "oxygen not found".to_string(),
+    };
+    let mode = match (manual, explorer.done) {
+        (true, _) => "(wasd) drive",
+        (false, true) => "explored",
+        (false, false) => "exploring",
+    };
+    let status = Paragraph::new(format!(
+        "Moves: {} | {} | {} | (+/-) speed | (q) quit",
+        explorer.moves, oxygen, mode
+    ))
+    .block(status_block)
+    .alignment(Alignment::Left);
+    f.render_widget(status, chunks[2]);
+
+    // Draw the map with north at the top. Everything we haven't seen yet is blank.
+    let block = Block::default()
+        .title(Title::from("MAP").alignment(Alignment::Center))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(ColorScheme::Orange.into()))
+        .border_type(BorderType::Rounded)
+        .style(
+            Style::default()
+                .fg(ColorScheme::White.into())
+                .bg(ColorScheme::Background.into()),
+        );
+    let grid = explorer
+        .map
+        .widget(|position, cell| match cell {
+            _ if position == explorer.droid => Some(('D', ColorScheme::Yellow)),
+            Cell::Wall => Some(('█', ColorScheme::Grey)),
+            Cell::Oxygen => Some(('O', ColorScheme::Blue)),
+            Cell::Open if position == Point::new(0, 0) => Some(('S', ColorScheme::Green)),
+            Cell::Open => Some(('.', ColorScheme::DarkGrey)),
+        })
+        .block(block);
+    f.render_widget(grid, chunks[1]);
 }
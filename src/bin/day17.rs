@@ -6,11 +6,12 @@ use futures::{
 use std::collections::{HashSet, VecDeque};
 use std::io::{stdout, Stdout};
 
+use intcode::grid::{Direction, Point};
 use intcode::ipc::{Channel, ChannelReceiver, ChannelSender};
 use intcode::process::Process;
 use intcode::renderer::ColorScheme;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Parser;
 use crossterm::event::{Event, EventStream, KeyEvent};
 use crossterm::{
@@ -21,7 +22,7 @@ use crossterm::{
 use ratatui::widgets::{block::Title, Block, BorderType, Borders, List, Paragraph};
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
+    layout::{self, Alignment, Constraint, Layout},
 };
 use ratatui::{style::Style, text::Text};
 use ratatui::{Frame, Terminal};
@@ -30,19 +31,6 @@ use tokio::select;
 
 const INPUT: &str = include_str!("inputs/day17");
 
-/// Point in 2D space
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Point {
-    x: isize,
-    y: isize,
-}
-
-impl Point {
-    fn new(x: isize, y: isize) -> Self {
-        Self { x, y }
-    }
-}
-
 /// Part to run
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Default, clap::ValueEnum)]
 #[serde(rename_all = "kebab-case")]
@@ -87,23 +75,30 @@ async fn run_program(part: Part) -> (ChannelSender, ChannelReceiver) {
     (input_tx, output_rx)
 }
 
-async fn part1() -> Result<()> {
-    // Run our program.
+/// Run the part 1 program and collect what the camera sees, one row per line.
+async fn camera() -> Vec<Vec<u8>> {
     let (_, mut output_rx) = run_program(Part::One).await;
+    let mut view = vec![vec![]];
+    while let Some(chunk) = output_rx.recv_chunk().await {
+        for output in chunk {
+            match output {
+                10 => view.push(vec![]),
+                _ => view.last_mut().unwrap().push(output as u8),
+            }
+        }
+    }
+    view.retain(|row| !row.is_empty());
+    view
+}
 
-    // Receive output until the program ends. We'll store the grid in a HashSet.
-    let mut x = 0;
-    let mut y = 0;
+async fn part1() -> Result<()> {
+    // We'll store the scaffold in a HashSet.
     let mut grid = HashSet::new();
-    while let Some(output) = output_rx.recv().await {
-        if output == 35 {
-            grid.insert(Point::new(x, y));
-        }
-        if output == 10 {
-            y += 1;
-            x = 0;
-        } else {
-            x += 1;
+    for (y, row) in camera().await.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            if cell == b'#' {
+                grid.insert(Point::new(x as isize, y as isize));
+            }
         }
     }
 
@@ -111,11 +106,7 @@ async fn part1() -> Result<()> {
     // a scaffold in all four directions.
     let mut p1 = 0;
     for key in grid.iter() {
-        if grid.contains(&Point::new(key.x + 1, key.y))
-            && grid.contains(&Point::new(key.x - 1, key.y))
-            && grid.contains(&Point::new(key.x, key.y + 1))
-            && grid.contains(&Point::new(key.x, key.y - 1))
-        {
+        if key.neighbors().all(|neighbor| grid.contains(&neighbor)) {
             p1 += key.x * key.y;
         }
     }
@@ -123,26 +114,153 @@ async fn part1() -> Result<()> {
     Ok(())
 }
 
+/// A move of the robot: turn left or right and then go forward some number of steps.
+type Move = (char, usize);
+
+/// Trace the scaffold from where the robot is, turning only when the scaffold ends ahead of it.
+/// The scaffold is one long path that only crosses itself, so this covers all of it.
+fn trace(view: &[Vec<u8>]) -> Vec<Move> {
+    let scaffold = |p: Point| {
+        usize::try_from(p.y)
+            .ok()
+            .and_then(|y| view.get(y))
+            .and_then(|row| usize::try_from(p.x).ok().and_then(|x| row.get(x)))
+            == Some(&b'#')
+    };
+    let robot = (0..view.len()).find_map(|y| {
+        (0..view[y].len()).find_map(|x| {
+            let direction = match view[y][x] {
+                b'^' => Direction::Up,
+                b'>' => Direction::Right,
+                b'v' => Direction::Down,
+                b'<' => Direction::Left,
+                _ => return None,
+            };
+            Some((Point::new(x as isize, y as isize), direction))
+        })
+    });
+    let Some((mut position, mut direction)) = robot else {
+        return vec![];
+    };
+
+    let mut moves = vec![];
+    loop {
+        let (left, right) = (direction.turn_left(), direction.turn_right());
+        let turn = match (scaffold(position + left), scaffold(position + right)) {
+            (true, _) => {
+                direction = left;
+                'L'
+            }
+            (_, true) => {
+                direction = right;
+                'R'
+            }
+            _ => return moves,
+        };
+        let mut steps = 0;
+        while scaffold(position + direction) {
+            position += direction;
+            steps += 1;
+        }
+        moves.push((turn, steps));
+    }
+}
+
+/// Write moves the way the robot reads them (e.g. `L,6,R,12`).
+fn text(moves: &[Move]) -> String {
+    moves
+        .iter()
+        .map(|(turn, steps)| format!("{},{}", turn, steps))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Split the moves into at most three functions and a main routine that calls them, keeping every
+/// line to the robot's limit of 20 characters. This returns the calls the main routine makes (as
+/// indexes into the functions) and the functions.
+fn compress(moves: &[Move]) -> Option<(Vec<usize>, Vec<&[Move]>)> {
+    // Each function is some prefix of what's left when it's first needed, so we try each prefix
+    // that fits and backtrack.
+    fn search<'a>(
+        moves: &'a [Move],
+        functions: &mut Vec<&'a [Move]>,
+        main: &mut Vec<usize>,
+    ) -> bool {
+        if moves.is_empty() {
+            return true;
+        }
+        // Ten calls is "A,B,..." at 19 characters, so there's no room for another.
+        if main.len() == 10 {
+            return false;
+        }
+        for function in 0..functions.len() {
+            let length = functions[function].len();
+            if moves.starts_with(functions[function]) {
+                main.push(function);
+                if search(&moves[length..], functions, main) {
+                    return true;
+                }
+                main.pop();
+            }
+        }
+        if functions.len() < 3 {
+            for length in 1..=moves.len() {
+                if text(&moves[..length]).len() > 20 {
+                    break;
+                }
+                functions.push(&moves[..length]);
+                main.push(functions.len() - 1);
+                if search(&moves[length..], functions, main) {
+                    return true;
+                }
+                main.pop();
+                functions.pop();
+            }
+        }
+        false
+    }
+
+    let (mut functions, mut main) = (vec![], vec![]);
+    search(moves, &mut functions, &mut main).then_some((main, functions))
+}
+
 async fn part2() -> Result<()> {
-    // You can use the GUI to do some manual work to find the path. Mine ended up being like this.
-    let input = "A,B,B,A,C,A,C,A,C,B\nL,6,R,12,R,8\nR,8,R,12,L,12\nR,12,L,12,L,4,L,4\nn\n"
-        .chars()
-        .map(|c| c as isize)
-        .collect::<Vec<_>>();
+    // Trace the path the robot needs to take and find routines that make it fit in memory.
+    let moves = trace(&camera().await);
+    let (main, mut functions) =
+        compress(&moves).ok_or_else(|| anyhow!("the path doesn't fit in three functions"))?;
+
+    // The robot always asks for three functions, so any we didn't need are just copies.
+    while functions.len() < 3 {
+        functions.push(functions[0]);
+    }
+    let main = main
+        .iter()
+        .map(|&function| ((b'A' + function as u8) as char).to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let input = format!(
+        "{}\n{}\n{}\n{}\nn\n",
+        main,
+        text(functions[0]),
+        text(functions[1]),
+        text(functions[2])
+    )
+    .chars()
+    .map(|c| c as isize)
+    .collect::<Vec<_>>();
 
     // Run the program and send the instructions to the program.
     let (mut input_tx, mut output_rx) = run_program(Part::Two).await;
     tokio::spawn(async move {
-        for c in input {
-            input_tx.send(c).await.unwrap();
-        }
+        input_tx.send_slice(&input).await.unwrap();
     });
 
     // It will spit out the graph and then eventually the dust collected. We'll look for the dust
     // collected and then print it.
-    while let Some(output) = output_rx.recv().await {
-        if output > 255 {
-            println!("p2: {}", output);
+    while let Some(chunk) = output_rx.recv_chunk().await {
+        if let Some(dust) = chunk.into_iter().find(|&output| output > 255) {
+            println!("p2: {}", dust);
         }
     }
     Ok(())
@@ -198,9 +316,17 @@ async fn tui() -> Result<()> {
             biased;
 
             // If we get output from the program, we should update our app state.
-            val = output_rx.recv(), if app.output_state != OutputState::Done => {
-                // Handle any output.
-                app.handle_output(val);
+            // We take everything that's ready at once, so a whole frame is handled before we
+            // draw again.
+            chunk = output_rx.recv_chunk(), if app.output_state != OutputState::Done => {
+                match chunk {
+                    Some(values) => {
+                        for value in values {
+                            app.handle_output(Some(value));
+                        }
+                    }
+                    None => app.handle_output(None),
+                }
             },
 
             // If we get and even from our event reader, we should update our inputs.
@@ -424,7 +550,7 @@ impl Tui {
 fn ui(app: &AppState, f: &mut Frame) {
     // Create a layout for our TUI.
     let chunks = Layout::default()
-        .direction(Direction::Vertical)
+        .direction(layout::Direction::Vertical)
         .constraints([
             Constraint::Length(1),
             Constraint::Min(26),
@@ -455,7 +581,7 @@ fn ui(app: &AppState, f: &mut Frame) {
     f.render_widget(status, chunks[2]);
 
     let chunks = Layout::default()
-        .direction(Direction::Horizontal)
+        .direction(layout::Direction::Horizontal)
         .constraints([Constraint::Min(30), Constraint::Length(53)])
         .split(chunks[1]);
 
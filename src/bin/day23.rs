@@ -1,8 +0,0 @@
-const INPUT: &str = include_str!("inputs/day23");
-
-use intcode::ipc::Channel;
-use intcode::process::Process;
-
-#[tokio::main]
-async fn main() {
-}
@@ -1,57 +1,34 @@
 use std::collections::{HashMap, HashSet};
 
-const INPUT: &str = include_str!("inputs/day24");
-
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
-struct Point {
-    x: usize,
-    y: usize,
-}
+use intcode::grid::{Grid, Point};
 
-impl Point {
-    fn new(x: usize, y: usize) -> Self {
-        Self { x, y }
-    }
-}
+const INPUT: &str = include_str!("inputs/day24");
 
+// A single level of Eris.
 #[derive(Debug)]
-struct Grid {
-    data: HashMap<Point, bool>,
+struct Bugs {
+    data: Grid<bool>,
 }
 
-impl Grid {
+impl Bugs {
     fn new(s: &str) -> Self {
-        let mut data = HashMap::new();
-        for (y, line) in s.lines().enumerate() {
-            for (x, c) in line.chars().enumerate() {
-                data.insert(Point::new(x, y), c == '#');
-            }
+        Self {
+            data: Grid::parse(s, |c| Some(c == '#')),
         }
-        Self { data }
     }
 
     fn adjacent_bugs(&self, point: &Point) -> usize {
-        let mut count = 0;
-        if let Some(true) = self.data.get(&Point::new(point.x, point.y - 1)) {
-            count += 1;
-        }
-        if let Some(true) = self.data.get(&Point::new(point.x, point.y + 1)) {
-            count += 1;
-        }
-        if let Some(true) = self.data.get(&Point::new(point.x - 1, point.y)) {
-            count += 1;
-        }
-        if let Some(true) = self.data.get(&Point::new(point.x + 1, point.y)) {
-            count += 1;
-        }
-        count
+        point
+            .neighbors()
+            .filter(|&neighbor| self.data.get(neighbor) == Some(&true))
+            .count()
     }
 
     fn biodiversity(&self) -> usize {
         let mut score = 0;
         for y in 0..5 {
             for x in 0..5 {
-                if *self.data.get(&Point::new(x, y)).unwrap() {
+                if *self.data.get(Point::new(x, y)).unwrap() {
                     score += 1 << (y * 5 + x);
                 }
             }
@@ -59,19 +36,11 @@ impl Grid {
         score
     }
 
-    fn to_string(&self) -> String {
-        let mut s = String::new();
-        for y in 0..5 {
-            for x in 0..5 {
-                s.push(if *self.data.get(&Point::new(x, y)).unwrap() {
-                    '#'
-                } else {
-                    '.'
-                });
-            }
-            s.push('\n');
-        }
-        s
+    fn layout(&self) -> String {
+        self.data.render(|bug| match bug {
+            Some(true) => '#',
+            _ => '.',
+        })
     }
 }
 
@@ -84,26 +53,17 @@ impl MultiGrid {
         let mut data = HashMap::new();
         for (y, line) in s.lines().enumerate() {
             for (x, c) in line.chars().enumerate() {
-                data.insert((0, Point::new(x, y)), c == '#');
+                data.insert((0, Point::new(x as isize, y as isize)), c == '#');
             }
         }
         Self { data }
     }
 
     fn adjacent_bugs(&self, level: i32, point: &Point) -> usize {
-        let mut count = 0;
-        if let Some(true) = self.data.get(&(level, Point::new(point.x, point.y - 1))) {
-            count += 1;
-        }
-        if let Some(true) = self.data.get(&(level, Point::new(point.x, point.y + 1))) {
-            count += 1;
-        }
-        if let Some(true) = self.data.get(&(level, Point::new(point.x - 1, point.y))) {
-            count += 1;
-        }
-        if let Some(true) = self.data.get(&(level, Point::new(point.x + 1, point.y))) {
-            count += 1;
-        }
+        let mut count = point
+            .neighbors()
+            .filter(|&neighbor| self.data.get(&(level, neighbor)) == Some(&true))
+            .count();
 
         if point.x == 0 {
             if let Some(true) = self.data.get(&(level - 1, Point::new(1, 2))) {
@@ -121,7 +81,7 @@ impl MultiGrid {
             if let Some(true) = self.data.get(&(level - 1, Point::new(3, 2))) {
                 count += 1;
             }
-        }   
+        }
 
         if point.y == 4 {
             if let Some(true) = self.data.get(&(level - 1, Point::new(2, 3))) {
@@ -164,7 +124,6 @@ impl MultiGrid {
         count
     }
 
-
     fn bugs(&mut self) -> usize {
         for _ in 0..200 {
             let mut next = HashMap::new();
@@ -176,11 +135,15 @@ impl MultiGrid {
                         if x == 2 && y == 2 {
                             continue;
                         }
-                        next.insert((level, Point::new(x, y)), if let Some(true) = self.data.get(&(level, Point::new(x, y))) {
-                            self.adjacent_bugs(level, &Point::new(x, y)) == 1
-                        } else {
-                            self.adjacent_bugs(level, &Point::new(x, y)) == 1 || self.adjacent_bugs(level, &Point::new(x, y)) == 2
-                        });
+                        next.insert(
+                            (level, Point::new(x, y)),
+                            if let Some(true) = self.data.get(&(level, Point::new(x, y))) {
+                                self.adjacent_bugs(level, &Point::new(x, y)) == 1
+                            } else {
+                                self.adjacent_bugs(level, &Point::new(x, y)) == 1
+                                    || self.adjacent_bugs(level, &Point::new(x, y)) == 2
+                            },
+                        );
                     }
                 }
             }
@@ -192,20 +155,23 @@ impl MultiGrid {
 
 #[tokio::main]
 async fn main() {
-    let mut grid = Grid::new(INPUT);
+    let mut grid = Bugs::new(INPUT);
     let mut found = HashSet::new();
     loop {
-        let mut next = HashMap::new();
-        for (point, bug) in &grid.data {
+        let mut next = Grid::new();
+        for (point, bug) in grid.data.iter() {
             let adjacent = grid.adjacent_bugs(point);
-            next.insert(point.clone(), if *bug {
-                adjacent == 1
-            } else {
-                adjacent == 1 || adjacent == 2
-            });
+            next.insert(
+                *point,
+                if *bug {
+                    adjacent == 1
+                } else {
+                    adjacent == 1 || adjacent == 2
+                },
+            );
         }
         grid.data = next;
-        if !found.insert(grid.to_string()) {
+        if !found.insert(grid.layout()) {
             break;
         }
     }
@@ -215,5 +181,4 @@ async fn main() {
     let mut multi_grid = MultiGrid::new(INPUT);
 
     println!("p2: {}", multi_grid.bugs());
-
 }
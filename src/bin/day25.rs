@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+
+use intcode::{
+    ipc::{self, Channel},
+    machine::Machine,
+    process::Process,
+};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+
+// The input for the Intcode program.
+const INPUT: &str = include_str!("inputs/day25");
+
+// The most instructions a single command can take before we decide the droid is stuck in an
+// infinite loop.
+const LIMIT: usize = 1_000_000;
+
+#[derive(Debug, Parser)]
+struct Cli {
+    /// Map the ship, pick up everything safe, and get through the pressure plate on our own
+    /// instead of playing.
+    #[arg(short, long)]
+    auto: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    match cli.auto {
+        true => {
+            println!("p1: {}", solve()?);
+            Ok(())
+        }
+        false => play().await,
+    }
+}
+
+// Play the game by typing commands. The game ends when the program halts or stdin is closed.
+async fn play() -> Result<()> {
+    let (_, input, rx) = Channel::new(true);
+    let (_, tx, output) = Channel::new(true);
+    tokio::spawn(ipc::stdin(input));
+    let printer = tokio::spawn(ipc::stdout(output));
+
+    // The output is closed once the process is gone, which lets the printer finish.
+    let mut process = Process::new(INPUT, rx, tx);
+    process.run().await?;
+    drop(process);
+    printer.await?
+}
+
+// The droid playing the game. It can be copied at any point, so risky commands can be tried on a
+// copy and dropped if they go badly.
+struct Droid {
+    machine: Machine,
+}
+
+impl Droid {
+    fn new(program: &str) -> Self {
+        Self {
+            machine: Machine::new(program),
+        }
+    }
+
+    fn snapshot(&self) -> Self {
+        Self {
+            machine: Machine::with_state(self.machine.state()),
+        }
+    }
+
+    // Run until the game asks for a command, returning what it said. The game is over if it isn't
+    // waiting afterward, either because it halted or because it never finished.
+    fn read(&mut self) -> Result<String> {
+        self.machine.run(LIMIT)?;
+        Ok(self
+            .machine
+            .poll_output()
+            .into_iter()
+            .map(|value| value as u8 as char)
+            .collect())
+    }
+
+    fn send(&mut self, command: &str) -> Result<String> {
+        command
+            .bytes()
+            .chain(std::iter::once(b'\n'))
+            .for_each(|byte| self.machine.push_input(byte as isize));
+        self.read()
+    }
+
+    fn playing(&self) -> bool {
+        self.machine.waiting()
+    }
+}
+
+// What the game tells us about the room we're in.
+#[derive(Debug)]
+struct Room {
+    name: String,
+    doors: Vec<String>,
+    items: Vec<String>,
+}
+
+impl Room {
+    // Read the last room in the text. Being ejected from the pressure plate shows two rooms and
+    // we're in the second.
+    fn parse(text: &str) -> Option<Self> {
+        let start = text.rfind("== ")?;
+        let mut lines = text[start..].lines();
+        let name = lines.next()?.trim_matches(|c| c == '=' || c == ' ');
+        let mut room = Room {
+            name: name.to_string(),
+            doors: Vec::new(),
+            items: Vec::new(),
+        };
+
+        // Each list is a heading followed by lines starting with a dash.
+        let mut list = None;
+        for line in lines {
+            match line {
+                "Doors here lead:" => list = Some(&mut room.doors),
+                "Items here:" => list = Some(&mut room.items),
+                _ => match (line.strip_prefix("- "), list.as_mut()) {
+                    (Some(entry), Some(list)) => list.push(entry.to_string()),
+                    _ => list = None,
+                },
+            }
+        }
+        Some(room)
+    }
+}
+
+fn reverse(door: &str) -> &'static str {
+    match door {
+        "north" => "south",
+        "south" => "north",
+        "east" => "west",
+        _ => "east",
+    }
+}
+
+// Everything we learn about the ship while exploring it.
+#[derive(Debug, Default)]
+struct Ship {
+    visited: HashSet<String>,
+    items: Vec<String>,
+    // The doors from the start to the security checkpoint.
+    checkpoint: Vec<String>,
+    // The door from the checkpoint to the pressure plate.
+    plate: Option<String>,
+}
+
+// Some items end the game or keep the droid from moving. We take the item on a copy of the droid
+// and try walking through a door to find out.
+fn safe(droid: &Droid, item: &str, room: &Room) -> Result<bool> {
+    let mut test = droid.snapshot();
+    test.send(&format!("take {}", item))?;
+    if !test.playing() {
+        return Ok(false);
+    }
+    let door = room
+        .doors
+        .first()
+        .ok_or_else(|| anyhow!("{} has no doors", room.name))?;
+    let text = test.send(door)?;
+    Ok(test.playing() && Room::parse(&text).is_some_and(|next| next.name != room.name))
+}
+
+// Walk every room reachable from this one, taking every safe item along the way and returning
+// here at the end. We don't step onto the pressure plate until we have everything.
+fn explore(droid: &mut Droid, room: Room, path: &mut Vec<String>, ship: &mut Ship) -> Result<()> {
+    ship.visited.insert(room.name.clone());
+    for item in &room.items {
+        if safe(droid, item, &room)? {
+            droid.send(&format!("take {}", item))?;
+            ship.items.push(item.clone());
+        }
+    }
+
+    let back = path.last().map(|door| reverse(door));
+    if room.name == "Security Checkpoint" {
+        ship.checkpoint = path.clone();
+        ship.plate = room
+            .doors
+            .iter()
+            .find(|&door| Some(door.as_str()) != back)
+            .cloned();
+        return Ok(());
+    }
+
+    for door in &room.doors {
+        if Some(door.as_str()) == back {
+            continue;
+        }
+        let text = droid.send(door)?;
+        let next =
+            Room::parse(&text).ok_or_else(|| anyhow!("no room through {}:\n{}", door, text))?;
+        if !ship.visited.contains(&next.name) {
+            path.push(door.clone());
+            explore(droid, next, path, ship)?;
+            path.pop();
+        }
+        droid.send(reverse(door))?;
+    }
+    Ok(())
+}
+
+// How the pressure plate judged the items the droid was carrying.
+enum Weight {
+    Light,
+    Heavy,
+    Right(String),
+}
+
+fn weigh(droid: &Droid, items: &[String], plate: &str) -> Result<Weight> {
+    let mut droid = droid.snapshot();
+    for item in items {
+        droid.send(&format!("take {}", item))?;
+    }
+    let text = droid.send(plate)?;
+    if text.contains("heavier than the detected value") {
+        return Ok(Weight::Light);
+    }
+    if text.contains("lighter than the detected value") {
+        return Ok(Weight::Heavy);
+    }
+    let code = text
+        .split("typing ")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .ok_or_else(|| anyhow!("unexpected reply from the pressure plate:\n{}", text))?;
+    Ok(Weight::Right(code.to_string()))
+}
+
+fn solve() -> Result<String> {
+    let mut droid = Droid::new(INPUT);
+    let text = droid.read()?;
+    let start = Room::parse(&text).ok_or_else(|| anyhow!("no starting room:\n{}", text))?;
+    let mut ship = Ship::default();
+    explore(&mut droid, start, &mut Vec::new(), &mut ship)?;
+    let plate = ship
+        .plate
+        .clone()
+        .ok_or_else(|| anyhow!("never found the pressure plate"))?;
+
+    // Go to the checkpoint and put everything down. This is where every combination starts from,
+    // so nothing has to be replayed.
+    for door in &ship.checkpoint {
+        droid.send(door)?;
+    }
+    for item in &ship.items {
+        droid.send(&format!("drop {}", item))?;
+    }
+
+    // Adding an item only makes the droid heavier, so a combination can be skipped if it holds a
+    // combination that was too heavy or is held by one that was too light.
+    let mut heavy: Vec<usize> = Vec::new();
+    let mut light: Vec<usize> = Vec::new();
+    for combination in 0..1usize << ship.items.len() {
+        if heavy.iter().any(|&h| h & !combination == 0)
+            || light.iter().any(|&l| combination & !l == 0)
+        {
+            continue;
+        }
+        let items = ship
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| combination & 1 << i != 0)
+            .map(|(_, item)| item.clone())
+            .collect::<Vec<_>>();
+        match weigh(&droid, &items, &plate)? {
+            Weight::Light => light.push(combination),
+            Weight::Heavy => heavy.push(combination),
+            Weight::Right(code) => return Ok(code),
+        }
+    }
+    Err(anyhow!("no combination of {:?} weighs enough", ship.items))
+}
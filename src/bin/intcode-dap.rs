@@ -0,0 +1,17 @@
+use intcode::dap::Server;
+
+use anyhow::Result;
+use clap::Parser;
+
+/// Debug Intcode programs from an editor using the Debug Adapter Protocol over stdio.
+#[derive(Parser)]
+#[command(author, about, version)]
+struct Cli {}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    Cli::parse();
+    let stdin = std::io::stdin();
+    let mut server = Server::new(std::io::stdout());
+    server.serve(&mut stdin.lock()).await
+}
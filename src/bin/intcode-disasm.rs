@@ -0,0 +1,28 @@
+use intcode::{disasm::disassemble, process::State, symbols::SymbolTable};
+
+use anyhow::Result;
+use clap::Parser;
+
+/// Disassemble an Intcode program.
+#[derive(Parser)]
+#[command(author, about, version)]
+struct Cli {
+    /// The file containing the comma separated program.
+    program: String,
+
+    /// A side file with names for addresses in the program.
+    #[arg(short, long)]
+    symbols: Option<String>,
+}
+
+fn main() -> Result<()> {
+    let args = Cli::parse();
+    let program = std::fs::read_to_string(args.program)?;
+    let state = State::new(&program);
+    let mut listing = disassemble(&state.memory);
+    if let Some(symbols) = args.symbols {
+        listing.symbols = SymbolTable::load(symbols)?;
+    }
+    print!("{}", listing);
+    Ok(())
+}
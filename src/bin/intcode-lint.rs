@@ -0,0 +1,28 @@
+use intcode::{analysis::lint, process::State};
+
+use anyhow::Result;
+use clap::Parser;
+
+/// Look for common problems in an Intcode program.
+#[derive(Parser)]
+#[command(author, about, version)]
+struct Cli {
+    /// The file containing the comma separated program.
+    program: String,
+}
+
+fn main() -> Result<()> {
+    let args = Cli::parse();
+    let program = std::fs::read_to_string(args.program)?;
+    let state = State::new(&program);
+    let diagnostics = lint(&state.memory);
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+
+    // Fail if we found anything so this can be used in scripts.
+    if !diagnostics.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use intcode::{machine::Machine, script::Script};
+
+use anyhow::Result;
+use clap::Parser;
+
+/// Run a debugger script against an Intcode program.
+#[derive(Parser)]
+#[command(author, about, version)]
+struct Cli {
+    /// The file containing the comma separated program.
+    program: String,
+
+    /// The file containing the script, one command per line. With the rhai feature, files ending
+    /// in `.rhai` are run as Rhai scripts.
+    script: String,
+}
+
+fn main() -> Result<()> {
+    let args = Cli::parse();
+    let program = std::fs::read_to_string(args.program)?;
+    let source = std::fs::read_to_string(&args.script)?;
+
+    #[cfg(feature = "rhai")]
+    if args.script.ends_with(".rhai") {
+        let script = source.parse::<intcode::script::RhaiScript>()?;
+        let machine = std::rc::Rc::new(std::cell::RefCell::new(Machine::new(&program)));
+        for line in script.run(&machine)? {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+
+    let script = source.parse::<Script>()?;
+    let mut machine = Machine::new(&program);
+    for line in script.run(&mut machine)? {
+        println!("{}", line);
+    }
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use std::io::{stdin, stdout, BufRead, Write};
+
+#[cfg(feature = "serde")]
+use intcode::dump::Dump;
+use intcode::machine::Machine;
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+
+/// Work with Intcode programs.
+#[derive(Parser)]
+#[command(author, about, version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a program until it halts.
+    Run {
+        /// The file containing the comma separated program.
+        program: String,
+
+        /// Values to give the program before anything else (e.g. 1,2,3).
+        #[arg(short, long, value_delimiter = ',', allow_hyphen_values = true)]
+        input: Vec<isize>,
+
+        /// Read input from stdin as text and write output as text.
+        #[arg(short, long)]
+        ascii: bool,
+
+        /// Set memory before running (e.g. 1=12,2=2).
+        #[arg(short, long, value_delimiter = ',', value_parser = parse_patch)]
+        patch: Vec<(usize, isize)>,
+
+        /// Give up after running this many instructions.
+        #[arg(short, long)]
+        max_steps: Option<usize>,
+
+        /// Write the memory to this file once the program stops.
+        #[arg(short, long)]
+        dump_memory: Option<String>,
+    },
+    /// Run a program until it halts or waits for input and write its state and IO as JSON.
+    ///
+    /// The format is documented on `intcode::dump::Dump`.
+    #[cfg(feature = "serde")]
+    DumpState {
+        /// The file containing the comma separated program.
+        program: String,
+
+        /// Values to give the program (e.g. 1,2,3).
+        #[arg(short, long, value_delimiter = ',', allow_hyphen_values = true)]
+        input: Vec<isize>,
+
+        /// Set memory before running (e.g. 1=12,2=2).
+        #[arg(short, long, value_delimiter = ',', value_parser = parse_patch)]
+        patch: Vec<(usize, isize)>,
+
+        /// Stop after running this many instructions.
+        #[arg(short, long)]
+        max_steps: Option<usize>,
+
+        /// Write the JSON to this file instead of stdout.
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Pick up running from a state written by dump-state.
+    #[cfg(feature = "serde")]
+    LoadState {
+        /// The file containing the JSON state.
+        state: String,
+
+        /// Values to give the program before anything else (e.g. 1,2,3).
+        #[arg(short, long, value_delimiter = ',', allow_hyphen_values = true)]
+        input: Vec<isize>,
+
+        /// Read input from stdin as text and write output as text.
+        #[arg(short, long)]
+        ascii: bool,
+
+        /// Give up after running this many instructions.
+        #[arg(short, long)]
+        max_steps: Option<usize>,
+    },
+}
+
+fn parse_patch(s: &str) -> Result<(usize, isize)> {
+    let (address, value) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("patch '{}' isn't address=value", s))?;
+    Ok((address.trim().parse()?, value.trim().parse()?))
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::Run {
+            program,
+            input,
+            ascii,
+            patch,
+            max_steps,
+            dump_memory,
+        } => run_program(&program, input, ascii, patch, max_steps, dump_memory),
+        #[cfg(feature = "serde")]
+        Command::DumpState {
+            program,
+            input,
+            patch,
+            max_steps,
+            output,
+        } => dump_state(&program, input, patch, max_steps, output),
+        #[cfg(feature = "serde")]
+        Command::LoadState {
+            state,
+            input,
+            ascii,
+            max_steps,
+        } => {
+            let dump = Dump::from_json(&std::fs::read_to_string(state)?)?;
+            let mut machine = Machine::with_state(dump.state);
+            input
+                .into_iter()
+                .for_each(|value| machine.push_input(value));
+            run(&mut machine, ascii, max_steps)
+        }
+    }
+}
+
+// Create a machine for the program with the memory patched and the inputs queued.
+fn load(program: &str, input: &[isize], patch: Vec<(usize, isize)>) -> Result<Machine> {
+    let mut machine = Machine::new(&std::fs::read_to_string(program)?);
+    for (address, value) in patch {
+        machine.set_memory(address, value);
+    }
+    input.iter().for_each(|&value| machine.push_input(value));
+    Ok(machine)
+}
+
+fn run_program(
+    program: &str,
+    input: Vec<isize>,
+    ascii: bool,
+    patch: Vec<(usize, isize)>,
+    max_steps: Option<usize>,
+    dump_memory: Option<String>,
+) -> Result<()> {
+    let mut machine = load(program, &input, patch)?;
+    let result = run(&mut machine, ascii, max_steps);
+
+    // Dump the memory even if something went wrong since that's usually when it's wanted.
+    if let Some(path) = dump_memory {
+        let memory = machine
+            .state()
+            .memory
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        std::fs::write(path, memory + "\n")?;
+    }
+    result
+}
+
+#[cfg(feature = "serde")]
+fn dump_state(
+    program: &str,
+    input: Vec<isize>,
+    patch: Vec<(usize, isize)>,
+    max_steps: Option<usize>,
+    output: Option<String>,
+) -> Result<()> {
+    let mut machine = load(program, &input, patch)?;
+
+    // Like the memory dump, the state is written even if something went wrong.
+    let result = machine.run(max_steps.unwrap_or(usize::MAX));
+    let json = Dump::new(machine.state(), input, machine.poll_output()).to_json()?;
+    match output {
+        Some(path) => std::fs::write(path, json + "\n")?,
+        None => println!("{}", json),
+    }
+    result.map(|_| ())
+}
+
+fn run(machine: &mut Machine, ascii: bool, max_steps: Option<usize>) -> Result<()> {
+    let mut stdout = stdout().lock();
+    let mut steps = 0;
+    loop {
+        let limit = max_steps.map_or(usize::MAX, |max| max - steps);
+        if limit == 0 {
+            return Err(anyhow!("stopped after {} steps", steps));
+        }
+        steps += machine.run(limit)?;
+
+        for value in machine.poll_output() {
+            match value {
+                0..=127 if ascii => write!(stdout, "{}", value as u8 as char)?,
+                _ => writeln!(stdout, "{}", value)?,
+            }
+        }
+        stdout.flush()?;
+
+        if machine.halted() {
+            return Ok(());
+        }
+        if machine.waiting() {
+            if !ascii {
+                return Err(anyhow!("waiting for input after {} steps", steps));
+            }
+            let mut line = String::new();
+            if stdin().lock().read_line(&mut line)? == 0 {
+                return Err(anyhow!("waiting for input but stdin is closed"));
+            }
+            let line = line.trim_end_matches(['\r', '\n']);
+            line.bytes()
+                .chain(std::iter::once(b'\n'))
+                .for_each(|byte| machine.push_input(byte as isize));
+        }
+    }
+}
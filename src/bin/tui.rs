@@ -1,13 +1,18 @@
-use intcode::app::Notification;
+use intcode::app::{run_process, AppTarget, Notification};
+use intcode::breakpoint::Breakpoint;
+use intcode::history::History;
 use intcode::ipc::Channel;
-use intcode::process::{Process, State};
+use intcode::process::Process;
+use intcode::script::Script;
+use intcode::symbols::SymbolTable;
+use intcode::theme::Theme;
 use intcode::{app::App, tui};
 
 use std::sync::{Arc, Mutex};
 
-use anyhow::Result;
-use clap::{command, Parser, ValueEnum};
-use tokio::sync::mpsc::{self, Receiver};
+use anyhow::{anyhow, Result};
+use clap::{Parser, ValueEnum};
+use tokio::sync::mpsc;
 
 #[derive(Clone, Copy, Default, ValueEnum)]
 #[clap(rename_all = "snake_case")]
@@ -26,48 +31,123 @@ enum Day {
 struct Cli {
     #[arg(short, long)]
     day: Day,
+
+    /// A side file with names for addresses in the first program.
+    #[arg(short, long)]
+    symbols: Option<String>,
+
+    /// A file to load breakpoints from, with a scope and a breakpoint on each line (like
+    /// `* ip 124`). They are saved back to it on exit. With the serde feature, a file ending in
+    /// `.json` is a session file that keeps the watch expressions too.
+    #[arg(short, long)]
+    breakpoints: Option<String>,
+
+    /// A script to run against the first process before the debugger starts. What it logs is
+    /// printed once the debugger exits. With the rhai feature, files ending in `.rhai` are run as
+    /// Rhai scripts.
+    #[arg(long)]
+    script: Option<String>,
+
+    /// The theme to draw with (catppuccin or monokai). It can be changed while running with `T`.
+    #[arg(short, long, default_value = "catppuccin")]
+    theme: String,
+
+    /// Write a log of what the debugger is doing to this file.
+    #[cfg(feature = "tracing")]
+    #[arg(long)]
+    log_file: Option<String>,
+
+    /// The most detailed level to log (error, warn, info, debug, or trace).
+    #[cfg(feature = "tracing")]
+    #[arg(long, default_value = "debug")]
+    log_level: tracing::Level,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Cli::parse();
-    let app = match args.day {
+    #[cfg(feature = "tracing")]
+    if let Some(path) = &args.log_file {
+        intcode::log::init(path, args.log_level)?;
+    }
+    let theme =
+        Theme::by_name(&args.theme).ok_or_else(|| anyhow!("unknown theme '{}'", args.theme))?;
+    Theme::set_current(theme);
+
+    let mut app = match args.day {
         Day::Day2 => day2().await?,
         Day::Day5 => day5().await?,
         Day::Day7 => day7().await?,
         Day::Day9 => day9().await?,
         Day::Day19 => day19().await?,
     };
+    if let Some(symbols) = args.symbols {
+        app.set_symbols(0, SymbolTable::load(symbols)?);
+    }
 
-    tui::run(app).await
-}
+    // A breakpoint file that doesn't exist yet just starts empty.
+    if let Some(path) = &args.breakpoints {
+        if std::path::Path::new(path).exists() {
+            load_breakpoints(&mut app, path)?;
+        }
+    }
 
-async fn main_process(
-    mut notifier: Receiver<Notification>,
-    mut process: Process,
-    state: Arc<Mutex<State>>,
-) {
-    tokio::spawn(async move {
-        while let Some(notification) = notifier.recv().await {
-            if process.state().halted {
-                break;
-            }
-            match notification {
-                Notification::Step => {
-                    process.step().await.unwrap();
-                    *state.lock().unwrap() = process.state();
-                }
-                Notification::StepUntil(breakpoints) => {
-                    process
-                        .run_until(|state, instruction| breakpoints.evaluate(state, instruction))
-                        .await
-                        .unwrap();
-                    *state.lock().unwrap() = process.state();
-                }
-            }
-            *state.lock().unwrap() = process.state();
+    let (app, log) = match &args.script {
+        Some(path) => run_script(app, path)?,
+        None => (app, Vec::new()),
+    };
+    let app = tui::run(app).await?;
+    if let Some(path) = &args.breakpoints {
+        for breakpoint in save_breakpoints(&app, path)? {
+            eprintln!(
+                "{} has a predicate, so it wasn't saved to {}",
+                breakpoint, path
+            );
         }
-    });
+    }
+    for line in log {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+// Load the breakpoint file, or the whole session when it's JSON.
+fn load_breakpoints(app: &mut App, path: &str) -> Result<()> {
+    #[cfg(feature = "serde")]
+    if path.ends_with(".json") {
+        return app.load_session(path);
+    }
+    app.load_breakpoints(path)
+}
+
+// Save the breakpoint file, or the whole session when it's JSON, giving back the breakpoints that
+// couldn't be saved.
+fn save_breakpoints(app: &App, path: &str) -> Result<Vec<Breakpoint>> {
+    #[cfg(feature = "serde")]
+    if path.ends_with(".json") {
+        return app.save_session(path);
+    }
+    app.save_breakpoints(path)
+}
+
+// Run the script against the first process, giving back the app and what the script logged.
+fn run_script(app: App, path: &str) -> Result<(App, Vec<String>)> {
+    let source = std::fs::read_to_string(path)?;
+
+    #[cfg(feature = "rhai")]
+    if path.ends_with(".rhai") {
+        let script = source.parse::<intcode::script::RhaiScript>()?;
+        let target = std::rc::Rc::new(std::cell::RefCell::new(AppTarget::new(app, 0)));
+        let log = script.run(&target)?;
+        let target = std::rc::Rc::into_inner(target)
+            .ok_or_else(|| anyhow!("the script held on to the app"))?;
+        return Ok((target.into_inner().into_app(), log));
+    }
+
+    let script = source.parse::<Script>()?;
+    let mut target = AppTarget::new(app, 0);
+    let log = script.run(&mut target)?;
+    Ok((target.into_app(), log))
 }
 
 async fn day2() -> Result<App> {
@@ -89,9 +169,18 @@ async fn day2() -> Result<App> {
     let state = Arc::new(Mutex::new(process.state()));
     states.push(state.clone());
 
-    main_process(notifier_receiver, process, state).await;
-
-    Ok(App::new(channels, states, notifiers))
+    let history = History::default();
+    let task = run_process(notifier_receiver, process, state, history.clone()).await;
+
+    let mut app = App::new(
+        vec!["gravity assist".to_string()],
+        channels,
+        states,
+        notifiers,
+    );
+    app.set_history(0, history);
+    app.set_task(0, task);
+    Ok(app)
 }
 
 async fn day5() -> Result<App> {
@@ -113,9 +202,16 @@ async fn day5() -> Result<App> {
     let state = Arc::new(Mutex::new(process.state()));
     states.push(state.clone());
 
-    main_process(notifier_receiver, process, state).await;
+    let history = History::default();
+    let task = run_process(notifier_receiver, process, state, history.clone()).await;
 
-    Ok(App::new(channels, states, notifiers))
+    let mut app = App::new(vec!["diagnostic".to_string()], channels, states, notifiers);
+    app.set_input(0, input_sender);
+    app.set_history(0, history);
+    app.connect(0, None, Some(0));
+    app.connect(1, Some(0), None);
+    app.set_task(0, task);
+    Ok(app)
 }
 
 async fn day7() -> Result<App> {
@@ -127,6 +223,8 @@ async fn day7() -> Result<App> {
     let mut channels = vec![channel];
     let mut states = Vec::new();
     let mut notifiers = Vec::new();
+    let mut histories = Vec::new();
+    let mut tasks = Vec::new();
 
     for (i, p) in permutation.iter().enumerate() {
         sender.send(*p as isize + 5).await?;
@@ -146,12 +244,29 @@ async fn day7() -> Result<App> {
         let (notifier, notifier_receiver) = mpsc::channel::<Notification>(32);
         notifiers.push(notifier);
 
-        main_process(notifier_receiver, process, state).await;
+        let history = History::default();
+        histories.push(history.clone());
+        tasks.push(run_process(notifier_receiver, process, state, history).await);
 
         (sender, receiver) = (new_sender, new_receiver);
     }
 
-    Ok(App::new(channels, states, notifiers))
+    let names = (0..permutation.len())
+        .map(|i| format!("AMP {}", (b'A' + i as u8) as char))
+        .collect();
+
+    // The last amplifier feeds the first, so typed input joins its signals.
+    let mut app = App::new(names, channels, states, notifiers);
+    app.set_input(0, first);
+    app.connect(0, Some(4), Some(0));
+    for (i, (history, task)) in histories.into_iter().zip(tasks).enumerate() {
+        app.set_history(i, history);
+        app.set_task(i, task);
+        if i > 0 {
+            app.connect(i, Some(i - 1), Some(i));
+        }
+    }
+    Ok(app)
 }
 
 async fn day9() -> Result<App> {
@@ -169,9 +284,16 @@ async fn day9() -> Result<App> {
     let (notifier, notifier_receiver) = mpsc::channel::<Notification>(32);
     let notifiers = vec![notifier];
 
-    main_process(notifier_receiver, process, state).await;
+    let history = History::default();
+    let task = run_process(notifier_receiver, process, state, history.clone()).await;
 
-    Ok(App::new(channels, states, notifiers))
+    let mut app = App::new(vec!["BOOST".to_string()], channels, states, notifiers);
+    app.set_input(0, tx);
+    app.set_history(0, history);
+    app.connect(0, None, Some(0));
+    app.connect(1, Some(0), None);
+    app.set_task(0, task);
+    Ok(app)
 }
 
 async fn day19() -> Result<App> {
@@ -190,7 +312,14 @@ async fn day19() -> Result<App> {
     let (notifier, notifier_receiver) = mpsc::channel::<Notification>(32);
     let notifiers = vec![notifier];
 
-    main_process(notifier_receiver, process, state).await;
+    let history = History::default();
+    let task = run_process(notifier_receiver, process, state, history.clone()).await;
 
-    Ok(App::new(channels, states, notifiers))
+    let mut app = App::new(vec!["drone".to_string()], channels, states, notifiers);
+    app.set_input(0, tx);
+    app.set_history(0, history);
+    app.connect(0, None, Some(0));
+    app.connect(1, Some(0), None);
+    app.set_task(0, task);
+    Ok(app)
 }
@@ -1,19 +1,114 @@
+use std::fmt::{Debug, Display};
 use std::mem::discriminant;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 
-use crate::{instruction::Instruction, process::State};
+use anyhow::{anyhow, Error, Result};
+use enum_iterator::all;
+
+use crate::{
+    condition::Condition,
+    instruction::{Instruction, InstructionKind, Operation},
+    process::State,
+};
 
 /// A breakpoint that can be set on the Intcode computer.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Breakpoint {
+    /// Break when the given address is part of the instruction about to run.
     MemoryLocation(usize),
+    /// Break when the instruction pointer is exactly the given address.
+    InstructionPointer(usize),
     Instruction(Instruction),
+    /// Break on any instruction of the given kind (e.g. any jump).
+    Kind(InstructionKind),
+    /// Break when the condition on the state is true (e.g. `mem[1000] > 50 && ip == 124`).
+    Condition(Condition),
+    /// Break when the instruction about to run outputs the given value.
+    OutputEquals(isize),
+    /// Break when the instruction about to run outputs a value the predicate accepts.
+    OutputPredicate(Predicate),
+    /// Break when the instruction about to run changes the relative base and, if there is a
+    /// predicate, the predicate accepts the new base.
+    RelativeBaseChanged(Option<Predicate>),
+}
+
+/// A check on a value for breakpoints like [Breakpoint::OutputPredicate]. Two predicates are only
+/// equal if they are clones of each other.
+#[derive(Clone)]
+pub struct Predicate(Arc<dyn Fn(isize) -> bool + Send + Sync>);
+
+impl Predicate {
+    /// Create a predicate from the given function.
+    pub fn new(f: impl Fn(isize) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    /// Check whether the value is accepted by the predicate.
+    pub fn test(&self, value: isize) -> bool {
+        (self.0)(value)
+    }
+}
+
+impl Debug for Predicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Predicate")
+    }
 }
 
+impl PartialEq for Predicate {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Predicate {}
+
 impl Breakpoint {
+    /// Get the address the breakpoint is tied to, if it's tied to one.
+    pub fn address(&self) -> Option<usize> {
+        match self {
+            Breakpoint::MemoryLocation(address) | Breakpoint::InstructionPointer(address) => {
+                Some(*address)
+            }
+            _ => None,
+        }
+    }
+
+    /// Check whether the breakpoint holds a [Predicate]. Predicates are code, so these can't be
+    /// written to a breakpoint or session file.
+    pub fn has_predicate(&self) -> bool {
+        matches!(
+            self,
+            Breakpoint::OutputPredicate(_) | Breakpoint::RelativeBaseChanged(Some(_))
+        )
+    }
+
     /// Evaluate whether the breakpoint should be triggered.
     pub fn evaluate(&self, state: &State, instruction: &Instruction) -> bool {
         match self {
             Breakpoint::Instruction(i) => discriminant(i) == discriminant(instruction),
+            Breakpoint::InstructionPointer(address) => state.instruction_pointer == *address,
+            Breakpoint::Kind(kind) => instruction.kind() == *kind,
+            Breakpoint::Condition(condition) => condition.is_true(state),
+            Breakpoint::OutputEquals(expected) => {
+                Self::output(state, instruction) == Some(*expected)
+            }
+            Breakpoint::OutputPredicate(predicate) => {
+                Self::output(state, instruction).is_some_and(|value| predicate.test(value))
+            }
+            Breakpoint::RelativeBaseChanged(predicate) => match instruction {
+                Instruction::AdjustRelativeBaseOffset(offset) => {
+                    let offset = state.resolve(*offset);
+                    let base = state.relative_base + offset;
+                    match predicate {
+                        Some(predicate) => offset != 0 && predicate.test(base),
+                        None => offset != 0,
+                    }
+                }
+                _ => false,
+            },
             Breakpoint::MemoryLocation(location) => {
                 let size = instruction.parameter_count() + 1;
                 let start = state.instruction_pointer;
@@ -21,33 +116,507 @@ impl Breakpoint {
             }
         }
     }
+
+    // Get the value the instruction will output if it's an output instruction.
+    fn output(state: &State, instruction: &Instruction) -> Option<isize> {
+        match instruction {
+            Instruction::Output(value) => Some(state.resolve(*value)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::MemoryLocation(location) => write!(f, "memory {}", location),
+            Breakpoint::InstructionPointer(address) => write!(f, "ip {}", address),
+            Breakpoint::Instruction(instruction) => {
+                write!(f, "instruction {}", instruction.mnemonic())
+            }
+            Breakpoint::Kind(kind) => write!(f, "kind {:?}", kind),
+            Breakpoint::Condition(condition) => write!(f, "condition {}", condition),
+            Breakpoint::OutputEquals(value) => write!(f, "output {}", value),
+            Breakpoint::OutputPredicate(_) => write!(f, "output predicate"),
+            Breakpoint::RelativeBaseChanged(None) => write!(f, "rb changed"),
+            Breakpoint::RelativeBaseChanged(Some(_)) => write!(f, "rb changed predicate"),
+        }
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = Error;
+
+    /// Parse a breakpoint in the same format it is displayed in (e.g. `ip 124`).
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("invalid breakpoint '{}'", s);
+        let (kind, value) = s.trim().split_once(' ').ok_or_else(invalid)?;
+        let value = value.trim();
+        match kind {
+            "memory" => Ok(Breakpoint::MemoryLocation(value.parse()?)),
+            "ip" => Ok(Breakpoint::InstructionPointer(value.parse()?)),
+            "instruction" => Operation::from_mnemonic(value)
+                .map(|operation| Breakpoint::Instruction(operation.into()))
+                .ok_or_else(invalid),
+            "kind" => all::<InstructionKind>()
+                .find(|kind| format!("{:?}", kind) == value)
+                .map(Breakpoint::Kind)
+                .ok_or_else(invalid),
+            "condition" => Ok(Breakpoint::Condition(value.parse()?)),
+            "output" => Ok(Breakpoint::OutputEquals(value.parse()?)),
+            "rb" if value == "changed" => Ok(Breakpoint::RelativeBaseChanged(None)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+// Breakpoints are written the same way they are in breakpoint files (e.g. `ip 124`), so they can't
+// hold a predicate.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Breakpoint {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match format_with_action(self, &Action::Stop) {
+            Some(text) => serializer.serialize_str(&text),
+            None => Err(serde::ser::Error::custom("can't serialize a predicate")),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Breakpoint {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The processes a breakpoint applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// The breakpoint applies to every process.
+    Global,
+    /// The breakpoint only applies to the process at the given index.
+    Process(usize),
+}
+
+impl Scope {
+    /// Check whether the scope includes the process at the given index.
+    pub fn contains(&self, index: usize) -> bool {
+        match self {
+            Scope::Global => true,
+            Scope::Process(process) => *process == index,
+        }
+    }
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scope::Global => write!(f, "*"),
+            Scope::Process(index) => write!(f, "{}", index),
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = Error;
+
+    /// Parse a scope in the same format it is displayed in, `*` or a process index.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "*" => Ok(Scope::Global),
+            _ => s
+                .parse()
+                .map(Scope::Process)
+                .map_err(|_| anyhow!("invalid scope '{}'", s)),
+        }
+    }
+}
+
+// Scopes are written the same way they are displayed, `*` or a process index.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Scope {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Scope {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// What to do when a breakpoint is hit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Action {
+    /// Stop the process.
+    #[default]
+    Stop,
+    /// Add a message to the trace and keep running, which turns the breakpoint into a tracepoint.
+    Log(Template),
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Stop => write!(f, "stop"),
+            Action::Log(template) => write!(f, "log {}", template),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = Error;
+
+    /// Parse an action in the same format it is displayed in (e.g. `log x is {mem[100]}`).
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().split_once(' ') {
+            _ if s.trim() == "stop" => Ok(Action::Stop),
+            Some(("log", template)) => Ok(Action::Log(template.parse()?)),
+            _ => Err(anyhow!("invalid action '{}'", s)),
+        }
+    }
+}
+
+// Actions are written the same way they are displayed (e.g. `log x is {mem[100]}`).
+#[cfg(feature = "serde")]
+impl serde::Serialize for Action {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Action {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A message for a tracepoint. Anything in braces is a [Condition] expression whose value is filled
+/// in when the message is logged (e.g. `x is {mem[100]} at {ip}`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Value(Condition),
+}
+
+impl Template {
+    /// Fill in the values of the message from the given state.
+    pub fn render(&self, state: &State) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Value(value) => value.evaluate(state).to_string(),
+            })
+            .collect()
+    }
+}
+
+impl Display for Template {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl FromStr for Template {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+        while let Some((text, value)) = rest.split_once('{') {
+            let (value, after) = value
+                .split_once('}')
+                .ok_or_else(|| anyhow!("unclosed '{{' in '{}'", s))?;
+            parts.push(Part::Text(text.to_string()));
+            parts.push(Part::Value(value.parse()?));
+            rest = after;
+        }
+        parts.push(Part::Text(rest.to_string()));
+        Ok(Self {
+            source: s.to_string(),
+            parts,
+        })
+    }
 }
 
-/// A collection of breakpoints.
+/// A collection of breakpoints. In a breakpoint file each line is a breakpoint in its display
+/// format (e.g. `ip 124` or `condition mem[1000] > 50`), optionally followed by `=>` and an action
+/// (e.g. `ip 124 => log x is {mem[100]}`). Lines starting with `#` are comments, so tracepoint
+/// messages can still use it.
 #[derive(Default, Clone)]
 pub struct Breakpoints {
-    breakpoints: Vec<Breakpoint>,
+    breakpoints: Vec<(Breakpoint, Action)>,
+    trace: Arc<Mutex<Vec<String>>>,
 }
 
 impl Breakpoints {
-    /// Add a breakpoint to the collection.
+    /// Load breakpoints from the given breakpoint file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// Save the breakpoints to the given breakpoint file. Breakpoints with a [Predicate] can't be
+    /// written down, so they are left out and returned.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<Vec<Breakpoint>> {
+        std::fs::write(path, self.to_string())?;
+        Ok(self.iter().filter(|b| b.has_predicate()).cloned().collect())
+    }
+
+    /// Create an empty collection whose tracepoints log to the given trace.
+    pub fn with_trace(trace: Arc<Mutex<Vec<String>>>) -> Self {
+        Self {
+            breakpoints: Vec::new(),
+            trace,
+        }
+    }
+
+    /// Add a breakpoint that stops the process to the collection.
     pub fn add(&mut self, breakpoint: Breakpoint) {
-        self.breakpoints.push(breakpoint);
+        self.add_with_action(breakpoint, Action::Stop);
+    }
+
+    /// Add a breakpoint that does the given action when it's hit to the collection.
+    pub fn add_with_action(&mut self, breakpoint: Breakpoint, action: Action) {
+        self.breakpoints.push((breakpoint, action));
+    }
+
+    /// Remove the breakpoint at the given index, if there is one.
+    pub fn remove(&mut self, index: usize) -> Option<Breakpoint> {
+        (index < self.breakpoints.len()).then(|| self.breakpoints.remove(index).0)
+    }
+
+    /// Get the number of breakpoints.
+    pub fn len(&self) -> usize {
+        self.breakpoints.len()
+    }
+
+    /// Check whether there are no breakpoints.
+    pub fn is_empty(&self) -> bool {
+        self.breakpoints.is_empty()
     }
 
-    /// Evaluate whether any of the breakpoints should be triggered.
+    /// Iterate over the breakpoints in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.breakpoints.iter().map(|(breakpoint, _)| breakpoint)
+    }
+
+    /// Get the messages the tracepoints have logged.
+    pub fn trace(&self) -> Vec<String> {
+        self.trace.lock().unwrap().clone()
+    }
+
+    /// Evaluate whether any of the breakpoints should stop the process. Any tracepoints that are
+    /// hit log their message along the way.
     pub fn evaluate(&self, state: &State, instruction: &Instruction) -> bool {
-        self.breakpoints
-            .iter()
-            .any(|b| b.evaluate(state, instruction))
+        let mut stop = false;
+        for (breakpoint, action) in &self.breakpoints {
+            if !breakpoint.evaluate(state, instruction) {
+                continue;
+            }
+            match action {
+                Action::Stop => {
+                    #[cfg(feature = "chrome-trace")]
+                    crate::chrome_trace::breakpoint(state.instruction_pointer);
+                    stop = true
+                }
+                Action::Log(template) => self.trace.lock().unwrap().push(template.render(state)),
+            }
+        }
+        stop
+    }
+}
+
+/// Parse a breakpoint and its action in the breakpoint file format (e.g.
+/// `ip 124 => log {mem[3]}`).
+pub fn parse_with_action(s: &str) -> Result<(Breakpoint, Action)> {
+    match s.split_once("=>") {
+        Some((breakpoint, action)) => Ok((breakpoint.parse()?, action.parse()?)),
+        None => Ok((s.parse()?, Action::Stop)),
+    }
+}
+
+/// Write a breakpoint and its action in the breakpoint file format. Predicates are code, so there
+/// is no way to write them down and `None` is returned for them.
+pub fn format_with_action(breakpoint: &Breakpoint, action: &Action) -> Option<String> {
+    match action {
+        _ if breakpoint.has_predicate() => None,
+        Action::Stop => Some(breakpoint.to_string()),
+        action => Some(format!("{} => {}", breakpoint, action)),
+    }
+}
+
+impl Display for Breakpoints {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (breakpoint, action) in &self.breakpoints {
+            if let Some(line) = format_with_action(breakpoint, action) {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Breakpoints {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut breakpoints = Self::default();
+        for (number, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (breakpoint, action) =
+                parse_with_action(line).map_err(|e| anyhow!("line {}: {}", number + 1, e))?;
+            breakpoints.add_with_action(breakpoint, action);
+        }
+        Ok(breakpoints)
     }
 }
 
 impl IntoIterator for Breakpoints {
-    type Item = Breakpoint;
+    type Item = (Breakpoint, Action);
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.breakpoints.into_iter()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_instruction_pointer() {
+        // The output at 0 covers addresses 0 and 1, but only starts at 0.
+        let mut state = State::new("4,0,99");
+        let (instruction, _) = state.next_instruction().unwrap();
+        assert!(Breakpoint::MemoryLocation(1).evaluate(&state, &instruction));
+        assert!(!Breakpoint::InstructionPointer(1).evaluate(&state, &instruction));
+        assert!(Breakpoint::InstructionPointer(0).evaluate(&state, &instruction));
+
+        state.instruction_pointer = 2;
+        let (instruction, _) = state.next_instruction().unwrap();
+        assert!(Breakpoint::InstructionPointer(2).evaluate(&state, &instruction));
+    }
+
+    #[test]
+    fn test_output() {
+        let state = State::new("104,256,99");
+        let (instruction, _) = state.next_instruction().unwrap();
+        assert!(Breakpoint::OutputEquals(256).evaluate(&state, &instruction));
+        assert!(!Breakpoint::OutputEquals(255).evaluate(&state, &instruction));
+        let damage = Breakpoint::OutputPredicate(Predicate::new(|value| value > 255));
+        assert!(damage.evaluate(&state, &instruction));
+        assert_eq!(damage.clone(), damage);
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.add(Breakpoint::MemoryLocation(1));
+        breakpoints.add(Breakpoint::InstructionPointer(2));
+        breakpoints.add(Breakpoint::Instruction(Instruction::from("JIT")));
+        breakpoints.add(Breakpoint::Kind(InstructionKind::Io));
+        breakpoints.add(Breakpoint::Condition("mem[3] > -1".parse().unwrap()));
+        breakpoints.add(Breakpoint::OutputEquals(-4));
+        breakpoints.add(Breakpoint::RelativeBaseChanged(None));
+        breakpoints.add_with_action(
+            Breakpoint::InstructionPointer(0),
+            "log ip is {ip} # {rb}".parse().unwrap(),
+        );
+        let text = format!("# a comment\n\n{}", breakpoints);
+        let parsed = text.parse::<Breakpoints>().unwrap();
+        assert_eq!(
+            parsed.iter().collect::<Vec<_>>(),
+            breakpoints.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(parsed.to_string(), breakpoints.to_string());
+        assert!("ip".parse::<Breakpoints>().is_err());
+    }
+
+    #[test]
+    fn test_save_skips_predicates() {
+        let path = std::env::temp_dir().join(format!("breakpoints-{}.txt", std::process::id()));
+        let deep = Breakpoint::RelativeBaseChanged(Some(Predicate::new(|base| base > 5)));
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.add(Breakpoint::InstructionPointer(2));
+        breakpoints.add(deep.clone());
+        assert_eq!(breakpoints.save(&path).unwrap(), vec![deep]);
+        let loaded = Breakpoints::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            vec![&Breakpoint::InstructionPointer(2)]
+        );
+    }
+
+    #[test]
+    fn test_relative_base_changed() {
+        let mut state = State::new("109,5,209,0,99");
+        let (instruction, _) = state.next_instruction().unwrap();
+        let any = Breakpoint::RelativeBaseChanged(None);
+        let deep = Breakpoint::RelativeBaseChanged(Some(Predicate::new(|base| base > 5)));
+        assert!(any.evaluate(&state, &instruction));
+        assert!(!deep.evaluate(&state, &instruction));
+
+        // Adding R[0] (the 0 at 3) doesn't change the base.
+        state.relative_base = 3;
+        state.instruction_pointer = 2;
+        let (instruction, _) = state.next_instruction().unwrap();
+        assert!(!any.evaluate(&state, &instruction));
+    }
+
+    #[test]
+    fn test_tracepoint() {
+        let state = State::new("104,256,99");
+        let (instruction, _) = state.next_instruction().unwrap();
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.add_with_action(
+            Breakpoint::InstructionPointer(0),
+            "log out {mem[ip + 1]}, {mem[2] - 9}!".parse().unwrap(),
+        );
+        assert!(!breakpoints.evaluate(&state, &instruction));
+        assert_eq!(breakpoints.trace(), vec!["out 256, 90!"]);
+        assert!("log {mem[".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.add(Breakpoint::MemoryLocation(1));
+        breakpoints.add(Breakpoint::InstructionPointer(2));
+        assert_eq!(breakpoints.remove(2), None);
+        assert_eq!(breakpoints.remove(0), Some(Breakpoint::MemoryLocation(1)));
+        assert_eq!(
+            breakpoints.iter().collect::<Vec<_>>(),
+            vec![&Breakpoint::InstructionPointer(2)]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use serde::de::value::{Error, StrDeserializer};
+        use serde::Deserialize;
+
+        let text = StrDeserializer::<Error>::new("condition mem[3] > 1");
+        let breakpoint = Breakpoint::deserialize(text).unwrap();
+        assert_eq!(breakpoint, "condition mem[3] > 1".parse().unwrap());
+        assert!(Breakpoint::deserialize(StrDeserializer::<Error>::new("ip")).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use std::sync::Mutex;
+use std::time::Instant;
+
+use anyhow::Result;
+use serde_json::{json, Map, Value};
+
+use crate::instruction::Instruction;
+
+// The trace groups its events under these, which show up as separate sections of the timeline.
+const PROCESSES: usize = 1;
+const CHANNELS: usize = 2;
+const DEBUGGER: usize = 3;
+
+/// A single event in the Chrome `trace_event` format. Times are in microseconds since recording
+/// started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub name: String,
+    pub category: &'static str,
+    /// `X` for something that took some time or `i` for something that happened at an instant.
+    pub phase: char,
+    pub timestamp: u128,
+    pub duration: u128,
+    /// The section of the timeline the event is in.
+    pub pid: usize,
+    /// The row within the section, which is the process or channel id.
+    pub tid: usize,
+    pub args: Vec<(&'static str, Value)>,
+}
+
+impl Event {
+    fn to_json(&self) -> Value {
+        let mut event = json!({
+            "name": self.name,
+            "cat": self.category,
+            "ph": self.phase.to_string(),
+            "ts": self.timestamp as i64,
+            "pid": self.pid,
+            "tid": self.tid,
+        });
+        match self.phase {
+            'X' => event["dur"] = json!(self.duration as i64),
+            // Instant events are drawn on just their own row.
+            _ => event["s"] = json!("t"),
+        }
+        if !self.args.is_empty() {
+            let args = self
+                .args
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect::<Map<_, _>>();
+            event["args"] = Value::Object(args);
+        }
+        event
+    }
+}
+
+struct Recorder {
+    start: Instant,
+    events: Vec<Event>,
+}
+
+// Nothing is recorded until recording is started, so the hooks cost a lock and nothing else.
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+/// Start recording events, throwing away anything recorded before.
+pub fn start() {
+    *RECORDER.lock().unwrap() = Some(Recorder {
+        start: Instant::now(),
+        events: Vec::new(),
+    });
+}
+
+/// Stop recording and take everything that was recorded.
+pub fn stop() -> Vec<Event> {
+    RECORDER
+        .lock()
+        .unwrap()
+        .take()
+        .map(|recorder| recorder.events)
+        .unwrap_or_default()
+}
+
+/// Stop recording and write what was recorded as a Chrome trace that can be opened in
+/// `chrome://tracing` or <https://ui.perfetto.dev>.
+pub fn write(path: &str) -> Result<()> {
+    std::fs::write(path, export(&stop()).to_string())?;
+    Ok(())
+}
+
+/// Turn the events into a Chrome trace, naming each process and channel row.
+pub fn export(events: &[Event]) -> Value {
+    let mut rows = events
+        .iter()
+        .map(|event| (event.pid, event.tid))
+        .collect::<Vec<_>>();
+    rows.sort();
+    rows.dedup();
+
+    let section = |pid: usize, name: &str| json!({ "name": "process_name", "ph": "M", "pid": pid, "args": { "name": name } });
+    let mut trace = vec![
+        section(PROCESSES, "processes"),
+        section(CHANNELS, "channels"),
+        section(DEBUGGER, "debugger"),
+    ];
+    trace.extend(rows.into_iter().map(|(pid, tid)| {
+        let name = match pid {
+            PROCESSES => format!("process {}", tid),
+            CHANNELS => format!("channel {}", tid),
+            _ => "breakpoints".to_string(),
+        };
+        json!({
+            "name": "thread_name",
+            "ph": "M",
+            "pid": pid,
+            "tid": tid,
+            "args": { "name": name },
+        })
+    }));
+    trace.extend(events.iter().map(Event::to_json));
+    json!({ "traceEvents": trace })
+}
+
+fn record(f: impl FnOnce(u128) -> Event) {
+    if let Some(recorder) = RECORDER.lock().unwrap().as_mut() {
+        let now = recorder.start.elapsed().as_micros();
+        recorder.events.push(f(now));
+    }
+}
+
+/// Record an instruction the process with the given id ran, which started at `start`.
+pub fn instruction(process: usize, address: usize, instruction: &Instruction, start: Instant) {
+    let duration = start.elapsed().as_micros();
+    record(|now| Event {
+        name: instruction.operation().mnemonic().to_string(),
+        category: "instruction",
+        phase: 'X',
+        timestamp: now.saturating_sub(duration),
+        duration,
+        pid: PROCESSES,
+        tid: process,
+        args: vec![("address", address.into())],
+    });
+}
+
+/// Record a value being sent or received on the channel with the given id.
+pub fn channel(name: &str, channel: usize, value: isize) {
+    record(|now| Event {
+        name: name.to_string(),
+        category: "channel",
+        phase: 'i',
+        timestamp: now,
+        duration: 0,
+        pid: CHANNELS,
+        tid: channel,
+        args: vec![("value", value.into())],
+    });
+}
+
+/// Record a breakpoint stopping a process at the given address.
+pub fn breakpoint(address: usize) {
+    record(|now| Event {
+        name: "breakpoint".to_string(),
+        category: "debugger",
+        phase: 'i',
+        timestamp: now,
+        duration: 0,
+        pid: DEBUGGER,
+        tid: 0,
+        args: vec![("address", address.into())],
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parameter::Parameter;
+
+    #[test]
+    fn test_export() {
+        // Other tests may be running processes while this records, so only our rows are checked.
+        let (process, channel_id, address) = (usize::MAX - 1, usize::MAX - 2, usize::MAX - 3);
+        start();
+        instruction(
+            process,
+            0,
+            &Instruction::Output(Parameter::Immediate(1)),
+            Instant::now(),
+        );
+        channel("send", channel_id, 1);
+        breakpoint(address);
+        let events = stop()
+            .into_iter()
+            .filter(|e| {
+                e.tid == process || e.tid == channel_id || e.args == [("address", json!(address))]
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(),
+            vec!["OUT", "send", "breakpoint"]
+        );
+
+        // Nothing is recorded once recording has stopped.
+        breakpoint(2);
+        assert!(stop().is_empty());
+
+        let trace = export(&events);
+        let rows = trace["traceEvents"].as_array().unwrap();
+        assert_eq!(rows.len(), 3 + 3 + 3);
+        assert!(trace
+            .to_string()
+            .contains(&format!(r#""name":"process {}""#, process)));
+    }
+}
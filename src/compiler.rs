@@ -0,0 +1,549 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use anyhow::{anyhow, Result};
+
+use crate::asm::assemble;
+use crate::lexer::{Token, Tokens};
+
+/// Compile a program written in a small C-like language into an Intcode program. A program is a
+/// list of functions and execution starts at `main`.
+///
+/// ```text
+/// // Print the factorial of the input.
+/// fn factorial(n) {
+///     if n < 2 {
+///         return 1;
+///     }
+///     return n * factorial(n - 1);
+/// }
+///
+/// fn main() {
+///     output(factorial(input()));
+/// }
+/// ```
+///
+/// The only type is an integer. Statements are `let x = e;`, `x = e;`, `if e { } else { }`,
+/// `while e { }`, `return e;`, and expressions. Expressions support `+`, `-`, `*`, comparisons,
+/// `!`, calls, and the builtins `input()` and `output(e)` (which evaluates to `e`). Variables are
+/// scoped to the function they are declared in and functions without a `return` return 0.
+pub fn compile(source: &str) -> Result<Vec<isize>> {
+    assemble(&compile_to_asm(source)?)
+}
+
+/// Compile the program into the assembly that the [assemble] function understands. This is mostly
+/// useful to see what the compiler generates.
+pub fn compile_to_asm(source: &str) -> Result<String> {
+    let tokens = Tokens::new(source, &SYMBOLS, "program")?;
+    let functions = Parser { tokens }.program()?;
+    Generator::new(&functions)?.program(&functions)
+}
+
+// The symbols in the language. Longer symbols come first so they are matched before their
+// prefixes.
+const SYMBOLS: [&str; 17] = [
+    "<=", ">=", "==", "!=", "(", ")", "{", "}", ",", ";", "=", "+", "-", "*", "<", ">", "!",
+];
+
+#[derive(Debug)]
+enum Expression {
+    Number(isize),
+    Variable(String),
+    Unary(&'static str, Box<Expression>),
+    Binary(&'static str, Box<Expression>, Box<Expression>),
+    Call(String, Vec<Expression>),
+}
+
+#[derive(Debug)]
+enum Statement {
+    Let(String, Expression),
+    Assign(String, Expression),
+    If(Expression, Vec<Statement>, Vec<Statement>),
+    While(Expression, Vec<Statement>),
+    Return(Option<Expression>),
+    Expression(Expression),
+}
+
+#[derive(Debug)]
+struct Function {
+    name: String,
+    parameters: Vec<String>,
+    body: Vec<Statement>,
+}
+
+// A recursive descent parser for the language.
+struct Parser {
+    tokens: Tokens,
+}
+
+impl Parser {
+    fn program(&mut self) -> Result<Vec<Function>> {
+        let mut functions = Vec::new();
+        while self.tokens.peek().is_some() {
+            functions.push(self.function()?);
+        }
+        Ok(functions)
+    }
+
+    fn function(&mut self) -> Result<Function> {
+        self.tokens.keyword("fn")?;
+        let name = self.tokens.identifier()?;
+        self.tokens.expect("(")?;
+        let mut parameters = Vec::new();
+        while !self.tokens.accept(")") {
+            if !parameters.is_empty() {
+                self.tokens.expect(",")?;
+            }
+            parameters.push(self.tokens.identifier()?);
+        }
+        let body = self.block()?;
+        Ok(Function {
+            name,
+            parameters,
+            body,
+        })
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>> {
+        self.tokens.expect("{")?;
+        let mut statements = Vec::new();
+        while !self.tokens.accept("}") {
+            statements.push(self.statement()?);
+        }
+        Ok(statements)
+    }
+
+    fn statement(&mut self) -> Result<Statement> {
+        if self.tokens.accept_keyword("let") {
+            let name = self.tokens.identifier()?;
+            self.tokens.expect("=")?;
+            let value = self.expression()?;
+            self.tokens.expect(";")?;
+            return Ok(Statement::Let(name, value));
+        }
+        if self.tokens.accept_keyword("if") {
+            let condition = self.expression()?;
+            let then = self.block()?;
+            let otherwise = match self.tokens.accept_keyword("else") {
+                // An `else if` is just an `if` in the else block.
+                true if self.tokens.peek() == Some(&Token::Identifier("if".to_string())) => {
+                    vec![self.statement()?]
+                }
+                true => self.block()?,
+                false => vec![],
+            };
+            return Ok(Statement::If(condition, then, otherwise));
+        }
+        if self.tokens.accept_keyword("while") {
+            let condition = self.expression()?;
+            let body = self.block()?;
+            return Ok(Statement::While(condition, body));
+        }
+        if self.tokens.accept_keyword("return") {
+            let value = match self.tokens.accept(";") {
+                true => None,
+                false => {
+                    let value = self.expression()?;
+                    self.tokens.expect(";")?;
+                    Some(value)
+                }
+            };
+            return Ok(Statement::Return(value));
+        }
+
+        // Assignments look like expressions until we see the `=`.
+        if let (Some(Token::Identifier(_)), Some(Token::Symbol("="))) =
+            (self.tokens.peek(), self.tokens.peek_nth(1))
+        {
+            let name = self.tokens.identifier()?;
+            self.tokens.expect("=")?;
+            let value = self.expression()?;
+            self.tokens.expect(";")?;
+            return Ok(Statement::Assign(name, value));
+        }
+
+        let expression = self.expression()?;
+        self.tokens.expect(";")?;
+        Ok(Statement::Expression(expression))
+    }
+
+    fn expression(&mut self) -> Result<Expression> {
+        self.binary(0)
+    }
+
+    // Parse binary operators by precedence level, lowest first.
+    fn binary(&mut self, level: usize) -> Result<Expression> {
+        const LEVELS: [&[&str]; 4] = [&["==", "!="], &["<", ">", "<=", ">="], &["+", "-"], &["*"]];
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        while let Some(op) = self.tokens.accept_any(LEVELS[level]) {
+            let right = self.binary(level + 1)?;
+            left = Expression::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expression> {
+        for op in ["-", "!"] {
+            if self.tokens.accept(op) {
+                return Ok(Expression::Unary(op, Box::new(self.unary()?)));
+            }
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expression> {
+        match self.tokens.next_token()? {
+            Token::Number(value) => Ok(Expression::Number(value)),
+            Token::Symbol("(") => {
+                let expression = self.expression()?;
+                self.tokens.expect(")")?;
+                Ok(expression)
+            }
+            Token::Identifier(name) if self.tokens.accept("(") => {
+                let mut arguments = Vec::new();
+                while !self.tokens.accept(")") {
+                    if !arguments.is_empty() {
+                        self.tokens.expect(",")?;
+                    }
+                    arguments.push(self.expression()?);
+                }
+                Ok(Expression::Call(name, arguments))
+            }
+            Token::Identifier(name) => Ok(Expression::Variable(name)),
+            token => Err(anyhow!("unexpected '{}'", token)),
+        }
+    }
+}
+
+// Generates the assembly for a program. The relative base is the stack pointer and always points
+// at the next free cell. Every expression pushes its result onto the stack, so we track how deep
+// the stack is relative to the start of the current function to find the variables.
+//
+// A call pushes a cell for the return value, the arguments, and then the return address, so the
+// frame of a function with `n` parameters looks like this relative to where the stack was when it
+// was entered:
+//
+// -(n + 2)     return value
+// -(n + 1)..-1 parameters
+// -1           return address
+// 0..          local variables
+struct Generator {
+    output: String,
+    arities: HashMap<String, usize>,
+    variables: HashMap<String, isize>,
+    return_slot: isize,
+    depth: isize,
+    labels: usize,
+}
+
+impl Generator {
+    fn new(functions: &[Function]) -> Result<Self> {
+        let mut arities = HashMap::new();
+        for function in functions {
+            if arities
+                .insert(function.name.clone(), function.parameters.len())
+                .is_some()
+            {
+                return Err(anyhow!("function '{}' is defined twice", function.name));
+            }
+        }
+        match arities.get("main") {
+            Some(0) => {}
+            Some(_) => return Err(anyhow!("main should not take any parameters")),
+            None => return Err(anyhow!("no main function")),
+        }
+
+        Ok(Self {
+            output: String::new(),
+            arities,
+            variables: HashMap::new(),
+            return_slot: 0,
+            depth: 0,
+            labels: 0,
+        })
+    }
+
+    fn program(mut self, functions: &[Function]) -> Result<String> {
+        self.emit("ARO I[stack]");
+        self.emit("PUSH I[0]");
+        self.emit("CALL I[fn_main]");
+        self.emit("HLT");
+        for function in functions {
+            self.function(function)?;
+        }
+        self.emit("stack:");
+        Ok(self.output)
+    }
+
+    fn function(&mut self, function: &Function) -> Result<()> {
+        let count = function.parameters.len() as isize;
+        self.variables.clear();
+        for (i, parameter) in function.parameters.iter().enumerate() {
+            self.variables
+                .insert(parameter.clone(), i as isize - (count + 1));
+        }
+        self.return_slot = -(count + 2);
+
+        // Every local gets a slot for the whole function.
+        let mut locals = 0;
+        declare(&function.body, &mut |name| {
+            if !self.variables.contains_key(name) {
+                self.variables.insert(name.to_string(), locals);
+                locals += 1;
+            }
+        });
+
+        self.emit(&format!("fn_{}:", function.name));
+        if locals > 0 {
+            self.emit(&format!("ARO I[{}]", locals));
+        }
+        self.depth = locals;
+        self.block(&function.body)?;
+        self.emit(&format!("ARO I[{}]", -self.depth));
+        self.emit("RET");
+        Ok(())
+    }
+
+    fn block(&mut self, statements: &[Statement]) -> Result<()> {
+        for statement in statements {
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Let(name, value) | Statement::Assign(name, value) => {
+                let slot = self.variable(name)?;
+                self.expression(value)?;
+                self.emit(&format!("ADD R[-1] + I[0] -> R[{}]", slot - self.depth));
+                self.pop(1);
+            }
+            Statement::If(condition, then, otherwise) => {
+                let (otherwise_label, end) = (self.label(), self.label());
+                self.condition(condition, &otherwise_label)?;
+                self.block(then)?;
+                self.emit(&format!("JIT I[1] -> I[{}]", end));
+                self.emit(&format!("{}:", otherwise_label));
+                self.block(otherwise)?;
+                self.emit(&format!("{}:", end));
+            }
+            Statement::While(condition, body) => {
+                let (start, end) = (self.label(), self.label());
+                self.emit(&format!("{}:", start));
+                self.condition(condition, &end)?;
+                self.block(body)?;
+                self.emit(&format!("JIT I[1] -> I[{}]", start));
+                self.emit(&format!("{}:", end));
+            }
+            Statement::Return(value) => {
+                if let Some(value) = value {
+                    self.expression(value)?;
+                    let slot = self.return_slot - self.depth;
+                    self.emit(&format!("ADD R[-1] + I[0] -> R[{}]", slot));
+                }
+                // Unwind the stack without changing what we think the depth is, since the code
+                // after the return is still at this depth.
+                self.emit(&format!("ARO I[{}]", -self.depth));
+                self.emit("RET");
+                if value.is_some() {
+                    self.depth -= 1;
+                }
+            }
+            Statement::Expression(value) => {
+                self.expression(value)?;
+                self.pop(1);
+            }
+        }
+        Ok(())
+    }
+
+    // Evaluate the condition and jump to the label if it's false.
+    fn condition(&mut self, condition: &Expression, label: &str) -> Result<()> {
+        self.expression(condition)?;
+        self.pop(1);
+        // The value is still just past the top of the stack after popping it.
+        self.emit(&format!("JIF R[0] -> I[{}]", label));
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<()> {
+        match expression {
+            Expression::Number(value) => self.push(&format!("I[{}]", value)),
+            Expression::Variable(name) => {
+                let slot = self.variable(name)?;
+                self.push(&format!("R[{}]", slot - self.depth));
+            }
+            Expression::Unary(op, value) => {
+                self.expression(value)?;
+                match *op {
+                    "-" => self.emit("MUL R[-1] * I[-1] -> R[-1]"),
+                    _ => self.emit("EQL R[-1] == I[0] -> R[-1]"),
+                }
+            }
+            Expression::Binary(op, left, right) => {
+                self.expression(left)?;
+                self.expression(right)?;
+                match *op {
+                    "+" => self.emit("ADD R[-2] + R[-1] -> R[-2]"),
+                    "*" => self.emit("MUL R[-2] * R[-1] -> R[-2]"),
+                    "-" => {
+                        self.emit("MUL R[-1] * I[-1] -> R[-1]");
+                        self.emit("ADD R[-2] + R[-1] -> R[-2]");
+                    }
+                    "<" => self.emit("LST R[-2] < R[-1] -> R[-2]"),
+                    ">" => self.emit("LST R[-1] < R[-2] -> R[-2]"),
+                    "==" => self.emit("EQL R[-2] == R[-1] -> R[-2]"),
+                    // The rest are the negation of one of the others.
+                    "<=" => {
+                        self.emit("LST R[-1] < R[-2] -> R[-2]");
+                        self.emit("EQL R[-2] == I[0] -> R[-2]");
+                    }
+                    ">=" => {
+                        self.emit("LST R[-2] < R[-1] -> R[-2]");
+                        self.emit("EQL R[-2] == I[0] -> R[-2]");
+                    }
+                    _ => {
+                        self.emit("EQL R[-2] == R[-1] -> R[-2]");
+                        self.emit("EQL R[-2] == I[0] -> R[-2]");
+                    }
+                }
+                self.pop(1);
+            }
+            Expression::Call(name, arguments) => self.call(name, arguments)?,
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, name: &str, arguments: &[Expression]) -> Result<()> {
+        match (name, arguments) {
+            ("input", []) => {
+                self.emit("INP R[0]");
+                self.emit("ARO I[1]");
+                self.depth += 1;
+            }
+            ("output", [value]) => {
+                self.expression(value)?;
+                self.emit("OUT R[-1]");
+            }
+            ("input" | "output", _) => {
+                return Err(anyhow!("wrong number of arguments to '{}'", name));
+            }
+            _ => {
+                let arity = self
+                    .arities
+                    .get(name)
+                    .ok_or_else(|| anyhow!("unknown function '{}'", name))?;
+                if *arity != arguments.len() {
+                    return Err(anyhow!("wrong number of arguments to '{}'", name));
+                }
+
+                self.push("I[0]");
+                for argument in arguments {
+                    self.expression(argument)?;
+                }
+                self.emit(&format!("CALL I[fn_{}]", name));
+                self.pop(arguments.len() as isize);
+            }
+        }
+        Ok(())
+    }
+
+    fn variable(&self, name: &str) -> Result<isize> {
+        self.variables
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow!("unknown variable '{}'", name))
+    }
+
+    fn push(&mut self, parameter: &str) {
+        self.emit(&format!("PUSH {}", parameter));
+        self.depth += 1;
+    }
+
+    fn pop(&mut self, count: isize) {
+        if count > 0 {
+            self.emit(&format!("ARO I[{}]", -count));
+            self.depth -= count;
+        }
+    }
+
+    fn label(&mut self) -> String {
+        self.labels += 1;
+        format!("L{}", self.labels)
+    }
+
+    fn emit(&mut self, line: &str) {
+        // Labels are flush left so the listing is easier to read.
+        let indent = match line.ends_with(':') {
+            true => "",
+            false => "    ",
+        };
+        writeln!(self.output, "{}{}", indent, line).unwrap();
+    }
+}
+
+// Call the given function for every variable declared in the statements.
+fn declare(statements: &[Statement], f: &mut impl FnMut(&str)) {
+    for statement in statements {
+        match statement {
+            Statement::Let(name, _) => f(name),
+            Statement::If(_, then, otherwise) => {
+                declare(then, f);
+                declare(otherwise, f);
+            }
+            Statement::While(_, body) => declare(body, f),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ipc::Channel, process::Process};
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_compile() {
+        let source = "
+            fn factorial(n) {
+                if n < 2 {
+                    return 1;
+                }
+                return n * factorial(n - 1);
+            }
+
+            // Count down from the input, then print its factorial.
+            fn main() {
+                let n = input();
+                let i = n;
+                while i > 0 {
+                    output(i);
+                    i = i - 1;
+                }
+                output(factorial(n));
+            }
+        ";
+        let program = compile(source)
+            .unwrap()
+            .iter()
+            .map(|cell| cell.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let (_, mut input_sender, input_receiver) = Channel::new(true);
+        let (_, output_sender, output_receiver) = Channel::new(true);
+        let mut computer = Process::new(&program, input_receiver, output_sender);
+        tokio::spawn(async move { computer.run().await });
+        input_sender.send(5).await.unwrap();
+        let outputs = output_receiver.collect::<Vec<_>>().await;
+        assert_eq!(outputs, vec![5, 4, 3, 2, 1, 120]);
+
+        assert!(compile("fn main() { x = 1; }").is_err());
+        assert!(compile("fn f() {}").is_err());
+    }
+}
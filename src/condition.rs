@@ -0,0 +1,226 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+
+use crate::lexer::{Token, Tokens};
+use crate::process::State;
+
+/// A condition on the state of a process, like `mem[1000] > 50 && ip == 124`. The values are
+/// numbers, `ip` (the instruction pointer), `rb` (the relative base), and `mem[e]` (the value in
+/// memory at `e`). They can be combined with `+`, `-`, `*`, comparisons, `!`, `&&`, `||`, and
+/// parentheses. Like C, anything that isn't zero is true. Arithmetic wraps around instead of
+/// overflowing, since the values come from the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    source: String,
+    expression: Expression,
+}
+
+impl Condition {
+    /// Evaluate the condition against the given state.
+    pub fn evaluate(&self, state: &State) -> isize {
+        self.expression.evaluate(state)
+    }
+
+    /// Check whether the condition holds for the given state.
+    pub fn is_true(&self, state: &State) -> bool {
+        self.evaluate(state) != 0
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl FromStr for Condition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: Tokens::new(s, &SYMBOLS, "condition")?,
+        };
+        let expression = parser.expression()?;
+        parser.tokens.finish()?;
+        Ok(Self {
+            source: s.trim().to_string(),
+            expression,
+        })
+    }
+}
+
+// Conditions are written the same way they are displayed (e.g. `mem[1000] > 50`).
+#[cfg(feature = "serde")]
+impl serde::Serialize for Condition {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Condition {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expression {
+    Number(isize),
+    InstructionPointer,
+    RelativeBase,
+    Memory(Box<Expression>),
+    Unary(&'static str, Box<Expression>),
+    Binary(&'static str, Box<Expression>, Box<Expression>),
+}
+
+impl Expression {
+    fn evaluate(&self, state: &State) -> isize {
+        match self {
+            Expression::Number(value) => *value,
+            Expression::InstructionPointer => state.instruction_pointer as isize,
+            Expression::RelativeBase => state.relative_base,
+            // Negative addresses can't hold anything, so they read as zero.
+            Expression::Memory(address) => match usize::try_from(address.evaluate(state)) {
+                Ok(address) => state[address],
+                Err(_) => 0,
+            },
+            Expression::Unary(op, value) => {
+                let value = value.evaluate(state);
+                match *op {
+                    "-" => value.wrapping_neg(),
+                    _ => (value == 0) as isize,
+                }
+            }
+            // These short circuit so `mem[x]` isn't read when it doesn't need to be.
+            Expression::Binary("&&", left, right) => {
+                (left.evaluate(state) != 0 && right.evaluate(state) != 0) as isize
+            }
+            Expression::Binary("||", left, right) => {
+                (left.evaluate(state) != 0 || right.evaluate(state) != 0) as isize
+            }
+            Expression::Binary(op, left, right) => {
+                let (left, right) = (left.evaluate(state), right.evaluate(state));
+                match *op {
+                    "+" => left.wrapping_add(right),
+                    "-" => left.wrapping_sub(right),
+                    "*" => left.wrapping_mul(right),
+                    "==" => (left == right) as isize,
+                    "!=" => (left != right) as isize,
+                    "<" => (left < right) as isize,
+                    "<=" => (left <= right) as isize,
+                    ">" => (left > right) as isize,
+                    _ => (left >= right) as isize,
+                }
+            }
+        }
+    }
+}
+
+// Longer symbols come first so they are matched before their prefixes.
+const SYMBOLS: [&str; 16] = [
+    "&&", "||", "<=", ">=", "==", "!=", "(", ")", "[", "]", "+", "-", "*", "<", ">", "!",
+];
+
+// A recursive descent parser for conditions.
+struct Parser {
+    tokens: Tokens,
+}
+
+impl Parser {
+    fn expression(&mut self) -> Result<Expression> {
+        self.binary(0)
+    }
+
+    // Parse binary operators by precedence level, lowest first.
+    fn binary(&mut self, level: usize) -> Result<Expression> {
+        const LEVELS: [&[&str]; 6] = [
+            &["||"],
+            &["&&"],
+            &["==", "!="],
+            &["<", ">", "<=", ">="],
+            &["+", "-"],
+            &["*"],
+        ];
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        while let Some(op) = self.tokens.accept_any(LEVELS[level]) {
+            let right = self.binary(level + 1)?;
+            left = Expression::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expression> {
+        for op in ["-", "!"] {
+            if self.tokens.accept(op) {
+                return Ok(Expression::Unary(op, Box::new(self.unary()?)));
+            }
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expression> {
+        match self.tokens.next_token()? {
+            Token::Number(value) => Ok(Expression::Number(value)),
+            Token::Symbol("(") => {
+                let expression = self.expression()?;
+                self.tokens.expect(")")?;
+                Ok(expression)
+            }
+            Token::Identifier(name) => match name.as_str() {
+                "ip" => Ok(Expression::InstructionPointer),
+                "rb" => Ok(Expression::RelativeBase),
+                "mem" => {
+                    self.tokens.expect("[")?;
+                    let address = self.expression()?;
+                    self.tokens.expect("]")?;
+                    Ok(Expression::Memory(Box::new(address)))
+                }
+                _ => Err(anyhow!("unknown value '{}'", name)),
+            },
+            token => Err(anyhow!("unexpected '{}'", token)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_condition() {
+        let mut state = State::new("1,2,3,4");
+        state.instruction_pointer = 2;
+        state.relative_base = 10;
+
+        let condition = "mem[3] > 3 && ip == 2".parse::<Condition>().unwrap();
+        assert!(condition.is_true(&state));
+        assert_eq!(condition.to_string(), "mem[3] > 3 && ip == 2");
+        state.instruction_pointer = 1;
+        assert!(!condition.is_true(&state));
+
+        let evaluate = |s: &str| s.parse::<Condition>().unwrap().evaluate(&state);
+        assert_eq!(evaluate("1 + 2 * 3"), 7);
+        assert_eq!(evaluate("mem[ip + 1] - rb"), -7);
+        assert_eq!(evaluate("!(ip == 1) || mem[-1]"), 0);
+        assert_eq!(evaluate("mem[100]"), 0);
+
+        // Values from the program can be anything, so overflowing wraps rather than panicking.
+        state[0] = isize::MAX;
+        let evaluate = |s: &str| s.parse::<Condition>().unwrap().evaluate(&state);
+        assert_eq!(evaluate("mem[0] + 1"), isize::MIN);
+        assert_eq!(evaluate("mem[0] * 2"), -2);
+        assert_eq!(evaluate("-(0 - mem[0] - 1)"), isize::MIN);
+        assert_eq!(evaluate("0 - mem[0] - 2"), isize::MAX);
+
+        assert!("mem[3".parse::<Condition>().is_err());
+        assert!("pc == 1".parse::<Condition>().is_err());
+        assert!("1 2".parse::<Condition>().is_err());
+    }
+}
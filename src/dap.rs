@@ -0,0 +1,554 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, Write};
+
+use crate::breakpoint::{Breakpoint, Breakpoints};
+use crate::disasm::disassemble;
+use crate::instruction::Instruction;
+use crate::ipc::{Channel, ChannelReceiver, ChannelSender};
+use crate::process::Process;
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+/// Read a single message from the client. Messages are a `Content-Length` header followed by a
+/// blank line and the JSON body. This returns `None` once the client closes the stream.
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| anyhow!("message without a Content-Length header"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// Write a single message to the client with its `Content-Length` header.
+pub fn write_message(writer: &mut impl Write, message: &Value) -> Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+// The variables references for the scopes. Memory is shown in rows of cells and each row gets its
+// own reference after these.
+const REGISTERS: i64 = 1;
+const MEMORY: i64 = 2;
+const ADDITIONAL_MEMORY: i64 = 3;
+const ROWS: i64 = 100;
+const ROW_SIZE: usize = 8;
+
+// The only thread and frame, since a process runs a single stream of instructions.
+const THREAD: i64 = 1;
+const SOURCE: i64 = 1;
+
+// Why the process stopped running.
+enum Stop {
+    Entry,
+    Step,
+    Breakpoint,
+    Halted,
+    Error(String),
+}
+
+// A program that has been launched along with the channels used to talk to it.
+struct Program {
+    name: String,
+    process: Process,
+    input: ChannelSender,
+    output: ChannelReceiver,
+    inputs: VecDeque<isize>,
+    stop_on_entry: bool,
+    // The disassembly we show as the source and the address on each of its lines.
+    listing: String,
+    addresses: Vec<Option<usize>>,
+}
+
+impl Program {
+    // Get the (1-based) line of the listing that contains the given address.
+    fn line(&self, address: usize) -> Option<usize> {
+        self.addresses
+            .iter()
+            .enumerate()
+            .filter_map(|(line, a)| a.map(|a| (line, a)))
+            .take_while(|(_, a)| *a <= address)
+            .last()
+            .map(|(line, _)| line + 1)
+    }
+
+    // Get the address on the given (1-based) line, or on the next line that has one.
+    fn address(&self, line: usize) -> Option<usize> {
+        self.addresses
+            .iter()
+            .skip(line.saturating_sub(1))
+            .find_map(|a| *a)
+    }
+}
+
+/// A debug adapter for a single Intcode process. The source the client sees is the disassembly of
+/// the program, so breakpoints are set on its lines. Inputs for the program are given as the
+/// `input` array when launching and outputs are sent to the client's console.
+pub struct Server<W: Write> {
+    writer: W,
+    seq: i64,
+    program: Option<Program>,
+    // The lines the client wants breakpoints on, kept so they can be set before launching.
+    lines: Vec<usize>,
+    breakpoints: Breakpoints,
+}
+
+impl<W: Write> Server<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            seq: 1,
+            program: None,
+            lines: Vec::new(),
+            breakpoints: Breakpoints::default(),
+        }
+    }
+
+    /// Serve requests from the reader until the client disconnects or closes the stream.
+    pub async fn serve(&mut self, reader: &mut impl BufRead) -> Result<()> {
+        while let Some(request) = read_message(reader)? {
+            if !self.handle(&request).await? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a single request from the client. This returns `false` once the client has
+    /// disconnected.
+    pub async fn handle(&mut self, request: &Value) -> Result<bool> {
+        let command = request["command"].as_str().unwrap_or("");
+        let arguments = request["arguments"].clone();
+        let result = match command {
+            "initialize" => self.initialize(request),
+            "launch" => self.launch(request, &arguments),
+            "setBreakpoints" => self.set_breakpoints(request, &arguments),
+            "configurationDone" => self.configuration_done(request).await,
+            "threads" => self.respond(
+                request,
+                json!({ "threads": [{ "id": THREAD, "name": self.name() }] }),
+            ),
+            "stackTrace" => self.stack_trace(request),
+            "source" => self.source(request),
+            "scopes" => self.scopes(request),
+            "variables" => self.variables(request, &arguments),
+            "next" | "stepIn" | "stepOut" => {
+                self.respond(request, Value::Null)?;
+                self.run(true).await
+            }
+            "continue" => {
+                self.respond(request, json!({ "allThreadsContinued": true }))?;
+                self.run(false).await
+            }
+            "pause" => self.respond(request, Value::Null),
+            "disconnect" | "terminate" => {
+                self.respond(request, Value::Null)?;
+                return Ok(false);
+            }
+            _ => Err(anyhow!("unsupported request '{}'", command)),
+        };
+
+        if let Err(e) = result {
+            self.fail(request, &e.to_string())?;
+        }
+        Ok(true)
+    }
+
+    fn name(&self) -> String {
+        self.program
+            .as_ref()
+            .map(|program| program.name.clone())
+            .unwrap_or_else(|| "intcode".to_string())
+    }
+
+    fn program(&self) -> Result<&Program> {
+        self.program
+            .as_ref()
+            .ok_or_else(|| anyhow!("no program has been launched"))
+    }
+
+    fn send(&mut self, mut message: Value) -> Result<()> {
+        message["seq"] = self.seq.into();
+        self.seq += 1;
+        write_message(&mut self.writer, &message)
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) -> Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": true,
+            "command": request["command"],
+            "body": body,
+        }))
+    }
+
+    fn fail(&mut self, request: &Value, message: &str) -> Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": false,
+            "command": request["command"],
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn initialize(&mut self, request: &Value) -> Result<()> {
+        self.respond(request, json!({ "supportsConfigurationDoneRequest": true }))?;
+        self.event("initialized", Value::Null)
+    }
+
+    fn launch(&mut self, request: &Value, arguments: &Value) -> Result<()> {
+        let path = arguments["program"]
+            .as_str()
+            .ok_or_else(|| anyhow!("launch needs a program"))?;
+        let inputs = arguments["input"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|value| {
+                value
+                    .as_i64()
+                    .map(|value| value as isize)
+                    .ok_or_else(|| anyhow!("inputs must be numbers"))
+            })
+            .collect::<Result<VecDeque<_>>>()?;
+
+        let text = std::fs::read_to_string(path)?;
+        let (_, input, receiver) = Channel::new(true);
+        let (_, sender, output) = Channel::new(true);
+        let process = Process::new(&text, receiver, sender);
+
+        let listing = disassemble(&process.state().memory).to_string();
+        let addresses = listing
+            .lines()
+            .map(|line| {
+                // The address is the first 8 digit column, after the gutter and marker.
+                line.split_whitespace()
+                    .find(|column| column.len() == 8 && column.chars().all(|c| c.is_ascii_digit()))
+                    .and_then(|column| column.parse().ok())
+            })
+            .collect();
+        let name = std::path::Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+
+        self.program = Some(Program {
+            name,
+            process,
+            input,
+            output,
+            inputs,
+            stop_on_entry: arguments["stopOnEntry"].as_bool().unwrap_or(false),
+            listing,
+            addresses,
+        });
+        self.update_breakpoints();
+        self.respond(request, Value::Null)
+    }
+
+    // Turn the lines the client asked for into addresses now that we may have a listing.
+    fn update_breakpoints(&mut self) {
+        self.breakpoints = Breakpoints::default();
+        if let Some(program) = &self.program {
+            for line in &self.lines {
+                if let Some(address) = program.address(*line) {
+                    self.breakpoints
+                        .add(Breakpoint::InstructionPointer(address));
+                }
+            }
+        }
+    }
+
+    fn set_breakpoints(&mut self, request: &Value, arguments: &Value) -> Result<()> {
+        self.lines = arguments["breakpoints"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|breakpoint| breakpoint["line"].as_i64())
+            .map(|line| line.max(1) as usize)
+            .collect();
+        self.update_breakpoints();
+
+        // Move each breakpoint to the line of the instruction it will actually stop on.
+        let breakpoints = self
+            .lines
+            .iter()
+            .map(|line| {
+                let line = self
+                    .program
+                    .as_ref()
+                    .and_then(|program| program.address(*line).and_then(|a| program.line(a)))
+                    .unwrap_or(*line);
+                json!({ "verified": true, "line": line })
+            })
+            .collect::<Vec<_>>();
+        self.respond(request, json!({ "breakpoints": breakpoints }))
+    }
+
+    async fn configuration_done(&mut self, request: &Value) -> Result<()> {
+        self.respond(request, Value::Null)?;
+        match self.program()?.stop_on_entry {
+            true => self.stopped(Stop::Entry),
+            false => self.run(false).await,
+        }
+    }
+
+    fn stack_trace(&mut self, request: &Value) -> Result<()> {
+        let program = self.program()?;
+        let state = program.process.state();
+        let ip = state.instruction_pointer;
+        let name = match state.next_instruction() {
+            _ if state.halted => "halted".to_string(),
+            Some((instruction, _)) => instruction.to_string(),
+            None => format!("{:08}", ip),
+        };
+        let frame = json!({
+            "id": 1,
+            "name": name,
+            "line": program.line(ip).unwrap_or(1),
+            "column": 1,
+            "instructionPointerReference": ip.to_string(),
+            "source": { "name": program.name, "sourceReference": SOURCE },
+        });
+        self.respond(request, json!({ "stackFrames": [frame], "totalFrames": 1 }))
+    }
+
+    fn source(&mut self, request: &Value) -> Result<()> {
+        let content = self.program()?.listing.clone();
+        self.respond(request, json!({ "content": content }))
+    }
+
+    fn scopes(&mut self, request: &Value) -> Result<()> {
+        let scope = |name: &str, reference: i64| {
+            json!({
+                "name": name,
+                "variablesReference": reference,
+                "expensive": reference != REGISTERS,
+            })
+        };
+        self.respond(
+            request,
+            json!({
+                "scopes": [
+                    scope("Registers", REGISTERS),
+                    scope("Memory", MEMORY),
+                    scope("Additional Memory", ADDITIONAL_MEMORY),
+                ]
+            }),
+        )
+    }
+
+    fn variables(&mut self, request: &Value, arguments: &Value) -> Result<()> {
+        let state = self.program()?.process.state();
+        let variable = |name: String, value: String, reference: i64| json!({ "name": name, "value": value, "variablesReference": reference });
+        let optional = |value: Option<isize>| match value {
+            Some(value) => value.to_string(),
+            None => "-".to_string(),
+        };
+
+        let variables = match arguments["variablesReference"].as_i64().unwrap_or(0) {
+            REGISTERS => vec![
+                variable("ip".into(), state.instruction_pointer.to_string(), 0),
+                variable("rb".into(), state.relative_base.to_string(), 0),
+                variable("last input".into(), optional(state.last_input), 0),
+                variable("last output".into(), optional(state.last_output), 0),
+                variable("halted".into(), state.halted.to_string(), 0),
+            ],
+            // Each row of memory expands into its cells.
+            MEMORY => state
+                .memory
+                .chunks(ROW_SIZE)
+                .enumerate()
+                .map(|(row, cells)| {
+                    let value = cells
+                        .iter()
+                        .map(|cell| cell.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    variable(format!("{:08}", row * ROW_SIZE), value, ROWS + row as i64)
+                })
+                .collect(),
+            ADDITIONAL_MEMORY => state
+                .additional_memory
+                .iter()
+                .map(|(address, value)| variable(format!("{:08}", address), value.to_string(), 0))
+                .collect(),
+            reference if reference >= ROWS => {
+                let start = (reference - ROWS) as usize * ROW_SIZE;
+                (start..(start + ROW_SIZE).min(state.len()))
+                    .map(|address| {
+                        variable(format!("[{}]", address), state[address].to_string(), 0)
+                    })
+                    .collect()
+            }
+            reference => return Err(anyhow!("unknown variables reference {}", reference)),
+        };
+        self.respond(request, json!({ "variables": variables }))
+    }
+
+    // Run the process for a single instruction or until a breakpoint is hit or it halts, then tell
+    // the client why it stopped. Nothing else is handled while it runs, so a program that never
+    // stops can't be paused.
+    async fn run(&mut self, step: bool) -> Result<()> {
+        let Self {
+            program,
+            breakpoints,
+            ..
+        } = self;
+        let program = program
+            .as_mut()
+            .ok_or_else(|| anyhow!("no program has been launched"))?;
+
+        let mut outputs = Vec::new();
+        let mut first = true;
+        let stop = loop {
+            let state = program.process.state();
+            if state.halted {
+                break Stop::Halted;
+            }
+            let instruction = match state.next_instruction() {
+                Some((instruction, _)) => instruction,
+                None => {
+                    break Stop::Error(format!("no instruction at {}", state.instruction_pointer))
+                }
+            };
+
+            // We always run the first instruction so we don't stop on the breakpoint we are at.
+            if !first && (step || breakpoints.evaluate(&state, &instruction)) {
+                break match step {
+                    true => Stop::Step,
+                    false => Stop::Breakpoint,
+                };
+            }
+            first = false;
+
+            // Inputs are given to the process as it asks for them. Once they run out, the input is
+            // closed so the process halts instead of waiting forever.
+            if let Instruction::Input(_) = instruction {
+                match program.inputs.pop_front() {
+                    Some(value) => program.input.send(value).await?,
+                    None => program.input.close(),
+                }
+            }
+            if let Err(e) = program.process.step().await {
+                break Stop::Error(e.to_string());
+            }
+            outputs.extend(program.output.drain());
+        };
+
+        if !outputs.is_empty() {
+            let text = outputs
+                .iter()
+                .map(|value| format!("{}\n", value))
+                .collect::<String>();
+            self.event("output", json!({ "category": "stdout", "output": text }))?;
+        }
+        self.stopped(stop)
+    }
+
+    fn stopped(&mut self, stop: Stop) -> Result<()> {
+        let (reason, text) = match stop {
+            Stop::Entry => ("entry", None),
+            Stop::Step => ("step", None),
+            Stop::Breakpoint => ("breakpoint", None),
+            Stop::Error(e) => ("exception", Some(e)),
+            Stop::Halted => {
+                self.event("exited", json!({ "exitCode": 0 }))?;
+                return self.event("terminated", Value::Null);
+            }
+        };
+        let mut body = json!({
+            "reason": reason,
+            "threadId": THREAD,
+            "allThreadsStopped": true,
+        });
+        if let Some(text) = text {
+            body["text"] = text.into();
+        }
+        self.event("stopped", body)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dap() {
+        let path = std::env::temp_dir().join("intcode-dap-test.txt");
+        std::fs::write(&path, "3,9,1001,9,1,10,4,10,99,0,0").unwrap();
+        let requests = [
+            r#"{"seq":1,"type":"request","command":"initialize","arguments":{}}"#.to_string(),
+            format!(
+                r#"{{"seq":2,"type":"request","command":"launch","arguments":{{"program":"{}","input":[41],"stopOnEntry":true}}}}"#,
+                path.display()
+            ),
+            r#"{"seq":3,"type":"request","command":"setBreakpoints","arguments":{"breakpoints":[{"line":3}]}}"#.to_string(),
+            r#"{"seq":4,"type":"request","command":"configurationDone"}"#.to_string(),
+            r#"{"seq":5,"type":"request","command":"continue","arguments":{"threadId":1}}"#.to_string(),
+            r#"{"seq":6,"type":"request","command":"continue","arguments":{"threadId":1}}"#.to_string(),
+            r#"{"seq":7,"type":"request","command":"disconnect"}"#.to_string(),
+        ];
+        let mut input = Vec::new();
+        for request in &requests {
+            write_message(&mut input, &request.parse().unwrap()).unwrap();
+        }
+
+        let mut output = Vec::new();
+        let mut server = Server::new(&mut output);
+        server.serve(&mut input.as_slice()).await.unwrap();
+
+        let mut reader = output.as_slice();
+        let mut messages = Vec::new();
+        while let Some(message) = read_message(&mut reader).unwrap() {
+            messages.push(message);
+        }
+        let events = messages
+            .iter()
+            .filter_map(|m| m["event"].as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                "initialized",
+                "stopped",
+                "stopped",
+                "output",
+                "exited",
+                "terminated"
+            ]
+        );
+        let reasons = messages
+            .iter()
+            .filter_map(|m| m["body"]["reason"].as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(reasons, vec!["entry", "breakpoint"]);
+        assert!(messages.iter().any(|m| m["body"]["output"] == "42\n"));
+        assert!(messages.iter().all(|m| m["success"] != false));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use crate::{analysis::cfg, instruction::Instruction, parameter::Parameter, symbols::SymbolTable};
+
+/// A single line of a disassembly listing. Code lines are a whole instruction and data lines are a
+/// single cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    /// The address of the first cell of the line.
+    pub address: usize,
+    /// The raw cells that make up the line.
+    pub cells: Vec<isize>,
+    /// The decoded instruction if the line is code.
+    pub instruction: Option<Instruction>,
+}
+
+impl Line {
+    /// Check whether this line is code (as opposed to data).
+    pub fn is_code(&self) -> bool {
+        self.instruction.is_some()
+    }
+}
+
+/// The disassembly of a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listing {
+    /// The lines of the listing in address order.
+    pub lines: Vec<Line>,
+    /// The addresses that are the target of a statically known jump.
+    pub jump_targets: BTreeSet<usize>,
+    /// The names of addresses to use when displaying the listing.
+    pub symbols: SymbolTable,
+    /// The addresses with breakpoints on them.
+    pub breakpoints: BTreeSet<usize>,
+}
+
+impl Listing {
+    /// Get the line that contains the given address.
+    pub fn line(&self, address: usize) -> Option<&Line> {
+        self.line_index(address).map(|index| &self.lines[index])
+    }
+
+    // Get the index of the line that contains the given address.
+    fn line_index(&self, address: usize) -> Option<usize> {
+        let index = self
+            .lines
+            .partition_point(|line| line.address + line.cells.len() <= address);
+        (index < self.lines.len() && self.lines[index].address <= address).then_some(index)
+    }
+
+    // Get the lines each statically known jump goes from and to.
+    fn jumps(&self) -> Vec<(usize, usize)> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter_map(|(from, line)| match line.instruction {
+                Some(Instruction::JumpIfTrue(_, Parameter::Immediate(target)))
+                | Some(Instruction::JumpIfFalse(_, Parameter::Immediate(target))) => {
+                    let to = self.line_index(usize::try_from(target).ok()?)?;
+                    Some((from, to))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Draw the arrows for the jumps in a gutter to the left of the listing, one column per lane.
+    // Shorter jumps get the lanes closest to the code so they nest inside the longer ones. Loops
+    // (backward jumps) are drawn with `:` instead of `|` so they stand out.
+    fn gutter(&self) -> Vec<String> {
+        const MAX_LANES: usize = 8;
+
+        let mut jumps = self.jumps();
+        jumps.sort_by_key(|(from, to)| from.abs_diff(*to));
+        let mut lanes: Vec<Vec<(usize, usize, bool)>> = Vec::new();
+        for (from, to) in jumps {
+            let span = (from.min(to), from.max(to), to <= from);
+            let free = lanes.iter().position(|lane| {
+                lane.iter()
+                    .all(|(start, end, _)| *end < span.0 || span.1 < *start)
+            });
+            match free {
+                Some(lane) => lanes[lane].push(span),
+                None if lanes.len() < MAX_LANES => lanes.push(vec![span]),
+                None => {}
+            }
+        }
+
+        // There is an extra column so even the outermost lane can be connected to the code.
+        let width = lanes.len();
+        let columns = match width {
+            0 => 0,
+            _ => width + 1,
+        };
+        let mut gutter = vec![vec![' '; columns]; self.lines.len()];
+        for (lane, spans) in lanes.iter().enumerate() {
+            let column = width - 1 - lane;
+            for &(start, end, backward) in spans {
+                gutter[start][column] = '+';
+                gutter[end][column] = '+';
+                for row in gutter.iter_mut().take(end).skip(start + 1) {
+                    row[column] = if backward { ':' } else { '|' };
+                }
+            }
+        }
+
+        // Connect the ends of each jump to the code.
+        for (lane, spans) in lanes.iter().enumerate() {
+            let column = width - 1 - lane;
+            for &(start, end, _) in spans {
+                for row in [start, end] {
+                    for c in gutter[row].iter_mut().skip(column + 1) {
+                        if *c == ' ' {
+                            *c = '-';
+                        }
+                    }
+                }
+            }
+        }
+        gutter
+            .into_iter()
+            .map(|row| row.into_iter().collect())
+            .collect()
+    }
+}
+
+impl Display for Listing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let gutter = self.gutter();
+        for (line, gutter) in self.lines.iter().zip(gutter) {
+            if let Some(name) = self.symbols.name(line.address) {
+                writeln!(f, "{}:", name)?;
+            }
+
+            // Mark the lines that are jumped to so loops and branches are easier to spot. A
+            // breakpoint anywhere in the line is more important, and the gutter still shows jumps.
+            let breakpoint = self
+                .breakpoints
+                .range(line.address..line.address + line.cells.len())
+                .next()
+                .is_some();
+            let marker = match (breakpoint, self.jump_targets.contains(&line.address)) {
+                (true, _) => '*',
+                (false, true) => '>',
+                (false, false) => ' ',
+            };
+            let cells = line
+                .cells
+                .iter()
+                .map(|cell| cell.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            write!(
+                f,
+                "{}{} {:08}  {:<24}  ",
+                gutter, marker, line.address, cells
+            )?;
+
+            match (&line.instruction, line.cells[0]) {
+                (
+                    Some(
+                        instruction @ (Instruction::JumpIfTrue(_, Parameter::Immediate(target))
+                        | Instruction::JumpIfFalse(_, Parameter::Immediate(target))),
+                    ),
+                    _,
+                ) if *target >= 0 && *target as usize <= line.address => {
+                    writeln!(f, "{} ; loop", instruction.with_symbols(&self.symbols))?
+                }
+                (Some(instruction), _) => {
+                    writeln!(f, "{}", instruction.with_symbols(&self.symbols))?
+                }
+                // Programs often store text, so show the character for printable data.
+                (None, value @ 32..=126) => {
+                    writeln!(f, "DAT {} ; '{}'", value, value as u8 as char)?
+                }
+                (None, value) => writeln!(f, "DAT {}", value)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Disassemble the given program. Code and data are separated on a best-effort basis using the
+/// control-flow graph of the program, so anything that isn't reachable is treated as data.
+pub fn disassemble(program: &[isize]) -> Listing {
+    let cfg = cfg(program);
+    let reachable = cfg
+        .instructions()
+        .map(|(address, instruction)| (address, (instruction, instruction.parameter_count() + 1)))
+        .collect::<BTreeMap<_, _>>();
+
+    // Now walk the program in order, using the reachable instructions as code and everything else
+    // as data.
+    let mut lines = Vec::new();
+    let mut address = 0;
+    while address < program.len() {
+        let line = match reachable.get(&address) {
+            Some(&(instruction, size)) => Line {
+                address,
+                cells: program[address..(address + size).min(program.len())].to_vec(),
+                instruction: Some(instruction),
+            },
+            None => Line {
+                address,
+                cells: vec![program[address]],
+                instruction: None,
+            },
+        };
+        address += line.cells.len();
+        lines.push(line);
+    }
+
+    Listing {
+        lines,
+        jump_targets: cfg.jump_targets(),
+        symbols: SymbolTable::new(),
+        breakpoints: BTreeSet::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_disassemble_separates_data() {
+        // Jump over the data at 3 to an output, then halt.
+        let listing = disassemble(&[1105, 1, 4, 72, 104, 1, 99]);
+        let code = listing
+            .lines
+            .iter()
+            .filter(|line| line.is_code())
+            .map(|line| line.address)
+            .collect::<Vec<_>>();
+        assert_eq!(code, vec![0, 4, 6]);
+        assert!(!listing.line(3).unwrap().is_code());
+        assert_eq!(listing.line(5).unwrap().address, 4);
+        assert!(listing.jump_targets.contains(&4));
+
+        // A loop back to the start gets an arrow in the gutter and is marked as a loop.
+        let listing = disassemble(&[104, 1, 1105, 1, 0]).to_string();
+        let lines = listing.lines().collect::<Vec<_>>();
+        assert!(lines[0].starts_with("+-> 00000000"));
+        assert!(lines[1].starts_with("+-  00000002"));
+        assert!(lines[1].ends_with("; loop"));
+
+        // Breakpoints take the place of the jump marker.
+        let mut listing = disassemble(&[104, 1, 1105, 1, 0]);
+        listing.breakpoints.insert(3);
+        assert!(listing
+            .to_string()
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("+-* 00000002"));
+    }
+}
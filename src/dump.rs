@@ -0,0 +1,88 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::process::State;
+
+/// The version of the dump format written by [Dump::to_json]. It changes whenever a dump written
+/// by one version can't be read by another.
+pub const VERSION: u32 = 1;
+
+/// A snapshot of a machine along with its IO, for bug reports and for comparing the states of
+/// different versions of the computer. As JSON it looks like this:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "state": {
+///     "memory": [3, 9, 1001, 9, 1, 10, 4, 10, 99, 41, 42],
+///     "additional_memory": { "1000": 5 },
+///     "instruction_pointer": 8,
+///     "relative_base": 0,
+///     "last_output": 42,
+///     "last_input": 41,
+///     "halted": false
+///   },
+///   "inputs": [41],
+///   "outputs": [42]
+/// }
+/// ```
+///
+/// `memory` is the program and anything written inside it. Writes past the end of the program are
+/// in `additional_memory`, keyed by address. `inputs` are the values the machine was given and
+/// `outputs` are the values it made, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dump {
+    pub version: u32,
+    pub state: State,
+    pub inputs: Vec<isize>,
+    pub outputs: Vec<isize>,
+}
+
+impl Dump {
+    pub fn new(state: State, inputs: Vec<isize>, outputs: Vec<isize>) -> Self {
+        Self {
+            version: VERSION,
+            state,
+            inputs,
+            outputs,
+        }
+    }
+
+    /// Write the dump as pretty printed JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Read a dump, failing if it was written in a different version of the format.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let dump: Dump = serde_json::from_str(json)?;
+        match dump.version {
+            VERSION => Ok(dump),
+            version => Err(anyhow!(
+                "dump is version {} but only version {} can be read",
+                version,
+                VERSION
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut state = State::new("3,9,1001,9,1,10,4,10,99,0,0");
+        state[1000] = 5;
+        state.last_input = Some(41);
+        let dump = Dump::new(state, vec![41], vec![42]);
+
+        let json = dump.to_json().unwrap();
+        assert!(json.contains(r#""1000": 5"#));
+        assert_eq!(Dump::from_json(&json).unwrap(), dump);
+
+        let old = json.replace(r#""version": 1"#, r#""version": 0"#);
+        assert!(Dump::from_json(&old).is_err());
+    }
+}
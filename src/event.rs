@@ -3,19 +3,29 @@ use std::time::Duration;
 use anyhow::{anyhow, Result};
 use crossterm::event::{EventStream, KeyEvent, MouseEvent};
 use futures::{FutureExt, StreamExt};
-use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver},
+    task::JoinHandle,
+};
 
 /// An event that can be handled by the event handler.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub enum Event {
     Tick,
     Key(KeyEvent),
     Mouse(MouseEvent),
+    /// The terminal changed to the given width and height.
+    Resize(u16, u16),
+    /// Text was pasted into the terminal.
+    Paste(String),
+    /// Reading terminal events failed. No more events will come after this.
+    Error(String),
 }
 
 /// An event handler that can be used to handle events for the tui.
 pub struct EventHandler {
     rx: UnboundedReceiver<Event>,
+    handler: Option<JoinHandle<()>>,
 }
 
 impl EventHandler {
@@ -24,13 +34,11 @@ impl EventHandler {
     pub fn new(tick_rate: Duration) -> Self {
         // Create the channel to communicate.
         let (tx, rx) = mpsc::unbounded_channel();
-        let _tx = tx.clone();
 
-        // TODO: pass handler to close on exit?
-        // Spawn the handler.
-        let _handler = tokio::spawn(async move {
+        // Spawn the handler. It stops when the receiver is dropped or closed, which we notice the
+        // next time we try to send something.
+        let handler = tokio::spawn(async move {
             // Create the event stream and ticker
-            let tx = _tx;
             let mut reader = EventStream::new();
             let mut tick = tokio::time::interval(tick_rate);
 
@@ -40,31 +48,48 @@ impl EventHandler {
                 let event = reader.next().fuse();
 
                 // Select on our futures and send the corresponding event.
-                tokio::select! {
-                    _ = tick_delay => {
-                        tx.send(Event::Tick).unwrap();
-                    }
-                    event = event => {
-                        if let Some(Ok(event)) = event {
-                            match event {
-                                crossterm::event::Event::Key(key) => {
-                                    tx.send(Event::Key(key)).unwrap();
-                                }
-                                crossterm::event::Event::Mouse(mouse) => {
-                                    tx.send(Event::Mouse(mouse)).unwrap();
-                                }
-                                _ => {}
-                            }
+                let event = tokio::select! {
+                    _ = tick_delay => Event::Tick,
+                    event = event => match event {
+                        Some(Ok(crossterm::event::Event::Key(key))) => Event::Key(key),
+                        Some(Ok(crossterm::event::Event::Mouse(mouse))) => Event::Mouse(mouse),
+                        Some(Ok(crossterm::event::Event::Resize(width, height))) => {
+                            Event::Resize(width, height)
                         }
-                    }
+                        Some(Ok(crossterm::event::Event::Paste(text))) => Event::Paste(text),
+                        Some(Ok(_)) => continue,
+                        Some(Err(e)) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!(error = %e, "reading terminal events");
+                            let _ = tx.send(Event::Error(e.to_string()));
+                            break;
+                        }
+                        None => break,
+                    },
+                };
+                if tx.send(event).is_err() {
+                    break;
                 }
             }
+            #[cfg(feature = "tracing")]
+            tracing::debug!("event handler stopped");
         });
-        Self { rx }
+        Self {
+            rx,
+            handler: Some(handler),
+        }
     }
 
     /// Get the next event from the event handler.
     pub async fn next(&mut self) -> Result<Event> {
         self.rx.recv().await.ok_or(anyhow!("no event"))
     }
+
+    /// Stop handling events and wait for the handler to finish.
+    pub async fn close(&mut self) {
+        self.rx.close();
+        if let Some(handler) = self.handler.take() {
+            let _ = handler.await;
+        }
+    }
 }
@@ -0,0 +1,148 @@
+use std::collections::VecDeque;
+use std::ffi::{c_char, c_int, CStr};
+use std::panic::catch_unwind;
+
+use crate::machine::Machine;
+
+/// An Intcode computer for C and anything else that can call C. The declarations are in
+/// `include/intcode.h`. Every function takes the pointer `intcode_new` returned, which must be
+/// given back to `intcode_free` when the host is done with it.
+pub struct Vm {
+    machine: Machine,
+    outputs: VecDeque<isize>,
+}
+
+/// Create a computer running the given comma separated program. This returns null if the program
+/// isn't valid.
+///
+/// # Safety
+///
+/// The program must be a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_new(program: *const c_char) -> *mut Vm {
+    if program.is_null() {
+        return std::ptr::null_mut();
+    }
+    let Ok(program) = CStr::from_ptr(program).to_str() else {
+        return std::ptr::null_mut();
+    };
+    // Parsing panics on a bad cell, which mustn't unwind into the host.
+    match catch_unwind(|| Machine::new(program)) {
+        Ok(machine) => Box::into_raw(Box::new(Vm {
+            machine,
+            outputs: VecDeque::new(),
+        })),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Free a computer.
+///
+/// # Safety
+///
+/// The computer must have come from `intcode_new` and can't be used again.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_free(vm: *mut Vm) {
+    if !vm.is_null() {
+        drop(Box::from_raw(vm));
+    }
+}
+
+/// Run a single instruction. This returns 1 if it was run, 0 if nothing was run because the
+/// computer has halted or is waiting for input, and -1 if the instruction failed.
+///
+/// # Safety
+///
+/// The computer must have come from `intcode_new`.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_step(vm: *mut Vm) -> c_int {
+    let vm = &mut *vm;
+    match vm.machine.step() {
+        Ok(ran) => {
+            vm.outputs.extend(vm.machine.poll_output());
+            ran as c_int
+        }
+        Err(_) => -1,
+    }
+}
+
+/// Queue a value for the computer to read.
+///
+/// # Safety
+///
+/// The computer must have come from `intcode_new`.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_push_input(vm: *mut Vm, value: i64) {
+    (*vm).machine.push_input(value as isize);
+}
+
+/// Take the oldest output the computer hasn't given yet and put it in `value`. This returns 0
+/// without touching `value` if there isn't one.
+///
+/// # Safety
+///
+/// The computer must have come from `intcode_new` and `value` must point to an `int64_t`.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_pop_output(vm: *mut Vm, value: *mut i64) -> c_int {
+    match (*vm).outputs.pop_front() {
+        Some(output) => {
+            *value = output as i64;
+            1
+        }
+        None => 0,
+    }
+}
+
+/// Read the memory at the address. Addresses past the end of the program read as zero.
+///
+/// # Safety
+///
+/// The computer must have come from `intcode_new`.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_read_mem(vm: *const Vm, address: usize) -> i64 {
+    (*vm).machine.state_ref()[address] as i64
+}
+
+/// Whether the computer has halted, as 1 or 0.
+///
+/// # Safety
+///
+/// The computer must have come from `intcode_new`.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_halted(vm: *const Vm) -> c_int {
+    (*vm).machine.halted() as c_int
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ffi() {
+        unsafe {
+            assert!(intcode_new(c"1,2,x".as_ptr()).is_null());
+
+            let vm = intcode_new(c"3,9,1001,9,1,10,4,10,99,0,0".as_ptr());
+            assert!(!vm.is_null());
+            assert_eq!(intcode_step(vm), 0);
+            intcode_push_input(vm, 41);
+            while intcode_step(vm) == 1 {}
+            assert_eq!(intcode_halted(vm), 1);
+
+            let mut value = 0;
+            assert_eq!(intcode_pop_output(vm, &mut value), 1);
+            assert_eq!(value, 42);
+            assert_eq!(intcode_pop_output(vm, &mut value), 0);
+            assert_eq!(intcode_read_mem(vm, 10), 42);
+            assert_eq!(intcode_read_mem(vm, 1_000), 0);
+            intcode_free(vm);
+
+            // An instruction that can't be decoded fails rather than looking like a wait.
+            let vm = intcode_new(c"1101,1,1,5,42,0".as_ptr());
+            assert_eq!(intcode_step(vm), 1);
+            assert_eq!(intcode_step(vm), -1);
+            assert_eq!(intcode_halted(vm), 0);
+            intcode_free(vm);
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::{hash_map, HashMap};
+#[cfg(feature = "tui")]
+use std::ops::RangeInclusive;
+use std::ops::{Add, AddAssign, Sub};
+
+use enum_iterator::{all, Sequence};
+#[cfg(feature = "tui")]
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    widgets::{Block, Widget},
+};
+
+#[cfg(feature = "tui")]
+use crate::theme::ColorScheme;
+
+/// A position on a grid. Like rows of text, y grows going down.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub x: isize,
+    pub y: isize,
+}
+
+impl Point {
+    pub fn new(x: isize, y: isize) -> Self {
+        Self { x, y }
+    }
+
+    /// The point one step away in the given direction.
+    pub fn step(self, direction: Direction) -> Self {
+        self + Point::from(direction)
+    }
+
+    /// The four points next to this one, in the order of [Direction].
+    pub fn neighbors(self) -> impl Iterator<Item = Point> {
+        all::<Direction>().map(move |direction| self.step(direction))
+    }
+}
+
+impl Add for Point {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl AddAssign for Point {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Point {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Add<Direction> for Point {
+    type Output = Self;
+
+    fn add(self, direction: Direction) -> Self {
+        self.step(direction)
+    }
+}
+
+impl AddAssign<Direction> for Point {
+    fn add_assign(&mut self, direction: Direction) {
+        *self = self.step(direction);
+    }
+}
+
+/// A direction on a grid. Up is toward smaller y.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Sequence)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    pub fn turn_left(self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    pub fn turn_right(self) -> Self {
+        self.turn_left().reverse()
+    }
+
+    pub fn reverse(self) -> Self {
+        self.turn_left().turn_left()
+    }
+}
+
+impl From<Direction> for Point {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Up => Point::new(0, -1),
+            Direction::Right => Point::new(1, 0),
+            Direction::Down => Point::new(0, 1),
+            Direction::Left => Point::new(-1, 0),
+        }
+    }
+}
+
+/// A sparse grid of cells, like the maps many of the puzzles make. Only the cells that have been
+/// set are stored, so the grid can grow in any direction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    cells: HashMap<Point, T>,
+}
+
+impl<T> Default for Grid<T> {
+    fn default() -> Self {
+        Self {
+            cells: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a grid from text, one row per line starting from 0, 0 at the top left. Only the
+    /// characters the function gives a cell for are kept.
+    pub fn parse(text: &str, cell: impl Fn(char) -> Option<T>) -> Self {
+        text.lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .map(move |(x, c)| (Point::new(x as isize, y as isize), c))
+            })
+            .filter_map(|(point, c)| cell(c).map(|value| (point, value)))
+            .collect()
+    }
+
+    pub fn get(&self, point: Point) -> Option<&T> {
+        self.cells.get(&point)
+    }
+
+    pub fn get_mut(&mut self, point: Point) -> Option<&mut T> {
+        self.cells.get_mut(&point)
+    }
+
+    /// Set the cell at the point, returning what was there.
+    pub fn insert(&mut self, point: Point, value: T) -> Option<T> {
+        self.cells.insert(point, value)
+    }
+
+    pub fn remove(&mut self, point: Point) -> Option<T> {
+        self.cells.remove(&point)
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        self.cells.contains_key(&point)
+    }
+
+    /// The number of cells that have been set.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// The cells that have been set, in no particular order.
+    pub fn iter(&self) -> hash_map::Iter<'_, Point, T> {
+        self.cells.iter()
+    }
+
+    /// The top left and bottom right corners of the smallest rectangle holding every cell, or None
+    /// if the grid is empty.
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let mut points = self.cells.keys();
+        let first = *points.next()?;
+        Some(points.fold((first, first), |(min, max), point| {
+            (
+                Point::new(min.x.min(point.x), min.y.min(point.y)),
+                Point::new(max.x.max(point.x), max.y.max(point.y)),
+            )
+        }))
+    }
+
+    /// Draw the cells within the bounds as text, one line per row. The function is given None for
+    /// cells that haven't been set.
+    pub fn render(&self, cell: impl Fn(Option<&T>) -> char) -> String {
+        let Some((min, max)) = self.bounds() else {
+            return String::new();
+        };
+        (min.y..=max.y)
+            .map(|y| {
+                (min.x..=max.x)
+                    .map(|x| cell(self.get(Point::new(x, y))))
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// A widget that draws the cells within the bounds, using the function to draw each cell that
+    /// has been set.
+    #[cfg(feature = "tui")]
+    pub fn widget<'a>(
+        &'a self,
+        cell: impl Fn(Point, &T) -> Option<(char, ColorScheme)> + 'a,
+    ) -> GridWidget<'a, impl Fn(isize, isize) -> Option<(char, ColorScheme)> + 'a> {
+        let (min, max) = self
+            .bounds()
+            .unwrap_or((Point::new(0, 0), Point::new(-1, -1)));
+        GridWidget::new(min.x..=max.x, min.y..=max.y, move |x, y| {
+            let point = Point::new(x, y);
+            self.get(point).and_then(|value| cell(point, value))
+        })
+    }
+}
+
+impl<T> FromIterator<(Point, T)> for Grid<T> {
+    fn from_iter<I: IntoIterator<Item = (Point, T)>>(iter: I) -> Self {
+        Self {
+            cells: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<T> IntoIterator for Grid<T> {
+    type Item = (Point, T);
+    type IntoIter = hash_map::IntoIter<Point, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cells.into_iter()
+    }
+}
+
+/// A widget that draws a map one character per cell. The cells come from a function of their
+/// position, so the map can be kept however is easiest. The map is centered in its area and if it
+/// doesn't fit, the cells closest to the top left are shown.
+#[cfg(feature = "tui")]
+pub struct GridWidget<'a, F> {
+    x: RangeInclusive<isize>,
+    y: RangeInclusive<isize>,
+    cell: F,
+    block: Option<Block<'a>>,
+}
+
+#[cfg(feature = "tui")]
+impl<'a, F> GridWidget<'a, F>
+where
+    F: Fn(isize, isize) -> Option<(char, ColorScheme)>,
+{
+    /// Create a widget for the cells in the given ranges. Cells the function gives None for are
+    /// left blank.
+    pub fn new(x: RangeInclusive<isize>, y: RangeInclusive<isize>, cell: F) -> Self {
+        Self {
+            x,
+            y,
+            cell,
+            block: None,
+        }
+    }
+
+    /// Draw the map inside the block.
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+#[cfg(feature = "tui")]
+impl<F> Widget for GridWidget<'_, F>
+where
+    F: Fn(isize, isize) -> Option<(char, ColorScheme)>,
+{
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = match self.block {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+
+        let size = |range: &RangeInclusive<isize>, room: u16| {
+            (range.end() - range.start() + 1).clamp(0, room as isize) as u16
+        };
+        let width = size(&self.x, area.width);
+        let height = size(&self.y, area.height);
+        let left = area.x + (area.width - width) / 2;
+        let top = area.y + (area.height - height) / 2;
+
+        for (row, y) in self.y.take(height as usize).enumerate() {
+            for (column, x) in self.x.clone().take(width as usize).enumerate() {
+                if let Some((c, color)) = (self.cell)(x, y) {
+                    buf.get_mut(left + column as u16, top + row as u16)
+                        .set_char(c)
+                        .set_fg(color.into());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_grid() {
+        let mut grid = Grid::parse("#.\n.#", |c| (c == '#').then_some(true));
+        assert_eq!(grid.len(), 2);
+        let mut point = Point::new(1, 1);
+        point += Direction::Up.turn_right().turn_right();
+        grid.insert(point, false);
+        assert_eq!(point, Point::new(1, 2));
+        assert_eq!(grid.bounds(), Some((Point::new(0, 0), Point::new(1, 2))));
+        assert_eq!(
+            Point::new(1, 1)
+                .neighbors()
+                .filter(|&p| grid.contains(p))
+                .count(),
+            1
+        );
+
+        let text = grid.render(|cell| match cell {
+            Some(true) => '#',
+            Some(false) => 'o',
+            None => ' ',
+        });
+        assert_eq!(text, "# \n #\n o");
+    }
+
+    #[cfg(feature = "tui")]
+    #[test]
+    fn test_grid_widget() {
+        let cell = |x: isize, y: isize| (x == y).then_some(('#', ColorScheme::White));
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 3));
+        GridWidget::new(-1..=1, -1..=1, cell).render(buf.area, &mut buf);
+        let lines = (0..buf.area.height)
+            .map(|y| {
+                (0..buf.area.width)
+                    .map(|x| buf.get(x, y).symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![" #   ", "  #  ", "   # "]);
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use crate::{instruction::Instruction, process::State, timing::Timing};
+
+/// An instruction that was run, along with where it was and the values it read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub instruction_pointer: usize,
+    pub instruction: Instruction,
+    /// The values of the read parameters when the instruction ran.
+    pub values: Vec<isize>,
+    /// The address the instruction wrote to, if it writes.
+    pub write: Option<usize>,
+}
+
+impl Entry {
+    /// Create an entry for the given instruction about to be run in the given state.
+    pub fn new(state: &State, instruction: &Instruction) -> Self {
+        Self {
+            instruction_pointer: state.instruction_pointer,
+            instruction: *instruction,
+            values: instruction
+                .read_parameters()
+                .into_iter()
+                .map(|parameter| state.resolve(parameter))
+                .collect(),
+            write: instruction
+                .write_parameter()
+                .and_then(|parameter| state.resolve_write(parameter).ok()),
+        }
+    }
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let values = self
+            .values
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(
+            f,
+            "{:>6}: {} ({})",
+            self.instruction_pointer,
+            self.instruction.mnemonic(),
+            values
+        )?;
+        if let Some(write) = self.write {
+            write!(f, " -> {}", write)?;
+        }
+        Ok(())
+    }
+}
+
+/// The last few instructions a process ran, oldest first, along with how many times the
+/// instruction at each address has run, how long the process has taken, and the last error it
+/// ran into. Clones share the same history so one can be recorded into while another is
+/// displayed.
+#[derive(Debug, Clone)]
+pub struct History {
+    entries: Arc<Mutex<VecDeque<Entry>>>,
+    counts: Arc<Mutex<HashMap<usize, usize>>>,
+    executed: Arc<AtomicUsize>,
+    timing: Timing,
+    error: Arc<Mutex<Option<String>>>,
+    capacity: usize,
+}
+
+impl History {
+    /// Create a history that remembers up to `capacity` instructions.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            counts: Arc::new(Mutex::new(HashMap::new())),
+            executed: Arc::new(AtomicUsize::new(0)),
+            timing: Timing::default(),
+            error: Arc::new(Mutex::new(None)),
+            capacity,
+        }
+    }
+
+    /// Record that the given instruction is about to be run in the given state, forgetting the
+    /// oldest instruction if the history is full.
+    pub fn record(&self, state: &State, instruction: &Instruction) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(Entry::new(state, instruction));
+        *self
+            .counts
+            .lock()
+            .unwrap()
+            .entry(state.instruction_pointer)
+            .or_insert(0) += 1;
+        self.executed.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Get the number of instructions that have been run.
+    pub fn executed(&self) -> usize {
+        self.executed.load(Ordering::SeqCst)
+    }
+
+    /// Get a copy of the instructions in the history, oldest first.
+    pub fn entries(&self) -> Vec<Entry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Get how many times the instruction at each address has run.
+    pub fn counts(&self) -> HashMap<usize, usize> {
+        self.counts.lock().unwrap().clone()
+    }
+
+    /// Get how long the process has spent running and waiting for input.
+    pub fn timing(&self) -> &Timing {
+        &self.timing
+    }
+
+    /// Note the error the process ran into, if any, the last time it was told to do something.
+    pub fn set_error(&self, error: Option<String>) {
+        *self.error.lock().unwrap() = error;
+    }
+
+    /// Get the last error the process ran into.
+    pub fn error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+
+    /// Forget everything in the history.
+    pub fn clear(&self) {
+        self.timing.clear();
+        *self.error.lock().unwrap() = None;
+        self.entries.lock().unwrap().clear();
+        self.counts.lock().unwrap().clear();
+        self.executed.store(0, Ordering::SeqCst);
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let state = State::new("1,5,6,7,99,20,22,0");
+        let history = History::new(2);
+        let (instruction, _) = state.next_instruction().unwrap();
+        for _ in 0..3 {
+            history.record(&state, &instruction);
+        }
+        let entries = history.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].values, vec![20, 22]);
+        assert_eq!(entries[0].write, Some(7));
+        assert_eq!(entries[0].to_string(), "     0: ADD (20, 22) -> 7");
+        assert_eq!(history.counts()[&0], 3);
+        assert_eq!(history.executed(), 3);
+        history.clear();
+        assert!(history.entries().is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, Result};
+
+/// The year of Advent of Code the Intcode puzzles are from.
+pub const YEAR: usize = 2019;
+
+/// The directory inputs are cached in.
+pub const CACHE: &str = "inputs";
+
+/// The environment variable holding the session token from the Advent of Code cookie.
+pub const SESSION: &str = "AOC_SESSION";
+
+/// Get the input for the given day. It's read from the cache if it's there, otherwise it's
+/// downloaded using the session token in `AOC_SESSION` and cached for next time.
+pub fn input(day: usize) -> Result<String> {
+    input_in(CACHE, day)
+}
+
+/// Get the input for the given day using the given directory as the cache.
+pub fn input_in(cache: impl AsRef<Path>, day: usize) -> Result<String> {
+    let path = path(cache, day);
+    if let Ok(input) = std::fs::read_to_string(&path) {
+        return Ok(input);
+    }
+
+    let session = std::env::var(SESSION).map_err(|_| {
+        anyhow!(
+            "{} isn't cached and {} isn't set to download it",
+            path.display(),
+            SESSION
+        )
+    })?;
+    let input = download(day, &session)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, &input)?;
+    Ok(input)
+}
+
+/// Get where the input for the given day is cached.
+pub fn path(cache: impl AsRef<Path>, day: usize) -> PathBuf {
+    cache.as_ref().join(format!("day{:02}", day))
+}
+
+// Download the input with curl so we don't need an HTTP client and TLS for one request a day. The
+// cookie is given to curl as a config on stdin, since anyone on the machine can read arguments.
+fn download(day: usize, session: &str) -> Result<String> {
+    let url = format!("https://adventofcode.com/{}/day/{}/input", YEAR, day);
+    let mut curl = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--config", "-"])
+        .arg(&url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("running curl: {}", e))?;
+
+    // Dropping stdin closes it so curl knows the config is done.
+    let config = format!("cookie = \"session={}\"\n", quote(session));
+    curl.stdin
+        .take()
+        .ok_or_else(|| anyhow!("curl has no stdin"))?
+        .write_all(config.as_bytes())?;
+    let output = curl.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "downloading {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+// Escape a value for a double quoted string in a curl config.
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cached_input() {
+        let cache = std::env::temp_dir().join("intcode-inputs-test");
+        std::fs::create_dir_all(&cache).unwrap();
+        std::fs::write(path(&cache, 2), "1,0,0,0,99").unwrap();
+        assert_eq!(input_in(&cache, 2).unwrap(), "1,0,0,0,99");
+        assert_eq!(path(&cache, 2), cache.join("day02"));
+        std::fs::remove_dir_all(cache).unwrap();
+        assert_eq!(quote(r#"a"b\c"#), r#"a\"b\\c"#);
+    }
+}
@@ -1,9 +1,14 @@
 use std::fmt::Display;
+use std::str::FromStr;
 
-use crate::parameter::Parameter;
+use anyhow::{anyhow, Error, Result};
+use enum_iterator::{all, Sequence};
+
+use crate::{parameter::Parameter, symbols::SymbolTable};
 
 /// An instruction that can be executed by the Intcode computer.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Instruction {
     /// Add two values and store the result in a third.
     Add(Parameter, Parameter, Parameter),
@@ -29,30 +34,287 @@ pub enum Instruction {
     Halt,
 }
 
+/// The operation an instruction performs, without any of its parameters.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Sequence)]
+pub enum Operation {
+    /// Add two values (opcode 1).
+    Add,
+    /// Multiply two values (opcode 2).
+    Multiply,
+    /// Read a value from the input channel (opcode 3).
+    Input,
+    /// Write a value to the output channel (opcode 4).
+    Output,
+    /// Jump if a value is non-zero (opcode 5).
+    JumpIfTrue,
+    /// Jump if a value is zero (opcode 6).
+    JumpIfFalse,
+    /// Compare whether one value is less than another (opcode 7).
+    LessThan,
+    /// Compare whether two values are equal (opcode 8).
+    Equals,
+    /// Adjust the relative base (opcode 9).
+    AdjustRelativeBaseOffset,
+    /// Halt the program (opcode 99).
+    Halt,
+}
+
+/// The broad categories of instructions.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Sequence)]
+pub enum InstructionKind {
+    /// Instructions that compute a value from their parameters, including comparisons.
+    Arithmetic,
+    /// Instructions that may change the instruction pointer.
+    Jump,
+    /// Instructions that read from the input or write to the output.
+    Io,
+    /// Instructions that change the computer itself rather than memory.
+    Meta,
+}
+
+impl Operation {
+    /// Find the operation with the given mnemonic (e.g. `ADD`).
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        all::<Self>().find(|operation| operation.mnemonic() == mnemonic)
+    }
+
+    /// Find the operation for the given opcode. Only the first two digits of the opcode are used,
+    /// so the parameter modes can be included.
+    pub fn from_opcode(opcode: isize) -> Option<Self> {
+        all::<Self>().find(|operation| operation.opcode() == opcode % 100)
+    }
+
+    /// The mnemonic used to display the operation.
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Operation::Add => "ADD",
+            Operation::Multiply => "MUL",
+            Operation::Input => "INP",
+            Operation::Output => "OUT",
+            Operation::JumpIfTrue => "JIT",
+            Operation::JumpIfFalse => "JIF",
+            Operation::LessThan => "LST",
+            Operation::Equals => "EQL",
+            Operation::AdjustRelativeBaseOffset => "ARO",
+            Operation::Halt => "HLT",
+        }
+    }
+
+    /// The opcode of the operation without any parameter modes.
+    pub fn opcode(&self) -> isize {
+        match self {
+            Operation::Add => 1,
+            Operation::Multiply => 2,
+            Operation::Input => 3,
+            Operation::Output => 4,
+            Operation::JumpIfTrue => 5,
+            Operation::JumpIfFalse => 6,
+            Operation::LessThan => 7,
+            Operation::Equals => 8,
+            Operation::AdjustRelativeBaseOffset => 9,
+            Operation::Halt => 99,
+        }
+    }
+
+    /// The number of parameters the operation takes.
+    pub fn arity(&self) -> usize {
+        match self {
+            Operation::Add | Operation::Multiply | Operation::LessThan | Operation::Equals => 3,
+            Operation::JumpIfTrue | Operation::JumpIfFalse => 2,
+            Operation::Input | Operation::Output | Operation::AdjustRelativeBaseOffset => 1,
+            Operation::Halt => 0,
+        }
+    }
+
+    /// The category of the operation.
+    pub fn kind(&self) -> InstructionKind {
+        match self {
+            Operation::Add | Operation::Multiply | Operation::LessThan | Operation::Equals => {
+                InstructionKind::Arithmetic
+            }
+            Operation::JumpIfTrue | Operation::JumpIfFalse => InstructionKind::Jump,
+            Operation::Input | Operation::Output => InstructionKind::Io,
+            Operation::AdjustRelativeBaseOffset | Operation::Halt => InstructionKind::Meta,
+        }
+    }
+}
+
 impl Instruction {
-    /// The list of the names of all the instructions.
-    pub const NAMES: [&'static str; 10] = [
-        "ADD", "MUL", "INP", "OUT", "JIT", "JIF", "LST", "EQL", "ARO", "HLT",
-    ];
+    /// Iterate over all of the operations an instruction can perform.
+    pub fn all() -> impl Iterator<Item = Operation> {
+        all::<Operation>()
+    }
+
+    /// Create an instruction for the operation with the given parameters.
+    pub fn new(operation: Operation, parameters: &[Parameter]) -> Result<Self> {
+        if parameters.len() != operation.arity() {
+            return Err(anyhow!(
+                "{} expects {} parameters but got {}",
+                operation.mnemonic(),
+                operation.arity(),
+                parameters.len()
+            ));
+        }
+        let p = parameters;
+        Ok(match operation {
+            Operation::Add => Instruction::Add(p[0], p[1], p[2]),
+            Operation::Multiply => Instruction::Multiply(p[0], p[1], p[2]),
+            Operation::Input => Instruction::Input(p[0]),
+            Operation::Output => Instruction::Output(p[0]),
+            Operation::JumpIfTrue => Instruction::JumpIfTrue(p[0], p[1]),
+            Operation::JumpIfFalse => Instruction::JumpIfFalse(p[0], p[1]),
+            Operation::LessThan => Instruction::LessThan(p[0], p[1], p[2]),
+            Operation::Equals => Instruction::Equals(p[0], p[1], p[2]),
+            Operation::AdjustRelativeBaseOffset => Instruction::AdjustRelativeBaseOffset(p[0]),
+            Operation::Halt => Instruction::Halt,
+        })
+    }
+
+    /// Decode the instruction at the given instruction pointer in memory and return it along with
+    /// its size. Any parameters that are past the end of memory are treated as zero. Instructions
+    /// that would write to an immediate parameter are rejected.
+    pub fn decode(memory: &[isize], ip: usize) -> Result<(Instruction, usize)> {
+        Self::decode_with(ip, |address| memory.get(address).copied())
+    }
+
+    /// Decode the instruction like [Instruction::decode] but allow writes to immediate parameters.
+    /// This is useful for tools that want to report them instead of stopping at them.
+    pub fn decode_unchecked(memory: &[isize], ip: usize) -> Result<(Instruction, usize)> {
+        Self::decode_unchecked_with(ip, |address| memory.get(address).copied())
+    }
+
+    /// Decode the instruction like [Instruction::decode] but get each cell from the given function
+    /// instead of a slice, so memory that isn't contiguous can be decoded where it is. Errors name
+    /// the given instruction pointer.
+    pub fn decode_with(
+        ip: usize,
+        cell: impl Fn(usize) -> Option<isize>,
+    ) -> Result<(Instruction, usize)> {
+        let (instruction, size) = Self::decode_unchecked_with(ip, cell)?;
+        if let Some(Parameter::Immediate(_)) = instruction.write_parameter() {
+            return Err(anyhow!(
+                "'{}' at {} writes to an immediate parameter",
+                instruction,
+                ip
+            ));
+        }
+        Ok((instruction, size))
+    }
+
+    fn decode_unchecked_with(
+        ip: usize,
+        cell: impl Fn(usize) -> Option<isize>,
+    ) -> Result<(Instruction, usize)> {
+        let opcode =
+            cell(ip).ok_or_else(|| anyhow!("instruction pointer {} is out of memory", ip))?;
+        let operation = Operation::from_opcode(opcode)
+            .ok_or_else(|| anyhow!("invalid opcode {} at {}", opcode, ip))?;
+        let parameters = (1..=operation.arity())
+            .map(|position| {
+                let value = cell(ip + position).unwrap_or(0);
+                Parameter::try_new(opcode, position as isize, value)
+                    .map_err(|e| anyhow!("{} at {}", e, ip))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let instruction = Instruction::new(operation, &parameters)?;
+        Ok((instruction, operation.arity() + 1))
+    }
+
+    /// Get the operation the instruction performs.
+    pub fn operation(&self) -> Operation {
+        match self {
+            Instruction::Add(..) => Operation::Add,
+            Instruction::Multiply(..) => Operation::Multiply,
+            Instruction::Input(..) => Operation::Input,
+            Instruction::Output(..) => Operation::Output,
+            Instruction::JumpIfTrue(..) => Operation::JumpIfTrue,
+            Instruction::JumpIfFalse(..) => Operation::JumpIfFalse,
+            Instruction::LessThan(..) => Operation::LessThan,
+            Instruction::Equals(..) => Operation::Equals,
+            Instruction::AdjustRelativeBaseOffset(..) => Operation::AdjustRelativeBaseOffset,
+            Instruction::Halt => Operation::Halt,
+        }
+    }
+
+    /// The mnemonic of the instruction's operation.
+    pub fn mnemonic(&self) -> &'static str {
+        self.operation().mnemonic()
+    }
+
+    /// The opcode of the instruction's operation without any parameter modes.
+    pub fn opcode(&self) -> isize {
+        self.operation().opcode()
+    }
+
+    /// The number of parameters the instruction takes.
+    pub fn arity(&self) -> usize {
+        self.operation().arity()
+    }
+
+    /// The category of the instruction's operation.
+    pub fn kind(&self) -> InstructionKind {
+        self.operation().kind()
+    }
 
     /// Get the number of parameters for a given instruction. This will be used by the tui to
     /// highlight the parameters of an operation. Also useful for incrementing the instruction
     /// pointer.
     pub fn parameter_count(&self) -> usize {
-        match self {
-            Instruction::Add(_, _, _) => 3,
-            Instruction::Multiply(_, _, _) => 3,
-            Instruction::Input(_) => 1,
-            Instruction::Output(_) => 1,
-            Instruction::JumpIfTrue(_, _) => 2,
-            Instruction::JumpIfFalse(_, _) => 2,
-            Instruction::LessThan(_, _, _) => 3,
-            Instruction::Equals(_, _, _) => 3,
-            Instruction::AdjustRelativeBaseOffset(_) => 1,
-            Instruction::Halt => 0,
+        self.arity()
+    }
+
+    /// Get all of the parameters of the instruction in order.
+    pub fn parameters(&self) -> Vec<Parameter> {
+        match *self {
+            Instruction::Add(a, b, c)
+            | Instruction::Multiply(a, b, c)
+            | Instruction::LessThan(a, b, c)
+            | Instruction::Equals(a, b, c) => vec![a, b, c],
+            Instruction::JumpIfTrue(a, b) | Instruction::JumpIfFalse(a, b) => vec![a, b],
+            Instruction::Input(a)
+            | Instruction::Output(a)
+            | Instruction::AdjustRelativeBaseOffset(a) => vec![a],
+            Instruction::Halt => vec![],
+        }
+    }
+
+    /// Get the parameters the instruction reads from.
+    pub fn read_parameters(&self) -> Vec<Parameter> {
+        let mut parameters = self.parameters();
+        if self.write_parameter().is_some() {
+            parameters.pop();
+        }
+        parameters
+    }
+
+    /// Get the parameter the instruction writes to, if it writes to memory.
+    pub fn write_parameter(&self) -> Option<Parameter> {
+        match *self {
+            Instruction::Add(_, _, dest)
+            | Instruction::Multiply(_, _, dest)
+            | Instruction::LessThan(_, _, dest)
+            | Instruction::Equals(_, _, dest)
+            | Instruction::Input(dest) => Some(dest),
+            _ => None,
         }
     }
 
+    /// Encode the instruction into the memory cells that represent it. The parameter modes are
+    /// folded into the opcode. This is the inverse of [Instruction::decode].
+    pub fn encode(&self) -> Vec<isize> {
+        let mut cells = vec![self.opcode()];
+        for (position, parameter) in self.parameters().into_iter().enumerate() {
+            let (mode, value) = match parameter {
+                Parameter::Position(pos) => (0, pos as isize),
+                Parameter::Immediate(value) => (1, value),
+                Parameter::Relative(offset) => (2, offset),
+            };
+            cells[0] += mode * 10_isize.pow(position as u32 + 2);
+            cells.push(value);
+        }
+        cells
+    }
+
     /// Get the parameters in relative mode for a given instruction. This will be used by the tui
     /// to highlight the memory locations that are being read from or written to.
     pub fn relative_parameters(&self, base: isize) -> Vec<usize> {
@@ -150,63 +412,204 @@ impl Instruction {
     }
 }
 
-impl Display for Instruction {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Instruction {
+    /// Get a displayable version of the instruction that uses the names from the symbol table for
+    /// addresses that have one (e.g. `OUT -> counter` instead of `OUT -> P[223]`).
+    pub fn with_symbols<'a>(&'a self, symbols: &'a SymbolTable) -> Symbolic<'a> {
+        Symbolic {
+            instruction: self,
+            symbols,
+        }
+    }
+
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, symbols: &SymbolTable) -> std::fmt::Result {
+        // Positions are named by the address they point at and jump targets by where they go.
+        let p = |parameter: &Parameter| {
+            match parameter {
+                Parameter::Position(address) => symbols.name(*address).map(|name| name.to_string()),
+                _ => None,
+            }
+            .unwrap_or_else(|| parameter.to_string())
+        };
+        let t = |parameter: &Parameter| {
+            match parameter {
+                Parameter::Immediate(target) if *target >= 0 => {
+                    symbols.name(*target as usize).map(|name| name.to_string())
+                }
+                _ => None,
+            }
+            .unwrap_or_else(|| p(parameter))
+        };
+
         match self {
             Instruction::Add(left, right, dest) => {
-                write!(f, "ADD {} + {} -> {}", left, right, dest)
+                write!(f, "ADD {} + {} -> {}", p(left), p(right), p(dest))
             }
             Instruction::Multiply(left, right, dest) => {
-                write!(f, "MUL {} * {} -> {}", left, right, dest)
+                write!(f, "MUL {} * {} -> {}", p(left), p(right), p(dest))
+            }
+            Instruction::Input(dest) => write!(f, "INP -> {}", p(dest)),
+            Instruction::Output(value) => write!(f, "OUT -> {}", p(value)),
+            Instruction::JumpIfTrue(value, dest) => write!(f, "JIT {} -> {}", p(value), t(dest)),
+            Instruction::JumpIfFalse(value, dest) => {
+                write!(f, "JIF {} -> {}", p(value), t(dest))
             }
-            Instruction::Input(dest) => write!(f, "INP -> {}", dest),
-            Instruction::Output(value) => write!(f, "OUT -> {}", value),
-            Instruction::JumpIfTrue(value, dest) => write!(f, "JIT {} -> {}", value, dest),
-            Instruction::JumpIfFalse(value, dest) => write!(f, "JIF {} -> {}", value, dest),
             Instruction::LessThan(left, right, dest) => {
-                write!(f, "LST {} < {} -> {}", left, right, dest)
+                write!(f, "LST {} < {} -> {}", p(left), p(right), p(dest))
             }
             Instruction::Equals(left, right, dest) => {
-                write!(f, "EQL {} == {} -> {}", left, right, dest)
+                write!(f, "EQL {} == {} -> {}", p(left), p(right), p(dest))
             }
-            Instruction::AdjustRelativeBaseOffset(value) => write!(f, "ARO {}", value),
+            Instruction::AdjustRelativeBaseOffset(value) => write!(f, "ARO {}", p(value)),
             Instruction::Halt => write!(f, "HLT"),
         }
     }
 }
 
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, &SymbolTable::new())
+    }
+}
+
+/// An instruction that is displayed using the names from a symbol table. See
+/// [Instruction::with_symbols].
+pub struct Symbolic<'a> {
+    instruction: &'a Instruction,
+    symbols: &'a SymbolTable,
+}
+
+impl Display for Symbolic<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.instruction.write(f, self.symbols)
+    }
+}
+
+impl From<Operation> for Instruction {
+    /// Create an instruction for the operation with all of its parameters at position 0.
+    fn from(operation: Operation) -> Self {
+        let parameters = vec![Parameter::Position(0); operation.arity()];
+        Instruction::new(operation, &parameters).unwrap()
+    }
+}
+
 impl From<&str> for Instruction {
+    /// Create an instruction from a bare mnemonic. Use [FromStr] to parse the full display format.
     fn from(s: &str) -> Self {
-        match s {
-            "ADD" => Instruction::Add(
-                Parameter::Position(0),
-                Parameter::Position(0),
-                Parameter::Position(0),
-            ),
-            "MUL" => Instruction::Multiply(
-                Parameter::Position(0),
-                Parameter::Position(0),
-                Parameter::Position(0),
-            ),
-            "INP" => Instruction::Input(Parameter::Position(0)),
-            "OUT" => Instruction::Output(Parameter::Position(0)),
-            "JIT" => Instruction::JumpIfTrue(Parameter::Position(0), Parameter::Position(0)),
-            "JIF" => Instruction::JumpIfFalse(Parameter::Position(0), Parameter::Position(0)),
-            "LST" => Instruction::LessThan(
-                Parameter::Position(0),
-                Parameter::Position(0),
-                Parameter::Position(0),
-            ),
-            "EQL" => Instruction::Equals(
-                Parameter::Position(0),
-                Parameter::Position(0),
-                Parameter::Position(0),
-            ),
-            "ARO" => Instruction::AdjustRelativeBaseOffset(Parameter::Position(0)),
-            "HLT" => Instruction::Halt,
-            // TODO, at this point, we could also see if they fit the pattern in Display and parse
-            // them.
-            _ => panic!("invalid instruction"),
+        Operation::from_mnemonic(s)
+            .expect("invalid instruction")
+            .into()
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = Error;
+
+    /// Parse an instruction in the same format it is displayed in (e.g. `ADD P[1] + I[2] -> R[3]`).
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("invalid instruction '{}'", s);
+        let tokens = s.split_whitespace().collect::<Vec<_>>();
+        let operation = tokens
+            .first()
+            .and_then(|name| Operation::from_mnemonic(name))
+            .ok_or_else(invalid)?;
+
+        // Everything that looks like a parameter is one. We check that the operators are where
+        // they should be by comparing against how the instruction is displayed.
+        let parameters = tokens
+            .iter()
+            .skip(1)
+            .filter_map(|token| token.parse::<Parameter>().ok())
+            .collect::<Vec<_>>();
+        let instruction = Instruction::new(operation, &parameters).map_err(|_| invalid())?;
+        match instruction.to_string() == tokens.join(" ") {
+            true => Ok(instruction),
+            false => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let (p, i, r) = (
+            Parameter::Position(7),
+            Parameter::Immediate(-3),
+            Parameter::Relative(12),
+        );
+        let instructions = [
+            Instruction::Add(p, i, r),
+            Instruction::Multiply(i, r, p),
+            Instruction::Input(r),
+            Instruction::Output(i),
+            Instruction::JumpIfTrue(r, i),
+            Instruction::JumpIfFalse(p, r),
+            Instruction::LessThan(r, p, p),
+            Instruction::Equals(i, i, r),
+            Instruction::AdjustRelativeBaseOffset(p),
+            Instruction::Halt,
+        ];
+        for instruction in instructions {
+            let cells = instruction.encode();
+            assert_eq!(
+                Instruction::decode(&cells, 0).unwrap(),
+                (instruction, cells.len())
+            );
+        }
+        assert_eq!(Instruction::Add(p, i, r).encode(), vec![21001, 7, -3, 12]);
+
+        // Writes to immediates and unknown modes are caught when decoding.
+        assert!(Instruction::decode(&[11101, 1, 2, 3], 0).is_err());
+        assert!(Instruction::decode_unchecked(&[11101, 1, 2, 3], 0).is_ok());
+        assert!(Instruction::decode(&[301, 1, 2, 3], 0).is_err());
+
+        // Errors name the instruction pointer they were decoding at.
+        let memory = [99, 42, 11101, 1, 2, 3, 301, 1, 2, 3];
+        let error = |ip| Instruction::decode(&memory, ip).unwrap_err().to_string();
+        assert_eq!(error(1), "invalid opcode 42 at 1");
+        assert_eq!(
+            error(2),
+            "'ADD I[1] + I[2] -> I[3]' at 2 writes to an immediate parameter"
+        );
+        assert_eq!(
+            error(6),
+            "invalid parameter mode 3 for parameter 1 of opcode 301 at 6"
+        );
+    }
+
+    #[test]
+    fn test_from_str_round_trip() {
+        let instruction = Instruction::Equals(
+            Parameter::Relative(-1),
+            Parameter::Immediate(4),
+            Parameter::Position(9),
+        );
+        assert_eq!(
+            instruction.to_string().parse::<Instruction>().unwrap(),
+            instruction
+        );
+        assert_eq!("HLT".parse::<Instruction>().unwrap(), Instruction::Halt);
+        assert!("ADD P[1] - I[2] -> R[3]".parse::<Instruction>().is_err());
+        assert!("OUT".parse::<Instruction>().is_err());
+        assert!("NOP".parse::<Instruction>().is_err());
+    }
+
+    #[test]
+    fn test_metadata() {
+        for operation in Instruction::all() {
+            assert_eq!(
+                Operation::from_mnemonic(operation.mnemonic()),
+                Some(operation)
+            );
+            assert_eq!(Operation::from_opcode(operation.opcode()), Some(operation));
+            assert_eq!(Instruction::from(operation).operation(), operation);
         }
+        assert_eq!(Instruction::all().count(), 10);
+        assert_eq!(Instruction::from("JIF").kind(), InstructionKind::Jump);
+        assert_eq!(Instruction::from("EQL").kind(), InstructionKind::Arithmetic);
+        assert_eq!(Instruction::from("HLT").kind(), InstructionKind::Meta);
     }
 }
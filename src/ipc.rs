@@ -1,79 +1,393 @@
 use std::{
     collections::VecDeque,
-    sync::{Arc, Mutex},
+    future::Future,
+    io::{BufRead, BufReader, Write},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
 };
 
-use anyhow::Result;
-use tokio::sync::mpsc::{self, Receiver, Sender};
+use anyhow::{anyhow, Error, Result};
+use futures::{Sink, Stream};
+use tokio::sync::mpsc::{
+    self,
+    error::{SendError, TryRecvError},
+    OwnedPermit, Receiver, Sender,
+};
+use tokio::time::Instant;
+
+use crate::rng::Rng;
+
+/// The result of receiving a value from a channel.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RecvResult {
+    /// A value was received.
+    Value(isize),
+    /// The channel is still open but there is currently nothing to receive. This is only returned
+    /// by channels that don't block on receive.
+    Empty,
+    /// The channel has been closed and everything in it has been received.
+    Closed,
+}
+
+impl From<RecvResult> for Option<isize> {
+    fn from(result: RecvResult) -> Self {
+        match result {
+            RecvResult::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+// The future we wait on for room in the notifier when the sender is used as a Sink.
+type Reserve = Pin<Box<dyn Future<Output = Result<OwnedPermit<()>, SendError<()>>> + Send + Sync>>;
+
+// The id to give the next channel that's created.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
 
 /// The sender end of a channel.
-#[derive(Debug, Clone)]
 pub struct ChannelSender {
+    id: usize,
     buffer: Arc<Mutex<VecDeque<isize>>>,
+    closed: Arc<AtomicBool>,
     notifier: Sender<()>,
+    reserve: Option<Reserve>,
+    permit: Option<OwnedPermit<()>>,
 }
 
 impl ChannelSender {
-    fn new(buffer: Arc<Mutex<VecDeque<isize>>>, notifier: Sender<()>) -> Self {
-        Self { buffer, notifier }
+    fn new(
+        id: usize,
+        buffer: Arc<Mutex<VecDeque<isize>>>,
+        closed: Arc<AtomicBool>,
+        notifier: Sender<()>,
+    ) -> Self {
+        Self {
+            id,
+            buffer,
+            closed,
+            notifier,
+            reserve: None,
+            permit: None,
+        }
     }
 
-    /// Send a value to the channel.
+    /// Send a value to the channel. This will fail if the channel has been closed.
     pub async fn send(&mut self, value: isize) -> Result<()> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(anyhow!("channel closed"));
+        }
         {
             self.buffer.lock().unwrap().push_back(value);
         }
+        #[cfg(feature = "tracing")]
+        tracing::trace!(channel = self.id, value, "send");
+        #[cfg(feature = "chrome-trace")]
+        crate::chrome_trace::channel("send", self.id, value);
+        self.notifier.send(()).await?;
+        Ok(())
+    }
+
+    /// Send all of the values at once. The buffer is locked once and the receiver is notified
+    /// once, so this is much cheaper than sending the values one at a time when there are a lot of
+    /// them, like a frame of ASCII output. This will fail if the channel has been closed.
+    pub async fn send_slice(&mut self, values: &[isize]) -> Result<()> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(anyhow!("channel closed"));
+        }
+        if values.is_empty() {
+            return Ok(());
+        }
+        {
+            self.buffer.lock().unwrap().extend(values);
+        }
+        #[cfg(feature = "tracing")]
+        tracing::trace!(channel = self.id, count = values.len(), "send_slice");
+        #[cfg(feature = "chrome-trace")]
+        for &value in values {
+            crate::chrome_trace::channel("send", self.id, value);
+        }
         self.notifier.send(()).await?;
         Ok(())
     }
+
+    /// Check whether sending would have to wait for the receiver to catch up.
+    pub fn is_full(&self) -> bool {
+        self.notifier.capacity() == 0
+    }
+
+    /// Close the channel. Values already in the channel can still be received but once they have
+    /// been, the receiver will get [RecvResult::Closed]. This closes the channel for all senders,
+    /// not just this one.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(channel = self.id, "close");
+
+        // Wake up the receiver in case it's waiting. If the notifier is full, the receiver has
+        // values to get through first and will notice the channel is closed after that.
+        let _ = self.notifier.try_send(());
+    }
+
+    /// Check whether the channel has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Get the id of the channel this sends to.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl Clone for ChannelSender {
+    fn clone(&self) -> Self {
+        Self::new(
+            self.id,
+            self.buffer.clone(),
+            self.closed.clone(),
+            self.notifier.clone(),
+        )
+    }
+}
+
+impl std::fmt::Debug for ChannelSender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChannelSender")
+            .field("id", &self.id)
+            .field("buffer", &self.buffer)
+            .field("closed", &self.closed)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The sender can be used as a sink. Closing the sink closes the channel, so forwarding a stream
+/// into it will close the channel once the stream ends.
+impl Sink<isize> for ChannelSender {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        if this.is_closed() {
+            return Poll::Ready(Err(anyhow!("channel closed")));
+        }
+        if this.permit.is_some() {
+            return Poll::Ready(Ok(()));
+        }
+
+        // Wait until there is room to notify the receiver and hold on to it for start_send.
+        let notifier = this.notifier.clone();
+        let reserve = this
+            .reserve
+            .get_or_insert_with(|| Box::pin(notifier.reserve_owned()));
+        match reserve.as_mut().poll(cx) {
+            Poll::Ready(result) => {
+                this.reserve = None;
+                this.permit = Some(result?);
+                Poll::Ready(Ok(()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, value: isize) -> Result<()> {
+        let this = self.get_mut();
+        let permit = this
+            .permit
+            .take()
+            .ok_or(anyhow!("start_send called before poll_ready"))?;
+        this.buffer.lock().unwrap().push_back(value);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(channel = this.id, value, "send");
+        #[cfg(feature = "chrome-trace")]
+        crate::chrome_trace::channel("send", this.id, value);
+        permit.send(());
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<()>> {
+        self.close();
+        Poll::Ready(Ok(()))
+    }
 }
 
 /// The receiver end of a channel.
 pub struct ChannelReceiver {
+    id: usize,
     buffer: Arc<Mutex<VecDeque<isize>>>,
+    closed: Arc<AtomicBool>,
+    empty_reads: Arc<AtomicUsize>,
     notifier: Receiver<()>,
     block_on_recv: bool,
+    empty_value: Option<isize>,
 }
 
 impl ChannelReceiver {
     fn new(
+        id: usize,
         buffer: Arc<Mutex<VecDeque<isize>>>,
+        closed: Arc<AtomicBool>,
+        empty_reads: Arc<AtomicUsize>,
         notifier: Receiver<()>,
         block_on_recv: bool,
     ) -> Self {
         Self {
+            id,
             buffer,
+            closed,
+            empty_reads,
             notifier,
             block_on_recv,
+            empty_value: None,
         }
     }
 
     /// Receive a value from the channel. If the channel is empty and the channel was set not to
-    /// block, then this will return `None` if the channel is empty.
-    pub async fn recv(&mut self) -> Option<isize> {
-        match self.block_on_recv {
-            true => match self.notifier.recv().await {
-                Some(_) => {
-                    let value = {
-                        let data = self.buffer.lock().unwrap();
-                        *data.front().unwrap()
-                    };
-                    self.buffer.lock().unwrap().pop_front();
-                    Some(value)
-                }
-                None => None,
-            },
-            false => match self.notifier.try_recv() {
-                Ok(_) => {
-                    let value = {
-                        let data = self.buffer.lock().unwrap();
-                        *data.front().unwrap()
-                    };
-                    self.buffer.lock().unwrap().pop_front();
-                    Some(value)
-                }
-                Err(_) => None,
-            },
+    /// block, then this will return [RecvResult::Empty] (or the empty value if the channel has
+    /// one). Once the channel has been closed (or all of the senders have been dropped) and it's
+    /// been emptied, this will return [RecvResult::Closed].
+    pub async fn recv(&mut self) -> RecvResult {
+        loop {
+            // A slice is sent with a single notification, so there may be values waiting for us
+            // without one.
+            if let Some(value) = self.pop() {
+                let _ = self.notifier.try_recv();
+                return RecvResult::Value(value);
+            }
+
+            // If we've been closed, we don't want to wait on a notification that may never come.
+            if self.closed.load(Ordering::SeqCst) && self.buffer.lock().unwrap().is_empty() {
+                return RecvResult::Closed;
+            }
+
+            let notified = match self.block_on_recv {
+                true => self.notifier.recv().await.is_some(),
+                false => match self.notifier.try_recv() {
+                    Ok(_) => true,
+                    Err(TryRecvError::Empty) => return self.empty().await,
+                    Err(TryRecvError::Disconnected) => false,
+                },
+            };
+
+            match self.pop() {
+                Some(value) => return RecvResult::Value(value),
+                // Closing the channel sends a notification without a value and a drain can leave
+                // one behind, so we just go around again.
+                None if notified => continue,
+                None => return RecvResult::Closed,
+            }
+        }
+    }
+
+    // Pop the next value off of the buffer after being notified.
+    fn pop(&mut self) -> Option<isize> {
+        let value = self.buffer.lock().unwrap().pop_front();
+        if let Some(_value) = value {
+            self.empty_reads.store(0, Ordering::SeqCst);
+            #[cfg(feature = "tracing")]
+            tracing::trace!(channel = self.id, value = _value, "recv");
+            #[cfg(feature = "chrome-trace")]
+            crate::chrome_trace::channel("recv", self.id, _value);
+        }
+        value
+    }
+
+    // Handle a non-blocking receive on an empty channel. We yield here so a process polling an empty
+    // channel doesn't starve everything else running on the same thread.
+    async fn empty(&mut self) -> RecvResult {
+        self.empty_reads.fetch_add(1, Ordering::SeqCst);
+        tokio::task::yield_now().await;
+        match self.empty_value {
+            Some(value) => RecvResult::Value(value),
+            None => RecvResult::Empty,
+        }
+    }
+
+    /// Get the id of the channel this receives from.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Check whether receiving would return a value (or find the channel closed) right away rather
+    /// than waiting or returning [RecvResult::Empty].
+    pub fn is_ready(&self) -> bool {
+        self.empty_value.is_some()
+            || self.closed.load(Ordering::SeqCst)
+            || !self.buffer.lock().unwrap().is_empty()
+    }
+
+    /// Receive everything in the channel at once, waiting for at least one value like [recv]
+    /// does. This returns None once the channel is closed and an empty list if the channel
+    /// doesn't block on receive and is empty.
+    ///
+    /// [recv]: ChannelReceiver::recv
+    pub async fn recv_chunk(&mut self) -> Option<Vec<isize>> {
+        match self.recv().await {
+            RecvResult::Value(value) => {
+                let mut chunk = vec![value];
+                chunk.extend(self.drain());
+                Some(chunk)
+            }
+            RecvResult::Empty => Some(Vec::new()),
+            RecvResult::Closed => None,
+        }
+    }
+
+    /// Look at the next value in the channel without receiving it.
+    pub fn peek(&self) -> Option<isize> {
+        self.buffer.lock().unwrap().front().copied()
+    }
+
+    /// Receive everything that is currently in the channel at once. This never blocks and will
+    /// return an empty list if there is nothing in the channel.
+    pub fn drain(&mut self) -> Vec<isize> {
+        let values = self.buffer.lock().unwrap().drain(..).collect::<Vec<_>>();
+
+        // Each value (or slice of values) comes with a notification, so we consume those as well to
+        // keep them in sync.
+        for _ in 0..values.len() {
+            if self.notifier.try_recv().is_err() {
+                break;
+            }
+        }
+        values
+    }
+}
+
+/// The receiver can be used as a stream of values. The stream always waits for the next value,
+/// even if the channel doesn't block on receive, and ends once the channel is closed.
+impl Stream for ChannelReceiver {
+    type Item = isize;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<isize>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(value) = this.pop() {
+                let _ = this.notifier.try_recv();
+                return Poll::Ready(Some(value));
+            }
+            if this.closed.load(Ordering::SeqCst) && this.buffer.lock().unwrap().is_empty() {
+                return Poll::Ready(None);
+            }
+
+            let notified = match this.notifier.poll_recv(cx) {
+                Poll::Ready(notified) => notified.is_some(),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            match this.pop() {
+                Some(value) => return Poll::Ready(Some(value)),
+                None if notified => continue,
+                None => return Poll::Ready(None),
+            }
         }
     }
 }
@@ -81,23 +395,617 @@ impl ChannelReceiver {
 /// An extremely simple implementation of a channel for use with the Intcode computer. We use it
 /// mostly so we can view what's being held in the channels buffer.
 pub struct Channel {
+    id: usize,
     buffer: Arc<Mutex<VecDeque<isize>>>,
+    closed: Arc<AtomicBool>,
+    empty_reads: Arc<AtomicUsize>,
 }
 
 impl Channel {
     /// Create a new channel. If `block_on_recv` is `true`, then the receiver will block until a
-    /// value is received. If `false`, then the receiver will return `None` if the channel is empty.
+    /// value is received. If `false`, then the receiver will return [RecvResult::Empty] if the
+    /// channel is empty.
     pub fn new(block_on_recv: bool) -> (Self, ChannelSender, ChannelReceiver) {
         let (notifier_send, notifier_recv) = mpsc::channel(32);
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
         let buffer = Arc::new(Mutex::new(VecDeque::new()));
-        let sender = ChannelSender::new(buffer.clone(), notifier_send);
-        let receiver = ChannelReceiver::new(buffer.clone(), notifier_recv, block_on_recv);
+        let closed = Arc::new(AtomicBool::new(false));
+        let empty_reads = Arc::new(AtomicUsize::new(0));
+        let sender = ChannelSender::new(id, buffer.clone(), closed.clone(), notifier_send);
+        let receiver = ChannelReceiver::new(
+            id,
+            buffer.clone(),
+            closed.clone(),
+            empty_reads.clone(),
+            notifier_recv,
+            block_on_recv,
+        );
+
+        (
+            Self {
+                id,
+                buffer,
+                closed,
+                empty_reads,
+            },
+            sender,
+            receiver,
+        )
+    }
+
+    /// Create two channels that link two sides together. Each side gets a sender for the other
+    /// side and a receiver for what the other side sends it. This is the usual setup for a
+    /// process and the code driving it.
+    pub fn pair(
+        block_on_recv: bool,
+    ) -> (
+        (ChannelSender, ChannelReceiver),
+        (ChannelSender, ChannelReceiver),
+    ) {
+        let (_, a_sender, a_receiver) = Self::new(block_on_recv);
+        let (_, b_sender, b_receiver) = Self::new(block_on_recv);
+        ((a_sender, b_receiver), (b_sender, a_receiver))
+    }
+
+    /// Create a new channel that doesn't block. Instead of returning [RecvResult::Empty], the
+    /// receiver will return the given value when the channel is empty. This is what the networked
+    /// computers of day 23 expect (they get a -1 when there are no packets).
+    pub fn with_empty_value(value: isize) -> (Self, ChannelSender, ChannelReceiver) {
+        let (channel, sender, mut receiver) = Self::new(false);
+        receiver.empty_value = Some(value);
+        (channel, sender, receiver)
+    }
+
+    /// Create a new channel that delays values between being sent and being able to be received.
+    /// Values are relayed through a task, so this must be called from within a tokio runtime. Each
+    /// value is due its delay after it was sent and, unless reordering, also waits for the values
+    /// sent before it, so no value waits longer than the latency's `max`. Once the sending side is
+    /// closed (or dropped), the channel is closed after everything in flight has been delivered.
+    pub fn with_latency(
+        block_on_recv: bool,
+        latency: Latency,
+    ) -> (Self, ChannelSender, ChannelReceiver) {
+        let (_, sender, mut relay_receiver) = Self::new(true);
+        let (channel, mut relay_sender, receiver) = Self::new(block_on_recv);
+
+        tokio::spawn(async move {
+            let mut rng = Rng::new(latency.seed);
+            let mut in_flight = VecDeque::<(Instant, isize)>::new();
+            let mut open = true;
+            while open || !in_flight.is_empty() {
+                // If we are reordering, the value due first goes next, so a shorter delay can pass
+                // a longer one. Otherwise, values go in the order they were sent.
+                let next = match latency.reorder {
+                    true => in_flight
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, (due, _))| *due)
+                        .map(|(i, _)| i),
+                    false => (!in_flight.is_empty()).then_some(0),
+                };
+                let due = next.map_or_else(Instant::now, |i| in_flight[i].0);
+
+                tokio::select! {
+                    result = relay_receiver.recv(), if open => match result {
+                        RecvResult::Value(value) => {
+                            in_flight.push_back((Instant::now() + latency.delay(&mut rng), value))
+                        }
+                        _ => open = false,
+                    },
+                    _ = tokio::time::sleep_until(due), if next.is_some() => {
+                        let value = next.and_then(|i| in_flight.remove(i));
+                        if let Some((_, value)) = value {
+                            if relay_sender.send(value).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+            relay_sender.close();
+        });
+
+        (channel, sender, receiver)
+    }
 
-        (Self { buffer }, sender, receiver)
+    /// Get the id of this channel. Every channel gets a unique id when it's created, which is also
+    /// used to identify it in traces.
+    pub fn id(&self) -> usize {
+        self.id
     }
 
     /// Get a copy of this channel's buffer.
     pub fn buffer(&self) -> Vec<isize> {
         self.buffer.lock().unwrap().iter().copied().collect()
     }
+
+    /// Check whether this channel has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Get the number of times in a row the receiver has found this channel empty. This is reset
+    /// whenever a value is received.
+    pub fn empty_reads(&self) -> usize {
+        self.empty_reads.load(Ordering::SeqCst)
+    }
+}
+
+/// Create the channels for `n` processes connected in series, where the output of each process is
+/// the input of the next. This returns the sender for the input of the first process, the input
+/// receiver and output sender for each process, and the receiver for the output of the last
+/// process.
+pub fn pipeline(
+    n: usize,
+    block_on_recv: bool,
+) -> (
+    ChannelSender,
+    Vec<(ChannelReceiver, ChannelSender)>,
+    ChannelReceiver,
+) {
+    let (_, first, mut receiver) = Channel::new(block_on_recv);
+    let mut stages = Vec::with_capacity(n);
+    for _ in 0..n {
+        let (_, sender, next) = Channel::new(block_on_recv);
+        stages.push((receiver, sender));
+        receiver = next;
+    }
+    (first, stages, receiver)
+}
+
+/// Send each line of the reader to the channel as ASCII, ending with the newline the ASCII
+/// programs expect. The reader is read on its own thread, so it can block like stdin does without
+/// holding up the runtime. This closes the channel once the reader runs out and stops early if
+/// the channel is closed.
+pub async fn send_lines(
+    reader: impl BufRead + Send + 'static,
+    mut sender: ChannelSender,
+) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in reader.lines() {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(line) = rx.recv().await {
+        let values = line?
+            .bytes()
+            .chain(std::iter::once(b'\n'))
+            .map(|byte| byte as isize)
+            .collect::<Vec<_>>();
+        if sender.send_slice(&values).await.is_err() {
+            return Ok(());
+        }
+    }
+    sender.close();
+    Ok(())
+}
+
+/// Write everything the channel receives to the writer until it's closed. ASCII values are written
+/// as characters and anything else as a number on its own line. The writer is flushed whenever
+/// the channel runs dry so prompts show up before the program waits for an answer.
+pub async fn write_ascii(mut receiver: ChannelReceiver, mut writer: impl Write) -> Result<()> {
+    while let Some(chunk) = receiver.recv_chunk().await {
+        for value in chunk {
+            match value {
+                0..=127 => write!(writer, "{}", value as u8 as char)?,
+                _ => writeln!(writer, "{}", value)?,
+            }
+        }
+        if !receiver.is_ready() {
+            writer.flush()?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Send the lines typed on stdin to the channel (see [send_lines]).
+pub async fn stdin(sender: ChannelSender) -> Result<()> {
+    send_lines(BufReader::new(std::io::stdin()), sender).await
+}
+
+/// Write what the channel receives to stdout (see [write_ascii]).
+pub async fn stdout(receiver: ChannelReceiver) -> Result<()> {
+    write_ascii(receiver, std::io::stdout()).await
+}
+
+/// The latency to inject into a channel created with [Channel::with_latency]. Each value is
+/// delayed by a random amount between `min` and `max`. This is useful for making sure solutions
+/// with multiple processes don't depend on the timing they happen to get.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Latency {
+    /// The shortest a value will be delayed.
+    pub min: Duration,
+    /// The longest a value will be delayed.
+    pub max: Duration,
+    /// Whether values can be delivered in a different order than they were sent.
+    pub reorder: bool,
+    /// The seed for the random delays, so a run can be reproduced.
+    pub seed: u64,
+}
+
+impl Latency {
+    /// Create a new latency that delays values between `min` and `max` without reordering them.
+    pub fn new(min: Duration, max: Duration) -> Self {
+        Self {
+            min,
+            max,
+            reorder: false,
+            seed: 0x2019,
+        }
+    }
+
+    // Get the next delay.
+    fn delay(&self, rng: &mut Rng) -> Duration {
+        let range = self.max.saturating_sub(self.min).as_nanos() as u64;
+        self.min + Duration::from_nanos(rng.next_u64() % (range + 1))
+    }
+}
+
+/// A packet sent between computers connected to a [Switch].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Packet {
+    /// The address the packet is being sent to.
+    pub address: isize,
+    /// The first value of the packet.
+    pub x: isize,
+    /// The second value of the packet.
+    pub y: isize,
+}
+
+impl Packet {
+    /// Create a new packet for the given address.
+    pub fn new(address: isize, x: isize, y: isize) -> Self {
+        Self { address, x, y }
+    }
+}
+
+/// A hook that sees every packet a [Switch] routes.
+pub type Observer = Box<dyn FnMut(&Packet) + Send>;
+
+/// What the switch should do after a NAT hook has been called.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NatAction {
+    /// Keep routing packets.
+    Continue,
+    /// Send the given packet into the network and keep routing packets.
+    Send(Packet),
+    /// Stop routing packets and shut the network down.
+    Stop,
+}
+
+/// The hooks a [Switch] uses for packets that aren't addressed to any of its computers and for when
+/// the network goes idle.
+pub trait Nat {
+    /// Handle a packet sent to an address that isn't connected to the switch.
+    fn receive(&mut self, packet: Packet) -> NatAction;
+
+    /// Handle the network being idle. The network is idle when every computer has found its queue
+    /// empty at least twice in a row and there is nothing left to route.
+    fn idle(&mut self) -> NatAction;
+}
+
+/// A network switch that routes packets between connected computers. Each computer gets its own
+/// input queue which starts with its address and returns -1 when empty. Everything a computer
+/// outputs is read as 3-value packets (address, x, y) and sent to the input queue of the computer
+/// with that address. Anything sent elsewhere is given to a [Nat].
+pub struct Switch {
+    inputs: Vec<(Channel, ChannelSender)>,
+    outputs: Vec<ChannelReceiver>,
+    partials: Vec<Vec<isize>>,
+    observers: Vec<Observer>,
+}
+
+impl Switch {
+    /// Create a new switch for the given number of computers. This returns the switch and the
+    /// input receiver and output sender for each computer, indexed by their address.
+    pub async fn new(size: usize) -> Result<(Self, Vec<(ChannelReceiver, ChannelSender)>)> {
+        let mut inputs = Vec::with_capacity(size);
+        let mut outputs = Vec::with_capacity(size);
+        let mut ports = Vec::with_capacity(size);
+        for address in 0..size {
+            let (input, mut input_sender, input_receiver) = Channel::with_empty_value(-1);
+            let (_, output_sender, output_receiver) = Channel::new(false);
+            input_sender.send(address as isize).await?;
+            inputs.push((input, input_sender));
+            outputs.push(output_receiver);
+            ports.push((input_receiver, output_sender));
+        }
+
+        let switch = Self {
+            inputs,
+            outputs,
+            partials: vec![Vec::with_capacity(3); size],
+            observers: Vec::new(),
+        };
+        Ok((switch, ports))
+    }
+
+    /// Call the observer with every packet a computer sends, before it's routed.
+    pub fn observe(&mut self, observer: impl FnMut(&Packet) + Send + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Route packets between the computers until the NAT says to stop. When we stop, all of the
+    /// input queues are closed so the computers will halt the next time they try to read.
+    pub async fn run(&mut self, nat: &mut impl Nat) -> Result<()> {
+        loop {
+            let mut routed = false;
+            for source in 0..self.outputs.len() {
+                for value in self.outputs[source].drain() {
+                    self.partials[source].push(value);
+                    if self.partials[source].len() < 3 {
+                        continue;
+                    }
+
+                    let partial = &mut self.partials[source];
+                    let packet = Packet::new(partial[0], partial[1], partial[2]);
+                    partial.clear();
+                    routed = true;
+                    #[cfg(feature = "metrics")]
+                    crate::metrics::packet();
+                    self.observers
+                        .iter_mut()
+                        .for_each(|observer| observer(&packet));
+
+                    let action = match self.address(packet.address) {
+                        Some(_) => NatAction::Send(packet),
+                        None => nat.receive(packet),
+                    };
+                    if !self.handle(action).await? {
+                        return Ok(());
+                    }
+                }
+            }
+
+            if !routed && self.is_idle() {
+                #[cfg(feature = "metrics")]
+                crate::metrics::idle();
+                if !self.handle(nat.idle()).await? {
+                    return Ok(());
+                }
+            }
+
+            tokio::task::yield_now().await;
+        }
+    }
+
+    /// Close all of the input queues. The computers will halt the next time they try to read.
+    pub fn close(&self) {
+        for (_, sender) in self.inputs.iter() {
+            sender.close();
+        }
+    }
+
+    /// Check whether the network is idle. See [Nat::idle] for what that means.
+    pub fn is_idle(&self) -> bool {
+        self.partials.iter().all(|p| p.is_empty())
+            && self
+                .inputs
+                .iter()
+                .all(|(channel, _)| channel.buffer().is_empty() && channel.empty_reads() >= 2)
+    }
+
+    // Get the index of the computer with the given address if it's connected to the switch.
+    fn address(&self, address: isize) -> Option<usize> {
+        usize::try_from(address)
+            .ok()
+            .filter(|&address| address < self.inputs.len())
+    }
+
+    // Handle an action, returning whether we should keep routing packets.
+    async fn handle(&mut self, action: NatAction) -> Result<bool> {
+        match action {
+            NatAction::Continue => Ok(true),
+            NatAction::Send(packet) => {
+                let index = self
+                    .address(packet.address)
+                    .ok_or_else(|| anyhow!("no computer at address {}", packet.address))?;
+                let sender = &mut self.inputs[index].1;
+                sender.send(packet.x).await?;
+                sender.send(packet.y).await?;
+                Ok(true)
+            }
+            NatAction::Stop => {
+                self.close();
+                Ok(false)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send_slice() {
+        let (_, mut sender, mut receiver) = Channel::new(true);
+        sender.send_slice(&[1, 2, 3]).await.unwrap();
+        sender.send(4).await.unwrap();
+        assert_eq!(receiver.recv().await, RecvResult::Value(1));
+        assert_eq!(receiver.recv_chunk().await, Some(vec![2, 3, 4]));
+
+        // Values from a slice don't each have a notification, so make sure none are lost when
+        // they're received one at a time.
+        sender.send_slice(&[5, 6]).await.unwrap();
+        sender.close();
+        assert_eq!(receiver.recv().await, RecvResult::Value(5));
+        assert_eq!(receiver.recv().await, RecvResult::Value(6));
+        assert_eq!(receiver.recv().await, RecvResult::Closed);
+        assert_eq!(receiver.recv_chunk().await, None);
+        assert!(sender.send_slice(&[7]).await.is_err());
+
+        let (_, mut sender, mut receiver) = Channel::new(false);
+        assert_eq!(receiver.recv_chunk().await, Some(vec![]));
+        sender.send_slice(&[8, 9]).await.unwrap();
+        assert_eq!(receiver.recv_chunk().await, Some(vec![8, 9]));
+    }
+
+    // Send the values at once, close the sender, and receive everything, checking that each value
+    // arrives between the latency's bounds of being sent.
+    async fn delayed(latency: Latency, values: &[isize]) -> Vec<isize> {
+        let start = Instant::now();
+        let (channel, mut sender, mut receiver) = Channel::with_latency(true, latency);
+        for value in values {
+            sender.send(*value).await.unwrap();
+        }
+        sender.close();
+
+        let mut received = Vec::new();
+        while let RecvResult::Value(value) = receiver.recv().await {
+            assert!(start.elapsed() >= latency.min && start.elapsed() <= latency.max);
+            received.push(value);
+        }
+        assert!(channel.is_closed());
+        received
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency() {
+        let values = (0..20).collect::<Vec<_>>();
+
+        // Delays don't add up, so everything still arrives within the longest delay, in order, and
+        // the channel is only closed after the last value.
+        let latency = Latency::new(Duration::from_millis(5), Duration::from_millis(50));
+        assert_eq!(delayed(latency, &values).await, values);
+
+        let mut latency = Latency::new(Duration::ZERO, Duration::from_millis(50));
+        latency.reorder = true;
+        let mut received = delayed(latency, &values).await;
+        assert_ne!(received, values);
+        received.sort();
+        assert_eq!(received, values);
+
+        // Nothing can be received before the shortest delay.
+        let latency = Latency::new(Duration::from_millis(5), Duration::from_millis(5));
+        let (_, mut sender, mut receiver) = Channel::with_latency(false, latency);
+        sender.send(1).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(4)).await;
+        assert_eq!(receiver.recv().await, RecvResult::Empty);
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        assert_eq!(receiver.recv().await, RecvResult::Value(1));
+    }
+
+    #[tokio::test]
+    async fn test_pair_and_pipeline() {
+        let ((mut a_sender, mut a_receiver), (mut b_sender, mut b_receiver)) = Channel::pair(true);
+        a_sender.send(1).await.unwrap();
+        b_sender.send(2).await.unwrap();
+        assert_eq!(b_receiver.recv().await, RecvResult::Value(1));
+        assert_eq!(a_receiver.recv().await, RecvResult::Value(2));
+        a_sender.close();
+        assert_eq!(b_receiver.recv().await, RecvResult::Closed);
+
+        // Each stage passes on one more than it gets.
+        let (mut first, stages, mut last) = pipeline(3, true);
+        assert_eq!(stages.len(), 3);
+        first.send(0).await.unwrap();
+        for (mut input, mut output) in stages {
+            let RecvResult::Value(value) = input.recv().await else {
+                panic!("stage didn't get a value");
+            };
+            output.send(value + 1).await.unwrap();
+        }
+        assert_eq!(last.recv().await, RecvResult::Value(3));
+
+        let (mut first, stages, mut last) = pipeline(0, false);
+        assert!(stages.is_empty());
+        assert_eq!(last.recv().await, RecvResult::Empty);
+        first.send(4).await.unwrap();
+        assert_eq!(last.recv().await, RecvResult::Value(4));
+    }
+
+    // A NAT that sends packets for 255 to computer 0 and any others on unchanged, which fails
+    // since they aren't for a computer on the switch. It stops the network once it's idle.
+    #[derive(Default)]
+    struct Recorder {
+        received: Vec<Packet>,
+        idles: usize,
+    }
+
+    impl Nat for Recorder {
+        fn receive(&mut self, packet: Packet) -> NatAction {
+            self.received.push(packet);
+            match packet.address {
+                255 => NatAction::Send(Packet::new(0, packet.x, packet.y)),
+                _ => NatAction::Send(packet),
+            }
+        }
+
+        fn idle(&mut self) -> NatAction {
+            self.idles += 1;
+            NatAction::Stop
+        }
+    }
+
+    // Receive the next value that isn't the -1 a switch's input gives when it's empty.
+    async fn next(receiver: &mut ChannelReceiver) -> RecvResult {
+        loop {
+            match receiver.recv().await {
+                RecvResult::Value(-1) => continue,
+                result => return result,
+            }
+        }
+    }
+
+    // Let the switch run for a while.
+    async fn yields() {
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_switch() {
+        let (mut switch, mut ports) = Switch::new(2).await.unwrap();
+        for (address, (input, _)) in ports.iter_mut().enumerate() {
+            assert_eq!(input.recv().await, RecvResult::Value(address as isize));
+            assert_eq!(input.recv().await, RecvResult::Value(-1));
+            assert_eq!(input.recv().await, RecvResult::Value(-1));
+        }
+
+        // Every queue has been found empty twice, but half a packet is still waiting to be routed.
+        ports[0].1.send_slice(&[1, 10]).await.unwrap();
+        let handle = tokio::spawn(async move {
+            let mut nat = Recorder::default();
+            let result = switch.run(&mut nat).await;
+            (result, nat)
+        });
+        yields().await;
+        assert!(!handle.is_finished());
+
+        ports[0].1.send_slice(&[20, 255, 30, 40]).await.unwrap();
+        assert_eq!(next(&mut ports[1].0).await, RecvResult::Value(10));
+        assert_eq!(next(&mut ports[1].0).await, RecvResult::Value(20));
+        assert_eq!(next(&mut ports[0].0).await, RecvResult::Value(30));
+        assert_eq!(next(&mut ports[0].0).await, RecvResult::Value(40));
+
+        // A single empty read from each isn't enough to be idle.
+        for (input, _) in ports.iter_mut() {
+            assert_eq!(input.recv().await, RecvResult::Value(-1));
+        }
+        yields().await;
+        assert!(!handle.is_finished());
+        for (input, _) in ports.iter_mut() {
+            assert_eq!(input.recv().await, RecvResult::Value(-1));
+        }
+
+        let (result, nat) = handle.await.unwrap();
+        result.unwrap();
+        assert_eq!(nat.received, vec![Packet::new(255, 30, 40)]);
+        assert_eq!(nat.idles, 1);
+        assert_eq!(ports[0].0.recv().await, RecvResult::Closed);
+
+        // Sending to an address that isn't on the switch is an error.
+        let (mut switch, mut ports) = Switch::new(1).await.unwrap();
+        ports[0].1.send_slice(&[7, 1, 2]).await.unwrap();
+        let mut nat = Recorder::default();
+        assert!(switch.run(&mut nat).await.is_err());
+        assert_eq!(nat.received, vec![Packet::new(7, 1, 2)]);
+    }
 }
@@ -0,0 +1,193 @@
+use std::fmt::Display;
+
+use anyhow::{anyhow, Result};
+
+/// A token in one of the small languages in the crate, like the compiler's language or breakpoint
+/// conditions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Number(isize),
+    Identifier(String),
+    Symbol(&'static str),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "{}", value),
+            Token::Identifier(name) => write!(f, "{}", name),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+/// Split the source into tokens. Numbers are unsigned, identifiers are letters, digits, and
+/// underscores that don't start with a digit, and `//` starts a comment that runs to the end of the
+/// line. Anything else has to be one of the given symbols. Longer symbols need to come before their
+/// prefixes so they are matched first.
+pub fn lex(source: &str, symbols: &[&'static str]) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    loop {
+        rest = rest.trim_start();
+        if rest.starts_with("//") {
+            rest = rest.split_once('\n').map(|(_, rest)| rest).unwrap_or("");
+            continue;
+        }
+        let Some(c) = rest.chars().next() else {
+            return Ok(tokens);
+        };
+
+        if c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            tokens.push(Token::Number(rest[..end].parse()?));
+            rest = &rest[end..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Identifier(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else {
+            let symbol = symbols
+                .iter()
+                .find(|symbol| rest.starts_with(*symbol))
+                .ok_or_else(|| anyhow!("unexpected character '{}'", c))?;
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        }
+    }
+}
+
+/// The tokens a recursive descent parser is working through.
+pub struct Tokens {
+    tokens: Vec<Token>,
+    position: usize,
+    // What we are parsing, for the error when we run out of tokens.
+    name: &'static str,
+}
+
+impl Tokens {
+    /// Lex the source (see [lex]) so it can be parsed. The name is what's being parsed, like
+    /// "program", and is used when the tokens run out too soon.
+    pub fn new(source: &str, symbols: &[&'static str], name: &'static str) -> Result<Self> {
+        Ok(Self {
+            tokens: lex(source, symbols)?,
+            position: 0,
+            name,
+        })
+    }
+
+    /// Look at the next token without taking it.
+    pub fn peek(&self) -> Option<&Token> {
+        self.peek_nth(0)
+    }
+
+    /// Look at the token `n` past the next one without taking anything.
+    pub fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.position + n)
+    }
+
+    /// Take the next token, failing if there isn't one.
+    pub fn next_token(&mut self) -> Result<Token> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| anyhow!("unexpected end of {}", self.name))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    /// Take the next token if it's the given symbol.
+    pub fn accept(&mut self, symbol: &str) -> bool {
+        self.accept_any(&[symbol]).is_some()
+    }
+
+    /// Take the next token if it's any of the given symbols and return which one it was. This is
+    /// how binary operators are matched at each level of precedence.
+    pub fn accept_any(&mut self, symbols: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Symbol(s)) if symbols.contains(s) => {
+                let symbol = *s;
+                self.position += 1;
+                Some(symbol)
+            }
+            _ => None,
+        }
+    }
+
+    /// Take the next token if it's the given keyword.
+    pub fn accept_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Identifier(s)) if s == keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    /// Take the next token, failing if it isn't the given symbol.
+    pub fn expect(&mut self, symbol: &str) -> Result<()> {
+        match self.accept(symbol) {
+            true => Ok(()),
+            false => Err(anyhow!("expected '{}'", symbol)),
+        }
+    }
+
+    /// Take the next token, failing if it isn't the given keyword.
+    pub fn keyword(&mut self, keyword: &str) -> Result<()> {
+        match self.accept_keyword(keyword) {
+            true => Ok(()),
+            false => Err(anyhow!("expected '{}'", keyword)),
+        }
+    }
+
+    /// Take the next token, failing if it isn't an identifier.
+    pub fn identifier(&mut self) -> Result<String> {
+        match self.next_token()? {
+            Token::Identifier(name) => Ok(name),
+            token => Err(anyhow!("expected an identifier but got '{}'", token)),
+        }
+    }
+
+    /// Fail if there are any tokens left.
+    pub fn finish(&self) -> Result<()> {
+        match self.peek() {
+            Some(token) => Err(anyhow!("unexpected '{}'", token)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let mut tokens = Tokens::new(
+            "let x_1 = 12; // twelve\n x_1 <= 3",
+            &["<=", "<", "=", ";"],
+            "test",
+        )
+        .unwrap();
+        tokens.keyword("let").unwrap();
+        assert_eq!(tokens.peek_nth(1), Some(&Token::Symbol("=")));
+        assert_eq!(tokens.identifier().unwrap(), "x_1");
+        assert_eq!(tokens.accept_any(&["<", "="]), Some("="));
+        assert_eq!(tokens.next_token().unwrap(), Token::Number(12));
+        tokens.expect(";").unwrap();
+        assert!(tokens.finish().is_err());
+        assert!(tokens.accept_keyword("x_1"));
+        assert!(!tokens.accept("<"));
+        assert!(tokens.accept("<="));
+        assert_eq!(tokens.next_token().unwrap(), Token::Number(3));
+        tokens.finish().unwrap();
+        assert_eq!(
+            tokens.next_token().unwrap_err().to_string(),
+            "unexpected end of test"
+        );
+        assert!(lex("1 ? 2", &[]).is_err());
+    }
+}
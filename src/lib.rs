@@ -1,10 +1,61 @@
 /// An implementation of the Intcode computer from Advent of Code 2019.
+pub mod analysis;
+#[cfg(feature = "tui")]
 pub mod app;
+pub mod arcade;
+pub mod asm;
 pub mod breakpoint;
+#[cfg(feature = "chrome-trace")]
+pub mod chrome_trace;
+pub mod compiler;
+pub mod condition;
+#[cfg(feature = "serde")]
+pub mod dap;
+pub mod disasm;
+#[cfg(feature = "serde")]
+pub mod dump;
+#[cfg(feature = "tui")]
 pub mod event;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod grid;
+pub mod history;
+#[cfg(feature = "inputs")]
+pub mod inputs;
 pub mod instruction;
 pub mod ipc;
+pub mod lexer;
+#[cfg(feature = "tracing")]
+pub mod log;
+pub mod machine;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod network;
+pub mod ocr;
+pub mod optimize;
+pub mod oracle;
+pub mod os;
 pub mod parameter;
+pub mod pipeline;
+#[cfg(feature = "bevy")]
+pub mod plugin;
 pub mod process;
+pub mod reference;
+#[cfg(feature = "tui")]
 pub mod renderer;
+pub mod rng;
+pub mod robot;
+pub mod scheduler;
+pub mod script;
+pub mod search;
+#[cfg(feature = "serde")]
+pub mod session;
+pub mod solutions;
+pub mod symbols;
+pub mod symexec;
+pub mod theme;
+pub mod timing;
+#[cfg(feature = "tui")]
 pub mod tui;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,187 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Write as _};
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// Send the `tracing` events at or above the level to the file, one per line. The tui owns the
+/// terminal, so this is how to see what the debugger is doing while it runs.
+pub fn init(path: impl AsRef<Path>, level: Level) -> Result<()> {
+    let logger = FileLogger {
+        file: Mutex::new(LineWriter::new(File::create(path)?)),
+        level,
+        start: Instant::now(),
+        next_id: AtomicU64::new(1),
+        spans: Mutex::new(HashMap::new()),
+    };
+    tracing::subscriber::set_global_default(logger).map_err(|e| anyhow!("{}", e))
+}
+
+// A span that is still open, along with how many handles to it there are.
+struct Span {
+    text: String,
+    references: usize,
+}
+
+// A subscriber that writes each event to a file along with the spans it happened in. It's
+// deliberately small since all we want is something to read after a run.
+struct FileLogger {
+    file: Mutex<LineWriter<File>>,
+    level: Level,
+    start: Instant,
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, Span>>,
+}
+
+thread_local! {
+    // The spans this thread is in, innermost last.
+    static STACK: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+// Collects the fields of an event or span as text, keeping the message separate.
+#[derive(Default)]
+struct Fields {
+    message: String,
+    fields: String,
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            name => {
+                let _ = write!(self.fields, " {}={:?}", name, value);
+            }
+        }
+    }
+}
+
+impl Subscriber for FileLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= self.level
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let text = match fields.fields.is_empty() {
+            true => span.metadata().name().to_string(),
+            false => format!("{}{{{}}}", span.metadata().name(), fields.fields.trim()),
+        };
+        self.spans.lock().unwrap().insert(
+            id,
+            Span {
+                text,
+                references: 1,
+            },
+        );
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let spans = {
+            let spans = self.spans.lock().unwrap();
+            STACK.with(|stack| {
+                stack
+                    .borrow()
+                    .iter()
+                    .filter_map(|id| spans.get(id))
+                    .map(|span| format!("{}:", span.text))
+                    .collect::<String>()
+            })
+        };
+        let metadata = event.metadata();
+        let _ = writeln!(
+            self.file.lock().unwrap(),
+            "{:>12.6} {:5} {}{}: {}{}",
+            self.start.elapsed().as_secs_f64(),
+            metadata.level(),
+            spans,
+            metadata.target(),
+            fields.message,
+            fields.fields
+        );
+    }
+
+    fn enter(&self, span: &Id) {
+        STACK.with(|stack| stack.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        STACK.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            if let Some(position) = stack.iter().rposition(|id| *id == span.into_u64()) {
+                stack.remove(position);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            span.references += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.spans.lock().unwrap();
+        let Some(open) = spans.get_mut(&span.into_u64()) else {
+            return false;
+        };
+        open.references -= 1;
+        if open.references > 0 {
+            return false;
+        }
+        spans.remove(&span.into_u64());
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_file_logger() {
+        let path = std::env::temp_dir().join(format!("intcode-log-{}", std::process::id()));
+        let logger = FileLogger {
+            file: Mutex::new(LineWriter::new(File::create(&path).unwrap())),
+            level: Level::DEBUG,
+            start: Instant::now(),
+            next_id: AtomicU64::new(1),
+            spans: Mutex::new(HashMap::new()),
+        };
+        tracing::subscriber::with_default(logger, || {
+            let span = tracing::debug_span!("process", id = 3);
+            let _entered = span.enter();
+            tracing::debug!(ip = 4, "step");
+            tracing::trace!("too noisy");
+        });
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines = log.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 1);
+        assert!(
+            lines[0].ends_with("DEBUG process{id=3}:intcode::log::test: step ip=4"),
+            "{}",
+            lines[0]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::VecDeque;
+
+use futures::executor::block_on;
+use tokio::task::unconstrained;
+
+use crate::instruction::Instruction;
+use crate::ipc::{Channel, ChannelReceiver, ChannelSender};
+use crate::process::{OverflowMode, Process, State};
+
+use anyhow::Result;
+
+/// A process that runs one instruction at a time without an async runtime. Inputs are queued and
+/// given to the process only when it asks for one and outputs are collected as they are made, so
+/// running never has to wait on a channel.
+///
+/// A machine can also be run from inside a tokio runtime. The channels are driven without tokio's
+/// cooperative budget, which would otherwise run out and never be refilled since we never yield.
+pub struct Machine {
+    process: Process,
+    input: ChannelSender,
+    output: ChannelReceiver,
+    inputs: VecDeque<isize>,
+    outputs: VecDeque<isize>,
+}
+
+impl Machine {
+    /// Create a machine running the given comma separated program.
+    pub fn new(program: &str) -> Self {
+        Self::with_state(State::new(program))
+    }
+
+    /// Create a machine that picks up from the given state.
+    pub fn with_state(state: State) -> Self {
+        let (_, input, receiver) = Channel::new(false);
+        let (_, sender, output) = Channel::new(false);
+        Self {
+            process: Process::with_state(state, receiver, sender),
+            input,
+            output,
+            inputs: VecDeque::new(),
+            outputs: VecDeque::new(),
+        }
+    }
+
+    /// Run a single instruction. This returns `false` if nothing was run because the process has
+    /// halted or is waiting for input, and an error if the next instruction can't be decoded or
+    /// fails.
+    pub fn step(&mut self) -> Result<bool> {
+        if self.process.halted() {
+            return Ok(false);
+        }
+        let (instruction, _) = self.process.state_ref().decode()?;
+
+        if let Instruction::Input(_) = instruction {
+            match self.inputs.pop_front() {
+                Some(value) => block_on(unconstrained(self.input.send(value)))?,
+                None => return Ok(false),
+            }
+        }
+        block_on(unconstrained(self.process.step()))?;
+        self.outputs.extend(self.output.drain());
+        Ok(true)
+    }
+
+    /// Run at most `limit` instructions, stopping early if the process halts or waits for input.
+    /// This returns the number of instructions that were run.
+    pub fn run(&mut self, limit: usize) -> Result<usize> {
+        let mut count = 0;
+        while count < limit && self.step()? {
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Queue a value for the process to read.
+    pub fn push_input(&mut self, value: isize) {
+        self.inputs.push_back(value);
+    }
+
+    /// Take all of the values the process has output since the last poll.
+    pub fn poll_output(&mut self) -> Vec<isize> {
+        self.outputs.drain(..).collect()
+    }
+
+    /// Set the memory at the given index to the given value.
+    pub fn set_memory(&mut self, index: usize, value: isize) {
+        self.process.set_memory(index, value);
+    }
+
+    /// Choose what happens when an add or multiply overflows.
+    pub fn set_overflow_mode(&mut self, overflow: OverflowMode) {
+        self.process.set_overflow_mode(overflow);
+    }
+
+    /// Get a copy of the current state of the process.
+    pub fn state(&self) -> State {
+        self.process.state()
+    }
+
+    /// Borrow the current state of the process without copying its memory.
+    pub fn state_ref(&self) -> &State {
+        self.process.state_ref()
+    }
+
+    /// Whether the process has halted.
+    pub fn halted(&self) -> bool {
+        self.process.halted()
+    }
+
+    /// Whether the process is stopped on an input instruction with no input queued.
+    pub fn waiting(&self) -> bool {
+        matches!(
+            self.process.next_instruction(),
+            Some((Instruction::Input(_), _))
+        ) && self.inputs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_machine() {
+        let mut machine = Machine::new("3,9,1001,9,1,10,4,10,99,0,0");
+        assert_eq!(machine.run(100).unwrap(), 0);
+        assert!(machine.waiting());
+        machine.push_input(41);
+        assert_eq!(machine.run(100).unwrap(), 4);
+        assert!(machine.halted());
+        assert_eq!(machine.poll_output(), vec![42]);
+        assert_eq!(machine.state()[10], 42);
+    }
+
+    #[test]
+    fn test_machine_errors() {
+        // The add runs and then we run into something that isn't an instruction.
+        let mut machine = Machine::new("1101,1,1,5,42,0");
+        let error = machine.run(10).unwrap_err();
+        assert_eq!(error.to_string(), "invalid opcode 42 at 4");
+        assert!(!machine.halted() && !machine.waiting());
+        assert_eq!(machine.state_ref().instruction_pointer, 4);
+        assert!(machine.step().is_err());
+
+        let mut machine = Machine::new("11101,1,1,1,99");
+        assert!(machine.step().is_err());
+    }
+}
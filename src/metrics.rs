@@ -0,0 +1,182 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, ToSocketAddrs};
+
+use crate::timing::ProcessStatus;
+
+// What we know about each process, keyed by its id.
+static PROCESSES: Mutex<BTreeMap<usize, (u64, ProcessStatus)>> = Mutex::new(BTreeMap::new());
+static PACKETS: AtomicU64 = AtomicU64::new(0);
+static IDLE: AtomicU64 = AtomicU64::new(0);
+
+/// Record that the process is about to run an instruction, which blocks it if it's an input.
+pub fn running(process: usize, blocked: bool) {
+    let status = match blocked {
+        true => ProcessStatus::Blocked,
+        false => ProcessStatus::Running,
+    };
+    PROCESSES.lock().unwrap().entry(process).or_default().1 = status;
+}
+
+/// Record that the process ran an instruction.
+pub fn executed(process: usize, halted: bool) {
+    let mut processes = PROCESSES.lock().unwrap();
+    let (count, status) = processes.entry(process).or_default();
+    *count += 1;
+    *status = match halted {
+        true => ProcessStatus::Halted,
+        false => ProcessStatus::Running,
+    };
+}
+
+/// Record that a switch routed a packet.
+pub fn packet() {
+    PACKETS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Record that a switch found its network idle.
+pub fn idle() {
+    IDLE.fetch_add(1, Ordering::Relaxed);
+}
+
+// Write the lines that describe a metric, which come right before its samples.
+fn header(text: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(text, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+}
+
+/// Everything recorded so far in the Prometheus text format.
+pub fn render() -> String {
+    let processes = PROCESSES.lock().unwrap().clone();
+    let mut text = String::new();
+
+    header(
+        &mut text,
+        "intcode_instructions_total",
+        "counter",
+        "Instructions executed by each process.",
+    );
+    for (process, (count, _)) in &processes {
+        let _ = writeln!(
+            text,
+            "intcode_instructions_total{{process=\"{}\"}} {}",
+            process, count
+        );
+    }
+
+    header(
+        &mut text,
+        "intcode_packets_routed_total",
+        "counter",
+        "Packets routed by network switches.",
+    );
+    let _ = writeln!(
+        text,
+        "intcode_packets_routed_total {}",
+        PACKETS.load(Ordering::Relaxed)
+    );
+
+    header(
+        &mut text,
+        "intcode_idle_cycles_total",
+        "counter",
+        "Times a network switch found its network idle.",
+    );
+    let _ = writeln!(
+        text,
+        "intcode_idle_cycles_total {}",
+        IDLE.load(Ordering::Relaxed)
+    );
+
+    header(
+        &mut text,
+        "intcode_process_status",
+        "gauge",
+        "Whether each process is in each status.",
+    );
+    for (process, (_, current)) in &processes {
+        for status in [
+            ProcessStatus::Paused,
+            ProcessStatus::Running,
+            ProcessStatus::Blocked,
+            ProcessStatus::Halted,
+        ] {
+            let _ = writeln!(
+                text,
+                "intcode_process_status{{process=\"{}\",status=\"{}\"}} {}",
+                process,
+                status.to_string().to_lowercase(),
+                (status == *current) as u8
+            );
+        }
+    }
+    text
+}
+
+/// Serve the metrics over HTTP at the given address until something goes wrong. Every request
+/// gets the metrics, whatever its path.
+pub async fn serve(address: impl ToSocketAddrs) -> Result<()> {
+    respond(TcpListener::bind(address).await?).await
+}
+
+async fn respond(listener: TcpListener) -> Result<()> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+
+        // We don't care what was asked for, but we read the request so the client doesn't see
+        // the connection reset before it's done sending it.
+        let mut request = [0; 1024];
+        let _ = stream.read(&mut request).await;
+        let body = render();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metrics() {
+        // Other tests may be running processes too, so this only looks for its own.
+        let process = usize::MAX;
+        running(process, true);
+        executed(process, false);
+        executed(process, true);
+        packet();
+        idle();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(respond(listener));
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        let process = format!("process=\"{}\"", process);
+        assert!(response.contains(&format!("intcode_instructions_total{{{}}} 2", process)));
+        assert!(response.contains(&format!(
+            "intcode_process_status{{{},status=\"halted\"}} 1",
+            process
+        )));
+        assert!(response.contains(&format!(
+            "intcode_process_status{{{},status=\"running\"}} 0",
+            process
+        )));
+        assert!(response.contains("# TYPE intcode_packets_routed_total counter"));
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+use crate::ipc::{Nat, NatAction, Observer, Packet, Switch};
+use crate::process::Process;
+
+/// A network of computers all running the same program, like the one from day 23. Each computer
+/// is given its address (0 through `size - 1`) as its first input and talks to the others with
+/// (address, x, y) packets routed by a [Switch]. Packets to any other address go to the [Nat].
+pub struct Network {
+    program: String,
+    size: usize,
+    observers: Arc<Mutex<Vec<Observer>>>,
+}
+
+impl Network {
+    /// Create a network of `size` computers running the given program. Nothing runs until
+    /// [Network::run] is called.
+    pub fn new(program: &str, size: usize) -> Self {
+        Self {
+            program: program.to_string(),
+            size,
+            observers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Call the observer with every packet a computer sends.
+    pub fn observe(&mut self, observer: impl FnMut(&Packet) + Send + 'static) {
+        self.observers.lock().unwrap().push(Box::new(observer));
+    }
+
+    /// Boot the computers and route their packets until the NAT says to stop. The computers are
+    /// shut down when this returns. A network can be run more than once, and each run starts
+    /// with freshly booted computers.
+    pub async fn run(&mut self, nat: &mut impl Nat) -> Result<()> {
+        let (mut switch, ports) = Switch::new(self.size).await?;
+        let observers = self.observers.clone();
+        switch.observe(move |packet| {
+            for observer in observers.lock().unwrap().iter_mut() {
+                observer(packet);
+            }
+        });
+        let computers = ports
+            .into_iter()
+            .map(|(receiver, sender)| {
+                let program = self.program.clone();
+                tokio::spawn(async move { Process::new(&program, receiver, sender).run().await })
+            })
+            .collect::<Vec<_>>();
+
+        let result = switch.run(nat).await;
+
+        // The computers usually never halt on their own, so we stop them once we're done.
+        for computer in computers {
+            computer.abort();
+        }
+        result
+    }
+}
+
+/// A NAT that stops the network at the first packet sent to it, keeping it.
+#[derive(Debug, Default)]
+pub struct FirstPacket {
+    /// The packet sent to the NAT, once the network has stopped.
+    pub packet: Option<Packet>,
+}
+
+impl Nat for FirstPacket {
+    fn receive(&mut self, packet: Packet) -> NatAction {
+        self.packet = Some(packet);
+        NatAction::Stop
+    }
+
+    fn idle(&mut self) -> NatAction {
+        NatAction::Continue
+    }
+}
+
+/// The NAT from day 23. It remembers the last packet sent to it and sends it to address 0
+/// whenever the network is idle. It stops the network when it would send the same y twice in a
+/// row, keeping that y.
+#[derive(Debug, Default)]
+pub struct Wakeup {
+    last: Option<Packet>,
+    last_sent: Option<isize>,
+    /// The y sent to address 0 twice in a row, once the network has stopped.
+    pub y: Option<isize>,
+}
+
+impl Nat for Wakeup {
+    fn receive(&mut self, packet: Packet) -> NatAction {
+        self.last = Some(packet);
+        NatAction::Continue
+    }
+
+    fn idle(&mut self) -> NatAction {
+        let Some(packet) = self.last else {
+            return NatAction::Continue;
+        };
+        if self.last_sent == Some(packet.y) {
+            self.y = Some(packet.y);
+            return NatAction::Stop;
+        }
+        self.last_sent = Some(packet.y);
+        NatAction::Send(Packet::new(0, packet.x, packet.y))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_network() {
+        // Each computer sends (255, address, address * 10) and then reads forever.
+        let program = "3,100,1002,100,10,101,104,255,4,100,4,101,3,102,1105,1,12";
+        let mut network = Network::new(program, 3);
+        let packets = Arc::new(Mutex::new(Vec::new()));
+        let observed = packets.clone();
+        network.observe(move |packet| observed.lock().unwrap().push(*packet));
+
+        let mut nat = FirstPacket::default();
+        network.run(&mut nat).await.unwrap();
+        let packet = nat.packet.unwrap();
+        assert_eq!((packet.address, packet.y), (255, packet.x * 10));
+
+        let mut nat = Wakeup::default();
+        network.run(&mut nat).await.unwrap();
+        assert!([0, 10, 20].contains(&nat.y.unwrap()));
+        let mut packets = packets.lock().unwrap().split_off(1);
+        packets.sort_by_key(|packet| packet.x);
+        assert_eq!(
+            packets,
+            vec![
+                Packet::new(255, 0, 0),
+                Packet::new(255, 1, 10),
+                Packet::new(255, 2, 20)
+            ]
+        );
+    }
+}
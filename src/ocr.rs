@@ -0,0 +1,109 @@
+use anyhow::{anyhow, Result};
+
+use crate::grid::{Grid, Point};
+
+/// The height of the block letters the puzzles draw.
+pub const HEIGHT: isize = 6;
+
+// The letters the puzzles are known to draw, without the blank columns around them.
+const LETTERS: &[(char, [&str; HEIGHT as usize])] = &[
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', ["###", ".#.", ".#.", ".#.", ".#.", "###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+/// Read the block letters drawn by the lit cells of the grid. Letters are split on columns with
+/// nothing lit, so any spacing between them works.
+pub fn read(grid: &Grid<bool>) -> Result<String> {
+    let lit = grid
+        .iter()
+        .filter(|(_, &lit)| lit)
+        .map(|(&point, _)| (point, true))
+        .collect::<Grid<bool>>();
+    let Some((min, max)) = lit.bounds() else {
+        return Ok(String::new());
+    };
+    if max.y - min.y + 1 != HEIGHT {
+        return Err(anyhow!(
+            "expected letters {} rows tall but found {}",
+            HEIGHT,
+            max.y - min.y + 1
+        ));
+    }
+
+    // Draw each column as text and gather runs of non-empty columns into letters.
+    let mut letters = String::new();
+    let mut columns = Vec::new();
+    for x in min.x..=max.x + 1 {
+        let column = (min.y..=max.y)
+            .map(|y| match lit.contains(Point::new(x, y)) {
+                true => '#',
+                false => '.',
+            })
+            .collect::<String>();
+        if column.contains('#') {
+            columns.push(column);
+        } else if !columns.is_empty() {
+            letters.push(letter(&columns)?);
+            columns.clear();
+        }
+    }
+    Ok(letters)
+}
+
+/// Read the block letters in text like [Grid::render] makes, where `#` is lit and anything else
+/// isn't.
+pub fn read_text(text: &str) -> Result<String> {
+    read(&Grid::parse(text, |c| Some(c == '#')))
+}
+
+// Find the letter drawn by the columns.
+fn letter(columns: &[String]) -> Result<char> {
+    let rows = (0..HEIGHT as usize)
+        .map(|y| {
+            columns
+                .iter()
+                .map(|column| column.as_bytes()[y] as char)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>();
+    LETTERS
+        .iter()
+        .find(|(_, glyph)| glyph.iter().eq(rows.iter()))
+        .map(|&(c, _)| c)
+        .ok_or_else(|| anyhow!("unknown letter:\n{}", rows.join("\n")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let text = " #  #  ##  #   #
+ #  # #  # #   #
+ #### #  #  # #
+ #  # #  #   #
+ #  # #  #   #
+ #  #  ##    #  ";
+        assert_eq!(read_text(text).unwrap(), "HOY");
+        assert_eq!(read(&Grid::new()).unwrap(), "");
+        assert!(read_text("#\n#").is_err());
+        assert!(read_text("##\n##\n##\n##\n##\n##").is_err());
+    }
+}
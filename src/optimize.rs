@@ -0,0 +1,474 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use crate::{
+    analysis::{cfg, Cfg},
+    instruction::Instruction,
+    parameter::Parameter,
+};
+
+/// The result of optimizing a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Optimized {
+    /// The optimized program.
+    pub program: Vec<isize>,
+    /// How much the program changed.
+    pub stats: Stats,
+}
+
+/// Statistics about how much an optimization changed a program.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Whether the program could be proven safe to optimize.
+    pub safe: bool,
+    /// The number of reachable instructions before optimizing.
+    pub instructions_before: usize,
+    /// The number of reachable instructions after optimizing.
+    pub instructions_after: usize,
+    /// The number of cells in the program before optimizing.
+    pub cells_before: usize,
+    /// The number of cells in the program after optimizing.
+    pub cells_after: usize,
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.safe {
+            return write!(f, "not optimized (the program may modify itself)");
+        }
+        write!(
+            f,
+            "instructions: {} -> {}, cells: {} -> {}",
+            self.instructions_before, self.instructions_after, self.cells_before, self.cells_after
+        )
+    }
+}
+
+/// Optimize the program. The program is first run through constant folding and propagation, which
+/// follows known values through the control-flow graph and replaces reads of them with immediates
+/// and arithmetic on immediates with its result. Then a peephole pass removes instructions that
+/// don't do anything:
+///
+/// - adding 0 to a cell and storing it back in the same cell.
+/// - multiplying a cell by 1 and storing it back in the same cell.
+/// - jumps to the next instruction and jumps that can never be taken.
+/// - writes that are overwritten before anything reads them.
+///
+/// Removing instructions moves everything after them, so the program is only changed when we can
+/// prove every address it uses is known statically. That means there can't be any dynamic jumps or
+/// relative base adjustments, and no instruction can read or write the code. Otherwise the program
+/// is returned unchanged.
+pub fn optimize(program: &[isize]) -> Optimized {
+    let graph = cfg(program);
+    let instructions = graph.instructions().count();
+    let mut stats = Stats {
+        safe: false,
+        instructions_before: instructions,
+        instructions_after: instructions,
+        cells_before: program.len(),
+        cells_after: program.len(),
+    };
+    if !is_safe(&graph) {
+        return Optimized {
+            program: program.to_vec(),
+            stats,
+        };
+    }
+    stats.safe = true;
+
+    // Folding may make some jumps impossible, so we need a new graph afterwards.
+    let program = fold(program, &graph);
+    let graph = cfg(&program);
+    let removed = graph
+        .blocks
+        .values()
+        .flat_map(|block| {
+            block
+                .instructions
+                .iter()
+                .enumerate()
+                .filter(|(i, (address, instruction))| {
+                    is_redundant(*address, instruction)
+                        || is_dead_store(instruction, &block.instructions[i + 1..])
+                })
+                .map(|(_, (address, _))| *address)
+        })
+        .collect::<BTreeSet<_>>();
+    let program = relocate(&program, &graph, &removed);
+
+    stats.instructions_after = cfg(&program).instructions().count();
+    stats.cells_after = program.len();
+    Optimized { program, stats }
+}
+
+// Check whether the program only uses addresses we know about statically and never touches its own
+// code.
+fn is_safe(cfg: &Cfg) -> bool {
+    if cfg.blocks.values().any(|block| block.dynamic_jump) {
+        return false;
+    }
+
+    let code = cfg
+        .instructions()
+        .flat_map(|(address, instruction)| address..address + size(&instruction))
+        .collect::<BTreeSet<_>>();
+    cfg.instructions().all(|(_, instruction)| {
+        // Without any adjustments the relative base is always 0, so relative parameters are
+        // really position parameters.
+        !matches!(instruction, Instruction::AdjustRelativeBaseOffset(_))
+            && instruction
+                .parameters()
+                .iter()
+                .all(|parameter| match *parameter {
+                    Parameter::Position(address) => !code.contains(&address),
+                    Parameter::Relative(offset) => {
+                        offset >= 0 && !code.contains(&(offset as usize))
+                    }
+                    Parameter::Immediate(_) => true,
+                })
+    })
+}
+
+// Check whether the instruction at the given address can be removed without changing what the
+// program does.
+fn is_redundant(address: usize, instruction: &Instruction) -> bool {
+    let next = (address + size(instruction)) as isize;
+    match *instruction {
+        Instruction::Add(Parameter::Immediate(0), value, dest)
+        | Instruction::Add(value, Parameter::Immediate(0), dest)
+        | Instruction::Multiply(Parameter::Immediate(1), value, dest)
+        | Instruction::Multiply(value, Parameter::Immediate(1), dest) => value == dest,
+        Instruction::JumpIfTrue(condition, target) => {
+            target == Parameter::Immediate(next) || condition == Parameter::Immediate(0)
+        }
+        Instruction::JumpIfFalse(condition, target) => {
+            target == Parameter::Immediate(next)
+                || matches!(condition, Parameter::Immediate(value) if value != 0)
+        }
+        _ => false,
+    }
+}
+
+// Check whether the instruction writes to a cell that is written again later in the block before
+// anything reads it. Inputs are never dead since reading the input matters. Position and relative
+// parameters can name the same cell, so we compare the addresses they refer to, and a read we can't
+// resolve could be of anything.
+fn is_dead_store(instruction: &Instruction, rest: &[(usize, Instruction)]) -> bool {
+    let Some(dest) = instruction.write_parameter().and_then(address) else {
+        return false;
+    };
+    if let Instruction::Input(_) = instruction {
+        return false;
+    }
+    for (_, next) in rest {
+        let reads = next
+            .read_parameters()
+            .into_iter()
+            .any(|parameter| match parameter {
+                Parameter::Immediate(_) => false,
+                parameter => address(parameter).is_none_or(|address| address == dest),
+            });
+        if reads {
+            return false;
+        }
+        if next.write_parameter().and_then(address) == Some(dest) {
+            return true;
+        }
+    }
+    false
+}
+
+// The values we know some of the written cells have at a point in the program.
+type Known = BTreeMap<usize, isize>;
+
+// Fold the constants in the program. The program must be safe, so every address is static and
+// relative parameters are just position parameters. Cells that are never written keep their value
+// from the program, so only the written ones need to be tracked through the graph.
+fn fold(program: &[isize], cfg: &Cfg) -> Vec<isize> {
+    let written = cfg
+        .instructions()
+        .filter_map(|(_, instruction)| address(instruction.write_parameter()?))
+        .collect::<BTreeSet<_>>();
+    let value = |known: &Known, parameter: Parameter| match parameter {
+        Parameter::Immediate(value) => Some(value),
+        parameter => {
+            let address = address(parameter)?;
+            match written.contains(&address) {
+                true => known.get(&address).copied(),
+                false => Some(program.get(address).copied().unwrap_or(0)),
+            }
+        }
+    };
+    let step = |known: &mut Known, instruction: &Instruction| {
+        let Some(dest) = instruction.write_parameter().and_then(address) else {
+            return;
+        };
+        let reads = instruction.read_parameters();
+        let result = match (reads.first(), reads.get(1)) {
+            (Some(&left), Some(&right)) => {
+                evaluate(instruction, value(known, left), value(known, right))
+            }
+            _ => None,
+        };
+        match result {
+            Some(result) => known.insert(dest, result),
+            None => known.remove(&dest),
+        };
+    };
+
+    // Find what we know at the start of every block. A block only knows what all of the blocks
+    // that lead to it agree on.
+    let mut starts = BTreeMap::new();
+    if cfg.blocks.contains_key(&0) {
+        let initial = written
+            .iter()
+            .map(|&address| (address, program.get(address).copied().unwrap_or(0)))
+            .collect::<Known>();
+        starts.insert(0, initial);
+    }
+    let mut pending = vec![0];
+    while let Some(start) = pending.pop() {
+        let (Some(block), Some(known)) = (cfg.blocks.get(&start), starts.get(&start)) else {
+            continue;
+        };
+        let mut known = known.clone();
+        for (_, instruction) in &block.instructions {
+            step(&mut known, instruction);
+        }
+        for successor in &block.successors {
+            let changed = match starts.get_mut(successor) {
+                None => {
+                    starts.insert(*successor, known.clone());
+                    true
+                }
+                Some(existing) => {
+                    let before = existing.len();
+                    existing.retain(|address, value| known.get(address) == Some(value));
+                    existing.len() != before
+                }
+            };
+            if changed {
+                pending.push(*successor);
+            }
+        }
+    }
+
+    // Now we can rewrite the instructions using what we know. The sizes don't change, so they can
+    // be written right back where they were.
+    let mut folded = program.to_vec();
+    for (start, known) in starts {
+        let mut known = known;
+        for (address, instruction) in &cfg.blocks[&start].instructions {
+            let replacement = match is_redundant(*address, instruction) {
+                // Leave these alone so the peephole pass can remove them.
+                true => *instruction,
+                false => rewrite(instruction, |parameter| value(&known, parameter)),
+            };
+            for (offset, cell) in replacement.encode().into_iter().enumerate() {
+                if let Some(slot) = folded.get_mut(address + offset) {
+                    *slot = cell;
+                }
+            }
+            step(&mut known, instruction);
+        }
+    }
+    folded
+}
+
+// Replace the reads of known values with immediates and arithmetic on known values with the result.
+fn rewrite(instruction: &Instruction, value: impl Fn(Parameter) -> Option<isize>) -> Instruction {
+    let c = |parameter: Parameter| {
+        value(parameter)
+            .map(Parameter::Immediate)
+            .unwrap_or(parameter)
+    };
+    match *instruction {
+        Instruction::Add(left, right, dest)
+        | Instruction::Multiply(left, right, dest)
+        | Instruction::LessThan(left, right, dest)
+        | Instruction::Equals(left, right, dest) => {
+            match evaluate(instruction, value(left), value(right)) {
+                Some(result) => {
+                    Instruction::Add(Parameter::Immediate(result), Parameter::Immediate(0), dest)
+                }
+                None => match instruction {
+                    Instruction::Add(..) => Instruction::Add(c(left), c(right), dest),
+                    Instruction::Multiply(..) => Instruction::Multiply(c(left), c(right), dest),
+                    Instruction::LessThan(..) => Instruction::LessThan(c(left), c(right), dest),
+                    _ => Instruction::Equals(c(left), c(right), dest),
+                },
+            }
+        }
+        Instruction::Output(value) => Instruction::Output(c(value)),
+        Instruction::JumpIfTrue(condition, target) => Instruction::JumpIfTrue(c(condition), target),
+        Instruction::JumpIfFalse(condition, target) => {
+            Instruction::JumpIfFalse(c(condition), target)
+        }
+        instruction => instruction,
+    }
+}
+
+// Work out the result of an arithmetic or comparison instruction if both sides are known.
+fn evaluate(instruction: &Instruction, left: Option<isize>, right: Option<isize>) -> Option<isize> {
+    let (left, right) = (left?, right?);
+    match instruction {
+        Instruction::Add(..) => left.checked_add(right),
+        Instruction::Multiply(..) => left.checked_mul(right),
+        Instruction::LessThan(..) => Some((left < right) as isize),
+        Instruction::Equals(..) => Some((left == right) as isize),
+        _ => None,
+    }
+}
+
+// Get the address a parameter refers to. Programs we optimize never adjust the relative base, so
+// relative parameters refer to their offset.
+fn address(parameter: Parameter) -> Option<usize> {
+    match parameter {
+        Parameter::Position(address) => Some(address),
+        Parameter::Relative(offset) if offset >= 0 => Some(offset as usize),
+        _ => None,
+    }
+}
+
+// Build the program without the removed instructions, moving all the addresses to match.
+fn relocate(program: &[isize], cfg: &Cfg, removed: &BTreeSet<usize>) -> Vec<isize> {
+    let gone = removed
+        .iter()
+        .filter_map(|&address| Some(address..address + size(&cfg.instruction(address)?)))
+        .flatten()
+        .collect::<BTreeSet<_>>();
+
+    // Work out where every cell ends up. A removed instruction maps to whatever comes after it and
+    // anything past the end of the program doesn't move.
+    let mut addresses = Vec::with_capacity(program.len());
+    let mut next = 0;
+    for address in 0..program.len() {
+        addresses.push(next);
+        if !gone.contains(&address) {
+            next += 1;
+        }
+    }
+    let map = |address: usize| addresses.get(address).copied().unwrap_or(address);
+    let m = |parameter: Parameter| match parameter {
+        Parameter::Position(address) => Parameter::Position(map(address)),
+        Parameter::Relative(offset) => Parameter::Relative(map(offset as usize) as isize),
+        Parameter::Immediate(value) => Parameter::Immediate(value),
+    };
+    let target = |parameter: Parameter| match parameter {
+        Parameter::Immediate(target) if target >= 0 => {
+            Parameter::Immediate(map(target as usize) as isize)
+        }
+        parameter => m(parameter),
+    };
+
+    let mut relocated = Vec::with_capacity(next);
+    let mut address = 0;
+    while address < program.len() {
+        let Some(instruction) = cfg.instruction(address) else {
+            relocated.push(program[address]);
+            address += 1;
+            continue;
+        };
+        let start = address;
+        address += size(&instruction);
+        if removed.contains(&start) {
+            continue;
+        }
+
+        let instruction = match instruction {
+            Instruction::Add(left, right, dest) => Instruction::Add(m(left), m(right), m(dest)),
+            Instruction::Multiply(left, right, dest) => {
+                Instruction::Multiply(m(left), m(right), m(dest))
+            }
+            Instruction::Input(dest) => Instruction::Input(m(dest)),
+            Instruction::Output(value) => Instruction::Output(m(value)),
+            Instruction::JumpIfTrue(value, dest) => Instruction::JumpIfTrue(m(value), target(dest)),
+            Instruction::JumpIfFalse(value, dest) => {
+                Instruction::JumpIfFalse(m(value), target(dest))
+            }
+            Instruction::LessThan(left, right, dest) => {
+                Instruction::LessThan(m(left), m(right), m(dest))
+            }
+            Instruction::Equals(left, right, dest) => {
+                Instruction::Equals(m(left), m(right), m(dest))
+            }
+            instruction => instruction,
+        };
+        relocated.extend(instruction.encode());
+    }
+    relocated
+}
+
+fn size(instruction: &Instruction) -> usize {
+    instruction.parameter_count() + 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::machine::Machine;
+
+    #[test]
+    fn test_optimize() {
+        // 0: read into 12, 2: add 0 to 12, 6: jump to the next instruction, 9: output 12, 11: halt,
+        // 12: data.
+        let optimized = optimize(&[3, 12, 1001, 12, 0, 12, 1105, 1, 9, 4, 12, 99, 0]);
+        assert_eq!(optimized.program, vec![3, 5, 4, 5, 99, 0]);
+        assert_eq!(optimized.stats.instructions_before, 5);
+        assert_eq!(optimized.stats.instructions_after, 3);
+
+        // Writing into the code means we can't touch it.
+        let program = [1001, 10, 0, 10, 1101, 0, 0, 0, 99, 0, 0];
+        let optimized = optimize(&program);
+        assert!(!optimized.stats.safe);
+        assert_eq!(optimized.program, program);
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        // 0: 2 + 3 -> 21, 4: 21 * 4 -> 21, 8: 21 < 10 -> 22, 12: jump to 18 if 22, 15: output 21,
+        // 17: halt, 18: output 7, 20: halt, 21: data.
+        let program = [
+            1101, 2, 3, 21, 1002, 21, 4, 21, 1007, 21, 10, 22, 1005, 22, 18, 4, 21, 99, 104, 7, 99,
+            0, 0,
+        ];
+        let optimized = optimize(&program);
+        assert_eq!(
+            optimized.program,
+            vec![1101, 20, 0, 14, 1101, 0, 0, 15, 104, 20, 99, 104, 7, 99, 0, 0]
+        );
+        assert_eq!(optimized.stats.instructions_before, 8);
+        assert_eq!(optimized.stats.instructions_after, 4);
+    }
+
+    #[test]
+    fn test_dead_stores() {
+        // 0: read into 21, 2: copy 21 to 20, 6: output R[20], 8: store 2 in 20, 12: output 20,
+        // 14: halt. The output through the relative parameter reads the copy, so it has to stay.
+        let mut program = vec![3, 21, 1001, 21, 0, 20, 204, 20, 1101, 2, 0, 20, 4, 20, 99];
+        program.resize(22, 0);
+        let run = |program: &[isize]| {
+            let text = program
+                .iter()
+                .map(|cell| cell.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            let mut machine = Machine::new(&text);
+            machine.push_input(7);
+            machine.run(100).unwrap();
+            machine.poll_output()
+        };
+        let optimized = optimize(&program);
+        assert!(optimized.stats.safe);
+        assert_eq!(run(&program), vec![7, 2]);
+        assert_eq!(run(&optimized.program), vec![7, 2]);
+
+        // Without the read, the copy is overwritten before it's used.
+        let program = [3, 15, 1001, 15, 0, 14, 1101, 2, 0, 14, 4, 14, 99, 0, 0, 0];
+        let optimized = optimize(&program);
+        assert_eq!(run(&program), vec![2]);
+        assert_eq!(run(&optimized.program), vec![2]);
+        assert_eq!(optimized.stats.instructions_before, 5);
+        assert_eq!(optimized.stats.instructions_after, 4);
+    }
+}
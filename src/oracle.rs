@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use rayon::prelude::*;
+
+use crate::machine::Machine;
+use crate::process::State;
+
+/// Asks questions of a program that reads a few inputs, outputs its answer, and halts, like the
+/// day 19 drone. The program is parsed once and each question is run on a copy of it. Answers are
+/// remembered, so asking the same question again doesn't run the program. The `oracle` benchmark
+/// compares this with starting a new process for every question.
+#[derive(Debug)]
+pub struct Oracle {
+    program: State,
+    answers: Mutex<HashMap<Vec<isize>, Vec<isize>>>,
+}
+
+impl Oracle {
+    pub fn new(program: &str) -> Self {
+        Self {
+            program: State::new(program),
+            answers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get everything the program outputs when given the inputs.
+    pub fn query(&self, inputs: &[isize]) -> Result<Vec<isize>> {
+        if let Some(answer) = self.answers.lock().unwrap().get(inputs) {
+            return Ok(answer.clone());
+        }
+        let answer = self.run(inputs)?;
+        self.answers
+            .lock()
+            .unwrap()
+            .insert(inputs.to_vec(), answer.clone());
+        Ok(answer)
+    }
+
+    /// Get the single value the program outputs when given the inputs.
+    pub fn ask(&self, inputs: &[isize]) -> Result<isize> {
+        match self.query(inputs)?[..] {
+            [answer] => Ok(answer),
+            ref answer => Err(anyhow!(
+                "expected one output for {:?} but got {:?}",
+                inputs,
+                answer
+            )),
+        }
+    }
+
+    /// Answer many questions at once. The ones that haven't been asked before are run in
+    /// parallel. The answers are in the same order as the questions.
+    pub fn query_all(&self, questions: &[Vec<isize>]) -> Result<Vec<Vec<isize>>> {
+        let unknown = {
+            let answers = self.answers.lock().unwrap();
+            let mut unknown = questions
+                .iter()
+                .filter(|inputs| !answers.contains_key(*inputs))
+                .collect::<Vec<_>>();
+            unknown.sort();
+            unknown.dedup();
+            unknown
+        };
+        let found = unknown
+            .into_par_iter()
+            .map(|inputs| Ok((inputs.clone(), self.run(inputs)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut answers = self.answers.lock().unwrap();
+        answers.extend(found);
+        Ok(questions
+            .iter()
+            .map(|inputs| answers[inputs].clone())
+            .collect())
+    }
+
+    /// The number of answers that have been remembered.
+    pub fn len(&self) -> usize {
+        self.answers.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Run a copy of the program to the end with the inputs.
+    fn run(&self, inputs: &[isize]) -> Result<Vec<isize>> {
+        let mut machine = Machine::with_state(self.program.clone());
+        inputs.iter().for_each(|&value| machine.push_input(value));
+        machine.run(usize::MAX)?;
+        if !machine.halted() {
+            return Err(anyhow!("the program wanted more inputs than {:?}", inputs));
+        }
+        Ok(machine.poll_output())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_oracle() {
+        // Output the product of the two inputs.
+        let oracle = Oracle::new("3,11,3,12,2,11,12,13,4,13,99,0,0,0");
+        assert_eq!(oracle.ask(&[6, 7]).unwrap(), 42);
+        assert_eq!(oracle.ask(&[6, 7]).unwrap(), 42);
+        assert_eq!(oracle.len(), 1);
+
+        let questions = (0..10).map(|x| vec![x, x]).collect::<Vec<_>>();
+        let answers = oracle.query_all(&questions).unwrap();
+        assert_eq!(answers[9], vec![81]);
+        assert_eq!(oracle.len(), 11);
+        assert!(oracle.ask(&[1]).is_err());
+    }
+}
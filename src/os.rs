@@ -0,0 +1,261 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+
+use crate::machine::Machine;
+use crate::rng::Rng;
+use crate::scheduler::{Policy, RoundRobin};
+
+/// The value a program outputs to start a syscall. It's "SYSC" in ASCII, negated so it's unlikely
+/// to be a real output.
+pub const SYSCALL: isize = -0x5359_5343;
+
+/// The syscalls an [Os] provides, numbered by what the program outputs after [SYSCALL].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Syscall {
+    /// `1`: The time in milliseconds since the Unix epoch.
+    Time,
+    /// `2 low high`: A random number from `low` through `high`.
+    Random,
+    /// `3 address`: Start a new process that is a copy of this one but starts running at
+    /// `address`. The caller gets the new process id.
+    Spawn,
+    /// `4 milliseconds`: Stop running for at least that long. The caller gets 0 when it wakes.
+    Sleep,
+}
+
+impl Syscall {
+    /// Find the syscall with the given number (the one in each variant's docs), or `None` if no
+    /// syscall has that number.
+    pub fn from_number(number: isize) -> Option<Self> {
+        match number {
+            1 => Some(Syscall::Time),
+            2 => Some(Syscall::Random),
+            3 => Some(Syscall::Spawn),
+            4 => Some(Syscall::Sleep),
+            _ => None,
+        }
+    }
+
+    /// The number of arguments the program outputs after the syscall number.
+    pub fn arity(&self) -> usize {
+        match self {
+            Syscall::Time => 0,
+            Syscall::Random => 2,
+            Syscall::Spawn | Syscall::Sleep => 1,
+        }
+    }
+}
+
+// A process running on the OS along with the syscall it's in the middle of making.
+struct Task {
+    machine: Machine,
+    call: Option<Vec<isize>>,
+    wake: Option<Instant>,
+}
+
+impl Task {
+    fn new(machine: Machine) -> Self {
+        Self {
+            machine,
+            call: None,
+            wake: None,
+        }
+    }
+
+    fn runnable(&self, now: Instant) -> bool {
+        !self.machine.halted()
+            && !self.machine.waiting()
+            && self.wake.is_none_or(|wake| wake <= now)
+    }
+}
+
+/// Runs Intcode programs with syscalls. The syscalls are made through the normal IO instructions
+/// so programs still run on any computer, they just won't get answers. A program makes a syscall
+/// by outputting [SYSCALL], the [Syscall] number, and then its arguments. The OS answers by giving
+/// the result to the program as its next input. Every other output is passed along as usual.
+///
+/// Processes are run one instruction at a time on the current thread, picked by a [Policy] like a
+/// [crate::scheduler::Scheduler]'s. Each gets an id, starting from 0 for the first.
+pub struct Os {
+    tasks: Vec<Task>,
+    outputs: Vec<(usize, isize)>,
+    rng: Rng,
+    policy: Box<dyn Policy + Send>,
+}
+
+impl Os {
+    /// Boot the OS with the given comma separated program as process 0. Random numbers come from
+    /// the seed, so runs with the same seed get the same numbers. Processes are run round-robin,
+    /// one instruction each.
+    pub fn new(program: &str, seed: u64) -> Self {
+        Self::with_policy(program, seed, RoundRobin::default())
+    }
+
+    /// Boot the OS like [Os::new], but let the policy pick which process runs next.
+    pub fn with_policy(program: &str, seed: u64, policy: impl Policy + Send + 'static) -> Self {
+        Self {
+            tasks: vec![Task::new(Machine::new(program))],
+            outputs: Vec::new(),
+            rng: Rng::new(seed),
+            policy: Box::new(policy),
+        }
+    }
+
+    /// Queue a value for the process with the given id to read.
+    pub fn push_input(&mut self, process: usize, value: isize) {
+        self.tasks[process].machine.push_input(value);
+    }
+
+    /// Take everything the processes have output since the last poll along with the id of the
+    /// process that output it.
+    pub fn poll_output(&mut self) -> Vec<(usize, isize)> {
+        std::mem::take(&mut self.outputs)
+    }
+
+    /// Whether every process has halted.
+    pub fn halted(&self) -> bool {
+        self.tasks.iter().all(|task| task.machine.halted())
+    }
+
+    /// Run at most `limit` instructions, stopping early once every process has halted or is
+    /// waiting for input. If every process that can run is asleep, this waits for the first to
+    /// wake up. This returns the number of instructions that were run.
+    pub fn run(&mut self, limit: usize) -> Result<usize> {
+        let mut count = 0;
+        while count < limit {
+            let now = Instant::now();
+            let runnable = (0..self.tasks.len())
+                .filter(|&process| self.tasks[process].runnable(now))
+                .collect::<Vec<_>>();
+            if runnable.is_empty() {
+                let wake = self
+                    .tasks
+                    .iter()
+                    .filter(|task| !task.machine.halted() && !task.machine.waiting())
+                    .filter_map(|task| task.wake)
+                    .min();
+                match wake {
+                    Some(wake) => std::thread::sleep(wake.saturating_duration_since(now)),
+                    None => break,
+                }
+                continue;
+            }
+
+            let process = self.policy.next(&runnable);
+            let task = &mut self.tasks[process];
+            task.wake = None;
+            task.machine.step()?;
+            for value in task.machine.poll_output() {
+                self.output(process, value)?;
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    // Handle a value the process output, which is either part of a syscall or a regular output.
+    fn output(&mut self, process: usize, value: isize) -> Result<()> {
+        let task = &mut self.tasks[process];
+        let call = match task.call.as_mut() {
+            Some(call) => call,
+            None if value == SYSCALL => {
+                task.call = Some(Vec::new());
+                return Ok(());
+            }
+            None => {
+                self.outputs.push((process, value));
+                return Ok(());
+            }
+        };
+
+        call.push(value);
+        let syscall = Syscall::from_number(call[0])
+            .ok_or_else(|| anyhow!("process {} made unknown syscall {}", process, call[0]))?;
+        if call.len() < syscall.arity() + 1 {
+            return Ok(());
+        }
+        let arguments = task.call.take().unwrap().split_off(1);
+        let result = self.syscall(process, syscall, &arguments)?;
+        self.tasks[process].machine.push_input(result);
+        Ok(())
+    }
+
+    fn syscall(&mut self, process: usize, syscall: Syscall, arguments: &[isize]) -> Result<isize> {
+        match syscall {
+            Syscall::Time => Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as isize),
+            Syscall::Random => {
+                let (low, high) = (arguments[0], arguments[1]);
+                if low > high {
+                    return Err(anyhow!("random range {}..={} is empty", low, high));
+                }
+                Ok(self.rng.range(low, high))
+            }
+            Syscall::Spawn => {
+                let mut state = self.tasks[process].machine.state();
+                state.instruction_pointer = usize::try_from(arguments[0])
+                    .map_err(|_| anyhow!("can't spawn at address {}", arguments[0]))?;
+                self.tasks.push(Task::new(Machine::with_state(state)));
+                Ok(self.tasks.len() as isize - 1)
+            }
+            Syscall::Sleep => {
+                let milliseconds = arguments[0].max(0) as u64;
+                let wake = Instant::now()
+                    .checked_add(Duration::from_millis(milliseconds))
+                    .ok_or_else(|| {
+                        anyhow!("process {} can't sleep for {}ms", process, milliseconds)
+                    })?;
+                self.tasks[process].wake = Some(wake);
+                Ok(0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_os() {
+        // Get a random number from 5 to 5, spawn a process at 40 that outputs 7, sleep, and then
+        // output 9.
+        let mut program = format!(
+            "104,{0},104,2,104,5,104,5,3,50,4,50,\
+             104,{0},104,3,104,40,3,51,4,51,\
+             104,{0},104,4,104,1,3,52,104,9,99",
+            SYSCALL
+        );
+        program.push_str(&",0".repeat(7));
+        program.push_str(",104,7,99");
+        program.push_str(&",0".repeat(10));
+
+        let mut os = Os::new(&program, 1);
+        os.run(1_000).unwrap();
+        assert!(os.halted());
+        let outputs = os.poll_output();
+        let of = |process| {
+            outputs
+                .iter()
+                .filter(|(p, _)| *p == process)
+                .map(|(_, value)| *value)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(of(0), vec![5, 1, 9]);
+        assert_eq!(of(1), vec![7]);
+    }
+
+    #[test]
+    fn test_os_random_full_range() {
+        // Any number will do when the range covers every value.
+        let program = format!(
+            "104,{},104,2,104,{},104,{},3,11,99,0",
+            SYSCALL,
+            isize::MIN,
+            isize::MAX
+        );
+        let mut os = Os::new(&program, 1);
+        os.run(100).unwrap();
+        assert!(os.halted());
+    }
+}
@@ -1,7 +1,11 @@
 use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
 
 /// A parameter to an instruction.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Parameter {
     /// A pointer to a position in memory.
     Position(usize),
@@ -21,16 +25,46 @@ impl Display for Parameter {
     }
 }
 
+impl FromStr for Parameter {
+    type Err = Error;
+
+    /// Parse a parameter in the same format it is displayed in (e.g. `P[1]`, `I[-2]`, `R[3]`).
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("invalid parameter '{}'", s);
+        let value = s
+            .get(2..)
+            .and_then(|value| value.strip_suffix(']'))
+            .ok_or_else(invalid)?;
+        match s.get(..2) {
+            Some("P[") => Ok(Self::Position(value.parse().map_err(|_| invalid())?)),
+            Some("I[") => Ok(Self::Immediate(value.parse().map_err(|_| invalid())?)),
+            Some("R[") => Ok(Self::Relative(value.parse().map_err(|_| invalid())?)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
 impl Parameter {
     /// Create a new parameter from an opcode, position, and value. It will use the opcode and
-    /// position to determine the parameter mode.
+    /// position to determine the parameter mode. This panics if the mode is invalid, use
+    /// [Parameter::try_new] when the opcode isn't known to be valid.
     pub fn new(opcode: isize, position: isize, value: isize) -> Self {
+        Self::try_new(opcode, position, value).expect("Invalid parameter mode")
+    }
+
+    /// Create a new parameter like [Parameter::new] but return an error if the mode is invalid.
+    pub fn try_new(opcode: isize, position: isize, value: isize) -> Result<Self> {
         let mode = (opcode / 10_isize.pow(position as u32 + 1)) % 10;
         match mode {
-            0 => Self::Position(value as usize),
-            1 => Self::Immediate(value),
-            2 => Self::Relative(value),
-            _ => panic!("Invalid parameter mode"),
+            0 => Ok(Self::Position(value as usize)),
+            1 => Ok(Self::Immediate(value)),
+            2 => Ok(Self::Relative(value)),
+            _ => Err(anyhow!(
+                "invalid parameter mode {} for parameter {} of opcode {}",
+                mode,
+                position,
+                opcode
+            )),
         }
     }
 }
@@ -45,5 +79,6 @@ mod test {
         assert_eq!(Parameter::new(1002, 1, 4), Parameter::Position(4));
         assert_eq!(Parameter::new(1002, 2, 3), Parameter::Immediate(3));
         assert_eq!(Parameter::new(1002, 3, 2), Parameter::Position(2));
+        assert!(Parameter::try_new(30002, 3, 2).is_err());
     }
 }
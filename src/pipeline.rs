@@ -0,0 +1,106 @@
+use anyhow::{anyhow, Result};
+use tokio::task::JoinHandle;
+
+use crate::ipc::{self, Channel, ChannelReceiver, ChannelSender, Latency};
+use crate::process::Process;
+
+/// Start a process for each phase, connected in series like the day 7 amplifiers. Each process is
+/// given its phase as its first input. This returns the sender for the input of the first process
+/// and the receiver for the output of the last. The processes run in their own tasks until they
+/// halt, so this must be called from inside a tokio runtime.
+pub async fn chain(program: &str, phases: &[isize]) -> Result<(ChannelSender, ChannelReceiver)> {
+    let (first, stages, receiver) = ipc::pipeline(phases.len(), true);
+    let mut sender = first.clone();
+    for (&phase, (input, output)) in phases.iter().zip(stages) {
+        sender.send(phase).await?;
+        sender = output.clone();
+        let mut process = Process::new(program, input, output);
+        tokio::spawn(async move { process.run().await });
+    }
+    Ok((first, receiver))
+}
+
+/// Start a process for each phase like [chain] but also send the output of the last process back
+/// to the first. This returns the sender for the input of the first process and a handle that
+/// finishes with the value the last process sends back after the first has halted, which is the
+/// final signal for day 7. The first process keeps reading until then, because a process that
+/// outputs to one that's gone never halts.
+pub async fn feedback_loop(
+    program: &str,
+    phases: &[isize],
+) -> Result<(ChannelSender, JoinHandle<Result<Option<isize>>>)> {
+    feedback_loop_with(program, phases, || Channel::new(true)).await
+}
+
+/// Start a [feedback_loop] where every value between the processes is delayed by the given
+/// latency. The answer shouldn't change, which makes sure it doesn't depend on timing.
+pub async fn feedback_loop_with_latency(
+    program: &str,
+    phases: &[isize],
+    latency: Latency,
+) -> Result<(ChannelSender, JoinHandle<Result<Option<isize>>>)> {
+    feedback_loop_with(program, phases, || Channel::with_latency(true, latency)).await
+}
+
+// Start a feedback loop using the given function to create the input channel for each process.
+async fn feedback_loop_with(
+    program: &str,
+    phases: &[isize],
+    channel: impl Fn() -> (Channel, ChannelSender, ChannelReceiver),
+) -> Result<(ChannelSender, JoinHandle<Result<Option<isize>>>)> {
+    let mut channels = Vec::with_capacity(phases.len());
+    for &phase in phases {
+        let (_, mut sender, receiver) = channel();
+        sender.send(phase).await?;
+        channels.push((sender, receiver));
+    }
+    let senders = channels
+        .iter()
+        .map(|(sender, _)| sender.clone())
+        .collect::<Vec<_>>();
+    let first = senders
+        .first()
+        .cloned()
+        .ok_or_else(|| anyhow!("a feedback loop needs at least one phase"))?;
+
+    let mut signal = None;
+    for (i, (_, receiver)) in channels.into_iter().enumerate() {
+        let sender = senders[(i + 1) % senders.len()].clone();
+        let mut process = Process::new(program, receiver, sender);
+        let handle = tokio::spawn(async move {
+            process.run().await?;
+            Ok(process.recv().await)
+        });
+        signal.get_or_insert(handle);
+    }
+    Ok((first, signal.unwrap()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ipc::RecvResult;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_pipeline() {
+        let program = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0";
+        let (mut first, mut last) = chain(program, &[4, 3, 2, 1, 0]).await.unwrap();
+        first.send(0).await.unwrap();
+        assert!(matches!(last.recv().await, RecvResult::Value(43210)));
+
+        let program = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,\
+            6,99,0,0,5";
+        let (mut first, signal) = feedback_loop(program, &[9, 8, 7, 6, 5]).await.unwrap();
+        first.send(0).await.unwrap();
+        assert_eq!(signal.await.unwrap().unwrap(), Some(139629729));
+        assert!(feedback_loop(program, &[]).await.is_err());
+
+        let latency = Latency::new(Duration::ZERO, Duration::from_millis(2));
+        let (mut first, signal) = feedback_loop_with_latency(program, &[9, 8, 7, 6, 5], latency)
+            .await
+            .unwrap();
+        first.send(0).await.unwrap();
+        assert_eq!(signal.await.unwrap().unwrap(), Some(139629729));
+    }
+}
@@ -0,0 +1,116 @@
+use bevy::prelude::*;
+
+use crate::machine::Machine;
+use crate::theme::{ColorScheme, Rgb, Theme};
+
+/// Runs every [Computer] each frame. Systems that react to the computers' output should run after
+/// [IntcodeSet] to see it the same frame.
+pub struct IntcodePlugin;
+
+impl Plugin for IntcodePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<IntcodeInput>()
+            .add_event::<IntcodeOutput>()
+            .add_systems(
+                Update,
+                (feed_inputs, run_computers).chain().in_set(IntcodeSet),
+            );
+    }
+}
+
+impl From<ColorScheme> for Color {
+    fn from(color: ColorScheme) -> Self {
+        let Rgb(r, g, b) = Theme::current().color(color);
+        Color::rgb_u8(r, g, b)
+    }
+}
+
+/// The systems that feed and run the computers.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IntcodeSet;
+
+/// An Intcode computer attached to an entity. Each frame it runs until it waits for input, halts,
+/// or has run `speed` instructions.
+#[derive(Component)]
+pub struct Computer {
+    machine: Machine,
+    /// The most instructions to run in a frame.
+    pub speed: usize,
+    error: Option<String>,
+}
+
+impl Computer {
+    pub fn new(program: &str, speed: usize) -> Self {
+        Self {
+            machine: Machine::new(program),
+            speed,
+            error: None,
+        }
+    }
+
+    /// Set the memory at the given index to the given value.
+    pub fn set_memory(&mut self, index: usize, value: isize) {
+        self.machine.set_memory(index, value);
+    }
+
+    /// Queue a value for the computer to read. This is the same as sending an [IntcodeInput].
+    pub fn push_input(&mut self, value: isize) {
+        self.machine.push_input(value);
+    }
+
+    /// Whether the computer is waiting for input.
+    pub fn waiting(&self) -> bool {
+        self.machine.waiting()
+    }
+
+    /// Whether the computer has halted.
+    pub fn halted(&self) -> bool {
+        self.machine.halted()
+    }
+
+    /// The error that stopped the computer, if there was one.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// A value for the computer on the given entity to read.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct IntcodeInput {
+    pub entity: Entity,
+    pub value: isize,
+}
+
+/// A value the computer on the given entity output.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct IntcodeOutput {
+    pub entity: Entity,
+    pub value: isize,
+}
+
+fn feed_inputs(mut inputs: EventReader<IntcodeInput>, mut computers: Query<&mut Computer>) {
+    for input in inputs.read() {
+        if let Ok(mut computer) = computers.get_mut(input.entity) {
+            computer.push_input(input.value);
+        }
+    }
+}
+
+fn run_computers(
+    mut computers: Query<(Entity, &mut Computer)>,
+    mut outputs: EventWriter<IntcodeOutput>,
+) {
+    for (entity, mut computer) in &mut computers {
+        if computer.error.is_some() {
+            continue;
+        }
+        let speed = computer.speed;
+        if let Err(e) = computer.machine.run(speed) {
+            error!("intcode computer {:?}: {}", entity, e);
+            computer.error = Some(e.to_string());
+        }
+        for value in computer.machine.poll_output() {
+            outputs.send(IntcodeOutput { entity, value });
+        }
+    }
+}
@@ -1,13 +1,14 @@
 use std::collections::BTreeMap;
 
 use crate::instruction::Instruction;
-use crate::ipc::{ChannelReceiver, ChannelSender};
+use crate::ipc::{ChannelReceiver, ChannelSender, RecvResult};
 use crate::parameter::Parameter;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
 /// The state of the Intcode computer.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct State {
     /// The memory of the computer.
     pub memory: Vec<isize>,
@@ -82,61 +83,122 @@ impl State {
             return None;
         }
 
-        // Get the opcode and the first two digits (the operation).
-        let opcode = self[self.instruction_pointer];
-        let op = opcode % 100;
-
-        // This macro simplifies creating the parameters for the instruction.
-        macro_rules! param {
-            ($instruction:expr, 3) => {
-                $instruction(
-                    Parameter::new(opcode, 1, self[self.instruction_pointer + 1]),
-                    Parameter::new(opcode, 2, self[self.instruction_pointer + 2]),
-                    Parameter::new(opcode, 3, self[self.instruction_pointer + 3]),
-                )
-            };
-            ($instruction:expr, 2) => {
-                $instruction(
-                    Parameter::new(opcode, 1, self[self.instruction_pointer + 1]),
-                    Parameter::new(opcode, 2, self[self.instruction_pointer + 2]),
-                )
-            };
-            ($instruction:expr, 1) => {
-                $instruction(Parameter::new(
-                    opcode,
-                    1,
-                    self[self.instruction_pointer + 1],
-                ))
-            };
-            ($instruction:expr) => {
-                $instruction
-            };
+        self.decode().ok()
+    }
+
+    /// Decode the instruction at the instruction pointer. Unlike [State::next_instruction], this
+    /// says why it couldn't be decoded, naming the address it's at.
+    pub fn decode(&self) -> Result<(Instruction, usize)> {
+        Instruction::decode_with(self.instruction_pointer, |address| Some(self[address]))
+    }
+
+    /// Get the value of a parameter the same way the interpreter does when it reads it.
+    pub fn resolve(&self, parameter: Parameter) -> isize {
+        match parameter {
+            Parameter::Position(pos) => self[pos],
+            Parameter::Relative(offset) => self[(self.relative_base + offset) as usize],
+            Parameter::Immediate(value) => value,
         }
+    }
 
-        // Create the instruction based on the opcode.
-        let instruction = match op {
-            1 => param!(Instruction::Add, 3),
-            2 => param!(Instruction::Multiply, 3),
-            3 => param!(Instruction::Input, 1),
-            4 => param!(Instruction::Output, 1),
-            5 => param!(Instruction::JumpIfTrue, 2),
-            6 => param!(Instruction::JumpIfFalse, 2),
-            7 => param!(Instruction::LessThan, 3),
-            8 => param!(Instruction::Equals, 3),
-            9 => param!(Instruction::AdjustRelativeBaseOffset, 1),
-            99 => Instruction::Halt,
-            _ => return None,
-        };
+    /// Get the address a parameter points to the same way the interpreter does when it writes to
+    /// it. Immediate parameters and negative addresses can't be written to.
+    pub fn resolve_write(&self, parameter: Parameter) -> Result<usize> {
+        match parameter {
+            Parameter::Position(pos) => Ok(pos),
+            Parameter::Relative(offset) => usize::try_from(self.relative_base + offset)
+                .map_err(|_| anyhow!("write to negative address {}", self.relative_base + offset)),
+            Parameter::Immediate(_) => Err(anyhow!("write to immediate parameter {}", parameter)),
+        }
+    }
 
-        Some((instruction, instruction.parameter_count() + 1))
+    /// Find the addresses where the given values appear one after another, in order. This looks
+    /// through the additional memory as well, but only starting from cells that have been written.
+    pub fn find(&self, values: &[isize]) -> Vec<usize> {
+        if values.is_empty() {
+            return Vec::new();
+        }
+        (0..self.memory.len())
+            .chain(self.additional_memory.keys().copied())
+            .filter(|start| {
+                values
+                    .iter()
+                    .enumerate()
+                    .all(|(offset, value)| self[start + offset] == *value)
+            })
+            .collect()
+    }
+}
+
+/// What a process does when an add or multiply doesn't fit in an [isize].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OverflowMode {
+    /// Stop with [ProcessError::Overflow].
+    #[default]
+    Checked,
+    /// Wrap around like two's complement.
+    Wrapping,
+    /// Stop at [isize::MIN] or [isize::MAX].
+    Saturating,
+}
+
+impl OverflowMode {
+    fn add(self, left: isize, right: isize) -> Option<isize> {
+        match self {
+            OverflowMode::Checked => left.checked_add(right),
+            OverflowMode::Wrapping => Some(left.wrapping_add(right)),
+            OverflowMode::Saturating => Some(left.saturating_add(right)),
+        }
+    }
+
+    fn multiply(self, left: isize, right: isize) -> Option<isize> {
+        match self {
+            OverflowMode::Checked => left.checked_mul(right),
+            OverflowMode::Wrapping => Some(left.wrapping_mul(right)),
+            OverflowMode::Saturating => Some(left.saturating_mul(right)),
+        }
     }
 }
 
+/// Errors a process stops with that callers may want to tell apart. They're returned inside an
+/// [anyhow::Error], so use `downcast_ref` to get at them.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ProcessError {
+    /// An add or multiply overflowed while the process was in [OverflowMode::Checked].
+    Overflow {
+        instruction: Instruction,
+        address: usize,
+        left: isize,
+        right: isize,
+    },
+}
+
+impl std::fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProcessError::Overflow {
+                instruction,
+                address,
+                left,
+                right,
+            } => write!(
+                f,
+                "overflow in {} at {} with {} and {}",
+                instruction, address, left, right
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ProcessError {}
+
 /// A process that runs an Intcode program.
 pub struct Process {
     state: State,
     channel_receiver: ChannelReceiver,
     channel_sender: ChannelSender,
+    overflow: OverflowMode,
 }
 
 impl Process {
@@ -151,6 +213,7 @@ impl Process {
             state: State::new(program),
             channel_receiver,
             channel_sender,
+            overflow: OverflowMode::default(),
         }
     }
 
@@ -165,13 +228,19 @@ impl Process {
             state,
             channel_receiver,
             channel_sender,
+            overflow: OverflowMode::default(),
         }
     }
 
+    /// Get the id of the process, which is the id of its input channel.
+    pub fn id(&self) -> usize {
+        self.channel_receiver.id()
+    }
+
     /// Receive a value from the input channel. Some programs expect to have one last value that
     /// needs to be read for the solution. This helps with that.
     pub async fn recv(&mut self) -> Option<isize> {
-        self.channel_receiver.recv().await
+        self.channel_receiver.recv().await.into()
     }
 
     /// Set the memory at the given index to the given value.
@@ -179,14 +248,65 @@ impl Process {
         self.state[index] = value;
     }
 
+    /// Move the instruction pointer to the given address.
+    pub fn set_instruction_pointer(&mut self, address: usize) {
+        self.state.instruction_pointer = address;
+    }
+
+    /// Set the relative base to the given value.
+    pub fn set_relative_base(&mut self, relative_base: isize) {
+        self.state.relative_base = relative_base;
+    }
+
+    /// Choose what happens when an add or multiply overflows. Processes start out checked.
+    pub fn set_overflow_mode(&mut self, overflow: OverflowMode) {
+        self.overflow = overflow;
+    }
+
+    /// What happens when an add or multiply overflows.
+    pub fn overflow_mode(&self) -> OverflowMode {
+        self.overflow
+    }
+
+    /// Start over from the given state, throwing away any input that is waiting to be read.
+    pub fn reset(&mut self, state: State) {
+        self.state = state;
+        self.channel_receiver.drain();
+    }
+
     /// Get a copy of the current state of this process.
     pub fn state(&self) -> State {
         self.state.clone()
     }
 
+    /// Borrow the current state of this process, for when a copy of the memory isn't needed.
+    pub fn state_ref(&self) -> &State {
+        &self.state
+    }
+
+    /// Get the next instruction and its size without copying the state.
+    pub fn next_instruction(&self) -> Option<(Instruction, usize)> {
+        self.state.next_instruction()
+    }
+
+    /// Check whether the process has halted without copying its state.
+    pub fn halted(&self) -> bool {
+        self.state.halted
+    }
+
+    /// Check whether the next instruction can't run yet because it's an input with nothing to
+    /// read or an output the receiver hasn't made room for.
+    pub fn blocked(&self) -> bool {
+        match self.state.next_instruction() {
+            Some((Instruction::Input(_), _)) => !self.channel_receiver.is_ready(),
+            Some((Instruction::Output(_), _)) => self.channel_sender.is_full(),
+            _ => false,
+        }
+    }
+
     /// Run the process until it halts.
     pub async fn run(&mut self) -> Result<()> {
-        while !self.state().halted {
+        while !self.state.halted {
             self.step().await?;
         }
         Ok(())
@@ -198,16 +318,16 @@ impl Process {
         &mut self,
         mut f: impl FnMut(&State, &Instruction) -> bool,
     ) -> Result<()> {
-        while !self.state().halted {
-            if let Some((instruction, instruction_size)) = self.state.next_instruction() {
-                if f(&self.state, &instruction) {
-                    break;
-                }
-                match self.evaluate_instruction(instruction).await {
-                    Ok(true) => self.state.instruction_pointer += instruction_size,
-                    Ok(false) => (),
-                    Err(e) => return Err(e),
-                }
+        while !self.state.halted && self.state.instruction_pointer < self.state.len() {
+            // Decode errors already say where the instruction is.
+            let (instruction, instruction_size) = self.state.decode()?;
+            if f(&self.state, &instruction) {
+                break;
+            }
+            match self.execute(instruction).await {
+                Ok(true) => self.state.instruction_pointer += instruction_size,
+                Ok(false) => (),
+                Err(e) => return Err(e),
             }
         }
         Ok(())
@@ -216,16 +336,48 @@ impl Process {
     /// Run a single step of the process. If the process successfully ran the instruction, then the
     /// instruction pointer will be incremented.
     pub async fn step(&mut self) -> Result<Option<Instruction>> {
-        if let Some((instruction, instruction_size)) = self.state.next_instruction() {
-            match self.evaluate_instruction(instruction).await {
-                Ok(true) => self.state.instruction_pointer += instruction_size,
-                Ok(false) => (),
-                Err(e) => return Err(e),
-            }
-            Ok(Some(instruction))
-        } else {
-            Ok(None)
+        if self.state.instruction_pointer >= self.state.len() || self.state.halted {
+            return Ok(None);
         }
+
+        // Decode errors already say where the instruction is.
+        let (instruction, instruction_size) = self.state.decode()?;
+        match self.execute(instruction).await {
+            Ok(true) => self.state.instruction_pointer += instruction_size,
+            Ok(false) => (),
+            Err(e) => return Err(e),
+        }
+        Ok(Some(instruction))
+    }
+
+    // Evaluate the instruction, recording it for the timeline, the log, or the metrics when those
+    // are turned on.
+    async fn execute(&mut self, instruction: Instruction) -> Result<bool> {
+        #[cfg(feature = "chrome-trace")]
+        let (start, address) = (std::time::Instant::now(), self.state.instruction_pointer);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            process = self.id(),
+            ip = self.state.instruction_pointer,
+            %instruction,
+            "execute"
+        );
+        #[cfg(feature = "metrics")]
+        crate::metrics::running(self.id(), matches!(instruction, Instruction::Input(_)));
+        let result = self.evaluate_instruction(instruction).await;
+        #[cfg(feature = "metrics")]
+        crate::metrics::executed(self.id(), self.state.halted);
+        #[cfg(feature = "tracing")]
+        if let Err(e) = &result {
+            tracing::debug!(process = self.id(), error = %e, "failed");
+        }
+        #[cfg(feature = "tracing")]
+        if self.state.halted {
+            tracing::debug!(process = self.id(), "halted");
+        }
+        #[cfg(feature = "chrome-trace")]
+        crate::chrome_trace::instruction(self.id(), address, &instruction, start);
+        result
     }
 
     async fn evaluate_instruction(&mut self, instruction: Instruction) -> Result<bool> {
@@ -237,18 +389,10 @@ impl Process {
         // This macro simplifies evaluating the parameters for the instruction.
         macro_rules! eval {
             (write $dest:ident) => {
-                let $dest = match $dest {
-                   Parameter::Position(pos) => pos,
-                   Parameter::Relative(offset) => (self.state.relative_base + offset) as usize,
-                   Parameter::Immediate(_) => panic!("invalid write parameter"),
-                };
+                let $dest = self.state.resolve_write($dest)?;
             };
             ($param:ident) => {
-                let $param = match $param {
-                    Parameter::Position(pos) => self.state[pos],
-                    Parameter::Relative(offset) => self.state[(self.state.relative_base + offset) as usize],
-                    Parameter::Immediate(value) => value,
-                };
+                let $param = self.state.resolve($param);
             };
             ($param:ident, $($params:ident),+) => {
                 eval! { $param }
@@ -260,14 +404,28 @@ impl Process {
             };
         }
 
+        // Arithmetic that doesn't fit is handled by the overflow mode.
+        let overflow = |left, right| ProcessError::Overflow {
+            instruction,
+            address: self.state.instruction_pointer,
+            left,
+            right,
+        };
+
         match instruction {
             Instruction::Add(left, right, dest) => {
                 eval! { write dest, left, right };
-                self.state[dest] = left + right;
+                self.state[dest] = self
+                    .overflow
+                    .add(left, right)
+                    .ok_or_else(|| overflow(left, right))?;
             }
             Instruction::Multiply(left, right, dest) => {
                 eval! { write dest, left, right };
-                self.state[dest] = left * right;
+                self.state[dest] = self
+                    .overflow
+                    .multiply(left, right)
+                    .ok_or_else(|| overflow(left, right))?;
             }
             Instruction::LessThan(left, right, dest) => {
                 eval! { write dest, left, right };
@@ -279,8 +437,13 @@ impl Process {
             Instruction::Input(dest) => {
                 eval! { write dest };
                 self.state[dest] = match self.channel_receiver.recv().await {
-                    Some(value) => value,
-                    None => return Ok(false),
+                    RecvResult::Value(value) => value,
+                    RecvResult::Empty => return Ok(false),
+                    // Nothing will ever be sent to us again, so there is no point in continuing.
+                    RecvResult::Closed => {
+                        self.state.halted = true;
+                        return Ok(false);
+                    }
                 };
 
                 self.state.last_input = Some(self.state[dest]);
@@ -288,9 +451,10 @@ impl Process {
             Instruction::Output(value) => {
                 eval! { value };
                 self.state.last_output = Some(value);
-                match self.channel_sender.send(value).await {
-                    Ok(_) => (),
-                    Err(_) => return Ok(false),
+                // Nobody will ever read what we send, so there is no point in continuing.
+                if self.channel_sender.send(value).await.is_err() {
+                    self.state.halted = true;
+                    return Ok(false);
                 }
             }
             Instruction::JumpIfTrue(value, dest) => {
@@ -327,3 +491,113 @@ impl Process {
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ipc::Channel;
+    use crate::machine::Machine;
+
+    #[test]
+    fn test_resolve() {
+        let mut state = State::new("5,6,7,8");
+        state.relative_base = 2;
+        assert_eq!(state.resolve(Parameter::Position(1)), 6);
+        assert_eq!(state.resolve(Parameter::Immediate(-4)), -4);
+        assert_eq!(state.resolve(Parameter::Relative(1)), 8);
+        assert_eq!(state.resolve(Parameter::Position(100)), 0);
+        assert_eq!(state.resolve_write(Parameter::Relative(-1)).unwrap(), 1);
+        assert!(state.resolve_write(Parameter::Relative(-3)).is_err());
+        assert!(state.resolve_write(Parameter::Immediate(1)).is_err());
+    }
+
+    #[test]
+    fn test_overflow_mode() {
+        // Add a large value to itself, then multiply the sum by it.
+        let program = format!("1,9,9,10,2,9,10,11,99,{},0,0", isize::MAX / 2 + 1);
+        let run = |overflow| {
+            let mut machine = Machine::new(&program);
+            machine.set_overflow_mode(overflow);
+            machine.run(100).map(|_| machine.state())
+        };
+
+        let error = run(OverflowMode::Checked).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ProcessError>(),
+            Some(ProcessError::Overflow { address: 0, .. })
+        ));
+
+        let state = run(OverflowMode::Wrapping).unwrap();
+        assert_eq!(state[10], isize::MIN);
+        assert_eq!(state[11], 0);
+
+        let state = run(OverflowMode::Saturating).unwrap();
+        assert_eq!(state[10], isize::MAX);
+        assert_eq!(state[11], isize::MAX);
+    }
+
+    #[tokio::test]
+    async fn test_decode_errors() {
+        // Each program runs an add and then runs into something that can't be decoded.
+        let (_, sender, receiver) = Channel::new(false);
+        let mut process = Process::new("1101,1,1,5,42,0", receiver, sender);
+        process.step().await.unwrap();
+        let error = process.step().await.unwrap_err();
+        assert_eq!(error.to_string(), "invalid opcode 42 at 4");
+
+        let (_, sender, receiver) = Channel::new(false);
+        let mut process = Process::new("1101,1,1,9,11101,1,1,1,99,0", receiver, sender);
+        process.step().await.unwrap();
+        let error = process.step().await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "'ADD I[1] + I[1] -> I[1]' at 4 writes to an immediate parameter"
+        );
+        assert_eq!(process.state_ref().instruction_pointer, 4);
+    }
+
+    #[tokio::test]
+    async fn test_run_until_decode_error() {
+        // The write to an immediate parameter can't be decoded, so this stops instead of spinning.
+        let (_, sender, receiver) = Channel::new(false);
+        let mut process = Process::new("11101,1,1,1,99", receiver, sender);
+        let mut checked = 0;
+        let error = process
+            .run_until(|_, _| {
+                checked += 1;
+                false
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "'ADD I[1] + I[1] -> I[1]' at 0 writes to an immediate parameter"
+        );
+        assert_eq!(checked, 0);
+        assert_eq!(process.state_ref().instruction_pointer, 0);
+    }
+
+    #[tokio::test]
+    async fn test_output_to_closed_channel() {
+        // Closing the output is a way to shut a process down, so it halts instead of spinning.
+        let (_, _, receiver) = Channel::new(false);
+        let (_, sender, _) = Channel::new(false);
+        sender.close();
+        let mut process = Process::new("104,1,99", receiver, sender);
+        process.step().await.unwrap();
+        assert!(process.halted());
+        assert_eq!(process.state_ref().instruction_pointer, 0);
+        process.run().await.unwrap();
+    }
+
+    #[test]
+    fn test_find() {
+        let mut state = State::new("104,72,104,105,99");
+        state[1000] = 72;
+        state[1001] = 105;
+        assert_eq!(state.find(&[104]), vec![0, 2]);
+        assert_eq!(state.find(&[72, 104, 105]), vec![1]);
+        assert_eq!(state.find(&[72, 105]), vec![1000]);
+        assert!(state.find(&[]).is_empty());
+    }
+}
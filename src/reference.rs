@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use anyhow::{anyhow, Result};
+
+use crate::machine::Machine;
+use crate::process::State;
+
+/// An Intcode interpreter written to be easy to check against the puzzle rather than fast. Memory
+/// is a map that reads zero wherever nothing was written. Anything the puzzle doesn't define, like
+/// negative addresses, is an error. It's only meant as something to test the real computer
+/// against.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reference {
+    pub memory: BTreeMap<usize, isize>,
+    pub instruction_pointer: usize,
+    pub relative_base: isize,
+    pub halted: bool,
+    pub inputs: VecDeque<isize>,
+    pub outputs: Vec<isize>,
+}
+
+impl Reference {
+    pub fn new(program: &[isize]) -> Self {
+        Self {
+            memory: program.iter().copied().enumerate().collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn read(&self, address: usize) -> isize {
+        self.memory.get(&address).copied().unwrap_or(0)
+    }
+
+    fn address(value: isize) -> Result<usize> {
+        usize::try_from(value).map_err(|_| anyhow!("negative address {}", value))
+    }
+
+    // The mode of the nth parameter of the current instruction.
+    fn mode(&self, n: u32) -> isize {
+        self.read(self.instruction_pointer) / 10isize.pow(n + 1) % 10
+    }
+
+    // The address the nth parameter points to.
+    fn location(&self, n: u32) -> Result<usize> {
+        let cell = self.read(self.instruction_pointer + n as usize);
+        match self.mode(n) {
+            0 => Self::address(cell),
+            2 => Self::address(self.relative_base + cell),
+            mode => Err(anyhow!("can't use mode {} as an address", mode)),
+        }
+    }
+
+    // The value of the nth parameter.
+    fn value(&self, n: u32) -> Result<isize> {
+        match self.mode(n) {
+            1 => Ok(self.read(self.instruction_pointer + n as usize)),
+            _ => Ok(self.read(self.location(n)?)),
+        }
+    }
+
+    /// Run a single instruction. This returns `false` if nothing was run because the program has
+    /// halted or is waiting for input.
+    pub fn step(&mut self) -> Result<bool> {
+        if self.halted {
+            return Ok(false);
+        }
+        let ip = self.instruction_pointer;
+        match self.read(ip) % 100 {
+            1 => {
+                let value = self.value(1)? + self.value(2)?;
+                self.memory.insert(self.location(3)?, value);
+                self.instruction_pointer += 4;
+            }
+            2 => {
+                let value = self.value(1)? * self.value(2)?;
+                self.memory.insert(self.location(3)?, value);
+                self.instruction_pointer += 4;
+            }
+            3 => {
+                let location = self.location(1)?;
+                let Some(value) = self.inputs.pop_front() else {
+                    return Ok(false);
+                };
+                self.memory.insert(location, value);
+                self.instruction_pointer += 2;
+            }
+            4 => {
+                self.outputs.push(self.value(1)?);
+                self.instruction_pointer += 2;
+            }
+            5 => match self.value(1)? != 0 {
+                true => self.instruction_pointer = Self::address(self.value(2)?)?,
+                false => self.instruction_pointer += 3,
+            },
+            6 => match self.value(1)? == 0 {
+                true => self.instruction_pointer = Self::address(self.value(2)?)?,
+                false => self.instruction_pointer += 3,
+            },
+            7 => {
+                let value = (self.value(1)? < self.value(2)?) as isize;
+                self.memory.insert(self.location(3)?, value);
+                self.instruction_pointer += 4;
+            }
+            8 => {
+                let value = (self.value(1)? == self.value(2)?) as isize;
+                self.memory.insert(self.location(3)?, value);
+                self.instruction_pointer += 4;
+            }
+            9 => {
+                self.relative_base += self.value(1)?;
+                self.instruction_pointer += 2;
+            }
+            99 => {
+                self.halted = true;
+                self.instruction_pointer += 1;
+            }
+            _ => return Err(anyhow!("invalid opcode {} at {}", self.read(ip), ip)),
+        }
+        Ok(true)
+    }
+
+    /// Describe the first way the state differs from this one, if it does.
+    pub fn diff(&self, state: &State) -> Option<String> {
+        if self.instruction_pointer != state.instruction_pointer {
+            return Some(format!(
+                "instruction pointer {} != {}",
+                self.instruction_pointer, state.instruction_pointer
+            ));
+        }
+        if self.relative_base != state.relative_base {
+            return Some(format!(
+                "relative base {} != {}",
+                self.relative_base, state.relative_base
+            ));
+        }
+        if self.halted != state.halted {
+            return Some(format!("halted {} != {}", self.halted, state.halted));
+        }
+        let addresses = self
+            .memory
+            .keys()
+            .copied()
+            .chain(0..state.memory.len())
+            .chain(state.additional_memory.keys().copied())
+            .collect::<BTreeSet<_>>();
+        addresses
+            .into_iter()
+            .find(|&address| self.read(address) != state[address])
+            .map(|address| {
+                format!(
+                    "memory at {}: {} != {}",
+                    address,
+                    self.read(address),
+                    state[address]
+                )
+            })
+    }
+}
+
+/// Run the program on the [Machine] and the [Reference] together with the same inputs, checking
+/// that their states and outputs match after every instruction. This stops at the first
+/// difference, when both stop, or after `limit` instructions and returns how many instructions
+/// were compared. Once the reference fails the program has done something the puzzle doesn't
+/// define, so anything the machine does from there is accepted.
+pub fn lockstep(program: &[isize], inputs: &[isize], limit: usize) -> Result<usize> {
+    let text = program
+        .iter()
+        .map(|cell| cell.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let mut machine = Machine::new(&text);
+    let mut reference = Reference::new(program);
+    for &input in inputs {
+        machine.push_input(input);
+        reference.inputs.push_back(input);
+    }
+
+    let mut outputs = Vec::new();
+    for step in 0..limit {
+        let Ok(expected) = reference.step() else {
+            return Ok(step);
+        };
+        let actual = machine
+            .step()
+            .map_err(|e| anyhow!("step {}: the machine failed: {}", step, e))?;
+        if expected != actual {
+            return Err(anyhow!(
+                "step {}: the reference {} but the machine {}",
+                step,
+                if expected { "ran" } else { "stopped" },
+                if actual { "ran" } else { "stopped" }
+            ));
+        }
+        if let Some(diff) = reference.diff(machine.state_ref()) {
+            return Err(anyhow!("step {}: {}", step, diff));
+        }
+        outputs.extend(machine.poll_output());
+        if outputs != reference.outputs {
+            return Err(anyhow!(
+                "step {}: outputs {:?} != {:?}",
+                step,
+                reference.outputs,
+                outputs
+            ));
+        }
+        if !expected {
+            return Ok(step);
+        }
+    }
+    Ok(limit)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lockstep() {
+        let quine = [
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut reference = Reference::new(&quine);
+        while reference.step().unwrap() {}
+        assert_eq!(reference.outputs, quine);
+        assert!(lockstep(&quine, &[], 1_000).unwrap() > 16);
+
+        // Reading and doubling an input, then waiting for another that never comes.
+        assert_eq!(
+            lockstep(&[3, 9, 1002, 9, 2, 9, 4, 9, 3, 0], &[21], 100).unwrap(),
+            3
+        );
+    }
+}
@@ -1,79 +1,43 @@
-use std::{collections::VecDeque, str::FromStr};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::time::Instant;
 
-use crate::{app::App, breakpoint::Breakpoints, instruction::Instruction, process};
+use crate::{
+    analysis::{cfg, Cfg},
+    app::App,
+    breakpoint::{Action, Breakpoint, Scope},
+    condition::Condition,
+    history::Entry,
+    instruction::{Instruction, InstructionKind},
+    ipc::Channel,
+    parameter::Parameter,
+    process,
+    symbols::SymbolTable,
+    theme::{Rgb, Theme},
+    timing::{ProcessStatus, Timing},
+};
 
+use anyhow::{anyhow, Result};
+use enum_iterator::all;
 use ratatui::{
+    backend::TestBackend,
+    buffer::Buffer,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{
-        block::Title, Block, BorderType, Borders, Cell, Clear, List, Paragraph, Row, Table,
+        block::{Position, Title},
+        Block, BorderType, Borders, Cell, Clear, List, ListState, Paragraph, Row, Table,
         TableState, Tabs, Wrap,
     },
-    Frame,
+    Frame, Terminal,
 };
 
-/// The colors palette for the monokai theme.
-#[allow(dead_code)]
-pub enum ColorScheme {
-    DarkBlack,
-    LightBlack,
-    Background,
-    DarkerGrey,
-    DarkGrey,
-    Grey,
-    LightGrey,
-    LighterGrey,
-    White,
-    Blue,
-    Green,
-    Violet,
-    Orange,
-    Red,
-    Yellow,
-}
+pub use crate::theme::ColorScheme;
 
 impl From<ColorScheme> for Color {
     fn from(color: ColorScheme) -> Self {
-        match color {
-            ColorScheme::DarkBlack => Color::from_str("#11111b").unwrap(),
-            ColorScheme::LightBlack => Color::from_str("#181825").unwrap(),
-            ColorScheme::Background => Color::from_str("#1e1e2e").unwrap(),
-            ColorScheme::DarkerGrey => Color::from_str("#313244").unwrap(),
-            ColorScheme::DarkGrey => Color::from_str("#45475a").unwrap(),
-            ColorScheme::Grey => Color::from_str("#585b70").unwrap(),
-            ColorScheme::LightGrey => Color::from_str("#6c7086").unwrap(),
-            ColorScheme::LighterGrey => Color::from_str("#9399b2").unwrap(),
-            ColorScheme::White => Color::from_str("#f5e0dc").unwrap(),
-            ColorScheme::Blue => Color::from_str("#89b4fa").unwrap(),
-            ColorScheme::Green => Color::from_str("#a6e3a1").unwrap(),
-            ColorScheme::Violet => Color::from_str("#b4befe").unwrap(),
-            ColorScheme::Orange => Color::from_str("#fab387").unwrap(),
-            ColorScheme::Red => Color::from_str("#f38ba8").unwrap(),
-            ColorScheme::Yellow => Color::from_str("#f9e2af").unwrap(),
-        }
-    }
-}
-
-impl From<ColorScheme> for bevy::prelude::Color {
-    fn from(color: ColorScheme) -> Self {
-        match color {
-            ColorScheme::DarkBlack => bevy::prelude::Color::hex("19181a").unwrap(),
-            ColorScheme::LightBlack => bevy::prelude::Color::hex("221f22").unwrap(),
-            ColorScheme::Background => bevy::prelude::Color::hex("2d2a2e").unwrap(),
-            ColorScheme::DarkerGrey => bevy::prelude::Color::hex("403e41").unwrap(),
-            ColorScheme::DarkGrey => bevy::prelude::Color::hex("5b595c").unwrap(),
-            ColorScheme::Grey => bevy::prelude::Color::hex("727072").unwrap(),
-            ColorScheme::LightGrey => bevy::prelude::Color::hex("939293").unwrap(),
-            ColorScheme::LighterGrey => bevy::prelude::Color::hex("c1c0c0").unwrap(),
-            ColorScheme::White => bevy::prelude::Color::hex("fcfcfa").unwrap(),
-            ColorScheme::Blue => bevy::prelude::Color::hex("78dce8").unwrap(),
-            ColorScheme::Green => bevy::prelude::Color::hex("a9dc76").unwrap(),
-            ColorScheme::Violet => bevy::prelude::Color::hex("ab9df2").unwrap(),
-            ColorScheme::Orange => bevy::prelude::Color::hex("fc9867").unwrap(),
-            ColorScheme::Red => bevy::prelude::Color::hex("ff6188").unwrap(),
-            ColorScheme::Yellow => bevy::prelude::Color::hex("ffd866").unwrap(),
-        }
+        let Rgb(r, g, b) = Theme::current().color(color);
+        Color::Rgb(r, g, b)
     }
 }
 
@@ -84,7 +48,136 @@ pub enum WindowState {
     BreakpointType,
     BreakpointList,
     BreakpointInstruction,
+    BreakpointKind,
     BreakpointMemory,
+    BreakpointPointer,
+    BreakpointCondition,
+    BreakpointTrace,
+    GotoAddress,
+    Search,
+    Input,
+    Registers,
+    Export,
+    ChannelList,
+    ChannelDetail,
+    Open,
+    Watch,
+}
+
+/// The sections of the sidebar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Process,
+    History,
+    Channels,
+    TalkingHead,
+}
+
+impl Pane {
+    // How much room the pane wants when it isn't collapsed.
+    fn constraint(&self) -> Constraint {
+        match self {
+            Pane::Process => Constraint::Min(14),
+            Pane::History => Constraint::Percentage(25),
+            Pane::Channels => Constraint::Max(7),
+            Pane::TalkingHead => Constraint::Max(10),
+        }
+    }
+}
+
+/// The arrangements of the panes the debugger can switch between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Memory and the trace with the sidebar next to them.
+    Debugger,
+    /// A large console of the chosen channel next to the memory, for programs that draw with
+    /// their output like the ASCII games.
+    Io,
+    /// Only memory.
+    Minimal,
+}
+
+impl Preset {
+    /// The preset after this one, wrapping around.
+    pub fn next(&self) -> Self {
+        match self {
+            Preset::Debugger => Preset::Io,
+            Preset::Io => Preset::Minimal,
+            Preset::Minimal => Preset::Debugger,
+        }
+    }
+}
+
+/// How the talking head is feeling about the active process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mood {
+    Idle,
+    Running,
+    Breakpoint,
+    Error(String),
+}
+
+// What's drawn over the memory table besides the values themselves.
+#[derive(Clone, Copy)]
+struct MemoryView<'a> {
+    // How many times each address has run, when the heatmap is on.
+    counts: Option<&'a HashMap<usize, usize>>,
+    highlights: &'a Highlights,
+    cfg: &'a Cfg,
+    breakpoints: &'a BTreeSet<usize>,
+    cursor: Option<usize>,
+    // Whether to show each row as text too.
+    ascii: bool,
+}
+
+// What the state pane shows about a process besides its registers.
+#[derive(Clone, Copy)]
+struct StateView<'a> {
+    symbols: &'a SymbolTable,
+    timing: &'a Timing,
+    error: Option<&'a str>,
+    cursor: Option<usize>,
+    watches: &'a [Condition],
+}
+
+// The memory the next instruction of a process uses, which the memory table highlights. Decoding
+// it allocates, so it's kept until the process moves on to another instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Highlights {
+    process: usize,
+    instruction_pointer: usize,
+    relative_base: isize,
+    instruction: Instruction,
+    positions: Vec<usize>,
+    relatives: Vec<usize>,
+}
+
+impl Highlights {
+    fn new(process: usize, process_state: &process::State) -> Self {
+        let instruction = process_state
+            .next_instruction()
+            .map_or(Instruction::Halt, |(instruction, _)| instruction);
+        Self {
+            process,
+            instruction_pointer: process_state.instruction_pointer,
+            relative_base: process_state.relative_base,
+            instruction,
+            positions: instruction.position_parameters(),
+            relatives: instruction.relative_parameters(process_state.relative_base),
+        }
+    }
+
+    // Whether these are still right for the process. The instruction is checked too since a
+    // program can rewrite the one it's about to run.
+    fn matches(&self, process: usize, process_state: &process::State) -> bool {
+        self.process == process
+            && self.instruction_pointer == process_state.instruction_pointer
+            && self.relative_base == process_state.relative_base
+            && Some(self.instruction)
+                == process_state
+                    .next_instruction()
+                    .map(|(instruction, _)| instruction)
+    }
 }
 
 /// The state of the renderer.
@@ -98,17 +191,83 @@ pub struct RendererState {
     /// The window state of the renderer.
     pub window_state: WindowState,
 
-    /// The breakpoints of the app.
-    pub breakpoints: Breakpoints,
-
     // The index of the chosen instruction for the breakpoint menu.
     pub chosen_instruction: usize,
 
-    // The value of the chosen memory location for the breakpoint menu.
+    // The index of the selected breakpoint in the breakpoint list.
+    pub chosen_breakpoint: usize,
+
+    // The index of the chosen instruction kind for the breakpoint menu.
+    pub chosen_kind: usize,
+
+    // The value of the chosen memory location for the breakpoint and goto menus.
     pub chosen_memory_location: usize,
 
+    // The index of the selected channel in the channels pane.
+    pub chosen_channel: usize,
+
+    // How far the contents of the chosen channel are scrolled.
+    pub channel_scroll: u16,
+
+    // The text being typed into one of the text entry popups.
+    pub text: String,
+
+    // Why the last text entered couldn't be parsed.
+    pub text_error: Option<String>,
+
+    /// Whether the memory table scrolls to keep the instruction pointer in view.
+    pub follow: bool,
+
+    /// Whether the memory table is tinted by how often each instruction has run.
+    pub heatmap: bool,
+
+    /// Whether the active process is running continuously.
+    pub run: bool,
+
+    /// The number of instructions to run each tick while running continuously.
+    pub run_speed: usize,
+
+    /// What the talking head has to say. A halted process always makes it happy.
+    pub mood: Mood,
+
+    // The number of frames drawn, so the talking head can blink.
+    frames: usize,
+
+    // When the debugger started, and the instruction count and rate from the last time we
+    // measured how fast the active process is going.
+    started: Instant,
+    sample: (Instant, usize, f64),
+
+    /// The width of the sidebar.
+    pub sidebar_width: u16,
+
+    /// The sections of the sidebar from top to bottom and whether each one is collapsed.
+    pub sidebar: [(Pane, bool); 4],
+
+    /// The arrangement of the panes.
+    pub preset: Preset,
+
+    /// Whether the memory table has a column showing each row as ASCII.
+    pub ascii: bool,
+
+    /// The column of the selected row of the memory table that the cursor is on.
+    pub cursor: usize,
+
+    /// The process whose memory is shown next to the active process with the differences
+    /// highlighted, if any.
+    pub compare: Option<usize>,
+
+    // The values being searched for in memory and the address of the last match.
+    search: Vec<isize>,
+    search_address: Option<usize>,
+
+    // The number of rows in each memory table and how many of them fit on the screen. Both are
+    // updated every frame so the selection can be moved by pages without waiting for a render.
     memory_rows: Vec<usize>,
+    page: usize,
     table_states: Vec<TableState>,
+    cfgs: Vec<Cfg>,
+    highlights: Option<Highlights>,
 }
 
 impl RendererState {
@@ -116,37 +275,345 @@ impl RendererState {
     pub fn new(app: &App) -> Self {
         let states = app.states();
         let total_processes = states.len();
-        let memory_rows = states
-            .iter()
-            .map(|state| state.len() / 8)
-            .collect::<Vec<_>>();
+        let memory_rows = states.iter().map(Self::rows).collect::<Vec<_>>();
         let table_states = vec![TableState::default(); total_processes];
+
+        // The control-flow graphs are built from the programs as they were loaded, so they won't
+        // know about any code a program writes for itself.
+        let cfgs = states.iter().map(|state| cfg(&state.memory)).collect();
         Self {
             active_process: 0,
             total_processes,
             window_state: WindowState::Main,
-            breakpoints: Breakpoints::default(),
             chosen_instruction: 0,
+            chosen_kind: 0,
+            chosen_breakpoint: 0,
             chosen_memory_location: 0,
+            chosen_channel: 0,
+            channel_scroll: 0,
+            text: String::new(),
+            text_error: None,
+            follow: false,
+            heatmap: false,
+            run: false,
+            run_speed: 1,
+            mood: Mood::Idle,
+            frames: 0,
+            started: Instant::now(),
+            sample: (Instant::now(), 0, 0.0),
+            sidebar_width: 30,
+            sidebar: [
+                (Pane::Process, false),
+                (Pane::History, false),
+                (Pane::Channels, false),
+                (Pane::TalkingHead, false),
+            ],
+            preset: Preset::Debugger,
+            ascii: false,
+            cursor: 0,
+            compare: None,
+            search: Vec::new(),
+            search_address: None,
             memory_rows,
+            page: 1,
             table_states,
+            cfgs,
+            highlights: None,
+        }
+    }
+
+    /// Compare the active process with the next process, or stop comparing after the last one.
+    pub fn cycle_compare(&mut self) {
+        let next = match self.compare {
+            None => self.active_process + 1,
+            Some(other) => other + 1,
+        };
+        let next = match next == self.active_process {
+            true => next + 1,
+            false => next,
+        };
+        self.compare = (next < self.total_processes).then_some(next);
+    }
+
+    /// Make room for a process that was added to the app after the renderer was created.
+    pub fn add_process(&mut self, process_state: &process::State) {
+        self.total_processes += 1;
+        self.memory_rows.push(Self::rows(process_state));
+        self.table_states.push(TableState::default());
+        self.cfgs.push(cfg(&process_state.memory));
+    }
+
+    /// Scroll the memory table of the active process to the row holding the given address and
+    /// select it. Addresses past the end of the program go to the nearest group of extended memory
+    /// at or before them.
+    pub fn goto(&mut self, process_state: &process::State, address: usize) {
+        let row = Self::row(process_state, address);
+        let table_state = &mut self.table_states[self.active_process];
+        table_state.select(Some(row));
+        *table_state.offset_mut() = row;
+    }
+
+    // Keep the row with the instruction pointer within the visible rows of the memory table. The
+    // row is selected as well, otherwise the table would scroll back to the old selection.
+    fn follow_instruction_pointer(&mut self, process_state: &process::State, visible: usize) {
+        let row = Self::row(process_state, process_state.instruction_pointer);
+        let table_state = &mut self.table_states[self.active_process];
+        table_state.select(Some(row));
+        if row < table_state.offset() || row >= table_state.offset() + visible {
+            *table_state.offset_mut() = row;
+        }
+    }
+
+    // Select the given row of the memory table of the active process, scrolling only as far as
+    // needed to keep it visible.
+    fn select_row(&mut self, row: usize) {
+        let row = row.min(self.memory_rows[self.active_process].saturating_sub(1));
+        let table_state = &mut self.table_states[self.active_process];
+        table_state.select(Some(row));
+        if row < table_state.offset() {
+            *table_state.offset_mut() = row;
+        } else if row >= table_state.offset() + self.page {
+            *table_state.offset_mut() = row + 1 - self.page;
+        }
+    }
+
+    /// Move the view of the memory table of the active process by the given number of rows
+    /// without changing the selection.
+    pub fn scroll_view(&mut self, rows: isize) {
+        let last = self.memory_rows[self.active_process].saturating_sub(1);
+        let table_state = &mut self.table_states[self.active_process];
+        *table_state.offset_mut() = table_state.offset().saturating_add_signed(rows).min(last);
+    }
+
+    /// Scroll the memory table so the selection is visible after the screen changed size.
+    pub fn resize(&mut self) {
+        if let Some(selected) = self.table_states[self.active_process].selected() {
+            self.select_row(selected);
+        }
+    }
+
+    /// Move the selection in the memory table up a screen.
+    pub fn page_up(&mut self) {
+        let selected = self.table_states[self.active_process].selected();
+        self.select_row(selected.unwrap_or(0).saturating_sub(self.page));
+    }
+
+    /// Move the selection in the memory table down a screen.
+    pub fn page_down(&mut self) {
+        let selected = self.table_states[self.active_process].selected();
+        self.select_row(selected.map_or(0, |selected| selected + self.page));
+    }
+
+    /// Select the first row of the memory table.
+    pub fn home(&mut self) {
+        self.select_row(0);
+    }
+
+    /// Select the last row of the memory table, which includes any extended memory.
+    pub fn end(&mut self) {
+        self.select_row(usize::MAX);
+    }
+
+    /// Move the cursor to the previous cell of the memory table, wrapping to the previous row.
+    pub fn cursor_left(&mut self) {
+        match self.cursor {
+            0 => {
+                if self.table_states[self.active_process].selected() != Some(0) {
+                    self.cursor = 7;
+                    self.scroll_up();
+                }
+            }
+            _ => self.cursor -= 1,
         }
     }
 
+    /// Move the cursor to the next cell of the memory table, wrapping to the next row.
+    pub fn cursor_right(&mut self) {
+        match self.cursor {
+            7 => {
+                self.cursor = 0;
+                self.scroll_down();
+            }
+            _ => self.cursor += 1,
+        }
+    }
+
+    // Get the address under the cursor in the memory table of the active process, if a row is
+    // selected.
+    fn cursor_address(&self, process_state: &process::State) -> Option<usize> {
+        let row = self.table_states[self.active_process].selected()?;
+        let memory_rows = process_state.memory.len().div_ceil(8);
+        let start = match row < memory_rows {
+            true => row * 8,
+            false => *Self::additional_memory_groups(process_state).get(row - memory_rows)?,
+        };
+        Some(start + self.cursor)
+    }
+
+    // Get the highlights for the process, decoding them again only if it has moved on.
+    fn highlights<'a>(
+        cache: &'a mut Option<Highlights>,
+        process: usize,
+        process_state: &process::State,
+    ) -> &'a Highlights {
+        if cache
+            .as_ref()
+            .is_some_and(|highlights| !highlights.matches(process, process_state))
+        {
+            *cache = None;
+        }
+        cache.get_or_insert_with(|| Highlights::new(process, process_state))
+    }
+
+    // Get the number of rows in the memory table for the given state.
+    fn rows(process_state: &process::State) -> usize {
+        process_state.memory.len().div_ceil(8) + Self::additional_memory_groups(process_state).len()
+    }
+
+    // Find the row of the memory table that holds the given address.
+    fn row(process_state: &process::State, address: usize) -> usize {
+        let rows = process_state.memory.len().div_ceil(8);
+        match address < process_state.memory.len() {
+            true => address / 8,
+            false => {
+                let groups = Self::additional_memory_groups(process_state);
+                let group = groups.iter().take_while(|head| **head <= address).count();
+                (rows + group.saturating_sub(1)).min((rows + groups.len()).saturating_sub(1))
+            }
+        }
+    }
+
+    /// Search the memory of the given process for a number or, failing that, the characters of a
+    /// string (which may be quoted) and go to the first match.
+    pub fn search(&mut self, process_state: &process::State, query: &str) -> Result<()> {
+        let query = query.trim();
+        let values = match query.parse::<isize>() {
+            Ok(value) => vec![value],
+            Err(_) => {
+                let text = query
+                    .strip_prefix('"')
+                    .and_then(|query| query.strip_suffix('"'))
+                    .unwrap_or(query);
+                text.chars().map(|c| c as isize).collect()
+            }
+        };
+        if values.is_empty() {
+            return Err(anyhow!("nothing to search for"));
+        }
+        let address = *process_state
+            .find(&values)
+            .first()
+            .ok_or_else(|| anyhow!("'{}' not found", query))?;
+        self.search = values;
+        self.search_address = Some(address);
+        self.goto(process_state, address);
+        Ok(())
+    }
+
+    /// Go to the next (or previous) match of the last search, wrapping around at the ends.
+    pub fn search_next(&mut self, process_state: &process::State, forward: bool) {
+        let matches = process_state.find(&self.search);
+        let address = match (forward, self.search_address) {
+            (true, Some(last)) => matches.iter().find(|address| **address > last),
+            (false, Some(last)) => matches.iter().rev().find(|address| **address < last),
+            _ => None,
+        };
+        let address = match (address, forward) {
+            (Some(address), _) => Some(*address),
+            (None, true) => matches.first().copied(),
+            (None, false) => matches.last().copied(),
+        };
+        if let Some(address) = address {
+            self.search_address = Some(address);
+            self.goto(process_state, address);
+        }
+    }
+
+    /// Run twice as many instructions each tick, up to a limit.
+    pub fn speed_up(&mut self) {
+        self.run_speed = (self.run_speed * 2).min(1 << 16);
+    }
+
+    /// Run half as many instructions each tick, down to one.
+    pub fn slow_down(&mut self) {
+        self.run_speed = (self.run_speed / 2).max(1);
+    }
+
+    // Describe how the active process is doing. The rate is measured about once a second so it
+    // doesn't jump around with every frame.
+    fn status(&mut self, app: &App) -> String {
+        let executed = app.history(self.active_process).executed();
+        let (at, count, _) = self.sample;
+        let since = at.elapsed().as_secs_f64();
+        if since >= 1.0 {
+            let rate = executed.saturating_sub(count) as f64 / since;
+            self.sample = (Instant::now(), executed, rate);
+        }
+        let (_, _, rate) = self.sample;
+
+        let depths = app
+            .channels()
+            .iter()
+            .enumerate()
+            .map(|(i, channel)| format!("{}:{}", i, channel.buffer().len()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let elapsed = self.started.elapsed().as_secs();
+        format!(
+            "process {} | {} instructions | {:.0}/s | channels {} | {:02}:{:02}:{:02}",
+            self.active_process,
+            executed,
+            rate,
+            depths,
+            elapsed / 3600,
+            elapsed / 60 % 60,
+            elapsed % 60
+        )
+    }
+
+    /// Make the sidebar wider, taking the room from the memory table.
+    pub fn grow_sidebar(&mut self) {
+        self.sidebar_width = (self.sidebar_width + 5).min(120);
+    }
+
+    /// Make the sidebar narrower, giving the room to the memory table.
+    pub fn shrink_sidebar(&mut self) {
+        self.sidebar_width = (self.sidebar_width - 5).max(20);
+    }
+
+    /// Collapse the sidebar section at the given position down to its border, or expand it again.
+    pub fn toggle_pane(&mut self, position: usize) {
+        if let Some((_, collapsed)) = self.sidebar.get_mut(position) {
+            *collapsed = !*collapsed;
+        }
+    }
+
+    /// Move the bottom section of the sidebar to the top.
+    pub fn rotate_panes(&mut self) {
+        self.sidebar.rotate_right(1);
+    }
+
     /// Update the scroll and table states to scroll them "up".
     pub fn scroll_up(&mut self) {
         match self.window_state {
             WindowState::Main => {
-                let table_state = &mut self.table_states[self.active_process];
-                if table_state.offset() > 0 {
-                    table_state.select(Some(table_state.offset() - 1));
-                    *table_state.offset_mut() -= 1;
-                }
+                let selected = self.table_states[self.active_process].selected();
+                self.select_row(selected.unwrap_or(0).saturating_sub(1));
             }
             WindowState::BreakpointInstruction => {
-                if self.chosen_instruction > 0 {
-                    self.chosen_instruction -= 1;
-                }
+                self.chosen_instruction = self.chosen_instruction.saturating_sub(1);
+            }
+            WindowState::BreakpointKind => {
+                self.chosen_kind = self.chosen_kind.saturating_sub(1);
+            }
+            WindowState::BreakpointList => {
+                self.chosen_breakpoint = self.chosen_breakpoint.saturating_sub(1);
+            }
+            WindowState::ChannelList => {
+                self.chosen_channel = self.chosen_channel.saturating_sub(1);
+            }
+            WindowState::ChannelDetail => {
+                self.channel_scroll = self.channel_scroll.saturating_sub(1);
             }
             _ => {}
         }
@@ -156,16 +623,26 @@ impl RendererState {
     pub fn scroll_down(&mut self) {
         match self.window_state {
             WindowState::Main => {
-                let table_state = &mut self.table_states[self.active_process];
-                if table_state.offset() < self.memory_rows[self.active_process] - 1 {
-                    table_state.select(Some(table_state.offset() + 1));
-                    *table_state.offset_mut() += 1;
-                }
+                let selected = self.table_states[self.active_process].selected();
+                self.select_row(selected.map_or(0, |selected| selected + 1));
             }
             WindowState::BreakpointInstruction => {
-                if self.chosen_instruction < Instruction::NAMES.len() - 1 {
-                    self.chosen_instruction += 1;
-                }
+                self.chosen_instruction =
+                    (self.chosen_instruction + 1).min(Instruction::all().count() - 1);
+            }
+            WindowState::BreakpointKind => {
+                self.chosen_kind = (self.chosen_kind + 1).min(all::<InstructionKind>().count() - 1);
+            }
+            // The list is clamped to the breakpoints when it's rendered.
+            WindowState::BreakpointList => {
+                self.chosen_breakpoint += 1;
+            }
+            // The channel list is clamped the same way and the contents are left to the paragraph.
+            WindowState::ChannelList => {
+                self.chosen_channel += 1;
+            }
+            WindowState::ChannelDetail => {
+                self.channel_scroll = self.channel_scroll.saturating_add(1);
             }
             _ => {}
         }
@@ -182,58 +659,324 @@ impl RendererState {
                     Constraint::Length(3),
                     Constraint::Min(1),
                     Constraint::Length(1),
+                    Constraint::Length(1),
                 ]
                 .as_ref(),
             )
             .split(frame.size());
 
-        let cols = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Min(50), Constraint::Max(30)].as_ref())
-            .split(rows[2]);
+        // The memory is always shown, the rest depends on the preset.
+        let (memory, trace, console, sidebar) = match self.preset {
+            Preset::Debugger => {
+                let cols = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(
+                        [Constraint::Min(50), Constraint::Max(self.sidebar_width)].as_ref(),
+                    )
+                    .split(rows[2]);
+                let main = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(10), Constraint::Length(8)].as_ref())
+                    .split(cols[0]);
+                (main[0], Some(main[1]), None, Some(cols[1]))
+            }
+            Preset::Io => {
+                let cols = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Length(92), Constraint::Min(20)].as_ref())
+                    .split(rows[2]);
+                (cols[0], None, Some(cols[1]), None)
+            }
+            Preset::Minimal => (rows[2], None, None, None),
+        };
 
-        let sidebar = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Min(9), Constraint::Max(7), Constraint::Max(10)].as_ref())
-            .split(cols[1]);
+        // A collapsed section only has room for its border and title.
+        let sidebar = sidebar.map(|sidebar| {
+            Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(self.sidebar.map(|(pane, collapsed)| match collapsed {
+                    true => Constraint::Length(2),
+                    false => pane.constraint(),
+                }))
+                .split(sidebar)
+        });
 
         // Get all the state information we need.
-        let buffers = app.buffers();
-        let process_states = app.states();
+        self.frames += 1;
+        // Only the states that are drawn are copied, since programs can have a lot of memory.
+        let active = app.state(self.active_process);
+        let cursor = self.cursor_address(&active);
+        self.chosen_channel = self
+            .chosen_channel
+            .min(app.channels().len().saturating_sub(1));
+
+        // The borders and the header take up three rows of the memory table. Programs can write
+        // to new memory, so the number of rows can change from frame to frame.
+        self.page = (memory.height.saturating_sub(3) as usize).max(1);
+        self.memory_rows[self.active_process] = Self::rows(&active);
+        if self.follow {
+            self.follow_instruction_pointer(&active, self.page);
+        }
 
         Self::draw_header(frame, rows[0]);
-        Self::draw_tabs(frame, rows[1], &process_states, self.active_process);
-        Self::draw_memory(
-            frame,
-            cols[0],
-            &process_states[self.active_process],
-            &mut self.table_states[self.active_process],
-        );
-        Self::draw_process_state(frame, sidebar[0], &process_states[self.active_process]);
-        Self::draw_channels(frame, sidebar[1], &buffers, self.active_process);
-        Self::draw_talking_head(frame, sidebar[2]);
-        Self::draw_help(frame, rows[3]);
+        let statuses = (0..self.total_processes)
+            .map(|i| app.history(i).timing().status())
+            .collect::<Vec<_>>();
+        Self::draw_tabs(frame, rows[1], app.names(), &statuses, self.active_process);
+        let title = match self.run {
+            true => format!("Memory (running {}/tick)", self.run_speed),
+            false => "Memory".to_string(),
+        };
+        let counts = match self.heatmap {
+            true => Some(app.history(self.active_process).counts()),
+            false => None,
+        };
+        let compare = self
+            .compare
+            .filter(|other| *other != self.active_process && *other < self.total_processes);
+        match compare {
+            Some(other) => {
+                // Both sides scroll together with the active process.
+                let other_state = app.state(other);
+                let starts = Self::compare_rows(&active, &other_state);
+                self.memory_rows[self.active_process] = starts.len();
+                let halves = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .split(memory);
+                let table_state = &mut self.table_states[self.active_process];
+                Self::draw_compare(
+                    frame,
+                    halves[0],
+                    (app.name(self.active_process), &active),
+                    &other_state,
+                    &starts,
+                    table_state,
+                );
+                Self::draw_compare(
+                    frame,
+                    halves[1],
+                    (app.name(other), &other_state),
+                    &active,
+                    &starts,
+                    table_state,
+                );
+            }
+            None => {
+                let breakpoints = app.breakpoint_addresses(self.active_process);
+                let view = MemoryView {
+                    counts: counts.as_ref(),
+                    highlights: Self::highlights(
+                        &mut self.highlights,
+                        self.active_process,
+                        &active,
+                    ),
+                    cfg: &self.cfgs[self.active_process],
+                    breakpoints: &breakpoints,
+                    cursor,
+                    ascii: self.ascii,
+                };
+                Self::draw_memory(
+                    frame,
+                    memory,
+                    &title,
+                    &active,
+                    &mut self.table_states[self.active_process],
+                    &view,
+                );
+            }
+        }
+        for ((pane, _), chunk) in self
+            .sidebar
+            .iter()
+            .zip(sidebar.iter().flat_map(|s| s.iter()))
+        {
+            match pane {
+                Pane::Process => Self::draw_process_state(
+                    frame,
+                    *chunk,
+                    &active,
+                    StateView {
+                        symbols: app.symbols(self.active_process),
+                        timing: app.history(self.active_process).timing(),
+                        error: app.last_error(self.active_process).as_deref(),
+                        cursor,
+                        watches: app.watches(),
+                    },
+                ),
+                Pane::Channels => Self::draw_channels(
+                    frame,
+                    *chunk,
+                    app.channels(),
+                    &(0..app.channels().len())
+                        .map(|i| app.channel_label(i))
+                        .collect::<Vec<_>>(),
+                    &app.topology(),
+                    self.chosen_channel,
+                    self.window_state == WindowState::ChannelList,
+                ),
+                Pane::History => {
+                    Self::draw_history(frame, *chunk, &app.history(self.active_process).entries())
+                }
+                Pane::TalkingHead => {
+                    Self::draw_talking_head(frame, *chunk, &self.mood, &active, self.frames)
+                }
+            }
+        }
+        if let Some(trace) = trace {
+            Self::draw_trace(frame, trace, &app.trace());
+        }
+        if let Some(console) = console {
+            Self::draw_console(frame, console, app.channels().get(self.chosen_channel));
+        }
+        let status = self.status(app);
+        Self::draw_status(frame, rows[3], &status);
+        Self::draw_help(frame, rows[4]);
 
         match self.window_state {
-            WindowState::Main => {}
+            // The channel list is drawn in its pane, so there's no popup for it.
+            WindowState::Main | WindowState::ChannelList => {}
             WindowState::BreakpointList => {
-                Self::draw_breakpoint_list(&self.breakpoints, frame);
+                let breakpoints = app.breakpoints();
+                self.chosen_breakpoint = self
+                    .chosen_breakpoint
+                    .min(breakpoints.len().saturating_sub(1));
+                Self::draw_breakpoint_list(breakpoints, app.names(), self.chosen_breakpoint, frame);
             }
             WindowState::BreakpointType => {
                 Self::draw_breakpoint_type(frame);
             }
             WindowState::BreakpointInstruction => {
-                Self::draw_breakpoint_instruction(
-                    Instruction::NAMES[self.chosen_instruction],
+                let names = Instruction::all()
+                    .map(|operation| operation.mnemonic().to_string())
+                    .collect::<Vec<_>>();
+                Self::draw_breakpoint_choice(
+                    "Breakpoint Instruction",
+                    &names,
+                    self.chosen_instruction,
                     frame,
                 );
             }
+            WindowState::BreakpointKind => {
+                let names = all::<InstructionKind>()
+                    .map(|kind| format!("{:?}", kind))
+                    .collect::<Vec<_>>();
+                Self::draw_breakpoint_choice("Breakpoint Kind", &names, self.chosen_kind, frame);
+            }
             WindowState::BreakpointMemory => {
-                Self::draw_breakpoint_memory(&self.chosen_memory_location.to_string(), frame);
+                Self::draw_number_entry(
+                    "Breakpoint Memory Location",
+                    &self.chosen_memory_location.to_string(),
+                    frame,
+                );
+            }
+            WindowState::BreakpointPointer => {
+                Self::draw_number_entry(
+                    "Breakpoint Instruction Pointer",
+                    &self.chosen_memory_location.to_string(),
+                    frame,
+                );
+            }
+            WindowState::GotoAddress => {
+                Self::draw_number_entry(
+                    "Go To Address",
+                    &self.chosen_memory_location.to_string(),
+                    frame,
+                );
+            }
+            WindowState::Input => {
+                Self::draw_text_entry(
+                    &format!("Input to {}", app.name(self.active_process)),
+                    &self.text,
+                    self.text_error.as_deref(),
+                    frame,
+                );
+            }
+            WindowState::ChannelDetail => {
+                if let Some(channel) = app.channels().get(self.chosen_channel) {
+                    Self::draw_channel_detail(channel, self.channel_scroll, frame);
+                }
+            }
+            WindowState::Export => {
+                Self::draw_text_entry(
+                    "Export To Directory",
+                    &self.text,
+                    self.text_error.as_deref(),
+                    frame,
+                );
+            }
+            WindowState::Registers => {
+                Self::draw_text_entry(
+                    "Registers (ip N rb N)",
+                    &self.text,
+                    self.text_error.as_deref(),
+                    frame,
+                );
+            }
+            WindowState::Open => {
+                Self::draw_text_entry(
+                    "Open Program",
+                    &self.text,
+                    self.text_error.as_deref(),
+                    frame,
+                );
+            }
+            WindowState::Search => {
+                Self::draw_text_entry(
+                    "Search Memory",
+                    &self.text,
+                    self.text_error.as_deref(),
+                    frame,
+                );
+            }
+            WindowState::BreakpointCondition => {
+                Self::draw_text_entry(
+                    "Breakpoint Condition",
+                    &self.text,
+                    self.text_error.as_deref(),
+                    frame,
+                );
+            }
+            WindowState::BreakpointTrace => {
+                Self::draw_text_entry(
+                    "Tracepoint Message",
+                    &self.text,
+                    self.text_error.as_deref(),
+                    frame,
+                );
+            }
+            WindowState::Watch => {
+                Self::draw_text_entry(
+                    "Watch Expression",
+                    &self.text,
+                    self.text_error.as_deref(),
+                    frame,
+                );
             }
         }
     }
 
+    /// Render the app into a buffer of the given size without a terminal. This is what the tests
+    /// use to check what the screen looks like.
+    pub fn render_to_buffer(&mut self, app: &App, width: u16, height: u16) -> Result<Buffer> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height))?;
+        terminal.draw(|frame| self.render(app, frame))?;
+        Ok(terminal.backend().buffer().clone())
+    }
+
+    /// Render the app into plain text of the given size, one line per row of the screen.
+    pub fn render_to_string(&mut self, app: &App, width: u16, height: u16) -> Result<String> {
+        let buffer = self.render_to_buffer(app, width, height)?;
+        let lines = (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        Ok(lines.join("\n"))
+    }
+
     fn draw_header(frame: &mut Frame, chunk: Rect) {
         let title_block = Block::default().style(
             Style::default()
@@ -251,7 +994,8 @@ impl RendererState {
     fn draw_tabs(
         frame: &mut Frame<'_>,
         chunk: Rect,
-        process_states: &[process::State],
+        names: &[String],
+        statuses: &[ProcessStatus],
         active_process: usize,
     ) {
         let block = Block::default()
@@ -265,17 +1009,18 @@ impl RendererState {
                     .bg(ColorScheme::Background.into()),
             );
 
-        let tabs = process_states
+        let tabs = names
             .iter()
+            .zip(statuses)
             .enumerate()
-            .map(|(i, state)| {
+            .map(|(i, (name, status))| {
                 let mut style = Style::default().bg(ColorScheme::Grey.into());
-                if state.halted {
+                if *status == ProcessStatus::Halted {
                     style = style.fg(ColorScheme::Red.into());
                 } else if i == active_process {
                     style = style.fg(ColorScheme::White.into());
                 }
-                Span::from(format!("<   {}   >", i)).style(style)
+                Span::from(format!("<  {} {}  >", name, status)).style(style)
             })
             .collect();
         let tabs = Tabs::new(tabs)
@@ -290,11 +1035,13 @@ impl RendererState {
     fn draw_memory(
         frame: &mut Frame<'_>,
         chunk: Rect,
+        title: &str,
         process_state: &process::State,
         table_state: &mut TableState,
+        view: &MemoryView<'_>,
     ) {
         let block = Block::default()
-            .title(Title::from("Memory").alignment(Alignment::Center))
+            .title(Title::from(title).alignment(Alignment::Center))
             .borders(Borders::ALL)
             .border_style(Style::default().fg(ColorScheme::Orange.into()))
             .border_type(BorderType::Rounded)
@@ -304,96 +1051,263 @@ impl RendererState {
                     .bg(ColorScheme::Background.into()),
             );
 
-        let (instruction, positions, relatives) = match process_state.next_instruction() {
-            Some((instruction, _)) => (
-                instruction,
-                instruction.position_parameters(),
-                instruction.relative_parameters(process_state.relative_base),
-            ),
-            None => (Instruction::Halt, Vec::new(), Vec::new()),
+        let MemoryView {
+            counts,
+            highlights,
+            cfg,
+            breakpoints,
+            cursor,
+            ascii,
+        } = *view;
+        let Highlights {
+            instruction,
+            positions,
+            relatives,
+            ..
+        } = highlights;
+
+        // If we are about to jump somewhere we know about, we highlight where we'd land.
+        let jump_target = match *instruction {
+            Instruction::JumpIfTrue(_, Parameter::Immediate(target))
+            | Instruction::JumpIfFalse(_, Parameter::Immediate(target)) => Some(target as usize),
+            _ => None,
+        };
+
+        let jump_targets = cfg.jump_targets();
+
+        // The heatmap is on a log scale, otherwise the hottest loop would wash out everything else.
+        let hottest = counts
+            .and_then(|counts| counts.values().max().copied())
+            .unwrap_or(0);
+        let heat = |address: usize| {
+            let count = counts?.get(&address).copied()?;
+            let level = (count as f64).ln() / ((hottest + 1) as f64).ln();
+            Some(match level {
+                l if l < 0.25 => ColorScheme::DarkerGrey,
+                l if l < 0.5 => ColorScheme::DarkGrey,
+                l if l < 0.75 => ColorScheme::Grey,
+                _ => ColorScheme::LightGrey,
+            })
         };
 
+        // The parameters of the next instruction are right after the instruction pointer.
+        let ip = process_state.instruction_pointer;
+        let params = ip + 1..=ip + instruction.parameter_count();
+
         // A helper function to draw a chunk of memory and create a row for the table.
-        let mut params_left = 0;
-        let mut draw_chunk = |start: usize, chunk: &[isize]| {
+        let draw_chunk = |start: usize, chunk: &[isize]| {
             let mut row = vec![Cell::from(format!("{:08}", start))
                 .style(Style::default().bg(ColorScheme::DarkerGrey.into()))];
             for (j, v) in chunk.iter().enumerate() {
-                let mut style = Style::default().bg(ColorScheme::Background.into());
-                if process_state.instruction_pointer == start + j {
+                let background = heat(start + j).unwrap_or(ColorScheme::Background);
+                let mut style = Style::default().bg(background.into());
+                if ip == start + j {
                     style = style.bg(ColorScheme::Green.into());
-                    params_left = instruction.parameter_count();
-                } else if params_left > 0 {
+                } else if params.contains(&(start + j)) {
                     style = style.bg(ColorScheme::Red.into());
-                    params_left -= 1;
                 } else if positions.contains(&(start + j)) || relatives.contains(&(start + j)) {
                     style = style.bg(ColorScheme::Blue.into());
+                } else if jump_target == Some(start + j) {
+                    style = style.bg(ColorScheme::Yellow.into());
+                } else if let Some(instruction) = cfg.instruction(start + j) {
+                    // Color the start of each instruction by what kind it is.
+                    style = style.fg(match instruction.kind() {
+                        InstructionKind::Arithmetic => ColorScheme::Violet.into(),
+                        InstructionKind::Jump => ColorScheme::Orange.into(),
+                        InstructionKind::Io => ColorScheme::Blue.into(),
+                        InstructionKind::Meta => ColorScheme::Red.into(),
+                    });
+                }
+
+                // Mark the places that get jumped to so the loops and branches stand out, along
+                // with the places that have breakpoints.
+                let breakpoint = match breakpoints.contains(&(start + j)) {
+                    true => "*",
+                    false => "",
+                };
+                let jump = match jump_targets.contains(&(start + j)) {
+                    true => ">",
+                    false => "",
+                };
+                let marker = format!("{}{}", breakpoint, jump);
+                if cursor == Some(start + j) {
+                    style = style.add_modifier(Modifier::REVERSED);
                 }
-                row.push(Cell::from(format!("{}", v)).style(style));
+                row.push(Cell::from(format!("{}{}", marker, v)).style(style));
+            }
+
+            // Like a hex editor, anything that isn't printable is shown as a dot.
+            if ascii {
+                let text = chunk
+                    .iter()
+                    .map(|v| match (32..127).contains(v) {
+                        true => *v as u8 as char,
+                        false => '.',
+                    })
+                    .collect::<String>();
+                row.push(
+                    Cell::from(text).style(Style::default().fg(ColorScheme::LightGrey.into())),
+                );
             }
             Row::new(row)
         };
 
-        let mut chunks: Vec<_> = process_state
-            .memory
-            .chunks(8)
-            .enumerate()
-            .map(|(i, chunk)| draw_chunk(i * 8, chunk))
-            .collect();
+        // Only the rows that fit are drawn, since there can be a lot of additional memory. The
+        // view can be scrolled away from the selection, in which case nothing is highlighted.
+        let key_groups = Self::additional_memory_groups(process_state);
+        let memory_rows = process_state.memory.len().div_ceil(8);
+        let rows = memory_rows + key_groups.len();
+        let height = (chunk.height.saturating_sub(3) as usize).max(1);
+        let offset = table_state.offset().min(rows.saturating_sub(1));
+        *table_state.offset_mut() = offset;
 
-        // Get the additional memory groups by sorting them and finding the head of each group of
-        // 8.
-        let mut keys = process_state
-            .additional_memory
-            .keys()
-            .cloned()
-            .collect::<Vec<_>>();
-        keys.sort_unstable();
-        let mut keys = keys.into_iter().collect::<VecDeque<_>>();
-        let mut key_groups = vec![];
-        while let Some(head) = keys.pop_front() {
-            let mut count = 1;
-            while !keys.is_empty() && count < 8 {
-                let next = keys.front().unwrap();
-                if *next < head + 8 {
-                    keys.pop_front();
-                    count += 1;
+        let chunks: Vec<_> = (offset..rows.min(offset + height))
+            .map(|row| {
+                if row < memory_rows {
+                    let start = row * 8;
+                    let end = (start + 8).min(process_state.memory.len());
+                    draw_chunk(start, &process_state.memory[start..end])
                 } else {
-                    break;
+                    let key = key_groups[row - memory_rows];
+                    let memory = (key..key + 8).map(|i| process_state[i]).collect::<Vec<_>>();
+                    draw_chunk(key, &memory)
                 }
-            }
-            key_groups.push(head);
-        }
+            })
+            .collect();
 
-        // Now we can add the additional memory groups to the chunks.
-        for key in key_groups {
-            let memory = (key..key + 8).map(|i| process_state[i]).collect::<Vec<_>>();
-            chunks.push(draw_chunk(key, &memory));
+        let mut widths = vec![Constraint::Length(10); 9];
+        let mut header = vec![
+            Cell::from("Location"),
+            Cell::from("+0"),
+            Cell::from("+1"),
+            Cell::from("+2"),
+            Cell::from("+3"),
+            Cell::from("+4"),
+            Cell::from("+5"),
+            Cell::from("+6"),
+            Cell::from("+7"),
+        ];
+        if ascii {
+            widths.push(Constraint::Length(10));
+            header.push(Cell::from("ASCII"));
         }
-
-        let widths = [Constraint::Length(10); 9];
         let table = Table::new(chunks, widths)
+            .block(block)
+            .header(Row::new(header).style(Style::default().bg(ColorScheme::DarkerGrey.into())))
+            .column_spacing(0);
+
+        let mut visible = TableState::default();
+        visible.select(
+            table_state
+                .selected()
+                .and_then(|selected| selected.checked_sub(offset))
+                .filter(|selected| *selected < height),
+        );
+        frame.render_stateful_widget(table, chunk, &mut visible);
+    }
+
+    // Get the address at the start of each row when comparing the memory of two processes. The
+    // rows cover the longer of the two programs and any additional memory either one has used.
+    fn compare_rows(state: &process::State, other: &process::State) -> Vec<usize> {
+        let len = state.memory.len().max(other.memory.len());
+        let mut merged = process::State::new("0");
+        merged.memory = vec![0; len];
+        merged.additional_memory = state
+            .additional_memory
+            .iter()
+            .chain(other.additional_memory.iter())
+            .filter(|(address, _)| **address >= len)
+            .map(|(address, value)| (*address, *value))
+            .collect();
+        (0..len)
+            .step_by(8)
+            .chain(Self::additional_memory_groups(&merged))
+            .collect()
+    }
+
+    fn draw_compare(
+        frame: &mut Frame<'_>,
+        chunk: Rect,
+        (name, process_state): (&str, &process::State),
+        other: &process::State,
+        starts: &[usize],
+        table_state: &mut TableState,
+    ) {
+        let differences = starts
+            .iter()
+            .flat_map(|start| *start..*start + 8)
+            .filter(|address| process_state[*address] != other[*address])
+            .count();
+        let title = format!("{} ({} differences)", name, differences);
+        let block = Block::default()
+            .title(Title::from(title).alignment(Alignment::Center))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(ColorScheme::Orange.into()))
+            .border_type(BorderType::Rounded)
+            .style(
+                Style::default()
+                    .fg(ColorScheme::White.into())
+                    .bg(ColorScheme::Background.into()),
+            );
+
+        // Only the visible rows are drawn, the same as the memory table.
+        let height = (chunk.height.saturating_sub(3) as usize).max(1);
+        let offset = table_state.offset().min(starts.len().saturating_sub(1));
+        *table_state.offset_mut() = offset;
+        let rows = starts
+            .iter()
+            .skip(offset)
+            .take(height)
+            .map(|start| {
+                let mut row = vec![Cell::from(format!("{:08}", start))
+                    .style(Style::default().bg(ColorScheme::DarkerGrey.into()))];
+                for address in *start..*start + 8 {
+                    let mut style = Style::default();
+                    if address == process_state.instruction_pointer {
+                        style = style.bg(ColorScheme::Green.into());
+                    } else if process_state[address] != other[address] {
+                        style = style.bg(ColorScheme::Red.into());
+                    }
+                    row.push(Cell::from(process_state[address].to_string()).style(style));
+                }
+                Row::new(row)
+            })
+            .collect::<Vec<_>>();
+
+        let mut widths = vec![Constraint::Length(9)];
+        widths.extend([Constraint::Min(4); 8]);
+        let header = ["Location", "+0", "+1", "+2", "+3", "+4", "+5", "+6", "+7"];
+        let table = Table::new(rows, widths)
             .block(block)
             .header(
-                Row::new(vec![
-                    Cell::from("Location"),
-                    Cell::from("+0"),
-                    Cell::from("+1"),
-                    Cell::from("+2"),
-                    Cell::from("+3"),
-                    Cell::from("+4"),
-                    Cell::from("+5"),
-                    Cell::from("+6"),
-                    Cell::from("+7"),
-                ])
-                .style(Style::default().bg(ColorScheme::DarkerGrey.into())),
+                Row::new(header.map(Cell::from))
+                    .style(Style::default().bg(ColorScheme::DarkerGrey.into())),
             )
-            .column_spacing(0);
+            .column_spacing(1);
 
-        frame.render_stateful_widget(table, chunk, table_state);
+        let mut visible = TableState::default();
+        visible.select(
+            table_state
+                .selected()
+                .and_then(|selected| selected.checked_sub(offset))
+                .filter(|selected| *selected < height),
+        );
+        frame.render_stateful_widget(table, chunk, &mut visible);
     }
 
-    fn draw_process_state(frame: &mut Frame<'_>, chunk: Rect, process_state: &process::State) {
+    fn draw_process_state(
+        frame: &mut Frame<'_>,
+        chunk: Rect,
+        process_state: &process::State,
+        view: StateView,
+    ) {
+        let StateView {
+            symbols,
+            timing,
+            error,
+            cursor,
+            watches,
+        } = view;
         let state_block = Block::default()
             .title(Title::from("State").alignment(Alignment::Center))
             .borders(Borders::ALL)
@@ -410,8 +1324,14 @@ impl RendererState {
             None => Instruction::Halt,
         };
 
-        let states = vec![
+        let mut states = vec![
             format!("HLT: {:?}", process_state.halted),
+            format!("STS: {}", timing.status()),
+            format!(
+                "RUN: {:.3}s BLK: {:.3}s",
+                timing.running().as_secs_f64(),
+                timing.blocked().as_secs_f64()
+            ),
             format!("IP:  {:?}", process_state.instruction_pointer),
             format!("RB:  {:?}", process_state.relative_base),
             format!(
@@ -419,10 +1339,38 @@ impl RendererState {
                 process_state.last_input, process_state.last_output
             ),
             format!(""),
-            format!("{}", instruction),
+            format!("{}", instruction.with_symbols(symbols)),
         ];
 
-        let items: Vec<_> = states.iter().map(Line::raw).collect();
+        if !watches.is_empty() {
+            states.push(String::new());
+            states.push("WATCH:".to_string());
+            for watch in watches {
+                states.push(format!("{} = {}", watch, watch.evaluate(process_state)));
+            }
+        }
+
+        // Decode whatever is under the cursor as if it were code. It may well be data, so it's
+        // labeled as a guess.
+        if let Some(address) = cursor {
+            states.push(String::new());
+            states.push(format!("AT {} (speculative):", address));
+            states.push(
+                match Instruction::decode_with(address, |i| Some(process_state[i])) {
+                    Ok((instruction, _)) => format!("{}", instruction.with_symbols(symbols)),
+                    Err(_) => "not an instruction".to_string(),
+                },
+            );
+        }
+
+        // An error is the most important thing about the process, so it goes first.
+        let mut items: Vec<_> = states.iter().map(Line::raw).collect();
+        if let Some(error) = error {
+            let style = Style::default()
+                .fg(ColorScheme::Background.into())
+                .bg(ColorScheme::Red.into());
+            items.insert(0, Line::styled(format!("ERR: {}", error), style));
+        }
         let list = Paragraph::new(items)
             .block(state_block)
             .wrap(Wrap { trim: true });
@@ -432,11 +1380,19 @@ impl RendererState {
     fn draw_channels(
         frame: &mut Frame<'_>,
         chunk: Rect,
-        channels: &[Vec<isize>],
-        active_process: usize,
+        channels: &[Channel],
+        labels: &[String],
+        topology: &str,
+        selected: usize,
+        focused: bool,
     ) {
         let block = Block::default()
             .title(Title::from("Channels").alignment(Alignment::Center))
+            .title(
+                Title::from(topology)
+                    .alignment(Alignment::Center)
+                    .position(Position::Bottom),
+            )
             .borders(Borders::ALL)
             .border_style(Style::default().fg(ColorScheme::Violet.into()))
             .border_type(BorderType::Rounded)
@@ -446,23 +1402,160 @@ impl RendererState {
                     .bg(ColorScheme::Background.into()),
             );
 
-        let channels: Vec<_> = channels
+        // Long buffers don't fit, so we show how many values there are and the ones at each end.
+        let items: Vec<_> = channels
             .iter()
+            .zip(labels)
             .enumerate()
-            .map(|(i, channel)| {
+            .map(|(i, (channel, label))| {
+                let buffer = channel.buffer();
+                let values = match buffer[..] {
+                    [] => "-".to_string(),
+                    [value] => value.to_string(),
+                    [head, .., tail] => format!("{}..{}", head, tail),
+                };
+                let mut line = format!("{}: {} [{}]", label, buffer.len(), values);
+                if channel.empty_reads() > 0 {
+                    line += &format!(" ?{}", channel.empty_reads());
+                }
+                if channel.is_closed() {
+                    line += " closed";
+                }
+
                 let mut style = Style::default().fg(ColorScheme::LightGrey.into());
-                if i == active_process {
+                if i == selected && focused {
                     style = style.fg(ColorScheme::White.into());
                 }
-                Span::from(format!("{i}: {:?}", channel)).style(style)
+                Line::styled(line, style)
             })
             .collect();
 
-        let list = List::new(channels).block(block);
+        let mut state = ListState::default();
+        state.select(Some(selected));
+        let list = List::new(items).block(block);
+        frame.render_stateful_widget(list, chunk, &mut state);
+    }
+
+    fn draw_channel_detail(channel: &Channel, scroll: u16, frame: &mut Frame) {
+        let area = Self::centered_rect(60, 70, frame.size());
+        frame.render_widget(Clear, area);
+
+        let title = format!(
+            "Channel {} ({} values)",
+            channel.id(),
+            channel.buffer().len()
+        );
+        let block = Block::default()
+            .title(Title::from(title).alignment(Alignment::Center))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(ColorScheme::Violet.into()))
+            .border_type(BorderType::Rounded)
+            .style(
+                Style::default()
+                    .fg(ColorScheme::White.into())
+                    .bg(ColorScheme::Background.into()),
+            );
+
+        let values = channel
+            .buffer()
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let text = Paragraph::new(values)
+            .block(block)
+            .wrap(Wrap { trim: true })
+            .scroll((scroll, 0));
+        frame.render_widget(text, area);
+    }
+
+    fn draw_console(frame: &mut Frame<'_>, chunk: Rect, channel: Option<&Channel>) {
+        let title = match channel {
+            Some(channel) => format!("Console (channel {})", channel.id()),
+            None => "Console".to_string(),
+        };
+        let block = Block::default()
+            .title(Title::from(title).alignment(Alignment::Center))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(ColorScheme::Violet.into()))
+            .border_type(BorderType::Rounded)
+            .style(
+                Style::default()
+                    .fg(ColorScheme::White.into())
+                    .bg(ColorScheme::Background.into()),
+            );
+
+        // Output that is all ASCII is shown as text, anything else as numbers.
+        let values = channel.map(|channel| channel.buffer()).unwrap_or_default();
+        let text = match values.iter().all(|value| (0..128).contains(value)) {
+            true => values.iter().map(|value| *value as u8 as char).collect(),
+            false => values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+
+        // Keep the end of the output in view since that's what was drawn last.
+        let lines = text.lines().count() as u16;
+        let scroll = lines.saturating_sub(chunk.height.saturating_sub(2));
+        let paragraph = Paragraph::new(text).block(block).scroll((scroll, 0));
+        frame.render_widget(paragraph, chunk);
+    }
+
+    fn draw_history(frame: &mut Frame<'_>, chunk: Rect, entries: &[Entry]) {
+        let block = Block::default()
+            .title(Title::from("History").alignment(Alignment::Center))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(ColorScheme::Yellow.into()))
+            .border_type(BorderType::Rounded)
+            .style(
+                Style::default()
+                    .fg(ColorScheme::White.into())
+                    .bg(ColorScheme::Background.into()),
+            );
+
+        // The most recent instructions are the interesting ones, so they go at the bottom.
+        let height = chunk.height.saturating_sub(2) as usize;
+        let lines: Vec<_> = entries
+            .iter()
+            .skip(entries.len().saturating_sub(height))
+            .map(|entry| Line::raw(entry.to_string()))
+            .collect();
+        let list = List::new(lines).block(block);
+        frame.render_widget(list, chunk);
+    }
+
+    fn draw_trace(frame: &mut Frame<'_>, chunk: Rect, trace: &[String]) {
+        let block = Block::default()
+            .title(Title::from("Trace").alignment(Alignment::Center))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(ColorScheme::Green.into()))
+            .border_type(BorderType::Rounded)
+            .style(
+                Style::default()
+                    .fg(ColorScheme::White.into())
+                    .bg(ColorScheme::Background.into()),
+            );
+
+        // Only the newest messages fit, so we show those.
+        let height = chunk.height.saturating_sub(2) as usize;
+        let lines: Vec<_> = trace
+            .iter()
+            .skip(trace.len().saturating_sub(height))
+            .map(|message| Line::raw(message.as_str()))
+            .collect();
+        let list = List::new(lines).block(block);
         frame.render_widget(list, chunk);
     }
 
-    fn draw_talking_head(frame: &mut Frame, chunk: Rect) {
+    fn draw_talking_head(
+        frame: &mut Frame,
+        chunk: Rect,
+        mood: &Mood,
+        process_state: &process::State,
+        frames: usize,
+    ) {
         let block = Block::default()
             .title(Title::from("Talking Head").alignment(Alignment::Center))
             .borders(Borders::ALL)
@@ -474,7 +1567,75 @@ impl RendererState {
                     .bg(ColorScheme::Background.into()),
             );
 
-        frame.render_widget(block, chunk);
+        // It blinks every few seconds and looks around while the process runs.
+        let blink = frames % 180 < 8;
+        let (eyes, mouth, color, words) = match (mood, process_state.halted) {
+            (_, true) => (
+                "^   ^",
+                "\\___/",
+                ColorScheme::Green,
+                match process_state.last_output {
+                    Some(output) => format!("All done! It said {}.", output),
+                    None => "All done!".to_string(),
+                },
+            ),
+            (Mood::Error(error), _) => ("x   x", " ~~~ ", ColorScheme::Red, error.clone()),
+            (Mood::Breakpoint, _) => (
+                "O   O",
+                "  o  ",
+                ColorScheme::Yellow,
+                format!("Stopped at {}.", process_state.instruction_pointer),
+            ),
+            (Mood::Running, _) => (
+                match (frames / 30) % 2 {
+                    0 => "o  o ",
+                    _ => " o  o",
+                },
+                " === ",
+                ColorScheme::Blue,
+                "Crunching numbers...".to_string(),
+            ),
+            (Mood::Idle, _) => (
+                match blink {
+                    true => "-   -",
+                    false => "o   o",
+                },
+                " --- ",
+                ColorScheme::White,
+                "Ready when you are.".to_string(),
+            ),
+        };
+
+        let face = [
+            " .-------. ".to_string(),
+            format!("|  {}  |", eyes),
+            "|    ^    |".to_string(),
+            format!("|  {}  |", mouth),
+            " '-------' ".to_string(),
+        ];
+        let mut lines = face
+            .into_iter()
+            .map(|line| Line::from(Span::styled(line, Style::default().fg(color.into()))))
+            .collect::<Vec<_>>();
+        lines.push(Line::raw(words));
+
+        let text = Paragraph::new(lines)
+            .block(block)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        frame.render_widget(text, chunk);
+    }
+
+    fn draw_status(frame: &mut Frame, chunk: Rect, status: &str) {
+        let block = Block::default().style(
+            Style::default()
+                .fg(ColorScheme::White.into())
+                .bg(ColorScheme::DarkerGrey.into()),
+        );
+        let status = Paragraph::new(status)
+            .block(block)
+            .alignment(Alignment::Left);
+        frame.render_widget(status, chunk);
     }
 
     fn draw_help(frame: &mut Frame, chunk: Rect) {
@@ -484,7 +1645,7 @@ impl RendererState {
                 .bg(ColorScheme::Green.into()),
         );
         let status =
-            Paragraph::new("(q)uit | (s)tep | (c)ontinue | (b)reakpoint | list (B)reakpoints | (0-9) select process")
+            Paragraph::new("(q)uit | (s)tep | (c)ontinue, (p)ause | (r)un, (+/-) speed | (b)reakpoint | list (B)reakpoints, (d)elete, (g)lobal, (t)race | (g)oto, (/) search, (n)ext, (N) previous, (f)ollow, (h)eatmap, (a)scii, (w)atch | (i)nput, (e)dit registers, (R)eset, e(x)port, (O)pen | channe(l)s | (T)heme, (</>) resize, (F1-F4) collapse, (o)rder, (L)ayout, (D)iff | (PgUp/PgDn/Home/End) navigate, (←/→) cell | (0-9) select process")
                 .block(block)
                 .alignment(Alignment::Left);
 
@@ -506,18 +1667,25 @@ impl RendererState {
                     .bg(ColorScheme::Background.into()),
             );
 
-        let types = vec!["(I)nstruction", "(M)emory"];
+        let types = vec![
+            "(I)nstruction",
+            "(K)ind",
+            "(M)emory",
+            "instruction (P)ointer",
+            "(C)ondition",
+            "(R)elative base changes",
+        ];
         let items: Vec<_> = types.into_iter().map(Line::raw).collect();
         let list = List::new(items).block(block);
         frame.render_widget(list, area);
     }
 
-    fn draw_breakpoint_instruction(selected: &str, frame: &mut Frame) {
+    fn draw_breakpoint_choice(title: &str, names: &[String], selected: usize, frame: &mut Frame) {
         let area = Self::centered_rect(25, 60, frame.size());
         frame.render_widget(Clear, area);
 
         let block = Block::default()
-            .title(Title::from("Breakpoint Instruction").alignment(Alignment::Center))
+            .title(Title::from(title).alignment(Alignment::Center))
             .borders(Borders::ALL)
             .border_style(Style::default().fg(ColorScheme::Violet.into()))
             .border_type(BorderType::Rounded)
@@ -527,27 +1695,54 @@ impl RendererState {
                     .bg(ColorScheme::Background.into()),
             );
 
-        let instructions: Vec<_> = Instruction::NAMES
+        let items: Vec<_> = names
             .iter()
-            .map(|name| {
+            .enumerate()
+            .map(|(i, name)| {
                 let mut style = Style::default().fg(ColorScheme::LightGrey.into());
-                if name == &selected {
+                if i == selected {
                     style = style.fg(ColorScheme::White.into());
                 }
-                Span::from(*name).style(style)
+                Span::from(name.as_str()).style(style)
             })
             .collect();
 
-        let list = List::new(instructions).block(block);
+        let list = List::new(items).block(block);
         frame.render_widget(list, area);
     }
 
-    fn draw_breakpoint_memory(location: &str, frame: &mut Frame) {
+    // Get the additional memory groups by sorting them and finding the head of each group of 8.
+    fn additional_memory_groups(process_state: &process::State) -> Vec<usize> {
+        let mut keys = process_state
+            .additional_memory
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+        let mut keys = keys.into_iter().collect::<VecDeque<_>>();
+        let mut key_groups = vec![];
+        while let Some(head) = keys.pop_front() {
+            let mut count = 1;
+            while !keys.is_empty() && count < 8 {
+                let next = keys.front().unwrap();
+                if *next < head + 8 {
+                    keys.pop_front();
+                    count += 1;
+                } else {
+                    break;
+                }
+            }
+            key_groups.push(head);
+        }
+        key_groups
+    }
+
+    fn draw_number_entry(title: &str, location: &str, frame: &mut Frame) {
         let area = Self::centered_rect(25, 30, frame.size());
         frame.render_widget(Clear, area);
 
         let block = Block::default()
-            .title(Title::from("Breakpoint Memory Location").alignment(Alignment::Center))
+            .title(Title::from(title).alignment(Alignment::Center))
             .borders(Borders::ALL)
             .border_style(Style::default().fg(ColorScheme::Violet.into()))
             .border_type(BorderType::Rounded)
@@ -564,7 +1759,42 @@ impl RendererState {
         frame.render_widget(text, area);
     }
 
-    fn draw_breakpoint_list(breakpoints: &Breakpoints, frame: &mut Frame) {
+    fn draw_text_entry(title: &str, text: &str, error: Option<&str>, frame: &mut Frame) {
+        let area = Self::centered_rect(50, 30, frame.size());
+        frame.render_widget(Clear, area);
+
+        let block = Block::default()
+            .title(Title::from(title).alignment(Alignment::Center))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(ColorScheme::Violet.into()))
+            .border_type(BorderType::Rounded)
+            .style(
+                Style::default()
+                    .fg(ColorScheme::White.into())
+                    .bg(ColorScheme::Background.into()),
+            );
+
+        // Show why the last text was rejected under what was typed.
+        let mut lines = vec![Line::raw(text)];
+        if let Some(error) = error {
+            lines.push(Line::styled(
+                error,
+                Style::default().fg(ColorScheme::Red.into()),
+            ));
+        }
+        let text = Paragraph::new(lines)
+            .block(block)
+            .style(Style::default().bg(ColorScheme::DarkerGrey.into()))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(text, area);
+    }
+
+    fn draw_breakpoint_list(
+        breakpoints: &[(Scope, Breakpoint, Action)],
+        names: &[String],
+        selected: usize,
+        frame: &mut Frame,
+    ) {
         let area = Self::centered_rect(60, 70, frame.size());
         frame.render_widget(Clear, area);
 
@@ -580,9 +1810,26 @@ impl RendererState {
             );
 
         let items: Vec<_> = breakpoints
-            .clone()
-            .into_iter()
-            .map(|bp| Line::raw(format!("{:?}", bp)))
+            .iter()
+            .enumerate()
+            .map(|(i, (scope, bp, action))| {
+                let mut style = Style::default().fg(ColorScheme::LightGrey.into());
+                if i == selected {
+                    style = style.fg(ColorScheme::White.into());
+                }
+                let scope = match scope {
+                    Scope::Process(index) => names
+                        .get(*index)
+                        .cloned()
+                        .unwrap_or_else(|| scope.to_string()),
+                    Scope::Global => scope.to_string(),
+                };
+                let line = match action {
+                    Action::Stop => format!("[{}] {}", scope, bp),
+                    Action::Log(_) => format!("[{}] {} => {}", scope, bp, action),
+                };
+                Line::styled(line, style)
+            })
             .collect();
         let list = List::new(items).block(block);
         frame.render_widget(list, area);
@@ -610,3 +1857,46 @@ impl RendererState {
             .split(popup_layout[1])[1] // Return the middle chunk
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_render() {
+        let (channel, _, _) = Channel::new(true);
+        let state = Arc::new(Mutex::new(process::State::new("1,0,0,0,99")));
+        let (notifier, _) = mpsc::channel(1);
+        let app = App::new(
+            vec!["test".to_string()],
+            vec![channel],
+            vec![state],
+            vec![notifier],
+        );
+        let mut renderer = RendererState::new(&app);
+
+        let screen = renderer.render_to_string(&app, 160, 50).unwrap();
+        assert_eq!(screen.lines().count(), 50);
+        assert_eq!(screen.lines().next().unwrap().trim(), "INTCODE COMPUTER");
+        assert!(screen.contains("00000000"));
+
+        // What the next instruction uses is only decoded again once the process moves on.
+        let highlights = renderer.highlights.clone().unwrap();
+        assert_eq!(highlights.positions, vec![0, 0, 0]);
+        renderer.render_to_string(&app, 160, 50).unwrap();
+        assert_eq!(renderer.highlights.as_ref(), Some(&highlights));
+        let mut moved = app.state(0);
+        moved.instruction_pointer = 4;
+        assert!(!highlights.matches(0, &moved));
+        let highlights = RendererState::highlights(&mut renderer.highlights, 0, &moved);
+        assert_eq!(highlights.instruction, Instruction::Halt);
+
+        renderer.ascii = true;
+        let screen = renderer.render_to_string(&app, 160, 50).unwrap();
+        assert!(screen.contains("ASCII"));
+        assert!(screen.contains("....c"));
+    }
+}
@@ -0,0 +1,46 @@
+/// A small xorshift generator for when a run needs to be reproduced, like channel latency and the
+/// OS's random syscall. It's not good for much else, but it means we don't need a dependency.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Create a generator from the seed. Generators with the same seed give the same numbers.
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck at zero.
+        Self(seed.max(1))
+    }
+
+    /// Get the next number.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Get a number from `low` through `high`. The range must not be empty.
+    pub fn range(&mut self, low: isize, high: isize) -> isize {
+        let value = self.next_u64();
+        match (high.abs_diff(low) as u64).checked_add(1) {
+            Some(range) => low.wrapping_add((value % range) as isize),
+            // The range covers every value, so any number will do.
+            None => value as isize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_range() {
+        let mut rng = Rng::new(7);
+        for _ in 0..100 {
+            assert!((-3..=3).contains(&rng.range(-3, 3)));
+        }
+        assert_eq!(rng.range(5, 5), 5);
+        rng.range(isize::MIN, isize::MAX);
+        assert_eq!(Rng::new(7).next_u64(), Rng::new(7).next_u64());
+    }
+}
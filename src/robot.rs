@@ -0,0 +1,199 @@
+use anyhow::{anyhow, Result};
+
+use crate::grid::{Direction, Grid, Point};
+use crate::ipc::{Channel, RecvResult};
+use crate::process::Process;
+
+/// The color of a black panel.
+pub const BLACK: isize = 0;
+/// The color of a white panel.
+pub const WHITE: isize = 1;
+
+/// A robot like the hull painting robot from day 11. Its program reads the color of the panel
+/// under it and outputs pairs of values: the color to paint that panel and which way to turn (0 for
+/// left, 1 for right) before moving forward one panel. The robot starts facing up on a single panel
+/// of the given color, and every other panel starts black.
+#[derive(Debug, Clone)]
+pub struct TurtleRobot {
+    start: isize,
+    position: Point,
+    direction: Direction,
+    grid: Grid<isize>,
+    paint: Option<isize>,
+}
+
+impl TurtleRobot {
+    pub fn new(start: isize) -> Self {
+        Self {
+            start,
+            position: Point::new(0, 0),
+            direction: Direction::Up,
+            grid: Grid::new(),
+            paint: None,
+        }
+    }
+
+    pub fn position(&self) -> Point {
+        self.position
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// The panels that have been painted at least once and their colors.
+    pub fn grid(&self) -> &Grid<isize> {
+        &self.grid
+    }
+
+    /// The color of the panel under the robot, which is what its camera gives the program.
+    pub fn camera(&self) -> isize {
+        match self.grid.get(self.position) {
+            Some(&color) => color,
+            None if self.position == Point::new(0, 0) => self.start,
+            None => BLACK,
+        }
+    }
+
+    /// Handle a value the program output. This returns true once the robot has turned and moved,
+    /// which is when the program wants the next camera reading.
+    pub fn output(&mut self, value: isize) -> Result<bool> {
+        let Some(color) = self.paint.take() else {
+            self.paint = Some(value);
+            return Ok(false);
+        };
+        self.grid.insert(self.position, color);
+        self.direction = match value {
+            0 => self.direction.turn_left(),
+            1 => self.direction.turn_right(),
+            _ => return Err(anyhow!("invalid direction {}", value)),
+        };
+        self.position += self.direction;
+        Ok(true)
+    }
+
+    /// Run the robot's program until it halts, starting on a panel of the given color.
+    pub async fn run(program: &str, start: isize) -> Result<Self> {
+        let ((mut tx, mut output), (tx2, rx)) = Channel::pair(true);
+        let mut process = Process::new(program, rx, tx2);
+        tokio::spawn(async move { process.run().await });
+
+        let mut robot = Self::new(start);
+        tx.send(robot.camera()).await?;
+        while let RecvResult::Value(value) = output.recv().await {
+            // The program has halted if it can't take the reading.
+            if robot.output(value)? && tx.send(robot.camera()).await.is_err() {
+                break;
+            }
+        }
+        Ok(robot)
+    }
+
+    /// Draw the painted panels as text, `#` for white and a space for anything else.
+    pub fn ascii(&self) -> String {
+        self.grid.render(|color| match color {
+            Some(&WHITE) => '#',
+            _ => ' ',
+        })
+    }
+
+    /// Draw the painted panels as a grayscale PNG image, with white panels white and everything
+    /// else black. Each panel is a square of `scale` pixels.
+    pub fn png(&self, scale: usize) -> Vec<u8> {
+        let scale = scale.max(1);
+        let (min, max) = self.grid.bounds().unwrap_or_default();
+        let width = (max.x - min.x + 1) as usize * scale;
+        let height = (max.y - min.y + 1) as usize * scale;
+
+        // Each row of pixels starts with the filter type, which is always 0 (none) for us.
+        let mut pixels = Vec::with_capacity((width + 1) * height);
+        for y in 0..height {
+            pixels.push(0);
+            for x in 0..width {
+                let panel = Point::new(min.x + (x / scale) as isize, min.y + (y / scale) as isize);
+                pixels.push(match self.grid.get(panel) {
+                    Some(&WHITE) => 0xff,
+                    _ => 0,
+                });
+            }
+        }
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&(width as u32).to_be_bytes());
+        header.extend_from_slice(&(height as u32).to_be_bytes());
+        // 8 bit grayscale with the default compression, filtering, and no interlacing.
+        header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut png, b"IHDR", &header);
+        chunk(&mut png, b"IDAT", &zlib(&pixels));
+        chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+// Append a PNG chunk with its length and checksum.
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let mut crc = !0u32;
+    for &byte in kind.iter().chain(data) {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xedb8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    png.extend_from_slice(&(!crc).to_be_bytes());
+}
+
+// Wrap the data in a zlib stream without compressing it. The images are small enough that it
+// isn't worth it.
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none() as u8;
+        let length = block.len() as u16;
+        stream.push(last);
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    stream.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    stream
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_turtle_robot() {
+        // The moves from the day 11 example.
+        let mut robot = TurtleRobot::new(BLACK);
+        for value in [1, 0, 0, 0, 1, 0, 1, 0, 0, 1, 1, 0, 1, 0] {
+            robot.output(value).unwrap();
+        }
+        assert_eq!(robot.grid().len(), 6);
+        assert_eq!(robot.position(), Point::new(0, -1));
+        assert_eq!(robot.direction(), Direction::Left);
+        assert_eq!(robot.ascii(), "  #\n  #\n## ");
+
+        let png = robot.png(2);
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(&png[16..24], &[0, 0, 0, 6, 0, 0, 0, 6]);
+        assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::Result;
+use futures::executor::block_on;
+
+use crate::process::Process;
+
+/// Decides which process a [Scheduler] runs next.
+pub trait Policy {
+    /// Pick the process to run the next instruction from the indexes of those that can run. This
+    /// is only called when at least one can.
+    fn next(&mut self, runnable: &[usize]) -> usize;
+}
+
+/// Run each process for `slice` instructions in turn, skipping those that can't run.
+#[derive(Debug, Clone)]
+pub struct RoundRobin {
+    slice: usize,
+    current: Option<usize>,
+    remaining: usize,
+}
+
+impl RoundRobin {
+    /// Create a policy that gives each process `slice` instructions before moving on. A slice of
+    /// 0 is treated as 1.
+    pub fn new(slice: usize) -> Self {
+        Self {
+            slice: slice.max(1),
+            current: None,
+            remaining: 0,
+        }
+    }
+}
+
+impl Default for RoundRobin {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl Policy for RoundRobin {
+    fn next(&mut self, runnable: &[usize]) -> usize {
+        if let Some(current) = self.current {
+            if self.remaining > 0 && runnable.contains(&current) {
+                self.remaining -= 1;
+                return current;
+            }
+        }
+        let next = match self.current {
+            Some(current) => runnable
+                .iter()
+                .copied()
+                .find(|&index| index > current)
+                .unwrap_or(runnable[0]),
+            None => runnable[0],
+        };
+        self.current = Some(next);
+        self.remaining = self.slice - 1;
+        next
+    }
+}
+
+/// Runs processes one instruction at a time on the current thread. Which process runs is decided
+/// only by the [Policy] and by which processes are blocked, so the same processes always run the
+/// same way, unlike tasks on tokio. Processes are never run while they are blocked, so they never
+/// wait on a channel. Like [crate::machine::Machine], this mustn't be used from inside an async
+/// runtime.
+pub struct Scheduler {
+    processes: Vec<Process>,
+    policy: Box<dyn Policy + Send>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::with_policy(RoundRobin::default())
+    }
+}
+
+impl Scheduler {
+    /// Create a scheduler that runs processes round-robin, one instruction each.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a scheduler that picks which process runs next with the given policy.
+    pub fn with_policy(policy: impl Policy + Send + 'static) -> Self {
+        Self {
+            processes: Vec::new(),
+            policy: Box::new(policy),
+        }
+    }
+
+    /// Add a process, returning its index.
+    pub fn add(&mut self, process: Process) -> usize {
+        self.processes.push(process);
+        self.processes.len() - 1
+    }
+
+    /// Get the process at the given index.
+    pub fn process(&self, index: usize) -> &Process {
+        &self.processes[index]
+    }
+
+    /// Get the process at the given index to change it, like sending it input.
+    pub fn process_mut(&mut self, index: usize) -> &mut Process {
+        &mut self.processes[index]
+    }
+
+    /// The indexes of the processes that haven't halted and aren't blocked.
+    pub fn runnable(&self) -> Vec<usize> {
+        (0..self.processes.len())
+            .filter(|&index| {
+                let process = &self.processes[index];
+                !process.halted() && !process.blocked()
+            })
+            .collect()
+    }
+
+    /// Run a single instruction of the process the policy picks. This returns the index of the
+    /// process or `None` if every process has halted or is blocked.
+    pub fn step(&mut self) -> Result<Option<usize>> {
+        let runnable = self.runnable();
+        if runnable.is_empty() {
+            return Ok(None);
+        }
+        let index = self.policy.next(&runnable);
+        block_on(self.processes[index].step())?;
+        Ok(Some(index))
+    }
+
+    /// Run at most `limit` instructions, stopping early once every process has halted or is
+    /// blocked. This returns the number of instructions that were run.
+    pub fn run(&mut self, limit: usize) -> Result<usize> {
+        let mut count = 0;
+        while count < limit && self.step()?.is_some() {
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ipc::Channel;
+
+    // Run the day 7 amplifiers in a feedback loop, returning the final signal and the order the
+    // processes ran in.
+    fn amplifiers(policy: impl Policy + Send + 'static) -> (isize, Vec<usize>) {
+        let program = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,\
+            6,99,0,0,5";
+        let channels = (0..5).map(|_| Channel::new(false)).collect::<Vec<_>>();
+        let mut senders = channels
+            .iter()
+            .map(|(_, sender, _)| sender.clone())
+            .collect::<Vec<_>>();
+        for (sender, phase) in senders.iter_mut().zip([9, 8, 7, 6, 5]) {
+            block_on(sender.send(phase)).unwrap();
+        }
+        block_on(senders[0].send(0)).unwrap();
+
+        let mut scheduler = Scheduler::with_policy(policy);
+        for (i, (_, _, receiver)) in channels.into_iter().enumerate() {
+            let sender = senders[(i + 1) % 5].clone();
+            scheduler.add(Process::new(program, receiver, sender));
+        }
+        let mut order = Vec::new();
+        while let Some(index) = scheduler.step().unwrap() {
+            order.push(index);
+        }
+        (scheduler.process(4).state().last_output.unwrap(), order)
+    }
+
+    #[test]
+    fn test_scheduler() {
+        let (signal, order) = amplifiers(RoundRobin::default());
+        assert_eq!(signal, 139629729);
+        assert_eq!(amplifiers(RoundRobin::default()).1, order);
+
+        let (signal, sliced) = amplifiers(RoundRobin::new(4));
+        assert_eq!(signal, 139629729);
+        assert_ne!(sliced, order);
+    }
+}
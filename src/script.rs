@@ -0,0 +1,552 @@
+use std::borrow::Cow;
+#[cfg(feature = "rhai")]
+use std::cell::RefCell;
+use std::fmt::Display;
+#[cfg(feature = "rhai")]
+use std::rc::Rc;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+
+use crate::breakpoint::{parse_with_action, Action, Breakpoint, Breakpoints, Template};
+use crate::condition::Condition;
+use crate::machine::Machine;
+use crate::process::State;
+
+/// Something a script can drive: a [Machine] on its own or, with the tui, a process in the
+/// debugger (see `intcode::app::AppTarget`).
+pub trait Target {
+    /// Queue values for the process to read.
+    fn input(&mut self, values: &[isize]) -> Result<()>;
+
+    /// Run the given number of instructions without checking breakpoints. This stops early if the
+    /// process halts or waits for input.
+    fn advance(&mut self, count: usize) -> Result<()>;
+
+    /// Run until one of the breakpoints is hit, the process halts, or it waits for input and say
+    /// which. The breakpoints aren't checked on the first instruction so running again moves past
+    /// the one we are stopped on.
+    fn resume(&mut self, breakpoints: &Breakpoints) -> Result<&'static str>;
+
+    /// Set the memory at the given address.
+    fn poke(&mut self, address: usize, value: isize) -> Result<()>;
+
+    /// Take the values the process has output since the last time they were taken.
+    fn outputs(&mut self) -> Vec<isize>;
+
+    /// Get the state of the process. Targets that can lend it out don't copy it.
+    fn state(&self) -> Cow<'_, State>;
+
+    /// Read the memory at the given address.
+    fn peek(&self, address: usize) -> isize {
+        self.state()[address]
+    }
+}
+
+impl Target for Machine {
+    fn input(&mut self, values: &[isize]) -> Result<()> {
+        values.iter().for_each(|value| self.push_input(*value));
+        Ok(())
+    }
+
+    fn advance(&mut self, count: usize) -> Result<()> {
+        self.run(count)?;
+        Ok(())
+    }
+
+    fn resume(&mut self, breakpoints: &Breakpoints) -> Result<&'static str> {
+        let mut first = true;
+        loop {
+            if self.halted() {
+                return Ok("halted");
+            }
+            if self.waiting() {
+                return Ok("waiting for input");
+            }
+            if let Some((instruction, _)) = self.state_ref().next_instruction() {
+                if !first && breakpoints.evaluate(self.state_ref(), &instruction) {
+                    return Ok("breakpoint");
+                }
+            }
+            first = false;
+            if !self.step()? {
+                return Ok("halted");
+            }
+        }
+    }
+
+    fn poke(&mut self, address: usize, value: isize) -> Result<()> {
+        self.set_memory(address, value);
+        Ok(())
+    }
+
+    fn outputs(&mut self) -> Vec<isize> {
+        self.poll_output()
+    }
+
+    fn state(&self) -> Cow<'_, State> {
+        Cow::Borrowed(self.state_ref())
+    }
+}
+
+/// A single command in a script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Queue numbers for the process to read (e.g. `input 1,2,3`).
+    Input(Vec<isize>),
+    /// Queue a line of text for the process to read as ASCII (e.g. `ascii north`).
+    Ascii(String),
+    /// Run the given number of instructions (e.g. `step` or `step 10`).
+    Step(usize),
+    /// Run until a breakpoint is hit, the process halts, or it waits for input.
+    Run,
+    /// Add a breakpoint in the session file format (e.g. `break output 42`).
+    Break(Breakpoint, Action),
+    /// Remove all of the breakpoints.
+    Clear,
+    /// Set the memory at an address (e.g. `poke 100 5`).
+    Poke(usize, isize),
+    /// Log the memory in a range (e.g. `dump 100..200`).
+    Dump(usize, usize),
+    /// Log the outputs since the last time they were logged.
+    Output,
+    /// Log a message with values filled in (e.g. `print ip is {ip}`).
+    Print(Template),
+    /// Stop the script with an error if the condition doesn't hold (e.g. `assert mem[100] == 5`).
+    Assert(Condition),
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Command::Input(values) => write!(
+                f,
+                "input {}",
+                values
+                    .iter()
+                    .map(|value| value.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Command::Ascii(text) => write!(f, "ascii {}", text),
+            Command::Step(count) => write!(f, "step {}", count),
+            Command::Run => write!(f, "run"),
+            Command::Break(breakpoint, Action::Stop) => write!(f, "break {}", breakpoint),
+            Command::Break(breakpoint, action) => write!(f, "break {} => {}", breakpoint, action),
+            Command::Clear => write!(f, "clear"),
+            Command::Poke(address, value) => write!(f, "poke {} {}", address, value),
+            Command::Dump(start, end) => write!(f, "dump {}..{}", start, end),
+            Command::Output => write!(f, "output"),
+            Command::Print(template) => write!(f, "print {}", template),
+            Command::Assert(condition) => write!(f, "assert {}", condition),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (name, rest) = s.split_once(' ').unwrap_or((s, ""));
+        let rest = rest.trim();
+        match name {
+            "input" => Ok(Command::Input(
+                rest.split(',')
+                    .map(|value| value.trim().parse::<isize>())
+                    .collect::<Result<_, _>>()?,
+            )),
+            "ascii" => Ok(Command::Ascii(rest.to_string())),
+            "step" if rest.is_empty() => Ok(Command::Step(1)),
+            "step" => Ok(Command::Step(rest.parse()?)),
+            "run" => Ok(Command::Run),
+            "break" => {
+                let (breakpoint, action) = parse_with_action(rest)?;
+                Ok(Command::Break(breakpoint, action))
+            }
+            "clear" => Ok(Command::Clear),
+            "poke" => {
+                let (address, value) = rest
+                    .split_once(' ')
+                    .ok_or_else(|| anyhow!("poke needs an address and a value"))?;
+                Ok(Command::Poke(
+                    address.trim().parse()?,
+                    value.trim().parse()?,
+                ))
+            }
+            "dump" => {
+                let (start, end) = rest
+                    .split_once("..")
+                    .ok_or_else(|| anyhow!("dump needs a range like 100..200"))?;
+                Ok(Command::Dump(start.trim().parse()?, end.trim().parse()?))
+            }
+            "output" => Ok(Command::Output),
+            "print" => Ok(Command::Print(rest.parse()?)),
+            "assert" => Ok(Command::Assert(rest.parse()?)),
+            _ => Err(anyhow!("unknown command '{}'", name)),
+        }
+    }
+}
+
+/// A script for automating the debugger, one command per line. Anything after a `#` is a comment.
+/// For example, this runs until the process outputs 42 and then shows some memory:
+///
+/// ```text
+/// break output 42
+/// run
+/// dump 100..200
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Script {
+    // The commands along with the line they came from.
+    commands: Vec<(usize, Command)>,
+}
+
+impl FromStr for Script {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut commands = Vec::new();
+        for (number, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let command = line
+                .parse()
+                .map_err(|e| anyhow!("line {}: {}", number + 1, e))?;
+            commands.push((number + 1, command));
+        }
+        Ok(Self { commands })
+    }
+}
+
+impl Script {
+    /// Run the script against the given target, returning everything it logged. A failed
+    /// assertion or a process error stops the script.
+    pub fn run(&self, target: &mut impl Target) -> Result<Vec<String>> {
+        let mut log = Vec::new();
+        let mut breakpoints = Breakpoints::default();
+        for (number, command) in &self.commands {
+            self.command(command, target, &mut breakpoints, &mut log)
+                .map_err(|e| anyhow!("line {}: {}", number, e))?;
+        }
+        Ok(log)
+    }
+
+    fn command(
+        &self,
+        command: &Command,
+        target: &mut impl Target,
+        breakpoints: &mut Breakpoints,
+        log: &mut Vec<String>,
+    ) -> Result<()> {
+        match command {
+            Command::Input(values) => target.input(values)?,
+            Command::Ascii(text) => target.input(&ascii(text))?,
+            Command::Step(count) => target.advance(*count)?,
+            Command::Run => {
+                let trace = breakpoints.trace().len();
+                let reason = target.resume(breakpoints)?;
+                log.extend(breakpoints.trace().into_iter().skip(trace));
+                log.push(format!("stopped: {}", reason));
+            }
+            Command::Break(breakpoint, action) => {
+                breakpoints.add_with_action(breakpoint.clone(), action.clone())
+            }
+            Command::Clear => *breakpoints = Breakpoints::default(),
+            Command::Poke(address, value) => target.poke(*address, *value)?,
+            Command::Dump(start, end) => log.extend(dump(&target.state(), *start, *end)),
+            Command::Output => {
+                let values = target
+                    .outputs()
+                    .into_iter()
+                    .map(|value| value.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                log.push(format!("output: {}", values));
+            }
+            Command::Print(template) => log.push(template.render(&target.state())),
+            Command::Assert(condition) => {
+                if !condition.is_true(&target.state()) {
+                    return Err(anyhow!("assertion failed: {}", condition));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Turn a line of text into what the ASCII programs read, including the newline that ends it.
+fn ascii(text: &str) -> Vec<isize> {
+    text.bytes()
+        .chain(std::iter::once(b'\n'))
+        .map(|byte| byte as isize)
+        .collect()
+}
+
+// Show the memory in the range, eight cells to a line.
+fn dump(state: &State, start: usize, end: usize) -> Vec<String> {
+    let cells = (start..end)
+        .map(|address| state[address])
+        .collect::<Vec<_>>();
+    cells
+        .chunks(8)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let values = chunk
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("{:08}: {}", start + row * 8, values)
+        })
+        .collect()
+}
+
+/// A [Rhai](https://rhai.rs) script for automating the debugger, for when a list of commands isn't
+/// enough. Along with Rhai's variables, loops, and functions, scripts can use:
+///
+/// - `input(value)`, `input([values])`, and `ascii(text)` to queue input.
+/// - `step()` or `step(count)` to run instructions and `run()` to run until a breakpoint is hit,
+///   the process halts, or it waits for input. `run` returns which one as a string.
+/// - `breakpoint(text)` to add a breakpoint in the session file format and `clear()` to remove
+///   them all.
+/// - `read(address)`, `write(address, value)`, `dump(start, end)`, `ip()`, `rb()`, and `halted()`
+///   to look at and change the process.
+/// - `output()` to take the outputs since the last call as an array.
+/// - `print(value)` to log and `assert(condition)` or `assert(condition, message)` to stop the
+///   script with an error if the condition is false.
+///
+/// For example, this runs until the process outputs 42 and then shows some memory:
+///
+/// ```text
+/// breakpoint("output 42");
+/// if run() == "breakpoint" {
+///     dump(100, 200);
+/// }
+/// ```
+#[cfg(feature = "rhai")]
+#[derive(Debug, Clone)]
+pub struct RhaiScript {
+    ast: rhai::AST,
+}
+
+#[cfg(feature = "rhai")]
+impl FromStr for RhaiScript {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let ast = rhai::Engine::new()
+            .compile(s)
+            .map_err(|e| anyhow!("{}", e))?;
+        Ok(Self { ast })
+    }
+}
+
+#[cfg(feature = "rhai")]
+impl RhaiScript {
+    /// Run the script against the given target, returning everything it logged. The target is
+    /// shared with the script while it runs, so it's passed in a [RefCell] the caller keeps a
+    /// handle to. A failed assertion, a script error, or a process error stops the script.
+    pub fn run<T: Target + 'static>(&self, target: &Rc<RefCell<T>>) -> Result<Vec<String>> {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut engine = rhai::Engine::new();
+        bind(&mut engine, target, &log);
+        engine.run_ast(&self.ast).map_err(|e| anyhow!("{}", e))?;
+        let log = log.borrow().clone();
+        Ok(log)
+    }
+}
+
+// Register the functions scripts use to drive the target. Everything the script logs, including
+// the messages from tracepoints, goes to the log.
+#[cfg(feature = "rhai")]
+fn bind<T: Target + 'static>(
+    engine: &mut rhai::Engine,
+    target: &Rc<RefCell<T>>,
+    log: &Rc<RefCell<Vec<String>>>,
+) {
+    use rhai::{Array, Dynamic, EvalAltResult};
+
+    type Fallible<T> = std::result::Result<T, Box<EvalAltResult>>;
+    fn fail(e: impl Display) -> Box<EvalAltResult> {
+        e.to_string().into()
+    }
+    fn address(value: i64) -> Fallible<usize> {
+        usize::try_from(value).map_err(|_| fail(format!("{} isn't an address", value)))
+    }
+    fn values(array: Array) -> Fallible<Vec<isize>> {
+        array
+            .into_iter()
+            .map(|value| value.as_int().map(|value| value as isize).map_err(fail))
+            .collect()
+    }
+    fn array(values: impl IntoIterator<Item = isize>) -> Array {
+        values
+            .into_iter()
+            .map(|value| Dynamic::from_int(value as i64))
+            .collect()
+    }
+
+    let breakpoints = Rc::new(RefCell::new(Breakpoints::default()));
+    let logger = log.clone();
+    engine.on_print(move |text| logger.borrow_mut().push(text.to_string()));
+
+    let t = target.clone();
+    engine.register_fn("input", move |value: i64| -> Fallible<()> {
+        t.borrow_mut().input(&[value as isize]).map_err(fail)
+    });
+    let t = target.clone();
+    engine.register_fn("input", move |array: Array| -> Fallible<()> {
+        t.borrow_mut().input(&values(array)?).map_err(fail)
+    });
+    let t = target.clone();
+    engine.register_fn("ascii", move |text: &str| -> Fallible<()> {
+        t.borrow_mut().input(&ascii(text)).map_err(fail)
+    });
+    let t = target.clone();
+    engine.register_fn("step", move || -> Fallible<()> {
+        t.borrow_mut().advance(1).map_err(fail)
+    });
+    let t = target.clone();
+    engine.register_fn("step", move |count: i64| -> Fallible<()> {
+        let count = usize::try_from(count).map_err(fail)?;
+        t.borrow_mut().advance(count).map_err(fail)
+    });
+    let (t, b, l) = (target.clone(), breakpoints.clone(), log.clone());
+    engine.register_fn("run", move || -> Fallible<String> {
+        let breakpoints = b.borrow();
+        let trace = breakpoints.trace().len();
+        let reason = t.borrow_mut().resume(&breakpoints).map_err(fail)?;
+        l.borrow_mut()
+            .extend(breakpoints.trace().into_iter().skip(trace));
+        Ok(reason.to_string())
+    });
+    let b = breakpoints.clone();
+    engine.register_fn("breakpoint", move |text: &str| -> Fallible<()> {
+        let (breakpoint, action) = parse_with_action(text).map_err(fail)?;
+        b.borrow_mut().add_with_action(breakpoint, action);
+        Ok(())
+    });
+    let b = breakpoints.clone();
+    engine.register_fn("clear", move || *b.borrow_mut() = Breakpoints::default());
+    let t = target.clone();
+    engine.register_fn("read", move |at: i64| -> Fallible<i64> {
+        Ok(t.borrow().peek(address(at)?) as i64)
+    });
+    let t = target.clone();
+    engine.register_fn("write", move |at: i64, value: i64| -> Fallible<()> {
+        t.borrow_mut()
+            .poke(address(at)?, value as isize)
+            .map_err(fail)
+    });
+    let (t, l) = (target.clone(), log.clone());
+    engine.register_fn("dump", move |start: i64, end: i64| -> Fallible<()> {
+        let lines = dump(&t.borrow().state(), address(start)?, address(end)?);
+        l.borrow_mut().extend(lines);
+        Ok(())
+    });
+    let t = target.clone();
+    engine.register_fn("ip", move || t.borrow().state().instruction_pointer as i64);
+    let t = target.clone();
+    engine.register_fn("rb", move || t.borrow().state().relative_base as i64);
+    let t = target.clone();
+    engine.register_fn("halted", move || t.borrow().state().halted);
+    let t = target.clone();
+    engine.register_fn("output", move || array(t.borrow_mut().outputs()));
+    engine.register_fn("assert", |condition: bool| -> Fallible<()> {
+        match condition {
+            true => Ok(()),
+            false => Err(fail("assertion failed")),
+        }
+    });
+    engine.register_fn("assert", |condition: bool, message: &str| -> Fallible<()> {
+        match condition {
+            true => Ok(()),
+            false => Err(fail(format!("assertion failed: {}", message))),
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_script() {
+        let script = "
+            # Double the input and check the result.
+            input 21
+            break output 42
+            run
+            print ip is {ip}
+            assert mem[10] == 42
+            run
+            output
+            dump 9..11
+        "
+        .parse::<Script>()
+        .unwrap();
+        let mut machine = Machine::new("3,9,1001,9,21,10,4,10,99,0,0");
+        assert_eq!(
+            script.run(&mut machine).unwrap(),
+            vec![
+                "stopped: breakpoint",
+                "ip is 6",
+                "stopped: halted",
+                "output: 42",
+                "00000009: 21,42",
+            ]
+        );
+
+        let script = "assert mem[0] == 4".parse::<Script>().unwrap();
+        let error = script.run(&mut machine).unwrap_err();
+        assert_eq!(error.to_string(), "line 1: assertion failed: mem[0] == 4");
+        assert!("jump 4".parse::<Script>().is_err());
+    }
+
+    #[cfg(feature = "rhai")]
+    #[test]
+    fn test_rhai_script() {
+        let script = r#"
+            // Add 21 to the input and check the sum on the way out.
+            breakpoint("output 42");
+            input(21);
+            print(run());
+            print(`ip is ${ip()}`);
+            assert(read(10) == 42, "the sum");
+            write(100, 7);
+            print(run());
+            print(output());
+            dump(9, 11);
+            let total = 0;
+            for x in 0..4 {
+                total += read(100);
+            }
+            print(total);
+        "#
+        .parse::<RhaiScript>()
+        .unwrap();
+        let machine = Rc::new(RefCell::new(Machine::new("3,9,1001,9,21,10,4,10,99,0,0")));
+        assert_eq!(
+            script.run(&machine).unwrap(),
+            vec![
+                "breakpoint",
+                "ip is 6",
+                "halted",
+                "[42]",
+                "00000009: 21,42",
+                "28"
+            ]
+        );
+        assert!(machine.borrow().halted());
+
+        let script = "assert(read(0) == 4, \"the opcode\");"
+            .parse::<RhaiScript>()
+            .unwrap();
+        let error = script.run(&machine).unwrap_err();
+        assert!(error.to_string().contains("assertion failed: the opcode"));
+        assert!("let x = ;".parse::<RhaiScript>().is_err());
+    }
+}
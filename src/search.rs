@@ -0,0 +1,118 @@
+use std::sync::Arc;
+use std::thread::available_parallelism;
+
+use anyhow::Result;
+use futures::{stream, StreamExt};
+
+use crate::machine::Machine;
+use crate::process::State;
+
+/// The changes to make to a copy of the program for one run of a search: values to write to
+/// memory before it starts and inputs to give it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params {
+    pub patch: Vec<(usize, isize)>,
+    pub inputs: Vec<isize>,
+}
+
+impl Params {
+    /// Run with memory patched, like the noun and verb in day 2.
+    pub fn patch(patch: Vec<(usize, isize)>) -> Self {
+        Self {
+            patch,
+            ..Self::default()
+        }
+    }
+
+    /// Run with the given inputs, like the coordinates in day 19.
+    pub fn inputs(inputs: Vec<isize>) -> Self {
+        Self {
+            inputs,
+            ..Self::default()
+        }
+    }
+}
+
+/// How a run of the program ended.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    /// The state of the program when it halted (or stopped waiting for more input).
+    pub state: State,
+    /// Everything the program output.
+    pub outputs: Vec<isize>,
+}
+
+/// Run a copy of the program for each of the params and return the first params (in the order
+/// they were given) whose outcome matches the predicate. Runs are spread across tokio's blocking
+/// pool, with at most one per CPU going at once, so this must be called from inside a tokio
+/// runtime. No more runs are started once a match is found.
+pub async fn parallel_find(
+    program: &str,
+    params: impl IntoIterator<Item = Params>,
+    predicate: impl Fn(&Params, &Outcome) -> bool,
+) -> Result<Option<Params>> {
+    let program = Arc::new(State::new(program));
+    let concurrency = available_parallelism().map_or(1, |n| n.get());
+    let mut runs = stream::iter(params)
+        .map(|params| {
+            let program = program.clone();
+            tokio::task::spawn_blocking(move || {
+                let outcome = run(&program, &params)?;
+                anyhow::Ok((params, outcome))
+            })
+        })
+        .buffered(concurrency);
+
+    while let Some(result) = runs.next().await {
+        let (params, outcome) = result??;
+        if predicate(&params, &outcome) {
+            return Ok(Some(params));
+        }
+    }
+    Ok(None)
+}
+
+// Run a patched copy of the program until it halts or wants more input than it was given.
+fn run(program: &State, params: &Params) -> Result<Outcome> {
+    let mut machine = Machine::with_state(program.clone());
+    for &(address, value) in &params.patch {
+        machine.set_memory(address, value);
+    }
+    for &value in &params.inputs {
+        machine.push_input(value);
+    }
+    machine.run(usize::MAX)?;
+    Ok(Outcome {
+        state: machine.state(),
+        outputs: machine.poll_output(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_parallel_find() {
+        // Add the values at the noun and verb. Both 0, 4 and 4, 0 give 100, but 0, 4 comes first.
+        let nouns = (0..5)
+            .flat_map(|noun| (0..5).map(move |verb| Params::patch(vec![(1, noun), (2, verb)])));
+        let found = parallel_find("1,0,0,0,99", nouns, |_, outcome| outcome.state[0] == 100)
+            .await
+            .unwrap();
+        assert_eq!(found, Some(Params::patch(vec![(1, 0), (2, 4)])));
+
+        // Double the input.
+        let inputs = (0..10).map(|x| Params::inputs(vec![x]));
+        let found = parallel_find("3,9,102,2,9,9,4,9,99,0", inputs.clone(), |_, outcome| {
+            outcome.outputs == [14]
+        })
+        .await
+        .unwrap();
+        assert_eq!(found, Some(Params::inputs(vec![7])));
+        let found = parallel_find("3,9,102,2,9,9,4,9,99,0", inputs, |_, _| false)
+            .await
+            .unwrap();
+        assert_eq!(found, None);
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::breakpoint::{Action, Breakpoint, Scope};
+use crate::condition::Condition;
+
+/// The version of the session format written by [Session::to_json]. It changes whenever a session
+/// written by one version can't be read by another.
+pub const VERSION: u32 = 1;
+
+/// A debugging setup that should survive restarting the debugger: the breakpoints and the watch
+/// expressions. As JSON it looks like this:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "breakpoints": [
+///     { "scope": "*", "breakpoint": "ip 124" },
+///     {
+///       "scope": "0",
+///       "breakpoint": "condition mem[1000] > 50",
+///       "action": "log #{mem[1000]} at {ip}"
+///     }
+///   ],
+///   "watches": ["mem[1000]", "rb + 3"]
+/// }
+/// ```
+///
+/// `scope` is `*` for every process or a process index. Breakpoints and actions are written the
+/// same way as in breakpoint files (see [crate::breakpoint::Breakpoints]) and a missing `action`
+/// means `stop`. `watches` are [Condition] expressions whose values are shown for each process.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    pub breakpoints: Vec<SessionBreakpoint>,
+    #[serde(default)]
+    pub watches: Vec<Condition>,
+}
+
+/// A breakpoint in a [Session] along with the processes it applies to and what it does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionBreakpoint {
+    pub scope: Scope,
+    pub breakpoint: Breakpoint,
+    #[serde(default)]
+    pub action: Action,
+}
+
+impl Session {
+    pub fn new(breakpoints: Vec<SessionBreakpoint>, watches: Vec<Condition>) -> Self {
+        Self {
+            version: VERSION,
+            breakpoints,
+            watches,
+        }
+    }
+
+    /// Write the session as pretty printed JSON. Breakpoints with a predicate can't be written
+    /// down, so this fails if there are any.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Read a session, failing if it was written in a different version of the format.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let session: Session = serde_json::from_str(json)?;
+        match session.version {
+            VERSION => Ok(session),
+            version => Err(anyhow!(
+                "session is version {} but only version {} can be read",
+                version,
+                VERSION
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let session = Session::new(
+            vec![
+                SessionBreakpoint {
+                    scope: Scope::Global,
+                    breakpoint: Breakpoint::InstructionPointer(124),
+                    action: Action::Stop,
+                },
+                SessionBreakpoint {
+                    scope: Scope::Process(0),
+                    breakpoint: "condition mem[1000] > 50".parse().unwrap(),
+                    action: "log #{mem[1000]} at {ip}".parse().unwrap(),
+                },
+            ],
+            vec!["mem[1000]".parse().unwrap(), "rb + 3".parse().unwrap()],
+        );
+
+        let json = session.to_json().unwrap();
+        assert!(json.contains(r#""scope": "*""#));
+        assert!(json.contains(r#""action": "log #{mem[1000]} at {ip}""#));
+        assert_eq!(Session::from_json(&json).unwrap(), session);
+
+        // Leaving out the action means stopping.
+        let json = r#"{ "version": 1, "breakpoints": [{ "scope": "2", "breakpoint": "ip 7" }] }"#;
+        let session = Session::from_json(json).unwrap();
+        assert_eq!(session.breakpoints[0].scope, Scope::Process(2));
+        assert_eq!(session.breakpoints[0].action, Action::Stop);
+        assert!(session.watches.is_empty());
+
+        let old = json.replace(r#""version": 1"#, r#""version": 0"#);
+        assert!(Session::from_json(&old).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use futures::future::LocalBoxFuture;
+
+/// A solution to one day of Advent of Code. Each part returns its answer rather than printing it
+/// so solutions can be run and timed together. Inputs are `'static` so they can be handed to
+/// processes running in their own tasks.
+pub trait Solution {
+    /// The day of the puzzle this solves.
+    fn day(&self) -> usize;
+
+    /// Solve the first part of the puzzle for the given input.
+    fn part1(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>>;
+
+    /// Solve the second part of the puzzle for the given input.
+    fn part2(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>>;
+
+    /// Show the solution running on the given input. Most days don't have anything to show.
+    fn visualize(&self, _input: &'static str) -> LocalBoxFuture<'static, Result<()>> {
+        let day = self.day();
+        Box::pin(async move { Err(anyhow!("day {} doesn't have a visualization", day)) })
+    }
+}
+
+/// Run a part of a solution on the given input, returning its answer and how long it took.
+pub async fn timed(
+    solution: &dyn Solution,
+    input: &'static str,
+    part: usize,
+) -> (Result<String>, Duration) {
+    let start = Instant::now();
+    let answer = match part {
+        1 => solution.part1(input).await,
+        2 => solution.part2(input).await,
+        _ => Err(anyhow!("there is no part {}", part)),
+    };
+    (answer, start.elapsed())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Example;
+
+    impl Solution for Example {
+        fn day(&self) -> usize {
+            1
+        }
+
+        fn part1(&self, input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+            Box::pin(async move { Ok(input.len().to_string()) })
+        }
+
+        fn part2(&self, _input: &'static str) -> LocalBoxFuture<'static, Result<String>> {
+            Box::pin(async { Err(anyhow!("unsolved")) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timed() {
+        assert_eq!(timed(&Example, "abc", 1).await.0.unwrap(), "3");
+        assert!(timed(&Example, "abc", 2).await.0.is_err());
+        assert!(timed(&Example, "abc", 3).await.0.is_err());
+        assert!(Example.visualize("abc").await.is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+
+/// Names for the addresses in a program, used to make listings easier to read. In a side file each
+/// line is an address followed by its name (e.g. `223 counter`) and anything after a `;` is a
+/// comment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    names: BTreeMap<usize, String>,
+}
+
+impl SymbolTable {
+    /// Create an empty symbol table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a symbol table from the given side file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// Save the symbol table to the given side file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        Ok(std::fs::write(path, self.to_string())?)
+    }
+
+    /// Name the given address. If the address already has a name, the first one is kept.
+    pub fn insert(&mut self, address: usize, name: &str) {
+        self.names
+            .entry(address)
+            .or_insert_with(|| name.to_string());
+    }
+
+    /// Get the name of the given address.
+    pub fn name(&self, address: usize) -> Option<&str> {
+        self.names.get(&address).map(|name| name.as_str())
+    }
+
+    /// Get the address with the given name.
+    pub fn address(&self, name: &str) -> Option<usize> {
+        self.names
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(address, _)| *address)
+    }
+
+    /// Check whether there are any symbols.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Iterate over the addresses and their names in address order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.names
+            .iter()
+            .map(|(address, name)| (*address, name.as_str()))
+    }
+}
+
+impl Display for SymbolTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (address, name) in &self.names {
+            writeln!(f, "{} {}", address, name)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SymbolTable {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut symbols = Self::new();
+        for (number, line) in s.lines().enumerate() {
+            let line = line.split(';').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (address, name) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| anyhow!("line {}: expected an address and a name", number + 1))?;
+            let address = address
+                .parse()
+                .map_err(|_| anyhow!("line {}: invalid address '{}'", number + 1, address))?;
+            symbols.insert(address, name.trim());
+        }
+        Ok(symbols)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{asm::assemble_with_symbols, disasm::disassemble};
+
+    #[test]
+    fn test_symbols_round_trip() {
+        let (program, symbols) = assemble_with_symbols(
+            "
+            .const ZERO 0
+            loop: OUT P[counter]
+                  JIF I[ZERO] -> I[loop]
+            counter: .word 7
+            ",
+        )
+        .unwrap();
+        assert_eq!(symbols.address("loop"), Some(0));
+        assert_eq!(symbols.name(5), Some("counter"));
+        assert_eq!(symbols.address("ZERO"), None);
+        assert_eq!(symbols.to_string().parse::<SymbolTable>().unwrap(), symbols);
+
+        let mut listing = disassemble(&program);
+        listing.symbols = symbols;
+        let listing = listing.to_string();
+        assert!(listing.contains("loop:"));
+        assert!(listing.contains("OUT -> counter"));
+        assert!(listing.contains("JIF I[0] -> loop"));
+    }
+}
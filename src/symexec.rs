@@ -0,0 +1,580 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::ops::{Add, Mul, RangeInclusive};
+use std::rc::Rc;
+
+use anyhow::{anyhow, Error, Result};
+
+use crate::instruction::Operation;
+
+/// A value the program doesn't know until it runs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Symbol {
+    /// The nth value the program reads from its input.
+    Input(usize),
+    /// The value in memory at an address when the program starts.
+    Memory(usize),
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Symbol::Input(n) => write!(f, "in{}", n),
+            Symbol::Memory(address) => write!(f, "mem[{}]", address),
+        }
+    }
+}
+
+/// A value computed from symbols. Constants are folded as expressions are built, so an expression
+/// with no symbols in it is always a [Expr::Const].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Const(isize),
+    Symbol(Symbol),
+    Add(Rc<Expr>, Rc<Expr>),
+    Multiply(Rc<Expr>, Rc<Expr>),
+    /// 1 if the first value is less than the second, otherwise 0.
+    LessThan(Rc<Expr>, Rc<Expr>),
+    /// 1 if the values are equal, otherwise 0.
+    Equals(Rc<Expr>, Rc<Expr>),
+}
+
+impl Expr {
+    pub fn less_than(self, other: Expr) -> Expr {
+        match (self, other) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const((a < b) as isize),
+            (a, b) if a == b => Expr::Const(0),
+            (a, b) => Expr::LessThan(Rc::new(a), Rc::new(b)),
+        }
+    }
+
+    pub fn equals(self, other: Expr) -> Expr {
+        match (self, other) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const((a == b) as isize),
+            (a, b) if a == b => Expr::Const(1),
+            (a, b) => Expr::Equals(Rc::new(a), Rc::new(b)),
+        }
+    }
+
+    /// The value of the expression if it doesn't depend on any symbols.
+    pub fn constant(&self) -> Option<isize> {
+        match self {
+            Expr::Const(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The symbols the expression depends on.
+    pub fn symbols(&self) -> BTreeSet<Symbol> {
+        let mut symbols = BTreeSet::new();
+        self.collect(&mut symbols);
+        symbols
+    }
+
+    fn collect(&self, symbols: &mut BTreeSet<Symbol>) {
+        match self {
+            Expr::Const(_) => (),
+            Expr::Symbol(symbol) => {
+                symbols.insert(*symbol);
+            }
+            Expr::Add(a, b) | Expr::Multiply(a, b) | Expr::LessThan(a, b) | Expr::Equals(a, b) => {
+                a.collect(symbols);
+                b.collect(symbols);
+            }
+        }
+    }
+
+    /// Evaluate the expression with the given values for its symbols. This is `None` if a symbol
+    /// doesn't have a value.
+    pub fn evaluate(&self, values: &BTreeMap<Symbol, isize>) -> Option<isize> {
+        Some(match self {
+            Expr::Const(value) => *value,
+            Expr::Symbol(symbol) => *values.get(symbol)?,
+            Expr::Add(a, b) => a.evaluate(values)? + b.evaluate(values)?,
+            Expr::Multiply(a, b) => a.evaluate(values)? * b.evaluate(values)?,
+            Expr::LessThan(a, b) => (a.evaluate(values)? < b.evaluate(values)?) as isize,
+            Expr::Equals(a, b) => (a.evaluate(values)? == b.evaluate(values)?) as isize,
+        })
+    }
+
+    /// Replace the symbol with a value, folding whatever becomes constant.
+    pub fn substitute(&self, symbol: Symbol, value: isize) -> Expr {
+        let both = |a: &Expr, b: &Expr| (a.substitute(symbol, value), b.substitute(symbol, value));
+        match self {
+            Expr::Symbol(s) if *s == symbol => Expr::Const(value),
+            Expr::Const(_) | Expr::Symbol(_) => self.clone(),
+            Expr::Add(a, b) => {
+                let (a, b) = both(a, b);
+                a + b
+            }
+            Expr::Multiply(a, b) => {
+                let (a, b) = both(a, b);
+                a * b
+            }
+            Expr::LessThan(a, b) => {
+                let (a, b) = both(a, b);
+                a.less_than(b)
+            }
+            Expr::Equals(a, b) => {
+                let (a, b) = both(a, b);
+                a.equals(b)
+            }
+        }
+    }
+}
+
+impl Add for Expr {
+    type Output = Expr;
+
+    fn add(self, other: Expr) -> Expr {
+        match (self, other) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const(a + b),
+            (Expr::Const(0), e) | (e, Expr::Const(0)) => e,
+            (a, b) => Expr::Add(Rc::new(a), Rc::new(b)),
+        }
+    }
+}
+
+impl Mul for Expr {
+    type Output = Expr;
+
+    fn mul(self, other: Expr) -> Expr {
+        match (self, other) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const(a * b),
+            (Expr::Const(0), _) | (_, Expr::Const(0)) => Expr::Const(0),
+            (Expr::Const(1), e) | (e, Expr::Const(1)) => e,
+            (a, b) => Expr::Multiply(Rc::new(a), Rc::new(b)),
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Const(value) => write!(f, "{}", value),
+            Expr::Symbol(symbol) => write!(f, "{}", symbol),
+            Expr::Add(a, b) => write!(f, "({} + {})", a, b),
+            Expr::Multiply(a, b) => write!(f, "({} * {})", a, b),
+            Expr::LessThan(a, b) => write!(f, "({} < {})", a, b),
+            Expr::Equals(a, b) => write!(f, "({} == {})", a, b),
+        }
+    }
+}
+
+/// What the program should do for the inputs we are looking for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// Output the value at some point.
+    Output(isize),
+    /// Halt with the value at the address.
+    Memory(usize, isize),
+}
+
+/// Runs a program with symbols in place of its inputs and some of its memory to find values for
+/// them that reach a [Target]. Each symbol has a range of values it can take, which keeps solving
+/// the constraints a search rather than needing a real solver. Paths split when a jump depends on
+/// a symbol, and when an address does they split once for each value the symbol can take.
+pub struct Engine {
+    program: Vec<isize>,
+    memory: BTreeMap<usize, RangeInclusive<isize>>,
+    /// The values each input can take.
+    pub inputs: RangeInclusive<isize>,
+    /// The most paths to explore before giving up.
+    pub max_paths: usize,
+    /// The most instructions to run on a single path before giving up on it.
+    pub max_steps: usize,
+}
+
+// Why a path stopped running an instruction: either it needs to split on a symbol first or it
+// can't go on at all.
+enum Stop {
+    Split(Symbol),
+    Error(Error),
+}
+
+impl From<Error> for Stop {
+    fn from(e: Error) -> Self {
+        Stop::Error(e)
+    }
+}
+
+// What happened when a path ran an instruction.
+enum Outcome {
+    Continue,
+    Fork(Vec<Path>),
+    Done,
+    Found(BTreeMap<Symbol, isize>),
+}
+
+// A single way through the program along with what had to be true to get there.
+#[derive(Clone)]
+struct Path {
+    memory: BTreeMap<usize, Expr>,
+    instruction_pointer: usize,
+    relative_base: isize,
+    inputs: usize,
+    steps: usize,
+    // Each constraint is an expression and whether it must be non-zero.
+    constraints: Vec<(Expr, bool)>,
+    // Symbols that were given a value when the path split on them.
+    fixed: BTreeMap<Symbol, isize>,
+    symbols: BTreeSet<Symbol>,
+}
+
+impl Path {
+    fn read(&self, program: &[isize], address: usize) -> Expr {
+        match self.memory.get(&address) {
+            Some(expr) => expr.clone(),
+            None => Expr::Const(program.get(address).copied().unwrap_or_default()),
+        }
+    }
+
+    fn substitute(&mut self, symbol: Symbol, value: isize) {
+        for expr in self.memory.values_mut() {
+            *expr = expr.substitute(symbol, value);
+        }
+        for (expr, _) in self.constraints.iter_mut() {
+            *expr = expr.substitute(symbol, value);
+        }
+        self.fixed.insert(symbol, value);
+    }
+
+    // Whether any constraint is already known to be broken.
+    fn broken(&self) -> bool {
+        self.constraints
+            .iter()
+            .any(|(expr, holds)| matches!(expr.constant(), Some(value) if (value != 0) != *holds))
+    }
+}
+
+// The value of an expression that must be known to go on, or the symbol to split on to know it.
+fn known(expr: &Expr) -> Result<isize, Stop> {
+    match expr.constant() {
+        Some(value) => Ok(value),
+        None => Err(Stop::Split(*expr.symbols().iter().next().unwrap())),
+    }
+}
+
+fn address(value: isize) -> Result<usize, Stop> {
+    usize::try_from(value).map_err(|_| Stop::Error(anyhow!("negative address {}", value)))
+}
+
+impl Engine {
+    /// Create an engine for the program. Inputs can be anything from 0 to 255 until changed.
+    pub fn new(program: &[isize]) -> Self {
+        Self {
+            program: program.to_vec(),
+            memory: BTreeMap::new(),
+            inputs: 0..=255,
+            max_paths: 100_000,
+            max_steps: 100_000,
+        }
+    }
+
+    /// Treat the value at the address as a symbol that can be anything in the range.
+    pub fn symbolic(&mut self, address: usize, values: RangeInclusive<isize>) {
+        self.memory.insert(address, values);
+    }
+
+    fn values(&self, symbol: Symbol) -> RangeInclusive<isize> {
+        match symbol {
+            Symbol::Input(_) => self.inputs.clone(),
+            Symbol::Memory(address) => self.memory[&address].clone(),
+        }
+    }
+
+    /// Find values for the symbols that make the program reach the target. Symbols that don't
+    /// matter are given the lowest value they can take. This is `None` if no values work and an
+    /// error if the program can't be run symbolically or the limits were hit first.
+    pub fn solve(&self, target: Target) -> Result<Option<BTreeMap<Symbol, isize>>> {
+        let mut memory = BTreeMap::new();
+        for &address in self.memory.keys() {
+            memory.insert(address, Expr::Symbol(Symbol::Memory(address)));
+        }
+        let mut paths = vec![Path {
+            symbols: self.memory.keys().map(|&a| Symbol::Memory(a)).collect(),
+            memory,
+            instruction_pointer: 0,
+            relative_base: 0,
+            inputs: 0,
+            steps: 0,
+            constraints: Vec::new(),
+            fixed: BTreeMap::new(),
+        }];
+
+        let mut explored = 0;
+        let mut abandoned = false;
+        while let Some(mut path) = paths.pop() {
+            explored += 1;
+            if explored > self.max_paths {
+                return Err(anyhow!("gave up after {} paths", self.max_paths));
+            }
+            loop {
+                if path.steps >= self.max_steps {
+                    abandoned = true;
+                    break;
+                }
+                match self.step(&mut path, target) {
+                    Ok(Outcome::Continue) => path.steps += 1,
+                    Ok(Outcome::Fork(forks)) => {
+                        // The stack is last in first out, so this explores the first fork first.
+                        paths.extend(forks.into_iter().rev());
+                        break;
+                    }
+                    Ok(Outcome::Done) => break,
+                    Ok(Outcome::Found(values)) => return Ok(Some(values)),
+                    Err(Stop::Split(symbol)) => {
+                        let forks = self
+                            .values(symbol)
+                            .map(|value| {
+                                let mut fork = path.clone();
+                                fork.substitute(symbol, value);
+                                fork
+                            })
+                            .filter(|fork| !fork.broken())
+                            .collect::<Vec<_>>();
+                        paths.extend(forks.into_iter().rev());
+                        break;
+                    }
+                    Err(Stop::Error(e)) => {
+                        return Err(anyhow!("at {}: {}", path.instruction_pointer, e))
+                    }
+                }
+            }
+        }
+
+        match abandoned {
+            true => Err(anyhow!(
+                "no values found, but some paths ran past {} steps",
+                self.max_steps
+            )),
+            false => Ok(None),
+        }
+    }
+
+    // The address a parameter of the instruction the path is on points to.
+    fn location(&self, path: &Path, n: usize) -> Result<usize, Stop> {
+        let ip = path.instruction_pointer;
+        let cell = known(&path.read(&self.program, ip + n))?;
+        match known(&path.read(&self.program, ip))? / 10isize.pow(n as u32 + 1) % 10 {
+            0 => address(cell),
+            2 => address(path.relative_base + cell),
+            1 => Err(anyhow!("can't write to an immediate parameter").into()),
+            m => Err(anyhow!("invalid parameter mode {}", m).into()),
+        }
+    }
+
+    // The value of a parameter of the instruction the path is on.
+    fn value(&self, path: &Path, n: usize) -> Result<Expr, Stop> {
+        let ip = path.instruction_pointer;
+        match known(&path.read(&self.program, ip))? / 10isize.pow(n as u32 + 1) % 10 {
+            1 => Ok(path.read(&self.program, ip + n)),
+            _ => Ok(path.read(&self.program, self.location(path, n)?)),
+        }
+    }
+
+    // Run the instruction the path is on. Anything the instruction needs to know is worked out
+    // before the path is changed so it can be split and run again.
+    fn step(&self, path: &mut Path, target: Target) -> Result<Outcome, Stop> {
+        let ip = path.instruction_pointer;
+        let opcode = known(&path.read(&self.program, ip))?;
+        let operation =
+            Operation::from_opcode(opcode).ok_or_else(|| anyhow!("invalid opcode {}", opcode))?;
+        let next = ip + operation.arity() + 1;
+        let binary = |path: &Path, f: fn(Expr, Expr) -> Expr| -> Result<(usize, Expr), Stop> {
+            Ok((
+                self.location(path, 3)?,
+                f(self.value(path, 1)?, self.value(path, 2)?),
+            ))
+        };
+
+        let write = match operation {
+            Operation::Add => Some(binary(path, Expr::add)?),
+            Operation::Multiply => Some(binary(path, Expr::mul)?),
+            Operation::LessThan => Some(binary(path, Expr::less_than)?),
+            Operation::Equals => Some(binary(path, Expr::equals)?),
+            Operation::Input => {
+                let symbol = Symbol::Input(path.inputs);
+                let location = self.location(path, 1)?;
+                path.inputs += 1;
+                path.symbols.insert(symbol);
+                Some((location, Expr::Symbol(symbol)))
+            }
+            _ => None,
+        };
+        if let Some((location, expr)) = write {
+            path.memory.insert(location, expr);
+            path.instruction_pointer = next;
+            return Ok(Outcome::Continue);
+        }
+
+        match operation {
+            Operation::Output => {
+                let output = self.value(path, 1)?;
+                path.instruction_pointer = next;
+                match target {
+                    Target::Output(expected) => {
+                        Ok(self.check(path, output.equals(Expr::Const(expected))))
+                    }
+                    Target::Memory(..) => Ok(Outcome::Continue),
+                }
+            }
+            Operation::JumpIfTrue | Operation::JumpIfFalse => {
+                let condition = self.value(path, 1)?;
+                let jump = operation == Operation::JumpIfTrue;
+                if let Some(condition) = condition.constant() {
+                    path.instruction_pointer = match (condition != 0) == jump {
+                        true => address(known(&self.value(path, 2)?)?)?,
+                        false => next,
+                    };
+                    return Ok(Outcome::Continue);
+                }
+
+                // The jump depends on a symbol, so we follow both ways that are possible.
+                let target = address(known(&self.value(path, 2)?)?)?;
+                let forks = [(target, jump), (next, !jump)]
+                    .into_iter()
+                    .map(|(instruction_pointer, holds)| {
+                        let mut fork = path.clone();
+                        fork.instruction_pointer = instruction_pointer;
+                        fork.steps += 1;
+                        fork.constraints.push((condition.clone(), holds));
+                        fork
+                    })
+                    .filter(|fork| self.satisfy(fork).is_some())
+                    .collect();
+                Ok(Outcome::Fork(forks))
+            }
+            Operation::AdjustRelativeBaseOffset => {
+                path.relative_base += known(&self.value(path, 1)?)?;
+                path.instruction_pointer = next;
+                Ok(Outcome::Continue)
+            }
+            _ => match target {
+                Target::Memory(address, expected) => {
+                    let value = path.read(&self.program, address);
+                    Ok(
+                        match self.check(path, value.equals(Expr::Const(expected))) {
+                            Outcome::Continue => Outcome::Done,
+                            found => found,
+                        },
+                    )
+                }
+                Target::Output(_) => Ok(Outcome::Done),
+            },
+        }
+    }
+
+    // See whether the path reaches the target if the goal holds as well.
+    fn check(&self, path: &Path, goal: Expr) -> Outcome {
+        let mut goals = path.clone();
+        goals.constraints.push((goal, true));
+        match self.satisfy(&goals) {
+            Some(values) => Outcome::Found(values),
+            None => Outcome::Continue,
+        }
+    }
+
+    // Search for values of the symbols that meet all of the path's constraints. Each constraint is
+    // checked as soon as all of its symbols have a value so most of the search is cut off early.
+    fn satisfy(&self, path: &Path) -> Option<BTreeMap<Symbol, isize>> {
+        if path.broken() {
+            return None;
+        }
+        let symbols = path
+            .constraints
+            .iter()
+            .flat_map(|(expr, _)| expr.symbols())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let mut checks = vec![Vec::new(); symbols.len()];
+        for (expr, holds) in &path.constraints {
+            if let Some(last) = expr
+                .symbols()
+                .iter()
+                .filter_map(|symbol| symbols.iter().position(|s| s == symbol))
+                .max()
+            {
+                checks[last].push((expr, *holds));
+            }
+        }
+
+        let mut values = path.fixed.clone();
+        if !self.search(&symbols, &checks, &mut values) {
+            return None;
+        }
+        for &symbol in &path.symbols {
+            values
+                .entry(symbol)
+                .or_insert_with(|| *self.values(symbol).start());
+        }
+        Some(values)
+    }
+
+    fn search(
+        &self,
+        symbols: &[Symbol],
+        checks: &[Vec<(&Expr, bool)>],
+        values: &mut BTreeMap<Symbol, isize>,
+    ) -> bool {
+        let Some((&symbol, rest)) = symbols.split_first() else {
+            return true;
+        };
+        for value in self.values(symbol) {
+            values.insert(symbol, value);
+            let holds = checks[0]
+                .iter()
+                .all(|(expr, holds)| expr.evaluate(values).map(|v| v != 0) == Some(*holds));
+            if holds && self.search(rest, &checks[1..], values) {
+                return true;
+            }
+        }
+        values.remove(&symbol);
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        // The noun and verb are addresses here, so this splits on each of their values.
+        let mut engine = Engine::new(&[1, 0, 0, 0, 99, 10, 20, 30, 40, 50]);
+        engine.symbolic(1, 5..=9);
+        engine.symbolic(2, 5..=9);
+        let values = engine.solve(Target::Memory(0, 70)).unwrap().unwrap();
+        assert_eq!(
+            values,
+            BTreeMap::from([(Symbol::Memory(1), 6), (Symbol::Memory(2), 9)])
+        );
+
+        // The noun and verb are multiplied directly, so this only has to solve the constraint.
+        let mut engine = Engine::new(&[1102, 12, 2, 0, 1001, 0, 19_687_720, 0, 99]);
+        engine.symbolic(1, 0..=99);
+        engine.symbolic(2, 0..=99);
+        let values = engine
+            .solve(Target::Memory(0, 19_690_720))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            values,
+            BTreeMap::from([(Symbol::Memory(1), 40), (Symbol::Memory(2), 75)])
+        );
+
+        // Output 1 only when the input is 42, otherwise output 0.
+        let engine = Engine::new(&[
+            3, 20, 1008, 20, 42, 21, 1005, 21, 12, 104, 0, 99, 104, 1, 99,
+        ]);
+        let values = engine.solve(Target::Output(1)).unwrap().unwrap();
+        assert_eq!(values, BTreeMap::from([(Symbol::Input(0), 42)]));
+        assert_eq!(engine.solve(Target::Output(2)).unwrap(), None);
+
+        // Output the input times 3 plus 7.
+        let engine = Engine::new(&[3, 20, 1002, 20, 3, 20, 1001, 20, 7, 20, 4, 20, 99]);
+        let values = engine.solve(Target::Output(100)).unwrap().unwrap();
+        assert_eq!(values, BTreeMap::from([(Symbol::Input(0), 31)]));
+    }
+}
@@ -0,0 +1,168 @@
+use std::sync::RwLock;
+
+/// The colors of the palette every theme provides. The renderers ask for these by name and the
+/// current [Theme] decides what they look like.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ColorScheme {
+    DarkBlack,
+    LightBlack,
+    Background,
+    DarkerGrey,
+    DarkGrey,
+    Grey,
+    LightGrey,
+    LighterGrey,
+    White,
+    Blue,
+    Green,
+    Violet,
+    Orange,
+    Red,
+    Yellow,
+}
+
+/// A color as its red, green, and blue components.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+/// The colors used by the tuis and the bevy visualizations. Besides the palette, a theme has a few
+/// named roles so things like errors look the same everywhere.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    palette: [Rgb; 15],
+}
+
+// The theme everything is currently drawn with.
+static CURRENT: RwLock<Theme> = RwLock::new(Theme::CATPPUCCIN);
+
+impl Theme {
+    /// The Catppuccin Mocha theme.
+    pub const CATPPUCCIN: Theme = Theme {
+        name: "catppuccin",
+        palette: [
+            Rgb(0x11, 0x11, 0x1b),
+            Rgb(0x18, 0x18, 0x25),
+            Rgb(0x1e, 0x1e, 0x2e),
+            Rgb(0x31, 0x32, 0x44),
+            Rgb(0x45, 0x47, 0x5a),
+            Rgb(0x58, 0x5b, 0x70),
+            Rgb(0x6c, 0x70, 0x86),
+            Rgb(0x93, 0x99, 0xb2),
+            Rgb(0xf5, 0xe0, 0xdc),
+            Rgb(0x89, 0xb4, 0xfa),
+            Rgb(0xa6, 0xe3, 0xa1),
+            Rgb(0xb4, 0xbe, 0xfe),
+            Rgb(0xfa, 0xb3, 0x87),
+            Rgb(0xf3, 0x8b, 0xa8),
+            Rgb(0xf9, 0xe2, 0xaf),
+        ],
+    };
+
+    /// The Monokai Pro theme.
+    pub const MONOKAI: Theme = Theme {
+        name: "monokai",
+        palette: [
+            Rgb(0x19, 0x18, 0x1a),
+            Rgb(0x22, 0x1f, 0x22),
+            Rgb(0x2d, 0x2a, 0x2e),
+            Rgb(0x40, 0x3e, 0x41),
+            Rgb(0x5b, 0x59, 0x5c),
+            Rgb(0x72, 0x70, 0x72),
+            Rgb(0x93, 0x92, 0x93),
+            Rgb(0xc1, 0xc0, 0xc0),
+            Rgb(0xfc, 0xfc, 0xfa),
+            Rgb(0x78, 0xdc, 0xe8),
+            Rgb(0xa9, 0xdc, 0x76),
+            Rgb(0xab, 0x9d, 0xf2),
+            Rgb(0xfc, 0x98, 0x67),
+            Rgb(0xff, 0x61, 0x88),
+            Rgb(0xff, 0xd8, 0x66),
+        ],
+    };
+
+    /// All of the themes that come with the crate.
+    pub const ALL: [Theme; 2] = [Theme::CATPPUCCIN, Theme::MONOKAI];
+
+    /// Find one of the themes that come with the crate by name.
+    pub fn by_name(name: &str) -> Option<Theme> {
+        Self::ALL
+            .into_iter()
+            .find(|theme| theme.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Get the theme everything is currently drawn with.
+    pub fn current() -> Theme {
+        *CURRENT.read().unwrap()
+    }
+
+    /// Change the theme everything is drawn with.
+    pub fn set_current(theme: Theme) {
+        *CURRENT.write().unwrap() = theme;
+    }
+
+    /// Switch to the theme after the current one and return it.
+    pub fn cycle() -> Theme {
+        let current = Self::current();
+        let index = Self::ALL.iter().position(|theme| *theme == current);
+        let next = match index {
+            Some(index) => Self::ALL[(index + 1) % Self::ALL.len()],
+            None => Self::ALL[0],
+        };
+        Self::set_current(next);
+        next
+    }
+
+    /// Get the color this theme uses for the given color of the palette.
+    pub fn color(&self, color: ColorScheme) -> Rgb {
+        self.palette[color as usize]
+    }
+
+    /// The color behind everything.
+    pub fn background(&self) -> Rgb {
+        self.color(ColorScheme::Background)
+    }
+
+    /// The color of text.
+    pub fn foreground(&self) -> Rgb {
+        self.color(ColorScheme::White)
+    }
+
+    /// The color used to draw attention to borders and titles.
+    pub fn accent(&self) -> Rgb {
+        self.color(ColorScheme::Violet)
+    }
+
+    /// The color of errors.
+    pub fn error(&self) -> Rgb {
+        self.color(ColorScheme::Red)
+    }
+
+    /// The color of whatever is selected or active.
+    pub fn highlight(&self) -> Rgb {
+        self.color(ColorScheme::Green)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::CATPPUCCIN
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_theme() {
+        assert_eq!(Theme::by_name("Monokai"), Some(Theme::MONOKAI));
+        assert_eq!(Theme::by_name("solarized"), None);
+        assert_eq!(Theme::MONOKAI.background(), Rgb(0x2d, 0x2a, 0x2e));
+        assert_eq!(
+            Theme::CATPPUCCIN.color(ColorScheme::Yellow),
+            Rgb(0xf9, 0xe2, 0xaf)
+        );
+        assert_eq!(Theme::CATPPUCCIN.error(), Rgb(0xf3, 0x8b, 0xa8));
+    }
+}
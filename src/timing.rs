@@ -0,0 +1,137 @@
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::instruction::Instruction;
+
+/// What a process is doing right now.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProcessStatus {
+    /// Waiting to be told to run.
+    #[default]
+    Paused,
+    /// Running instructions.
+    Running,
+    /// Waiting for input.
+    Blocked,
+    /// Done running.
+    Halted,
+}
+
+impl Display for ProcessStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProcessStatus::Paused => write!(f, "Paused"),
+            ProcessStatus::Running => write!(f, "Running"),
+            ProcessStatus::Blocked => write!(f, "Blocked"),
+            ProcessStatus::Halted => write!(f, "Halted"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    status: ProcessStatus,
+    running: Duration,
+    blocked: Duration,
+    since: Option<Instant>,
+}
+
+impl Inner {
+    // Add the time since the last change to whatever the process was doing.
+    fn account(&mut self) {
+        let now = Instant::now();
+        if let Some(since) = self.since.replace(now) {
+            match self.status {
+                ProcessStatus::Running => self.running += now - since,
+                ProcessStatus::Blocked => self.blocked += now - since,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// How long a process has spent running and waiting for input, along with its status. Clones
+/// share the same timing so the process can update it while the tui shows it.
+#[derive(Debug, Clone, Default)]
+pub struct Timing {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Timing {
+    /// Note that the process is about to start running.
+    pub fn start(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.account();
+        inner.status = ProcessStatus::Running;
+    }
+
+    /// Note that the process is about to run the given instruction. Input instructions count as
+    /// blocked until the next instruction since they wait for a value.
+    pub fn before(&self, instruction: &Instruction) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.account();
+        inner.status = match instruction {
+            Instruction::Input(_) => ProcessStatus::Blocked,
+            _ => ProcessStatus::Running,
+        };
+    }
+
+    /// Note that the process stopped running, either because it halted or because it's waiting
+    /// to be told to run again.
+    pub fn stop(&self, halted: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.account();
+        inner.since = None;
+        inner.status = match halted {
+            true => ProcessStatus::Halted,
+            false => ProcessStatus::Paused,
+        };
+    }
+
+    /// Get what the process is doing right now.
+    pub fn status(&self) -> ProcessStatus {
+        self.inner.lock().unwrap().status
+    }
+
+    /// Get the wall-clock time the process has spent running instructions.
+    pub fn running(&self) -> Duration {
+        let mut inner = self.inner.lock().unwrap();
+        inner.account();
+        inner.running
+    }
+
+    /// Get the wall-clock time the process has spent waiting for input.
+    pub fn blocked(&self) -> Duration {
+        let mut inner = self.inner.lock().unwrap();
+        inner.account();
+        inner.blocked
+    }
+
+    /// Forget the times and go back to paused.
+    pub fn clear(&self) {
+        *self.inner.lock().unwrap() = Inner::default();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parameter::Parameter;
+
+    #[test]
+    fn test_timing() {
+        let timing = Timing::default();
+        assert_eq!(timing.status(), ProcessStatus::Paused);
+        timing.start();
+        assert_eq!(timing.status(), ProcessStatus::Running);
+        std::thread::sleep(Duration::from_millis(1));
+        timing.before(&Instruction::Input(Parameter::Position(0)));
+        assert_eq!(timing.status(), ProcessStatus::Blocked);
+        timing.stop(true);
+        assert_eq!(timing.status(), ProcessStatus::Halted);
+        assert!(timing.running() >= Duration::from_millis(1));
+        timing.clear();
+        assert_eq!(timing.running(), Duration::ZERO);
+    }
+}
@@ -1,27 +1,34 @@
 use std::io::stdout;
 use std::time::Duration;
 
-use crate::breakpoint::Breakpoint;
+use crate::breakpoint::{Action, Breakpoint, Scope, Template};
+use crate::condition::Condition;
 use crate::event::Event;
-use crate::instruction::Instruction;
-use crate::renderer::{RendererState, WindowState};
+use crate::instruction::{Instruction, InstructionKind};
+use crate::process::State;
+use crate::renderer::{Mood, RendererState, WindowState};
+use crate::theme::Theme;
 use crate::{app::App, event::EventHandler};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use crossterm::{
     event::{
-        DisableMouseCapture, EnableMouseCapture, KeyCode, KeyEvent, KeyEventKind, MouseEvent,
-        MouseEventKind,
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        KeyCode, KeyEvent, KeyEventKind, MouseEvent, MouseEventKind,
     },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use enum_iterator::all;
 use ratatui::{
     backend::{Backend, CrosstermBackend},
     Terminal,
 };
+use tokio::sync::oneshot::{self, error::TryRecvError};
 
-pub async fn run(app: App) -> Result<()> {
+/// Run the tui until the user quits. The app is returned so things like the breakpoints can be
+/// saved.
+pub async fn run(app: App) -> Result<App> {
     // Setup our tui, and state.
     let backend = CrosstermBackend::new(stdout());
     let terminal = Terminal::new(backend)?;
@@ -31,15 +38,30 @@ pub async fn run(app: App) -> Result<()> {
     // Start our event handler.
     let mut events = EventHandler::new(Duration::from_millis(16));
 
-    // Our main loop. We draw and then handle events.
-    while tui.running {
-        tui.draw()?;
-        let event = events.next().await?;
-        tui.handle_event(event).await?;
+    // Our main loop. We draw and then handle events. An error stops the loop but we still clean
+    // up so the terminal is usable.
+    let mut result = Ok(());
+    while tui.running && result.is_ok() {
+        result = async {
+            tui.draw()?;
+            let event = events.next().await?;
+            #[cfg(feature = "tracing")]
+            tracing::trace!(?event, "event");
+            tui.handle_event(event).await
+        }
+        .await;
+    }
+
+    #[cfg(feature = "tracing")]
+    if let Err(e) = &result {
+        tracing::error!(error = %e, "tui stopped");
     }
 
     // Cleanup the tui.
-    tui.exit()
+    events.close().await;
+    tui.exit().await?;
+    result?;
+    Ok(tui.app)
 }
 
 /// The tui for the application.
@@ -47,6 +69,7 @@ pub struct Tui<B: Backend> {
     terminal: Terminal<B>,
     app: App,
     renderer_state: RendererState,
+    pending: Option<oneshot::Receiver<bool>>,
     pub running: bool,
 }
 
@@ -58,13 +81,19 @@ impl<B: Backend> Tui<B> {
             terminal,
             app,
             renderer_state,
+            pending: None,
             running: true,
         }
     }
 
     /// Setup the terminal for the tui. This should be called before the tui is run.
     pub fn init(&mut self) -> Result<()> {
-        execute!(stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+        execute!(
+            stdout(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )?;
         enable_raw_mode()?;
 
         let panic_hook = std::panic::take_hook();
@@ -80,7 +109,12 @@ impl<B: Backend> Tui<B> {
 
     fn reset() -> Result<()> {
         disable_raw_mode()?;
-        execute!(stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+        execute!(
+            stdout(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableBracketedPaste
+        )?;
         Ok(())
     }
 
@@ -91,8 +125,10 @@ impl<B: Backend> Tui<B> {
         Ok(())
     }
 
-    /// Reset the terminal to its original state. This should be called after the tui is done.
-    pub fn exit(&mut self) -> Result<()> {
+    /// Stop the processes and reset the terminal to its original state. This should be called
+    /// after the tui is done.
+    pub async fn exit(&mut self) -> Result<()> {
+        self.app.shutdown().await;
         Self::reset()?;
         self.terminal.show_cursor()?;
         Ok(())
@@ -103,38 +139,190 @@ impl<B: Backend> Tui<B> {
         match event {
             Event::Key(key) if key.kind == KeyEventKind::Press => self.handle_key(key).await,
             Event::Mouse(mouse) => self.handle_mouse(mouse).await,
-            _ => Ok(()),
+            Event::Tick => self.handle_tick().await,
+            Event::Resize(_, _) => self.handle_resize(),
+            Event::Paste(text) => self.handle_paste(&text).await,
+            Event::Error(e) => Err(anyhow!("reading terminal events: {}", e)),
+            Event::Key(_) => Ok(()),
         }
     }
 
-    async fn handle_mouse(&mut self, mouse: MouseEvent) -> Result<()> {
-        match mouse.kind {
-            MouseEventKind::ScrollUp => {
-                self.renderer_state.scroll_up();
+    // Draw with the new size right away so the renderer knows how many rows fit now, then make
+    // sure the selection is still on screen.
+    fn handle_resize(&mut self) -> Result<()> {
+        self.terminal.autoresize()?;
+        self.draw()?;
+        self.renderer_state.resize();
+        Ok(())
+    }
+
+    // Pasting into the input console sends every complete line to the process and leaves whatever
+    // follows the last newline to be finished by hand. The other text entry popups only take the
+    // first line.
+    async fn handle_paste(&mut self, text: &str) -> Result<()> {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        match self.renderer_state.window_state {
+            WindowState::Input => {
+                let text = format!("{}{}", self.renderer_state.text, text);
+                let mut lines = text.split('\n').collect::<Vec<_>>();
+                let rest = lines.pop().unwrap_or_default().to_string();
+                let active_process = self.renderer_state.active_process;
+                for line in lines {
+                    if let Err(e) = self.app.send_input(active_process, line).await {
+                        self.renderer_state.text_error = Some(e.to_string());
+                        return Ok(());
+                    }
+                }
+                self.renderer_state.text = rest;
             }
-            MouseEventKind::ScrollDown => {
-                self.renderer_state.scroll_down();
+            WindowState::Search
+            | WindowState::Registers
+            | WindowState::Export
+            | WindowState::Open
+            | WindowState::BreakpointCondition
+            | WindowState::BreakpointTrace
+            | WindowState::Watch => {
+                let line = text.lines().next().unwrap_or_default();
+                self.renderer_state.text.push_str(line);
             }
             _ => {}
         }
         Ok(())
     }
 
+    // Keep stepping the active process while we are running continuously. We only ask for more
+    // steps once the last ones are done, so a process waiting on input doesn't pile them up.
+    async fn handle_tick(&mut self) -> Result<()> {
+        if !self.renderer_state.run {
+            return Ok(());
+        }
+        if let Some(pending) = &mut self.pending {
+            match pending.try_recv() {
+                Err(TryRecvError::Empty) => return Ok(()),
+                Ok(false) => self.pending = None,
+                Ok(true) => {
+                    self.pending = None;
+                    self.renderer_state.run = false;
+                    self.renderer_state.mood = Mood::Breakpoint;
+                    return Ok(());
+                }
+                Err(TryRecvError::Closed) => {
+                    // A halted process drops the notification without answering, anything else
+                    // means the process has stopped for good.
+                    self.pending = None;
+                    self.renderer_state.run = false;
+                    if !self
+                        .app
+                        .with_state(self.renderer_state.active_process, |state| state.halted)
+                    {
+                        self.renderer_state.mood = Mood::Error("Something broke!".to_string());
+                    }
+                    return Ok(());
+                }
+            }
+        }
+
+        let active_process = self.renderer_state.active_process;
+        if let Some(error) = self.app.last_error(active_process) {
+            self.renderer_state.run = false;
+            self.renderer_state.mood = Mood::Error(error);
+            return Ok(());
+        }
+        if self.app.with_state(active_process, |state| state.halted) {
+            self.renderer_state.run = false;
+            return Ok(());
+        }
+        let steps = self.renderer_state.run_speed;
+        self.pending = Some(self.app.step_count(active_process, steps).await?);
+        Ok(())
+    }
+
+    async fn handle_mouse(&mut self, mouse: MouseEvent) -> Result<()> {
+        match mouse.kind {
+            // The wheel moves the memory view on its own but the selection everywhere else.
+            MouseEventKind::ScrollUp => match self.renderer_state.window_state {
+                WindowState::Main => self.renderer_state.scroll_view(-1),
+                _ => self.renderer_state.scroll_up(),
+            },
+            MouseEventKind::ScrollDown => match self.renderer_state.window_state {
+                WindowState::Main => self.renderer_state.scroll_view(1),
+                _ => self.renderer_state.scroll_down(),
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Load the program at the given path into a new process and switch to it.
+    async fn open(&mut self, path: &str) -> Result<()> {
+        let path = std::path::Path::new(path.trim());
+        let program = std::fs::read_to_string(path)?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let index = self.app.open(&name, &program).await?;
+        self.renderer_state.add_process(&self.app.state(index));
+        self.renderer_state.active_process = index;
+        Ok(())
+    }
+
+    // New breakpoints belong to the active process until they are made global.
+    fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        let scope = Scope::Process(self.renderer_state.active_process);
+        self.app.add_breakpoint(scope, breakpoint);
+    }
+
     async fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
         match (self.renderer_state.window_state, key.code) {
             // Main window
             (WindowState::Main, KeyCode::Char('s')) => {
-                return self.app.step(self.renderer_state.active_process).await
+                self.renderer_state.mood = Mood::Idle;
+                return self.app.step(self.renderer_state.active_process).await;
+            }
+            (WindowState::Main, KeyCode::Char('p')) => {
+                self.renderer_state.run = false;
+                self.renderer_state.mood = Mood::Idle;
+                return self.app.interrupt(self.renderer_state.active_process).await;
             }
             (WindowState::Main, KeyCode::Char('c')) => {
                 return self
                     .app
-                    .step_until(
-                        self.renderer_state.active_process,
-                        self.renderer_state.breakpoints.clone(),
-                    )
+                    .step_until(self.renderer_state.active_process)
                     .await
             }
+            (WindowState::Main, KeyCode::Char('<')) => {
+                self.renderer_state.shrink_sidebar();
+            }
+            (WindowState::Main, KeyCode::Char('>')) => {
+                self.renderer_state.grow_sidebar();
+            }
+            (WindowState::Main, KeyCode::F(n)) => {
+                self.renderer_state
+                    .toggle_pane(n.saturating_sub(1) as usize);
+            }
+            (WindowState::Main, KeyCode::Char('o')) => {
+                self.renderer_state.rotate_panes();
+            }
+            (WindowState::Main, KeyCode::Char('L')) => {
+                self.renderer_state.preset = self.renderer_state.preset.next();
+            }
+            (WindowState::Main, KeyCode::Char('T')) => {
+                Theme::cycle();
+            }
+            (WindowState::Main, KeyCode::Char('r')) => {
+                self.renderer_state.run = !self.renderer_state.run;
+                self.renderer_state.mood = match self.renderer_state.run {
+                    true => Mood::Running,
+                    false => Mood::Idle,
+                };
+            }
+            (WindowState::Main, KeyCode::Char('+')) => {
+                self.renderer_state.speed_up();
+            }
+            (WindowState::Main, KeyCode::Char('-')) => {
+                self.renderer_state.slow_down();
+            }
             (WindowState::Main, KeyCode::Char('q')) | (WindowState::Main, KeyCode::Esc) => {
                 self.running = false;
             }
@@ -144,6 +332,63 @@ impl<B: Backend> Tui<B> {
             (WindowState::Main, KeyCode::Char('B')) => {
                 self.renderer_state.window_state = WindowState::BreakpointList;
             }
+            (WindowState::Main, KeyCode::Char('g')) => {
+                self.renderer_state.chosen_memory_location = 0;
+                self.renderer_state.window_state = WindowState::GotoAddress;
+            }
+            (WindowState::Main, KeyCode::Char('f')) => {
+                self.renderer_state.follow = !self.renderer_state.follow;
+            }
+            (WindowState::Main, KeyCode::Char('a')) => {
+                self.renderer_state.ascii = !self.renderer_state.ascii;
+            }
+            (WindowState::Main, KeyCode::Char('h')) => {
+                self.renderer_state.heatmap = !self.renderer_state.heatmap;
+            }
+            (WindowState::Main, KeyCode::Char('l')) => {
+                self.renderer_state.window_state = WindowState::ChannelList;
+            }
+            (WindowState::Main, KeyCode::Char('x')) => {
+                self.renderer_state.text = "session".to_string();
+                self.renderer_state.window_state = WindowState::Export;
+            }
+            (WindowState::Main, KeyCode::Char('D')) => {
+                self.renderer_state.cycle_compare();
+            }
+            (WindowState::Main, KeyCode::Char('O')) => {
+                self.renderer_state.window_state = WindowState::Open;
+            }
+            (WindowState::Main, KeyCode::Char('w')) => {
+                self.renderer_state.window_state = WindowState::Watch;
+            }
+            (WindowState::Main, KeyCode::Char('R')) => {
+                self.renderer_state.run = false;
+                self.renderer_state.mood = Mood::Idle;
+                self.pending = None;
+                return self.app.reset(self.renderer_state.active_process).await;
+            }
+            (WindowState::Main, KeyCode::Char('e')) => {
+                // Start with the current values so only the one being changed needs editing.
+                let state = self.app.state(self.renderer_state.active_process);
+                self.renderer_state.text = format!(
+                    "ip {} rb {}",
+                    state.instruction_pointer, state.relative_base
+                );
+                self.renderer_state.window_state = WindowState::Registers;
+            }
+            (WindowState::Main, KeyCode::Char('i')) => {
+                self.renderer_state.window_state = WindowState::Input;
+            }
+            (WindowState::Main, KeyCode::Char('/')) => {
+                self.renderer_state.window_state = WindowState::Search;
+            }
+            (WindowState::Main, KeyCode::Char('n')) | (WindowState::Main, KeyCode::Char('N')) => {
+                let active_process = self.renderer_state.active_process;
+                self.renderer_state.search_next(
+                    &self.app.state(active_process),
+                    key.code == KeyCode::Char('n'),
+                );
+            }
             (WindowState::Main, KeyCode::Char(c)) => {
                 if let Some(i) = c.to_digit(10) {
                     let i = i as usize;
@@ -158,12 +403,96 @@ impl<B: Backend> Tui<B> {
             (WindowState::Main, KeyCode::Down) => {
                 self.renderer_state.scroll_down();
             }
+            (WindowState::Main, KeyCode::Left) => {
+                self.renderer_state.cursor_left();
+            }
+            (WindowState::Main, KeyCode::Right) => {
+                self.renderer_state.cursor_right();
+            }
+            (WindowState::Main, KeyCode::PageUp) => {
+                self.renderer_state.page_up();
+            }
+            (WindowState::Main, KeyCode::PageDown) => {
+                self.renderer_state.page_down();
+            }
+            (WindowState::Main, KeyCode::Home) => {
+                self.renderer_state.home();
+            }
+            (WindowState::Main, KeyCode::End) => {
+                self.renderer_state.end();
+            }
+
+            // Channels pane, while it's selected
+            (WindowState::ChannelList, KeyCode::Char('q'))
+            | (WindowState::ChannelList, KeyCode::Esc) => {
+                self.renderer_state.window_state = WindowState::Main;
+            }
+            (WindowState::ChannelList, KeyCode::Char('k'))
+            | (WindowState::ChannelList, KeyCode::Up) => {
+                self.renderer_state.scroll_up();
+            }
+            (WindowState::ChannelList, KeyCode::Char('j'))
+            | (WindowState::ChannelList, KeyCode::Down) => {
+                self.renderer_state.scroll_down();
+            }
+            (WindowState::ChannelList, KeyCode::Enter) => {
+                self.renderer_state.channel_scroll = 0;
+                self.renderer_state.window_state = WindowState::ChannelDetail;
+            }
+
+            // Channel contents window
+            (WindowState::ChannelDetail, KeyCode::Char('q'))
+            | (WindowState::ChannelDetail, KeyCode::Esc) => {
+                self.renderer_state.window_state = WindowState::ChannelList;
+            }
+            (WindowState::ChannelDetail, KeyCode::Char('k'))
+            | (WindowState::ChannelDetail, KeyCode::Up) => {
+                self.renderer_state.scroll_up();
+            }
+            (WindowState::ChannelDetail, KeyCode::Char('j'))
+            | (WindowState::ChannelDetail, KeyCode::Down) => {
+                self.renderer_state.scroll_down();
+            }
 
             // Breakpoint list window
             (WindowState::BreakpointList, KeyCode::Char('q'))
             | (WindowState::BreakpointList, KeyCode::Esc) => {
                 self.renderer_state.window_state = WindowState::Main;
             }
+            (WindowState::BreakpointList, KeyCode::Char('k'))
+            | (WindowState::BreakpointList, KeyCode::Up) => {
+                self.renderer_state.scroll_up();
+            }
+            (WindowState::BreakpointList, KeyCode::Char('j'))
+            | (WindowState::BreakpointList, KeyCode::Down) => {
+                self.renderer_state.scroll_down();
+            }
+            (WindowState::BreakpointList, KeyCode::Char('d')) => {
+                self.app
+                    .remove_breakpoint(self.renderer_state.chosen_breakpoint);
+            }
+            (WindowState::BreakpointList, KeyCode::Char('t')) => {
+                // Turn a tracepoint back into a breakpoint, or ask for the message to log.
+                let index = self.renderer_state.chosen_breakpoint;
+                match self.app.breakpoints().get(index) {
+                    Some((_, _, Action::Log(_))) => {
+                        self.app.set_breakpoint_action(index, Action::Stop);
+                    }
+                    Some(_) => self.renderer_state.window_state = WindowState::BreakpointTrace,
+                    None => {}
+                }
+            }
+            (WindowState::BreakpointList, KeyCode::Char('g')) => {
+                // Toggle between global and belonging to the active process.
+                let index = self.renderer_state.chosen_breakpoint;
+                if let Some((scope, _, _)) = self.app.breakpoints().get(index) {
+                    let scope = match scope {
+                        Scope::Global => Scope::Process(self.renderer_state.active_process),
+                        Scope::Process(_) => Scope::Global,
+                    };
+                    self.app.set_breakpoint_scope(index, scope);
+                }
+            }
 
             // Breakpoint type window
             (WindowState::BreakpointType, KeyCode::Char('q'))
@@ -173,33 +502,281 @@ impl<B: Backend> Tui<B> {
             (WindowState::BreakpointType, KeyCode::Char('m')) => {
                 self.renderer_state.window_state = WindowState::BreakpointMemory;
             }
+            (WindowState::BreakpointType, KeyCode::Char('p')) => {
+                self.renderer_state.window_state = WindowState::BreakpointPointer;
+            }
             (WindowState::BreakpointType, KeyCode::Char('i')) => {
                 self.renderer_state.window_state = WindowState::BreakpointInstruction;
             }
+            (WindowState::BreakpointType, KeyCode::Char('k')) => {
+                self.renderer_state.window_state = WindowState::BreakpointKind;
+            }
+            (WindowState::BreakpointType, KeyCode::Char('c')) => {
+                self.renderer_state.window_state = WindowState::BreakpointCondition;
+            }
+            (WindowState::BreakpointType, KeyCode::Char('r')) => {
+                self.add_breakpoint(Breakpoint::RelativeBaseChanged(None));
+                self.renderer_state.window_state = WindowState::Main;
+            }
 
-            // Breakpoint memory window
-            (WindowState::BreakpointMemory, KeyCode::Char('q'))
-            | (WindowState::BreakpointMemory, KeyCode::Esc) => {
+            // Breakpoint memory, instruction pointer, and goto windows
+            (
+                WindowState::BreakpointMemory
+                | WindowState::BreakpointPointer
+                | WindowState::GotoAddress,
+                KeyCode::Char('q') | KeyCode::Esc,
+            ) => {
                 self.renderer_state.window_state = WindowState::Main;
             }
-            (WindowState::BreakpointMemory, KeyCode::Char(c)) => {
+            (
+                WindowState::BreakpointMemory
+                | WindowState::BreakpointPointer
+                | WindowState::GotoAddress,
+                KeyCode::Char(c),
+            ) => {
                 if let Some(i) = c.to_digit(10) {
                     self.renderer_state.chosen_memory_location *= 10;
                     self.renderer_state.chosen_memory_location += i as usize;
                 }
             }
-            (WindowState::BreakpointMemory, KeyCode::Backspace) => {
+            (
+                WindowState::BreakpointMemory
+                | WindowState::BreakpointPointer
+                | WindowState::GotoAddress,
+                KeyCode::Backspace,
+            ) => {
                 self.renderer_state.chosen_memory_location /= 10;
             }
-            (WindowState::BreakpointMemory, KeyCode::Enter) => {
+            (WindowState::GotoAddress, KeyCode::Enter) => {
+                let active_process = self.renderer_state.active_process;
+                let location = self.renderer_state.chosen_memory_location;
                 self.renderer_state
-                    .breakpoints
-                    .add(Breakpoint::MemoryLocation(
-                        self.renderer_state.chosen_memory_location,
-                    ));
+                    .goto(&self.app.state(active_process), location);
                 self.renderer_state.chosen_memory_location = 0;
                 self.renderer_state.window_state = WindowState::Main;
             }
+            (WindowState::BreakpointMemory | WindowState::BreakpointPointer, KeyCode::Enter) => {
+                let location = self.renderer_state.chosen_memory_location;
+                let breakpoint = match self.renderer_state.window_state {
+                    WindowState::BreakpointPointer => Breakpoint::InstructionPointer(location),
+                    _ => Breakpoint::MemoryLocation(location),
+                };
+                self.add_breakpoint(breakpoint);
+                self.renderer_state.chosen_memory_location = 0;
+                self.renderer_state.window_state = WindowState::Main;
+            }
+
+            // Export window. This is text entry, so only escape leaves it.
+            (WindowState::Export, KeyCode::Esc) => {
+                self.renderer_state.text.clear();
+                self.renderer_state.text_error = None;
+                self.renderer_state.window_state = WindowState::Main;
+            }
+            (WindowState::Export, KeyCode::Char(c)) => {
+                self.renderer_state.text.push(c);
+            }
+            (WindowState::Export, KeyCode::Backspace) => {
+                self.renderer_state.text.pop();
+            }
+            (WindowState::Export, KeyCode::Enter) => {
+                let active_process = self.renderer_state.active_process;
+                match self.app.export(active_process, &self.renderer_state.text) {
+                    Ok(()) => {
+                        self.renderer_state.text.clear();
+                        self.renderer_state.text_error = None;
+                        self.renderer_state.window_state = WindowState::Main;
+                    }
+                    Err(e) => self.renderer_state.text_error = Some(e.to_string()),
+                }
+            }
+
+            // Open window. This is text entry, so only escape leaves it.
+            (WindowState::Open, KeyCode::Esc) => {
+                self.renderer_state.text.clear();
+                self.renderer_state.text_error = None;
+                self.renderer_state.window_state = WindowState::Main;
+            }
+            (WindowState::Open, KeyCode::Char(c)) => {
+                self.renderer_state.text.push(c);
+            }
+            (WindowState::Open, KeyCode::Backspace) => {
+                self.renderer_state.text.pop();
+            }
+            (WindowState::Open, KeyCode::Enter) => {
+                match self.open(&self.renderer_state.text.clone()).await {
+                    Ok(()) => {
+                        self.renderer_state.text.clear();
+                        self.renderer_state.text_error = None;
+                        self.renderer_state.window_state = WindowState::Main;
+                    }
+                    Err(e) => self.renderer_state.text_error = Some(e.to_string()),
+                }
+            }
+
+            // Registers window. This is text entry, so only escape leaves it.
+            (WindowState::Registers, KeyCode::Esc) => {
+                self.renderer_state.text.clear();
+                self.renderer_state.text_error = None;
+                self.renderer_state.window_state = WindowState::Main;
+            }
+            (WindowState::Registers, KeyCode::Char(c)) => {
+                self.renderer_state.text.push(c);
+            }
+            (WindowState::Registers, KeyCode::Backspace) => {
+                self.renderer_state.text.pop();
+            }
+            (WindowState::Registers, KeyCode::Enter) => {
+                let active_process = self.renderer_state.active_process;
+                let state = self.app.state(active_process);
+                match parse_registers(&self.renderer_state.text, &state) {
+                    Ok((instruction_pointer, relative_base)) => {
+                        self.app
+                            .set_registers(active_process, instruction_pointer, relative_base)
+                            .await?;
+                        self.renderer_state.text.clear();
+                        self.renderer_state.text_error = None;
+                        self.renderer_state.window_state = WindowState::Main;
+                    }
+                    Err(e) => self.renderer_state.text_error = Some(e.to_string()),
+                }
+            }
+
+            // Input window. This is text entry, so only escape leaves it.
+            (WindowState::Input, KeyCode::Esc) => {
+                self.renderer_state.text.clear();
+                self.renderer_state.text_error = None;
+                self.renderer_state.window_state = WindowState::Main;
+            }
+            (WindowState::Input, KeyCode::Char(c)) => {
+                self.renderer_state.text.push(c);
+            }
+            (WindowState::Input, KeyCode::Backspace) => {
+                self.renderer_state.text.pop();
+            }
+            (WindowState::Input, KeyCode::Enter) => {
+                let active_process = self.renderer_state.active_process;
+                match self
+                    .app
+                    .send_input(active_process, &self.renderer_state.text)
+                    .await
+                {
+                    Ok(()) => {
+                        self.renderer_state.text.clear();
+                        self.renderer_state.text_error = None;
+                        self.renderer_state.window_state = WindowState::Main;
+                    }
+                    Err(e) => self.renderer_state.text_error = Some(e.to_string()),
+                }
+            }
+
+            // Memory search window. This is text entry, so only escape leaves it.
+            (WindowState::Search, KeyCode::Esc) => {
+                self.renderer_state.text.clear();
+                self.renderer_state.text_error = None;
+                self.renderer_state.window_state = WindowState::Main;
+            }
+            (WindowState::Search, KeyCode::Char(c)) => {
+                self.renderer_state.text.push(c);
+            }
+            (WindowState::Search, KeyCode::Backspace) => {
+                self.renderer_state.text.pop();
+            }
+            (WindowState::Search, KeyCode::Enter) => {
+                let active_process = self.renderer_state.active_process;
+                let query = self.renderer_state.text.clone();
+                match self
+                    .renderer_state
+                    .search(&self.app.state(active_process), &query)
+                {
+                    Ok(()) => {
+                        self.renderer_state.text.clear();
+                        self.renderer_state.text_error = None;
+                        self.renderer_state.window_state = WindowState::Main;
+                    }
+                    Err(e) => self.renderer_state.text_error = Some(e.to_string()),
+                }
+            }
+
+            // Breakpoint condition window. This is text entry, so only escape leaves it.
+            (WindowState::BreakpointCondition, KeyCode::Esc) => {
+                self.renderer_state.text.clear();
+                self.renderer_state.text_error = None;
+                self.renderer_state.window_state = WindowState::Main;
+            }
+            (WindowState::BreakpointCondition, KeyCode::Char(c)) => {
+                self.renderer_state.text.push(c);
+            }
+            (WindowState::BreakpointCondition, KeyCode::Backspace) => {
+                self.renderer_state.text.pop();
+            }
+            (WindowState::BreakpointCondition, KeyCode::Enter) => {
+                match self.renderer_state.text.parse::<Condition>() {
+                    Ok(condition) => {
+                        self.add_breakpoint(Breakpoint::Condition(condition));
+                        self.renderer_state.text.clear();
+                        self.renderer_state.text_error = None;
+                        self.renderer_state.window_state = WindowState::Main;
+                    }
+                    Err(e) => self.renderer_state.text_error = Some(e.to_string()),
+                }
+            }
+
+            // Tracepoint message window. This is text entry, so only escape leaves it.
+            (WindowState::BreakpointTrace, KeyCode::Esc) => {
+                self.renderer_state.text.clear();
+                self.renderer_state.text_error = None;
+                self.renderer_state.window_state = WindowState::BreakpointList;
+            }
+            (WindowState::BreakpointTrace, KeyCode::Char(c)) => {
+                self.renderer_state.text.push(c);
+            }
+            (WindowState::BreakpointTrace, KeyCode::Backspace) => {
+                self.renderer_state.text.pop();
+            }
+            (WindowState::BreakpointTrace, KeyCode::Enter) => {
+                match self.renderer_state.text.parse::<Template>() {
+                    Ok(template) => {
+                        self.app.set_breakpoint_action(
+                            self.renderer_state.chosen_breakpoint,
+                            Action::Log(template),
+                        );
+                        self.renderer_state.text.clear();
+                        self.renderer_state.text_error = None;
+                        self.renderer_state.window_state = WindowState::BreakpointList;
+                    }
+                    Err(e) => self.renderer_state.text_error = Some(e.to_string()),
+                }
+            }
+
+            // Watch window. This is text entry, so only escape leaves it. Watching an expression
+            // that's already watched stops watching it.
+            (WindowState::Watch, KeyCode::Esc) => {
+                self.renderer_state.text.clear();
+                self.renderer_state.text_error = None;
+                self.renderer_state.window_state = WindowState::Main;
+            }
+            (WindowState::Watch, KeyCode::Char(c)) => {
+                self.renderer_state.text.push(c);
+            }
+            (WindowState::Watch, KeyCode::Backspace) => {
+                self.renderer_state.text.pop();
+            }
+            (WindowState::Watch, KeyCode::Enter) => {
+                match self.renderer_state.text.parse::<Condition>() {
+                    Ok(watch) => {
+                        match self.app.watches().iter().position(|w| *w == watch) {
+                            Some(index) => {
+                                self.app.remove_watch(index);
+                            }
+                            None => self.app.add_watch(watch),
+                        }
+                        self.renderer_state.text.clear();
+                        self.renderer_state.text_error = None;
+                        self.renderer_state.window_state = WindowState::Main;
+                    }
+                    Err(e) => self.renderer_state.text_error = Some(e.to_string()),
+                }
+            }
 
             // Breakpoint instruction window
             (WindowState::BreakpointInstruction, KeyCode::Char('q'))
@@ -219,16 +796,65 @@ impl<B: Backend> Tui<B> {
                 self.renderer_state.scroll_down();
             }
             (WindowState::BreakpointInstruction, KeyCode::Enter) => {
-                let instruction =
-                    Instruction::from(Instruction::NAMES[self.renderer_state.chosen_instruction]);
-                self.renderer_state
-                    .breakpoints
-                    .add(Breakpoint::Instruction(instruction));
+                let operation = Instruction::all()
+                    .nth(self.renderer_state.chosen_instruction)
+                    .unwrap();
+                let instruction = Instruction::from(operation);
+                self.add_breakpoint(Breakpoint::Instruction(instruction));
                 self.renderer_state.chosen_instruction = 0;
                 self.renderer_state.window_state = WindowState::Main;
             }
+
+            // Breakpoint kind window
+            (WindowState::BreakpointKind, KeyCode::Char('q'))
+            | (WindowState::BreakpointKind, KeyCode::Esc) => {
+                self.renderer_state.window_state = WindowState::Main;
+            }
+            (WindowState::BreakpointKind, KeyCode::Char('k'))
+            | (WindowState::BreakpointKind, KeyCode::Up) => {
+                self.renderer_state.scroll_up();
+            }
+            (WindowState::BreakpointKind, KeyCode::Char('j'))
+            | (WindowState::BreakpointKind, KeyCode::Down) => {
+                self.renderer_state.scroll_down();
+            }
+            (WindowState::BreakpointKind, KeyCode::Enter) => {
+                let kind = all::<InstructionKind>()
+                    .nth(self.renderer_state.chosen_kind)
+                    .unwrap();
+                self.add_breakpoint(Breakpoint::Kind(kind));
+                self.renderer_state.chosen_kind = 0;
+                self.renderer_state.window_state = WindowState::Main;
+            }
             _ => {}
         }
         Ok(())
     }
 }
+
+// Parse the instruction pointer and relative base from something like `ip 124 rb 2000`. Anything
+// left out keeps its current value.
+fn parse_registers(text: &str, state: &State) -> Result<(usize, isize)> {
+    let (mut instruction_pointer, mut relative_base) =
+        (state.instruction_pointer, state.relative_base);
+    let mut tokens = text.split_whitespace();
+    while let Some(name) = tokens.next() {
+        let value = tokens
+            .next()
+            .ok_or_else(|| anyhow!("expected a value for '{}'", name))?;
+        match name {
+            "ip" => {
+                instruction_pointer = value
+                    .parse()
+                    .map_err(|_| anyhow!("invalid instruction pointer '{}'", value))?
+            }
+            "rb" => {
+                relative_base = value
+                    .parse()
+                    .map_err(|_| anyhow!("invalid relative base '{}'", value))?
+            }
+            _ => return Err(anyhow!("unknown register '{}'", name)),
+        }
+    }
+    Ok((instruction_pointer, relative_base))
+}
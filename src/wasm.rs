@@ -0,0 +1,90 @@
+use wasm_bindgen::prelude::*;
+
+use crate::machine::Machine;
+
+/// An Intcode computer for JavaScript. Values cross the boundary as numbers. Cells are `isize`,
+/// which is only 32 bits on wasm32, so programs that need bigger values won't run correctly.
+#[wasm_bindgen]
+pub struct Vm {
+    machine: Machine,
+}
+
+#[wasm_bindgen]
+impl Vm {
+    /// Create a computer running the given comma separated program.
+    #[wasm_bindgen(constructor)]
+    pub fn new(program: &str) -> Vm {
+        Vm {
+            machine: Machine::new(program),
+        }
+    }
+
+    /// Start over with the given program, forgetting any inputs and outputs.
+    pub fn load(&mut self, program: &str) {
+        self.machine = Machine::new(program);
+    }
+
+    /// Run a single instruction. This returns `false` if nothing was run because the computer has
+    /// halted or is waiting for input, and throws if the instruction can't be decoded or fails.
+    pub fn step(&mut self) -> Result<bool, JsError> {
+        self.machine.step().map_err(to_js)
+    }
+
+    /// Run at most `limit` instructions, stopping early if the computer halts or waits for input.
+    /// This returns the number of instructions that were run, and throws like `step` does.
+    pub fn run(&mut self, limit: usize) -> Result<usize, JsError> {
+        self.machine.run(limit).map_err(to_js)
+    }
+
+    /// Read `length` cells of memory starting at `start`. Cells past the end of the program read
+    /// as zero.
+    pub fn read_memory(&self, start: usize, length: usize) -> Vec<f64> {
+        let state = self.machine.state_ref();
+        (start..start + length)
+            .map(|address| state[address] as f64)
+            .collect()
+    }
+
+    /// Queue a value for the computer to read.
+    pub fn push_input(&mut self, value: f64) {
+        self.machine.push_input(value as isize);
+    }
+
+    /// Take all of the values the computer has output since the last poll.
+    pub fn poll_output(&mut self) -> Vec<f64> {
+        self.machine
+            .poll_output()
+            .into_iter()
+            .map(|value| value as f64)
+            .collect()
+    }
+
+    /// The address of the next instruction to run. After a throw, it's the instruction that failed.
+    #[wasm_bindgen(getter)]
+    pub fn instruction_pointer(&self) -> usize {
+        self.machine.state_ref().instruction_pointer
+    }
+
+    /// The base that relative parameters are offset from.
+    #[wasm_bindgen(getter)]
+    pub fn relative_base(&self) -> f64 {
+        self.machine.state_ref().relative_base as f64
+    }
+
+    /// Whether the computer has run a halt instruction. A computer that threw hasn't halted, but
+    /// stepping it again throws the same error.
+    #[wasm_bindgen(getter)]
+    pub fn halted(&self) -> bool {
+        self.machine.halted()
+    }
+
+    /// Whether the computer is stopped on an input instruction with no input queued.
+    #[wasm_bindgen(getter)]
+    pub fn waiting(&self) -> bool {
+        self.machine.waiting()
+    }
+}
+
+fn to_js(e: anyhow::Error) -> JsError {
+    JsError::new(&e.to_string())
+}
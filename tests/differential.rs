@@ -0,0 +1,55 @@
+//! Runs the computer in lockstep with the reference interpreter on random programs and the sample
+//! programs, failing at the first instruction where they disagree. Changes that make the computer
+//! faster should keep this passing.
+
+use std::path::Path;
+
+use intcode::reference::lockstep;
+use proptest::{collection::vec, prelude::*, sample::select};
+
+const CASES: u32 = 2_000;
+const LIMIT: usize = 1_000;
+
+// A random program that is mostly valid instructions with small values mixed in, so it reads and
+// writes near itself and jumps around within itself.
+fn program() -> impl Strategy<Value = Vec<isize>> {
+    (4..=40isize).prop_flat_map(|length| {
+        let instruction = (
+            select(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 99]),
+            0..=2isize,
+            0..=2isize,
+            0..=2isize,
+        )
+            .prop_map(|(opcode, a, b, c)| opcode + a * 100 + b * 1_000 + c * 10_000);
+        vec(prop_oneof![1 => instruction, 2 => -5..=length], length as usize)
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    #[test]
+    fn test_random_programs(program in program(), inputs in vec(-10..=10isize, 0..5)) {
+        let result = lockstep(&program, &inputs, LIMIT);
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+    }
+}
+
+#[test]
+fn test_sample_programs() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let program = std::fs::read_to_string(&path)
+            .unwrap()
+            .trim()
+            .split(',')
+            .map(|cell| cell.parse().unwrap())
+            .collect::<Vec<isize>>();
+        for input in [1, 2, 5] {
+            if let Err(e) = lockstep(&program, &[input, 0, 0, 0, 0], 100_000) {
+                panic!("{} with input {}: {}", path.display(), input, e);
+            }
+        }
+    }
+}
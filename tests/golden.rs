@@ -0,0 +1,137 @@
+//! Runs each day of the aoc binary and checks its answers so changes to the computer can't quietly
+//! break an old puzzle. The samples in `tests/golden` always run. Real inputs are checked too when
+//! `AOC_GOLDEN` names a directory with inputs laid out like the input cache and an `answers` file
+//! with a `day part answer` line for each answer to check.
+
+#[path = "../src/bin/aoc/day02.rs"]
+mod day02;
+#[path = "../src/bin/aoc/day05.rs"]
+mod day05;
+#[path = "../src/bin/aoc/day07.rs"]
+mod day07;
+#[path = "../src/bin/aoc/day09.rs"]
+mod day09;
+#[path = "../src/bin/aoc/day11.rs"]
+mod day11;
+#[path = "../src/bin/aoc/day19.rs"]
+mod day19;
+#[path = "../src/bin/aoc/day21.rs"]
+mod day21;
+#[path = "../src/bin/aoc/day23.rs"]
+mod day23;
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use intcode::{
+    inputs::path,
+    solutions::{timed, Solution},
+};
+
+/// The environment variable naming the directory of real inputs and answers.
+const GOLDEN: &str = "AOC_GOLDEN";
+
+// The samples along with the part they are for and the answer it should give.
+const SAMPLES: &[(usize, usize, &str, &str)] = &[
+    (2, 1, "day02.txt", "19687744"),
+    (2, 2, "day02.txt", "4075"),
+    (5, 1, "day05.txt", "1"),
+    (5, 2, "day05.txt", "5"),
+    (7, 1, "day07-part1.txt", "43210"),
+    (7, 2, "day07-part2.txt", "139629729"),
+    (9, 1, "day09.txt", "1125899906842624"),
+    (9, 2, "day09.txt", "1125899906842624"),
+];
+
+fn solutions() -> Vec<Box<dyn Solution>> {
+    vec![
+        Box::new(day02::Day02),
+        Box::new(day05::Day05),
+        Box::new(day07::Day07),
+        Box::new(day09::Day09),
+        Box::new(day11::Day11),
+        Box::new(day19::Day19),
+        Box::new(day21::Day21),
+        Box::new(day23::Day23),
+    ]
+}
+
+// How long a part of each day gets. These are generous because tests usually aren't optimized.
+fn limit(day: usize) -> Duration {
+    Duration::from_secs(match day {
+        2 | 5 | 7 | 11 => 10,
+        9 | 21 | 23 => 30,
+        _ => 60,
+    })
+}
+
+fn leak(input: String) -> &'static str {
+    Box::leak(input.into_boxed_str())
+}
+
+// Run a part of the given day and check its answer, saying what went wrong if it didn't match.
+async fn check(day: usize, part: usize, input: &'static str, expected: &str) -> Result<()> {
+    let solutions = solutions();
+    let solution = solutions
+        .iter()
+        .find(|solution| solution.day() == day)
+        .ok_or_else(|| anyhow!("there is no solution for day {}", day))?;
+    let (answer, elapsed) = tokio::time::timeout(limit(day), timed(solution.as_ref(), input, part))
+        .await
+        .map_err(|_| anyhow!("took longer than {:?}", limit(day)))?;
+    let answer = answer?;
+    if answer != expected {
+        return Err(anyhow!(
+            "expected {} but got {} ({:?})",
+            expected,
+            answer,
+            elapsed
+        ));
+    }
+    Ok(())
+}
+
+// Read the answers file, which has a `day part answer` line for each answer.
+fn answers(dir: &Path) -> Result<Vec<(usize, usize, String)>> {
+    let answers = std::fs::read_to_string(dir.join("answers"))?;
+    answers
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(
+            |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [day, part, answer] => Ok((day.parse()?, part.parse()?, answer.to_string())),
+                _ => Err(anyhow!("bad answer line '{}'", line)),
+            },
+        )
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_samples() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut failures = Vec::new();
+    for &(day, part, file, expected) in SAMPLES {
+        let input = leak(std::fs::read_to_string(dir.join(file)).unwrap());
+        if let Err(e) = check(day, part, input, expected).await {
+            failures.push(format!("day {:02} p{} ({}): {}", day, part, file, e));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_inputs() {
+    let Ok(dir) = std::env::var(GOLDEN) else {
+        return;
+    };
+    let dir = Path::new(&dir);
+    let mut failures = Vec::new();
+    for (day, part, expected) in answers(dir).unwrap() {
+        let input = leak(std::fs::read_to_string(path(dir, day)).unwrap());
+        if let Err(e) = check(day, part, input, &expected).await {
+            failures.push(format!("day {:02} p{}: {}", day, part, e));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
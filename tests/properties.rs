@@ -0,0 +1,230 @@
+//! Properties of the VM checked with proptest against generated instructions and programs. A
+//! failure is shrunk to the smallest case that still breaks and saved under `proptest-regressions`
+//! so it's tried first on the next run.
+
+use futures::executor::block_on;
+use intcode::{
+    asm::assemble,
+    disasm::disassemble,
+    instruction::{Instruction, Operation},
+    ipc::Channel,
+    machine::Machine,
+    parameter::Parameter,
+    process::Process,
+};
+use proptest::{collection::vec, prelude::*, sample::select};
+
+const CASES: u32 = 500;
+
+fn parameter() -> impl Strategy<Value = Parameter> {
+    prop_oneof![
+        (0..10_000usize).prop_map(Parameter::Position),
+        (-1_000_000..=1_000_000isize).prop_map(Parameter::Immediate),
+        (-10_000..=10_000isize).prop_map(Parameter::Relative),
+    ]
+}
+
+// Any instruction that can be encoded, which rules out writing to an immediate.
+fn instruction(operations: Vec<Operation>) -> impl Strategy<Value = Instruction> {
+    select(operations)
+        .prop_flat_map(|operation| {
+            vec(parameter(), operation.arity())
+                .prop_map(move |parameters| Instruction::new(operation, &parameters).unwrap())
+        })
+        .prop_filter("writes to an immediate", |instruction| {
+            !matches!(instruction.write_parameter(), Some(Parameter::Immediate(_)))
+        })
+}
+
+fn cells(instructions: &[Instruction]) -> Vec<isize> {
+    instructions.iter().flat_map(|i| i.encode()).collect()
+}
+
+fn text(program: &[isize]) -> String {
+    program
+        .iter()
+        .map(|cell| cell.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// A program that runs straight through some arithmetic and outputs on a few data cells after the
+// code and then halts. The values stay small enough that nothing overflows. Reads and writes are
+// generated as offsets into the data, which is only placed once we know how long the code is.
+fn straight_line() -> impl Strategy<Value = Vec<isize>> {
+    let read = || {
+        prop_oneof![
+            (0..8usize).prop_map(Parameter::Position),
+            (-100..=100isize).prop_map(Parameter::Immediate),
+        ]
+    };
+    let step = (
+        select(vec![
+            Operation::Add,
+            Operation::Multiply,
+            Operation::LessThan,
+            Operation::Equals,
+            Operation::Output,
+        ]),
+        read(),
+        read(),
+        0..8usize,
+        -3..=3isize,
+    );
+    (vec(step, 1..12), vec(-100..=100isize, 8)).prop_map(|(steps, values)| {
+        let data = steps.iter().map(|(o, ..)| o.arity() + 1).sum::<usize>() + 1;
+        let place = |parameter| match parameter {
+            Parameter::Position(offset) => Parameter::Position(data + offset),
+            parameter => parameter,
+        };
+        let cell = |offset| Parameter::Position(data + offset);
+
+        let mut instructions = steps
+            .into_iter()
+            .map(|(operation, a, b, dest, factor)| match operation {
+                Operation::Add => Instruction::Add(place(a), place(b), cell(dest)),
+                Operation::Multiply => {
+                    Instruction::Multiply(place(a), Parameter::Immediate(factor), cell(dest))
+                }
+                Operation::LessThan => Instruction::LessThan(place(a), place(b), cell(dest)),
+                Operation::Equals => Instruction::Equals(place(a), place(b), cell(dest)),
+                _ => Instruction::Output(place(a)),
+            })
+            .collect::<Vec<_>>();
+        instructions.push(Instruction::Halt);
+
+        let mut program = cells(&instructions);
+        program.extend(values);
+        program
+    })
+}
+
+// Run the instructions and get what they output.
+fn outputs(instructions: &[Instruction]) -> Vec<isize> {
+    let mut machine = Machine::new(&text(&cells(instructions)));
+    machine.run(1_000).unwrap();
+    assert!(machine.halted());
+    machine.poll_output()
+}
+
+// Run a binary instruction on two immediates and get the value it stores.
+fn binary(
+    instruction: fn(Parameter, Parameter, Parameter) -> Instruction,
+    a: isize,
+    b: isize,
+) -> isize {
+    let (i, p) = (Parameter::Immediate, Parameter::Position);
+    outputs(&[
+        instruction(i(a), i(b), p(100)),
+        Instruction::Output(p(100)),
+        Instruction::Halt,
+    ])[0]
+}
+
+// Jumps are left out so everything is reachable and the disassembler sees it all as code.
+fn straight_operations() -> Vec<Operation> {
+    Instruction::all()
+        .filter(|o| {
+            !matches!(
+                o,
+                Operation::JumpIfTrue | Operation::JumpIfFalse | Operation::Halt
+            )
+        })
+        .collect()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    #[test]
+    fn test_encode_decode_round_trip(instruction in instruction(Instruction::all().collect())) {
+        let encoded = instruction.encode();
+        prop_assert_eq!(
+            Instruction::decode(&encoded, 0).unwrap(),
+            (instruction, encoded.len())
+        );
+        prop_assert_eq!(
+            instruction.to_string().parse::<Instruction>().unwrap(),
+            instruction
+        );
+    }
+
+    #[test]
+    fn test_assemble_disassemble_round_trip(
+        mut instructions in vec(instruction(straight_operations()), 1..10)
+    ) {
+        instructions.push(Instruction::Halt);
+
+        let source = instructions
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let program = assemble(&source).unwrap();
+        prop_assert_eq!(&program, &cells(&instructions));
+
+        let listing = disassemble(&program);
+        let disassembled = listing
+            .lines
+            .iter()
+            .map(|line| line.instruction)
+            .collect::<Vec<_>>();
+        let expected = instructions.into_iter().map(Some).collect::<Vec<_>>();
+        prop_assert_eq!(disassembled, expected);
+    }
+
+    // This runs outside of tokio because the machine blocks on the process itself, which doesn't
+    // work from inside the runtime.
+    #[test]
+    fn test_sync_and_async_runners_agree(program in straight_line()) {
+        let program = text(&program);
+
+        let mut machine = Machine::new(&program);
+        machine.run(1_000).unwrap();
+
+        let (_, _input, receiver) = Channel::new(true);
+        let (_, sender, mut output) = Channel::new(true);
+        let mut process = Process::new(&program, receiver, sender);
+        block_on(process.run()).unwrap();
+
+        prop_assert_eq!(machine.poll_output(), output.drain());
+        prop_assert_eq!(machine.state(), process.state());
+    }
+
+    #[test]
+    fn test_arithmetic_properties(
+        a in -1_000..=1_000isize,
+        b in -1_000..=1_000isize,
+        c in -1_000..=1_000isize,
+    ) {
+        prop_assert_eq!(binary(Instruction::Add, a, b), a + b);
+        prop_assert_eq!(binary(Instruction::Add, a, b), binary(Instruction::Add, b, a));
+        prop_assert_eq!(binary(Instruction::Add, a, 0), a);
+        prop_assert_eq!(binary(Instruction::Multiply, a, b), a * b);
+        prop_assert_eq!(
+            binary(Instruction::Multiply, a, b),
+            binary(Instruction::Multiply, b, a)
+        );
+        prop_assert_eq!(binary(Instruction::Multiply, a, 1), a);
+        prop_assert_eq!(binary(Instruction::Multiply, a, 0), 0);
+        prop_assert_eq!(binary(Instruction::LessThan, a, b), (a < b) as isize);
+        prop_assert_eq!(binary(Instruction::Equals, a, a), 1);
+
+        // a * (b + c) == a * b + a * c
+        let (i, p) = (Parameter::Immediate, Parameter::Position);
+        let left = outputs(&[
+            Instruction::Add(i(b), i(c), p(100)),
+            Instruction::Multiply(i(a), p(100), p(101)),
+            Instruction::Output(p(101)),
+            Instruction::Halt,
+        ]);
+        let right = outputs(&[
+            Instruction::Multiply(i(a), i(b), p(100)),
+            Instruction::Multiply(i(a), i(c), p(101)),
+            Instruction::Add(p(100), p(101), p(102)),
+            Instruction::Output(p(102)),
+            Instruction::Halt,
+        ]);
+        prop_assert_eq!(left, right);
+    }
+}
@@ -0,0 +1,96 @@
+//! Renders the debugger in every window state and compares the screen against the snapshots in
+//! `tests/snapshots`. Set `UPDATE_SNAPSHOTS` to write the current screens instead after a change to
+//! the renderer, and look over the diff before committing them.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use intcode::{
+    app::App,
+    ipc::Channel,
+    process,
+    renderer::{RendererState, WindowState},
+};
+use tokio::sync::mpsc;
+
+const WIDTH: u16 = 160;
+const HEIGHT: u16 = 50;
+
+const STATES: &[WindowState] = &[
+    WindowState::Main,
+    WindowState::BreakpointType,
+    WindowState::BreakpointList,
+    WindowState::BreakpointInstruction,
+    WindowState::BreakpointKind,
+    WindowState::BreakpointMemory,
+    WindowState::BreakpointPointer,
+    WindowState::BreakpointCondition,
+    WindowState::BreakpointTrace,
+    WindowState::GotoAddress,
+    WindowState::Search,
+    WindowState::Input,
+    WindowState::Registers,
+    WindowState::Export,
+    WindowState::Open,
+    WindowState::ChannelList,
+    WindowState::ChannelDetail,
+    WindowState::Watch,
+];
+
+fn snapshot(window_state: WindowState) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{:?}.txt", window_state))
+}
+
+// Trailing spaces are dropped so the snapshots survive editors that strip them.
+fn trim(screen: &str) -> String {
+    screen
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n"
+}
+
+#[test]
+fn test_window_state_snapshots() {
+    // This is the only channel made in this binary, so its id is always the same.
+    let (channel, _, _) = Channel::new(true);
+    let state = Arc::new(Mutex::new(process::State::new("1,0,0,0,99")));
+    let (notifier, _) = mpsc::channel(1);
+    let app = App::new(
+        vec!["test".to_string()],
+        vec![channel],
+        vec![state],
+        vec![notifier],
+    );
+    let mut renderer = RendererState::new(&app);
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+
+    for &window_state in STATES {
+        renderer.window_state = window_state;
+        let screen = trim(&renderer.render_to_string(&app, WIDTH, HEIGHT).unwrap());
+        let path = snapshot(window_state);
+        if update {
+            fs::write(&path, screen).unwrap();
+            continue;
+        }
+
+        let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+            panic!(
+                "{}: {} (set UPDATE_SNAPSHOTS to write it)",
+                path.display(),
+                e
+            )
+        });
+        assert_eq!(
+            screen,
+            expected,
+            "{:?} doesn't match {}",
+            window_state,
+            path.display()
+        );
+    }
+}